@@ -0,0 +1,183 @@
+//! Bounding volumes. All of them can be tested against a `Frustum` through `BoundingVolume`.
+
+use glam::{Mat3, Mat4, Vec3};
+
+/// What a frustum test needs from a volume.
+pub trait BoundingVolume {
+    fn center(&self) -> Vec3;
+    /// Half the volume's extent along unit `axis`.
+    fn radius_along(&self, axis: Vec3) -> f32;
+}
+
+/// Axis-aligned box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    pub fn from_center_half_extents(center: Vec3, half_extents: Vec3) -> Self {
+        Self { min: center - half_extents, max: center + half_extents }
+    }
+
+    /// `None` without points.
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Option<Self> {
+        points.into_iter().fold(None, |acc, p| match acc {
+            None => Some(Self::new(p, p)),
+            Some(b) => Some(Self::new(b.min.min(p), b.max.max(p))),
+        })
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn half_extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    pub fn contains_point(&self, p: Vec3) -> bool {
+        p.cmpge(self.min).all() && p.cmple(self.max).all()
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.max).all() && other.min.cmple(self.max).all()
+    }
+
+    pub fn intersects_sphere(&self, sphere: &Sphere) -> bool {
+        sphere.center.clamp(self.min, self.max).distance_squared(sphere.center) <= sphere.radius * sphere.radius
+    }
+
+    /// The smallest box around this one after `transform`.
+    pub fn transformed(&self, transform: &Mat4) -> Aabb {
+        let obb = Obb::from_aabb(self, transform);
+        let h = obb.half_axes;
+        Aabb::from_center_half_extents(obb.center, h.x_axis.abs() + h.y_axis.abs() + h.z_axis.abs())
+    }
+}
+
+impl BoundingVolume for Aabb {
+    fn center(&self) -> Vec3 {
+        Aabb::center(self)
+    }
+
+    fn radius_along(&self, axis: Vec3) -> f32 {
+        self.half_extents().dot(axis.abs())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl Sphere {
+    pub fn new(center: Vec3, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    /// Centered on the points' box, so not the tightest fit but never a miss. `None` without points.
+    pub fn from_points(points: impl IntoIterator<Item = Vec3> + Clone) -> Option<Self> {
+        let center = Aabb::from_points(points.clone())?.center();
+        let radius = points.into_iter().map(|p| p.distance_squared(center)).fold(0.0, f32::max).sqrt();
+        Some(Self::new(center, radius))
+    }
+
+    pub fn contains_point(&self, p: Vec3) -> bool {
+        p.distance_squared(self.center) <= self.radius * self.radius
+    }
+
+    pub fn intersects(&self, other: &Sphere) -> bool {
+        let r = self.radius + other.radius;
+        self.center.distance_squared(other.center) <= r * r
+    }
+
+    /// Still a sphere after `transform`, grown by its largest scale.
+    pub fn transformed(&self, transform: &Mat4) -> Sphere {
+        let scale = [transform.x_axis, transform.y_axis, transform.z_axis]
+            .iter()
+            .map(|a| a.truncate().length())
+            .fold(0.0, f32::max);
+        Sphere::new(transform.transform_point3(self.center), self.radius * scale)
+    }
+}
+
+impl BoundingVolume for Sphere {
+    fn center(&self) -> Vec3 {
+        self.center
+    }
+
+    fn radius_along(&self, _axis: Vec3) -> f32 {
+        self.radius
+    }
+}
+
+/// Oriented box: a transformed `Aabb`, shear and non-uniform scale included.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Obb {
+    pub center: Vec3,
+    /// Columns are the box's edges from its center to its faces.
+    pub half_axes: Mat3,
+}
+
+impl Obb {
+    pub fn new(center: Vec3, half_axes: Mat3) -> Self {
+        Self { center, half_axes }
+    }
+
+    pub fn from_aabb(aabb: &Aabb, transform: &Mat4) -> Self {
+        let half = aabb.half_extents();
+        let linear = Mat3::from_mat4(*transform);
+        Self {
+            center: transform.transform_point3(aabb.center()),
+            half_axes: Mat3::from_cols(linear.x_axis * half.x, linear.y_axis * half.y, linear.z_axis * half.z),
+        }
+    }
+
+    /// False for flat boxes, which contain nothing.
+    pub fn contains_point(&self, p: Vec3) -> bool {
+        if self.half_axes.determinant() == 0.0 {
+            return false;
+        }
+        let local = self.half_axes.inverse() * (p - self.center);
+        local.abs().cmple(Vec3::ONE).all()
+    }
+
+    /// Separating-axis test over both boxes' faces and their edges' cross products.
+    pub fn intersects(&self, other: &Obb) -> bool {
+        let a = [self.half_axes.x_axis, self.half_axes.y_axis, self.half_axes.z_axis];
+        let b = [other.half_axes.x_axis, other.half_axes.y_axis, other.half_axes.z_axis];
+        let d = other.center - self.center;
+        // Face normals, which sheared boxes don't have as columns.
+        let normals = |[x, y, z]: [Vec3; 3]| [y.cross(z), z.cross(x), x.cross(y)];
+        let faces = normals(a).into_iter().chain(normals(b));
+        let edges = a.iter().flat_map(|&x| b.iter().map(move |&y| x.cross(y)));
+        faces.chain(edges).filter_map(|axis| axis.try_normalize()).all(|axis| {
+            d.dot(axis).abs() <= self.radius_along(axis) + other.radius_along(axis)
+        })
+    }
+}
+
+impl BoundingVolume for Obb {
+    fn center(&self) -> Vec3 {
+        self.center
+    }
+
+    fn radius_along(&self, axis: Vec3) -> f32 {
+        (self.half_axes.transpose() * axis).abs().element_sum()
+    }
+}
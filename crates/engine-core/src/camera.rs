@@ -1,14 +1,48 @@
 use bytemuck::{Pod, Zeroable};
-use glam::{Mat4, Vec3};
+use glam::{Mat4, Vec2, Vec3, Vec4};
 
+/// Per-view uniforms; model matrices are supplied per draw/instance.
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
-pub struct CameraUBO { pub mvp: [[f32; 4]; 4] }
+pub struct CameraUBO { pub view_proj: [[f32; 4]; 4] }
 
+/// How the view volume is shaped. Every kind is divided by `Camera::zoom`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    /// Uses `Camera::fov_y`.
+    Perspective,
+    /// `height` world units fit the view vertically.
+    Orthographic { height: f32 },
+    /// Orthographic at one world unit per pixel, with the view snapped to whole pixels so
+    /// pixel art stays crisp.
+    Pixel,
+    /// Perspective through an asymmetric window on the near plane (view-space extents), for
+    /// off-axis views like multi-screen walls or head tracking. Ignores `Camera::origin`.
+    Frustum { left: f32, right: f32, bottom: f32, top: f32 },
+}
+
+/// Methods taking a `viewport` want its size in pixels; screen positions are in pixels from the
+/// top-left corner. `Projection::Pixel` only works through those: the `aspect` ones see a
+/// viewport one pixel high.
 #[derive(Clone)]
 pub struct Camera {
     pub position: Vec3, pub target: Vec3, pub up: Vec3,
+    pub projection: Projection,
+    /// `z_far` may be `f32::INFINITY`, which keeps everything in front of `z_near` in view
+    /// (perspective kinds only).
     pub fov_y: f32, pub z_near: f32, pub z_far: f32,
+    /// Magnifies the view; 2 shows half as much.
+    pub zoom: f32,
+    /// Where the view axis crosses the screen, as a fraction of it from the top-left corner.
+    /// Anything off center shifts the view without turning it (a lens shift).
+    pub origin: Vec2,
+    /// World-space plane `n·p + d = 0` replacing the near plane; only what's on its positive
+    /// side is drawn, and the camera must be on the other. For reflections and portals, where
+    /// geometry between the camera and the mirror must go.
+    pub clip_plane: Option<Vec4>,
+    /// Maps `z_near` to depth 1 and `z_far` to 0, which spreads float precision far better.
+    /// The renderer has to agree (`Renderer::set_reverse_z`).
+    pub reverse_z: bool,
 }
 
 impl Camera {
@@ -17,15 +51,166 @@ impl Camera {
             position,
             target,
             up: Vec3::Y,
+            projection: Projection::Perspective,
             fov_y: 60f32.to_radians(),
             z_near: 0.1,
             z_far: 100.0,
+            zoom: 1.0,
+            origin: Vec2::splat(0.5),
+            clip_plane: None,
+            reverse_z: false,
+        }
+    }
+
+    /// Looking down -Z at `height` world units.
+    pub fn orthographic(position: Vec3, target: Vec3, height: f32) -> Self {
+        Self { projection: Projection::Orthographic { height }, ..Self::new(position, target) }
+    }
+
+    /// A 2D view centered on `center`, one unit per pixel, y up. Sees z from -500 to 500.
+    pub fn pixel_2d(center: Vec2) -> Self {
+        let position = center.extend(500.0);
+        Self {
+            projection: Projection::Pixel,
+            z_near: 0.0,
+            z_far: 1000.0,
+            ..Self::new(position, center.extend(0.0))
+        }
+    }
+
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    pub fn with_zoom(mut self, zoom: f32) -> Self {
+        self.zoom = zoom;
+        self
+    }
+
+    pub fn with_origin(mut self, origin: Vec2) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    pub fn with_clip_plane(mut self, plane: Option<Vec4>) -> Self {
+        self.clip_plane = plane;
+        self
+    }
+
+    pub fn with_reverse_z(mut self, on: bool) -> Self {
+        self.reverse_z = on;
+        self
+    }
+
+    pub fn with_infinite_far(mut self) -> Self {
+        self.z_far = f32::INFINITY;
+        self
+    }
+
+    pub fn is_orthographic(&self) -> bool {
+        matches!(self.projection, Projection::Orthographic { .. } | Projection::Pixel)
+    }
+
+    pub fn view(&self) -> Mat4 {
+        let mut view = Mat4::look_at_rh(self.position, self.target, self.up);
+        if self.projection == Projection::Pixel {
+            let pixel = 1.0 / self.zoom;
+            view.w_axis.x = (view.w_axis.x / pixel).round() * pixel;
+            view.w_axis.y = (view.w_axis.y / pixel).round() * pixel;
+        }
+        view
+    }
+
+    /// `aspect` is width over height.
+    pub fn projection(&self, aspect: f32) -> Mat4 {
+        self.projection_viewport(Vec2::new(aspect, 1.0))
+    }
+
+    pub fn projection_viewport(&self, viewport: Vec2) -> Mat4 {
+        let aspect = viewport.x / viewport.y.max(1.0);
+        let (l, r, b, t) = self.extents(viewport, aspect);
+        let (n, f) = (self.z_near, self.z_far);
+        let mut proj = if self.is_orthographic() {
+            // Depth needs a finite range here.
+            let f = if f.is_finite() { f } else { n + 1000.0 };
+            Mat4::orthographic_rh(l, r, b, t, n, f)
+        } else {
+            let (z, w) = if f.is_finite() { (f / (n - f), n * f / (n - f)) } else { (-1.0, -n) };
+            Mat4::from_cols(
+                Vec4::new(2.0 * n / (r - l), 0.0, 0.0, 0.0),
+                Vec4::new(0.0, 2.0 * n / (t - b), 0.0, 0.0),
+                Vec4::new((r + l) / (r - l), (t + b) / (t - b), z, -1.0),
+                Vec4::new(0.0, 0.0, w, 0.0),
+            )
+        };
+        if let Some(plane) = self.clip_plane {
+            // View-space plane, then Lengyel's oblique near plane (for 0..1 depth).
+            let c = self.view().inverse().transpose() * plane;
+            let q = proj.inverse() * Vec4::new(c.x.signum(), c.y.signum(), 1.0, 1.0);
+            let c = c / c.dot(q);
+            proj = proj.transpose();
+            proj.z_axis = c;
+            proj = proj.transpose();
+        }
+        if self.reverse_z {
+            // depth' = 1 - depth, for every kind at once.
+            proj = Mat4::from_cols(Vec4::X, Vec4::Y, Vec4::new(0.0, 0.0, -1.0, 0.0), Vec4::new(0.0, 0.0, 1.0, 1.0)) * proj;
         }
+        proj
+    }
+
+    /// The view volume's (left, right, bottom, top): on the near plane for perspective kinds.
+    fn extents(&self, viewport: Vec2, aspect: f32) -> (f32, f32, f32, f32) {
+        let size = match self.projection {
+            Projection::Frustum { left, right, bottom, top } => {
+                return (left / self.zoom, right / self.zoom, bottom / self.zoom, top / self.zoom);
+            }
+            Projection::Perspective => {
+                let h = 2.0 * self.z_near * (self.fov_y * 0.5).tan();
+                Vec2::new(h * aspect, h)
+            }
+            Projection::Orthographic { height } => Vec2::new(height * aspect, height),
+            Projection::Pixel => viewport,
+        } / self.zoom;
+        let (left, top) = match self.projection {
+            // Whole pixels on either side, or odd sizes put every texel on a pixel boundary.
+            Projection::Pixel => ((viewport.x * self.origin.x).round() / self.zoom, (viewport.y * self.origin.y).round() / self.zoom),
+            _ => (size.x * self.origin.x, size.y * self.origin.y),
+        };
+        (-left, size.x - left, top - size.y, top)
     }
-    pub fn make_mvp(&self, aspect: f32, t: f32) -> CameraUBO { 
-        let proj = Mat4::perspective_rh(self.fov_y, aspect, self.z_near, self.z_far);
-        let view = Mat4::look_at_rh(self.position, self.target, self.up);
-        let model = Mat4::from_rotation_y(t);
-        CameraUBO { mvp: (proj * view * model).to_cols_array_2d() }
+
+    pub fn view_proj(&self, aspect: f32) -> Mat4 {
+        self.projection(aspect) * self.view()
     }
-}
\ No newline at end of file
+
+    pub fn view_proj_viewport(&self, viewport: Vec2) -> Mat4 {
+        self.projection_viewport(viewport) * self.view()
+    }
+
+    pub fn make_ubo(&self, aspect: f32) -> CameraUBO {
+        CameraUBO { view_proj: self.view_proj(aspect).to_cols_array_2d() }
+    }
+
+    pub fn make_ubo_viewport(&self, viewport: Vec2) -> CameraUBO {
+        CameraUBO { view_proj: self.view_proj_viewport(viewport).to_cols_array_2d() }
+    }
+
+    /// Pixel position and depth (0..1, as the depth buffer stores it) of a world point; `None`
+    /// behind a perspective camera.
+    pub fn world_to_screen(&self, point: Vec3, viewport: Vec2) -> Option<Vec3> {
+        let clip = self.view_proj_viewport(viewport) * point.extend(1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+        let ndc = clip.truncate() / clip.w;
+        Some(Vec3::new((ndc.x + 1.0) * 0.5 * viewport.x, (1.0 - ndc.y) * 0.5 * viewport.y, ndc.z))
+    }
+
+    /// The world point under pixel `screen` at `depth` (as `world_to_screen` gives it).
+    pub fn screen_to_world(&self, screen: Vec2, depth: f32, viewport: Vec2) -> Vec3 {
+        let ndc = Vec3::new(screen.x / viewport.x * 2.0 - 1.0, 1.0 - screen.y / viewport.y * 2.0, depth);
+        self.view_proj_viewport(viewport).inverse().project_point3(ndc)
+    }
+}
@@ -0,0 +1,323 @@
+//! Camera controllers: each turns an `InputState` into camera motion once per frame. Smoothing
+//! is exponential in `dt`, so it feels the same at any frame rate.
+
+use std::f32::consts::FRAC_PI_2;
+
+use glam::{Vec2, Vec3};
+
+use crate::camera::Camera;
+use crate::input::{InputState, Key, MouseButton};
+
+pub trait CameraController {
+    /// Applies this frame's `input`, `dt` seconds after the last one, and points `camera`.
+    fn update(&mut self, camera: &mut Camera, input: &InputState, dt: f32);
+}
+
+/// Share of the remaining gap to cover this frame when closing `sharpness` e-folds of it per
+/// second. Infinite sharpness snaps.
+pub fn damp_factor(sharpness: f32, dt: f32) -> f32 {
+    if sharpness.is_infinite() { 1.0 } else { 1.0 - (-sharpness * dt).exp() }
+}
+
+/// Unit view direction; yaw 0 looks down -Z, positive yaw turns right, positive pitch looks up.
+pub fn look_direction(yaw: f32, pitch: f32) -> Vec3 {
+    let (sy, cy) = yaw.sin_cos();
+    let (sp, cp) = pitch.sin_cos();
+    Vec3::new(sy * cp, sp, -cy * cp)
+}
+
+/// Inverse of `look_direction`.
+fn yaw_pitch(dir: Vec3) -> (f32, f32) {
+    let dir = dir.normalize_or(Vec3::NEG_Z);
+    (dir.x.atan2(-dir.z), dir.y.clamp(-1.0, 1.0).asin())
+}
+
+/// Just short of straight up or down, where yaw stops meaning anything.
+const PITCH_LIMIT: f32 = FRAC_PI_2 - 0.01;
+
+/// Keys moving the fly and first-person controllers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MoveKeys {
+    pub forward: Key,
+    pub back: Key,
+    pub left: Key,
+    pub right: Key,
+    pub up: Key,
+    pub down: Key,
+    /// Held to move faster.
+    pub boost: Key,
+}
+
+impl Default for MoveKeys {
+    fn default() -> Self {
+        Self { forward: Key::W, back: Key::S, left: Key::A, right: Key::D, up: Key::E, down: Key::Q, boost: Key::Shift }
+    }
+}
+
+/// Mouse movement to look with: always, or only while `button` is held.
+fn look_delta(input: &InputState, button: Option<MouseButton>) -> Vec2 {
+    match button {
+        Some(b) if !input.is_button_down(b) => Vec2::ZERO,
+        _ => input.mouse_delta(),
+    }
+}
+
+/// Circles a target: drag to turn the world with the mouse, drag with `pan_button` to move the
+/// target, scroll to zoom.
+#[derive(Clone, Debug)]
+pub struct OrbitController {
+    pub target: Vec3,
+    pub distance: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    /// Radians per pixel dragged.
+    pub rotate_speed: f32,
+    /// Share of the distance moved per pixel dragged.
+    pub pan_speed: f32,
+    /// Share of the distance moved per scroll line.
+    pub zoom_speed: f32,
+    pub rotate_button: MouseButton,
+    pub pan_button: MouseButton,
+    /// How quickly the view catches up with the input (see `damp_factor`).
+    pub smoothing: f32,
+    /// Where the view is on its way to the fields above: (target, distance, yaw, pitch).
+    current: (Vec3, f32, f32, f32),
+}
+
+impl OrbitController {
+    pub fn new(target: Vec3, distance: f32) -> Self {
+        Self {
+            target,
+            distance,
+            yaw: 0.0,
+            pitch: 0.0,
+            min_distance: 0.1,
+            max_distance: 1000.0,
+            rotate_speed: 0.005,
+            pan_speed: 0.0015,
+            zoom_speed: 0.1,
+            rotate_button: MouseButton::Left,
+            pan_button: MouseButton::Middle,
+            smoothing: 15.0,
+            current: (target, distance, 0.0, 0.0),
+        }
+    }
+
+    /// Orbits `camera.target` from where the camera is now.
+    pub fn from_camera(camera: &Camera) -> Self {
+        let offset = camera.target - camera.position;
+        let (yaw, pitch) = yaw_pitch(offset);
+        Self { yaw, pitch, ..Self::new(camera.target, offset.length()) }.snap()
+    }
+
+    /// Jumps straight to the fields' values instead of easing there.
+    pub fn snap(mut self) -> Self {
+        self.current = (self.target, self.distance, self.yaw, self.pitch);
+        self
+    }
+
+    pub fn with_buttons(mut self, rotate: MouseButton, pan: MouseButton) -> Self {
+        (self.rotate_button, self.pan_button) = (rotate, pan);
+        self
+    }
+
+    pub fn with_smoothing(mut self, smoothing: f32) -> Self {
+        self.smoothing = smoothing;
+        self
+    }
+}
+
+impl CameraController for OrbitController {
+    fn update(&mut self, camera: &mut Camera, input: &InputState, dt: f32) {
+        let delta = input.mouse_delta();
+        if input.is_button_down(self.rotate_button) {
+            self.yaw += delta.x * self.rotate_speed;
+            self.pitch -= delta.y * self.rotate_speed;
+        }
+        self.pitch = self.pitch.clamp(-PITCH_LIMIT, PITCH_LIMIT);
+        if input.is_button_down(self.pan_button) {
+            let forward = look_direction(self.yaw, self.pitch);
+            let right = forward.cross(Vec3::Y).normalize();
+            let up = right.cross(forward);
+            self.target += (up * delta.y - right * delta.x) * self.pan_speed * self.distance;
+        }
+        self.distance = (self.distance * (1.0 - self.zoom_speed).powf(input.scroll()))
+            .clamp(self.min_distance, self.max_distance);
+
+        let (t, d, y, p) = self.current;
+        let k = damp_factor(self.smoothing, dt);
+        let (target, distance, yaw, pitch) =
+            (t.lerp(self.target, k), d + (self.distance - d) * k, y + (self.yaw - y) * k, p + (self.pitch - p) * k);
+        self.current = (target, distance, yaw, pitch);
+        camera.target = target;
+        camera.position = target - look_direction(yaw, pitch) * distance;
+        camera.up = Vec3::Y;
+    }
+}
+
+/// Free flight: thrust along the view with the move keys, drifting to a stop when let go.
+#[derive(Clone, Debug)]
+pub struct FlyController {
+    pub position: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Units per second squared.
+    pub acceleration: f32,
+    /// Per second; top speed is `acceleration / damping`.
+    pub damping: f32,
+    /// Multiplies the acceleration while the boost key is held.
+    pub boost: f32,
+    /// Radians per pixel.
+    pub look_speed: f32,
+    pub look_button: Option<MouseButton>,
+    pub keys: MoveKeys,
+    velocity: Vec3,
+}
+
+impl FlyController {
+    pub fn new(position: Vec3, yaw: f32, pitch: f32) -> Self {
+        Self {
+            position,
+            yaw,
+            pitch,
+            acceleration: 40.0,
+            damping: 8.0,
+            boost: 4.0,
+            look_speed: 0.003,
+            look_button: Some(MouseButton::Right),
+            keys: MoveKeys::default(),
+            velocity: Vec3::ZERO,
+        }
+    }
+
+    pub fn from_camera(camera: &Camera) -> Self {
+        let (yaw, pitch) = yaw_pitch(camera.target - camera.position);
+        Self::new(camera.position, yaw, pitch)
+    }
+
+    pub fn with_look_button(mut self, button: Option<MouseButton>) -> Self {
+        self.look_button = button;
+        self
+    }
+
+    pub fn velocity(&self) -> Vec3 {
+        self.velocity
+    }
+}
+
+impl CameraController for FlyController {
+    fn update(&mut self, camera: &mut Camera, input: &InputState, dt: f32) {
+        let look = look_delta(input, self.look_button) * self.look_speed;
+        self.yaw += look.x;
+        self.pitch = (self.pitch - look.y).clamp(-PITCH_LIMIT, PITCH_LIMIT);
+
+        let forward = look_direction(self.yaw, self.pitch);
+        let right = forward.cross(Vec3::Y).normalize();
+        let k = &self.keys;
+        let wish = (forward * input.axis(k.back, k.forward) + right * input.axis(k.left, k.right)
+            + Vec3::Y * input.axis(k.down, k.up))
+            .normalize_or_zero();
+        let boost = if input.is_down(k.boost) { self.boost } else { 1.0 };
+        let accel = wish * self.acceleration * boost;
+
+        // dv/dt = accel - damping * v, integrated exactly over `dt`.
+        if self.damping > 0.0 {
+            let terminal = accel / self.damping;
+            let decay = (-self.damping * dt).exp();
+            self.position += terminal * dt + (self.velocity - terminal) * (1.0 - decay) / self.damping;
+            self.velocity = terminal + (self.velocity - terminal) * decay;
+        } else {
+            self.position += self.velocity * dt + accel * (0.5 * dt * dt);
+            self.velocity += accel * dt;
+        }
+
+        camera.position = self.position;
+        camera.target = self.position + forward;
+        camera.up = Vec3::Y;
+    }
+}
+
+/// Walks on the horizontal plane at a steady speed; looking is smoothed and can't go past
+/// `max_pitch`.
+#[derive(Clone, Debug)]
+pub struct FirstPersonController {
+    pub position: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Units per second.
+    pub speed: f32,
+    pub boost: f32,
+    pub look_speed: f32,
+    pub look_button: Option<MouseButton>,
+    /// Radians above or below the horizon.
+    pub max_pitch: f32,
+    /// How quickly the view catches up with the mouse (see `damp_factor`).
+    pub smoothing: f32,
+    pub keys: MoveKeys,
+    /// Smoothed (yaw, pitch).
+    look: (f32, f32),
+}
+
+impl FirstPersonController {
+    pub fn new(position: Vec3, yaw: f32) -> Self {
+        Self {
+            position,
+            yaw,
+            pitch: 0.0,
+            speed: 4.0,
+            boost: 2.0,
+            look_speed: 0.003,
+            look_button: Some(MouseButton::Right),
+            max_pitch: 85f32.to_radians(),
+            smoothing: 25.0,
+            keys: MoveKeys::default(),
+            look: (yaw, 0.0),
+        }
+    }
+
+    pub fn from_camera(camera: &Camera) -> Self {
+        let (yaw, pitch) = yaw_pitch(camera.target - camera.position);
+        let mut fp = Self::new(camera.position, yaw);
+        fp.pitch = pitch.clamp(-fp.max_pitch, fp.max_pitch);
+        fp.snap()
+    }
+
+    /// Looks straight at `yaw`/`pitch` instead of easing there.
+    pub fn snap(mut self) -> Self {
+        self.look = (self.yaw, self.pitch);
+        self
+    }
+
+    pub fn with_look_button(mut self, button: Option<MouseButton>) -> Self {
+        self.look_button = button;
+        self
+    }
+}
+
+impl CameraController for FirstPersonController {
+    fn update(&mut self, camera: &mut Camera, input: &InputState, dt: f32) {
+        let look = look_delta(input, self.look_button) * self.look_speed;
+        self.yaw += look.x;
+        let max_pitch = self.max_pitch.min(PITCH_LIMIT);
+        self.pitch = (self.pitch - look.y).clamp(-max_pitch, max_pitch);
+
+        let (y, p) = self.look;
+        let k = damp_factor(self.smoothing, dt);
+        let (yaw, pitch) = (y + (self.yaw - y) * k, p + (self.pitch - p) * k);
+        self.look = (yaw, pitch);
+
+        // Heading only, so looking down doesn't slow walking.
+        let forward = look_direction(yaw, 0.0);
+        let right = forward.cross(Vec3::Y);
+        let k = &self.keys;
+        let dir = (forward * input.axis(k.back, k.forward) + right * input.axis(k.left, k.right)).normalize_or_zero();
+        let boost = if input.is_down(k.boost) { self.boost } else { 1.0 };
+        self.position += dir * self.speed * boost * dt;
+
+        camera.position = self.position;
+        camera.target = self.position + look_direction(yaw, pitch);
+        camera.up = Vec3::Y;
+    }
+}
@@ -0,0 +1,14 @@
+//! A sparse-set entity-component system: each component type lives in its own packed array
+//! indexed by entity, so adding and removing components is cheap and queries walk the smallest
+//! array they need.
+
+mod entity;
+mod query;
+mod schedule;
+mod storage;
+mod world;
+
+pub use entity::Entity;
+pub use query::{Added, Changed, Mut, Query, QueryData, QueryFilter, With, Without};
+pub use schedule::{Schedule, Stage, System};
+pub use world::{Bundle, World};
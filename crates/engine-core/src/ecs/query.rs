@@ -0,0 +1,300 @@
+//! Typed access to the entities having a set of components. Queries borrow the storages they
+//! touch for as long as they run, so a query reading and writing the same component type panics,
+//! as does touching that type through the world from inside `for_each`.
+
+use std::cell::{Ref, RefMut};
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+use super::entity::Entity;
+use super::storage::SparseSet;
+use super::world::World;
+
+/// A component borrowed for writing; writing through it marks the component changed.
+pub struct Mut<'a, T> {
+    pub(crate) value: &'a mut T,
+    pub(crate) changed: &'a mut u32,
+    pub(crate) tick: u32,
+}
+
+impl<T> Deref for Mut<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T> DerefMut for Mut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        *self.changed = self.tick;
+        self.value
+    }
+}
+
+/// What a query fetches per entity: `&T`, `&mut T` (as `Mut<T>`), `Option` of either, or a
+/// tuple of those.
+pub trait QueryData {
+    /// The storages, borrowed for the query's run.
+    type Guard<'w>;
+    type Item<'g>;
+    fn borrow(world: &World) -> Self::Guard<'_>;
+    /// Entities of the smallest storage this needs, or `None` if it needs none.
+    fn candidates<'a>(guard: &'a Self::Guard<'_>) -> Option<&'a [Entity]>;
+    fn matches(guard: &Self::Guard<'_>, entity: Entity) -> bool;
+    /// Only for entities that match.
+    fn fetch<'g>(guard: &'g mut Self::Guard<'_>, entity: Entity, tick: u32) -> Self::Item<'g>;
+}
+
+/// Which entities a query keeps, beyond having its data: `With`, `Without`, `Added`, `Changed`,
+/// or a tuple of those, all of which have to pass.
+pub trait QueryFilter {
+    type Guard<'w>;
+    fn borrow(world: &World) -> Self::Guard<'_>;
+    /// `since` is the tick changes count from (`World::last_change_tick`).
+    fn matches(guard: &Self::Guard<'_>, entity: Entity, since: u32) -> bool;
+}
+
+fn read<T: 'static>(world: &World) -> Option<Ref<'_, SparseSet<T>>> {
+    world.storage::<T>().map(|cell| Ref::map(cell.borrow(), |s| s.as_any().downcast_ref().expect("storage type")))
+}
+
+fn write<T: 'static>(world: &World) -> Option<RefMut<'_, SparseSet<T>>> {
+    world.storage::<T>().map(|cell| RefMut::map(cell.borrow_mut(), |s| s.as_any_mut().downcast_mut().expect("storage type")))
+}
+
+impl<T: 'static> QueryData for &T {
+    type Guard<'w> = Option<Ref<'w, SparseSet<T>>>;
+    type Item<'g> = &'g T;
+
+    fn borrow(world: &World) -> Self::Guard<'_> {
+        read::<T>(world)
+    }
+
+    fn candidates<'a>(guard: &'a Self::Guard<'_>) -> Option<&'a [Entity]> {
+        Some(guard.as_ref().map_or(&[], |s| &s.entities))
+    }
+
+    fn matches(guard: &Self::Guard<'_>, entity: Entity) -> bool {
+        guard.as_ref().is_some_and(|s| s.dense(entity).is_some())
+    }
+
+    fn fetch<'g>(guard: &'g mut Self::Guard<'_>, entity: Entity, _tick: u32) -> &'g T {
+        let set = guard.as_ref().expect("matched");
+        &set.data[set.dense(entity).expect("matched")]
+    }
+}
+
+impl<T: 'static> QueryData for &mut T {
+    type Guard<'w> = Option<RefMut<'w, SparseSet<T>>>;
+    type Item<'g> = Mut<'g, T>;
+
+    fn borrow(world: &World) -> Self::Guard<'_> {
+        write::<T>(world)
+    }
+
+    fn candidates<'a>(guard: &'a Self::Guard<'_>) -> Option<&'a [Entity]> {
+        Some(guard.as_ref().map_or(&[], |s| &s.entities))
+    }
+
+    fn matches(guard: &Self::Guard<'_>, entity: Entity) -> bool {
+        guard.as_ref().is_some_and(|s| s.dense(entity).is_some())
+    }
+
+    fn fetch<'g>(guard: &'g mut Self::Guard<'_>, entity: Entity, tick: u32) -> Mut<'g, T> {
+        let set = &mut **guard.as_mut().expect("matched");
+        let d = set.dense(entity).expect("matched");
+        Mut { value: &mut set.data[d], changed: &mut set.ticks[d].changed, tick }
+    }
+}
+
+impl<Q: QueryData> QueryData for Option<Q> {
+    type Guard<'w> = Q::Guard<'w>;
+    type Item<'g> = Option<Q::Item<'g>>;
+
+    fn borrow(world: &World) -> Self::Guard<'_> {
+        Q::borrow(world)
+    }
+
+    fn candidates<'a>(_guard: &'a Self::Guard<'_>) -> Option<&'a [Entity]> {
+        None
+    }
+
+    fn matches(_guard: &Self::Guard<'_>, _entity: Entity) -> bool {
+        true
+    }
+
+    fn fetch<'g>(guard: &'g mut Self::Guard<'_>, entity: Entity, tick: u32) -> Self::Item<'g> {
+        Q::matches(guard, entity).then(|| Q::fetch(guard, entity, tick))
+    }
+}
+
+macro_rules! tuple_data {
+    ($($q:ident $i:tt),+) => {
+        impl<$($q: QueryData),+> QueryData for ($($q,)+) {
+            type Guard<'w> = ($($q::Guard<'w>,)+);
+            type Item<'g> = ($($q::Item<'g>,)+);
+
+            fn borrow(world: &World) -> Self::Guard<'_> {
+                ($($q::borrow(world),)+)
+            }
+
+            fn candidates<'a>(guard: &'a Self::Guard<'_>) -> Option<&'a [Entity]> {
+                [$($q::candidates(&guard.$i)),+].into_iter().flatten().min_by_key(|c| c.len())
+            }
+
+            fn matches(guard: &Self::Guard<'_>, entity: Entity) -> bool {
+                $($q::matches(&guard.$i, entity))&&+
+            }
+
+            fn fetch<'g>(guard: &'g mut Self::Guard<'_>, entity: Entity, tick: u32) -> Self::Item<'g> {
+                ($($q::fetch(&mut guard.$i, entity, tick),)+)
+            }
+        }
+    };
+}
+
+tuple_data!(A 0);
+tuple_data!(A 0, B 1);
+tuple_data!(A 0, B 1, C 2);
+tuple_data!(A 0, B 1, C 2, D 3);
+tuple_data!(A 0, B 1, C 2, D 3, E 4);
+tuple_data!(A 0, B 1, C 2, D 3, E 4, F 5);
+
+/// Has a `T`.
+pub struct With<T>(PhantomData<T>);
+/// Has no `T`.
+pub struct Without<T>(PhantomData<T>);
+/// Got its `T` since the last change tick.
+pub struct Added<T>(PhantomData<T>);
+/// Got or changed its `T` since the last change tick.
+pub struct Changed<T>(PhantomData<T>);
+
+impl<T: 'static> QueryFilter for With<T> {
+    type Guard<'w> = Option<Ref<'w, SparseSet<T>>>;
+
+    fn borrow(world: &World) -> Self::Guard<'_> {
+        read::<T>(world)
+    }
+
+    fn matches(guard: &Self::Guard<'_>, entity: Entity, _since: u32) -> bool {
+        guard.as_ref().is_some_and(|s| s.dense(entity).is_some())
+    }
+}
+
+impl<T: 'static> QueryFilter for Without<T> {
+    type Guard<'w> = Option<Ref<'w, SparseSet<T>>>;
+
+    fn borrow(world: &World) -> Self::Guard<'_> {
+        read::<T>(world)
+    }
+
+    fn matches(guard: &Self::Guard<'_>, entity: Entity, _since: u32) -> bool {
+        guard.as_ref().is_none_or(|s| s.dense(entity).is_none())
+    }
+}
+
+impl<T: 'static> QueryFilter for Added<T> {
+    type Guard<'w> = Option<Ref<'w, SparseSet<T>>>;
+
+    fn borrow(world: &World) -> Self::Guard<'_> {
+        read::<T>(world)
+    }
+
+    fn matches(guard: &Self::Guard<'_>, entity: Entity, since: u32) -> bool {
+        guard.as_ref().and_then(|s| Some(s.ticks[s.dense(entity)?].added > since)).unwrap_or(false)
+    }
+}
+
+impl<T: 'static> QueryFilter for Changed<T> {
+    type Guard<'w> = Option<Ref<'w, SparseSet<T>>>;
+
+    fn borrow(world: &World) -> Self::Guard<'_> {
+        read::<T>(world)
+    }
+
+    fn matches(guard: &Self::Guard<'_>, entity: Entity, since: u32) -> bool {
+        guard.as_ref().and_then(|s| Some(s.ticks[s.dense(entity)?].changed > since)).unwrap_or(false)
+    }
+}
+
+impl QueryFilter for () {
+    type Guard<'w> = ();
+
+    fn borrow(_world: &World) -> Self::Guard<'_> {}
+
+    fn matches(_guard: &Self::Guard<'_>, _entity: Entity, _since: u32) -> bool {
+        true
+    }
+}
+
+macro_rules! tuple_filter {
+    ($($f:ident $i:tt),+) => {
+        impl<$($f: QueryFilter),+> QueryFilter for ($($f,)+) {
+            type Guard<'w> = ($($f::Guard<'w>,)+);
+
+            fn borrow(world: &World) -> Self::Guard<'_> {
+                ($($f::borrow(world),)+)
+            }
+
+            fn matches(guard: &Self::Guard<'_>, entity: Entity, since: u32) -> bool {
+                $($f::matches(&guard.$i, entity, since))&&+
+            }
+        }
+    };
+}
+
+tuple_filter!(A 0);
+tuple_filter!(A 0, B 1);
+tuple_filter!(A 0, B 1, C 2);
+tuple_filter!(A 0, B 1, C 2, D 3);
+
+/// From `World::query`/`World::query_filtered`. Nothing is borrowed until it runs.
+pub struct Query<'w, Q: QueryData, F: QueryFilter = ()> {
+    world: &'w World,
+    _marker: PhantomData<(Q, F)>,
+}
+
+impl<'w, Q: QueryData, F: QueryFilter> Query<'w, Q, F> {
+    pub(crate) fn new(world: &'w World) -> Self {
+        Self { world, _marker: PhantomData }
+    }
+
+    /// Matching entities, in storage order.
+    pub fn entities(&self) -> Vec<Entity> {
+        // Data and filter borrow apart, so `Changed<T>` goes with `&mut T`.
+        let mut entities = {
+            let guard = Q::borrow(self.world);
+            match Q::candidates(&guard) {
+                Some(c) => c.iter().copied().filter(|&e| Q::matches(&guard, e)).collect(),
+                None => self.world.entities().filter(|&e| Q::matches(&guard, e)).collect::<Vec<_>>(),
+            }
+        };
+        let guard = F::borrow(self.world);
+        let since = self.world.last_change_tick();
+        entities.retain(|&e| F::matches(&guard, e, since));
+        entities
+    }
+
+    pub fn count(&self) -> usize {
+        self.entities().len()
+    }
+
+    pub fn for_each(&self, mut f: impl for<'g> FnMut(Entity, Q::Item<'g>)) {
+        let entities = self.entities();
+        let mut guard = Q::borrow(self.world);
+        let tick = self.world.change_tick();
+        for e in entities {
+            f(e, Q::fetch(&mut guard, e, tick));
+        }
+    }
+
+    /// Runs `f` on `entity`'s data if it matches.
+    pub fn get<R>(&self, entity: Entity, f: impl for<'g> FnOnce(Q::Item<'g>) -> R) -> Option<R> {
+        let since = self.world.last_change_tick();
+        if !F::matches(&F::borrow(self.world), entity, since) || !self.world.is_alive(entity) {
+            return None;
+        }
+        let mut guard = Q::borrow(self.world);
+        Q::matches(&guard, entity).then(|| f(Q::fetch(&mut guard, entity, self.world.change_tick())))
+    }
+}
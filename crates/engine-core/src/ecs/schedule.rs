@@ -0,0 +1,80 @@
+use super::world::World;
+
+/// When in a frame a system runs; stages run in this order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Stage {
+    First,
+    PreUpdate,
+    Update,
+    PostUpdate,
+    Last,
+}
+
+pub trait System {
+    fn name(&self) -> &str;
+    fn run(&mut self, world: &mut World);
+}
+
+struct FnSystem<F> {
+    name: String,
+    f: F,
+}
+
+impl<F: FnMut(&mut World)> System for FnSystem<F> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn run(&mut self, world: &mut World) {
+        (self.f)(world)
+    }
+}
+
+struct Entry {
+    stage: Stage,
+    system: Box<dyn System>,
+    last_run: u32,
+}
+
+/// Systems by stage, each stage's in the order they were added.
+///
+/// Every system sees as `Added`/`Changed` what happened since it last ran, its deferred commands
+/// are applied right after it, and the change tick moves on between systems.
+#[derive(Default)]
+pub struct Schedule {
+    systems: Vec<Entry>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_system(&mut self, stage: Stage, name: impl Into<String>, f: impl FnMut(&mut World) + 'static) -> &mut Self {
+        self.add_boxed(stage, Box::new(FnSystem { name: name.into(), f }))
+    }
+
+    pub fn add_boxed(&mut self, stage: Stage, system: Box<dyn System>) -> &mut Self {
+        let at = self.systems.partition_point(|e| e.stage <= stage);
+        self.systems.insert(at, Entry { stage, system, last_run: 0 });
+        self
+    }
+
+    /// System names in run order.
+    pub fn systems(&self) -> impl Iterator<Item = (Stage, &str)> {
+        self.systems.iter().map(|e| (e.stage, e.system.name()))
+    }
+
+    /// Leaves `World::last_change_tick` as it found it.
+    pub fn run(&mut self, world: &mut World) {
+        let last_change_tick = world.last_change_tick();
+        for entry in &mut self.systems {
+            world.set_last_change_tick(entry.last_run);
+            entry.system.run(world);
+            world.apply_deferred();
+            entry.last_run = world.change_tick();
+            world.increment_change_tick();
+        }
+        world.set_last_change_tick(last_change_tick);
+    }
+}
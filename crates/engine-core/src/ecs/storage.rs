@@ -0,0 +1,85 @@
+use std::any::Any;
+
+use super::entity::Entity;
+
+/// Ticks a component was added and last changed at (see `World::change_tick`).
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ComponentTicks {
+    pub added: u32,
+    pub changed: u32,
+}
+
+const EMPTY: u32 = u32::MAX;
+
+/// One component type's values, packed, with a sparse index from entity slots into them.
+/// Public only so queries can name it in their guards.
+pub struct SparseSet<T> {
+    sparse: Vec<u32>,
+    pub(crate) entities: Vec<Entity>,
+    pub(crate) data: Vec<T>,
+    pub(crate) ticks: Vec<ComponentTicks>,
+}
+
+impl<T> Default for SparseSet<T> {
+    fn default() -> Self {
+        Self { sparse: Vec::new(), entities: Vec::new(), data: Vec::new(), ticks: Vec::new() }
+    }
+}
+
+impl<T> SparseSet<T> {
+    /// Where `entity`'s value is in `data`.
+    pub(crate) fn dense(&self, entity: Entity) -> Option<usize> {
+        let d = *self.sparse.get(entity.index() as usize)?;
+        (d != EMPTY && self.entities[d as usize] == entity).then_some(d as usize)
+    }
+
+    /// Replacing a value counts as a change, not an addition.
+    pub(crate) fn insert(&mut self, entity: Entity, value: T, tick: u32) {
+        if let Some(d) = self.dense(entity) {
+            self.data[d] = value;
+            self.ticks[d].changed = tick;
+            return;
+        }
+        let i = entity.index() as usize;
+        if self.sparse.len() <= i {
+            self.sparse.resize(i + 1, EMPTY);
+        }
+        self.sparse[i] = self.data.len() as u32;
+        self.entities.push(entity);
+        self.data.push(value);
+        self.ticks.push(ComponentTicks { added: tick, changed: tick });
+    }
+
+    pub(crate) fn remove(&mut self, entity: Entity) -> Option<T> {
+        let d = self.dense(entity)?;
+        self.sparse[entity.index() as usize] = EMPTY;
+        self.entities.swap_remove(d);
+        self.ticks.swap_remove(d);
+        let value = self.data.swap_remove(d);
+        if let Some(moved) = self.entities.get(d) {
+            self.sparse[moved.index() as usize] = d as u32;
+        }
+        Some(value)
+    }
+}
+
+/// A `SparseSet` of any type.
+pub(crate) trait Storage: Any {
+    fn remove_entity(&mut self, entity: Entity);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> Storage for SparseSet<T> {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,235 @@
+use std::any::{Any, TypeId};
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::HashMap;
+
+use super::entity::{Entities, Entity};
+use super::query::{Mut, Query, QueryData, QueryFilter};
+use super::storage::{SparseSet, Storage};
+
+/// Components spawned together: any tuple of up to eight `'static` values.
+pub trait Bundle: 'static {
+    fn insert_into(self, world: &mut World, entity: Entity);
+}
+
+macro_rules! tuple_bundle {
+    ($($t:ident $i:tt),*) => {
+        impl<$($t: 'static),*> Bundle for ($($t,)*) {
+            #[allow(unused_variables)]
+            fn insert_into(self, world: &mut World, entity: Entity) {
+                $(world.insert(entity, self.$i);)*
+            }
+        }
+    };
+}
+
+tuple_bundle!();
+tuple_bundle!(A 0);
+tuple_bundle!(A 0, B 1);
+tuple_bundle!(A 0, B 1, C 2);
+tuple_bundle!(A 0, B 1, C 2, D 3);
+tuple_bundle!(A 0, B 1, C 2, D 3, E 4);
+tuple_bundle!(A 0, B 1, C 2, D 3, E 4, F 5);
+tuple_bundle!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+tuple_bundle!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+
+type Command = Box<dyn FnOnce(&mut World)>;
+
+/// Entities, their components (one sparse set per type) and resources (one value per type).
+///
+/// Changes are tracked with ticks: a component counts as added or changed for `Added`/`Changed`
+/// filters if that happened after `last_change_tick`. A `Schedule` moves that per system, so each
+/// system sees what happened since it last ran; without one, `clear_trackers` moves it by hand.
+pub struct World {
+    entities: Entities,
+    storages: HashMap<TypeId, RefCell<Box<dyn Storage>>>,
+    resources: HashMap<TypeId, RefCell<Box<dyn Any>>>,
+    deferred: RefCell<Vec<Command>>,
+    change_tick: u32,
+    last_change_tick: u32,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self {
+            entities: Entities::default(),
+            storages: HashMap::new(),
+            resources: HashMap::new(),
+            deferred: RefCell::new(Vec::new()),
+            change_tick: 1,
+            last_change_tick: 0,
+        }
+    }
+
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> Entity {
+        let entity = self.entities.alloc();
+        bundle.insert_into(self, entity);
+        entity
+    }
+
+    /// Drops its components. Whether it was alive.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.entities.free(entity) {
+            return false;
+        }
+        for storage in self.storages.values_mut() {
+            storage.get_mut().remove_entity(entity);
+        }
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.entities.contains(entity)
+    }
+
+    /// The living entity with this `Entity::index`.
+    pub fn entity_at(&self, index: u32) -> Option<Entity> {
+        self.entities.at(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entities.iter()
+    }
+
+    /// Adds or replaces `entity`'s `T`. Whether it's alive.
+    pub fn insert<T: 'static>(&mut self, entity: Entity, value: T) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+        let tick = self.change_tick;
+        self.set_mut::<T>().insert(entity, value, tick);
+        true
+    }
+
+    pub fn remove<T: 'static>(&mut self, entity: Entity) -> Option<T> {
+        self.storages
+            .get_mut(&TypeId::of::<T>())?
+            .get_mut()
+            .as_any_mut()
+            .downcast_mut::<SparseSet<T>>()
+            .expect("storage type")
+            .remove(entity)
+    }
+
+    pub fn has<T: 'static>(&self, entity: Entity) -> bool {
+        self.get::<T>(entity).is_some()
+    }
+
+    pub fn get<T: 'static>(&self, entity: Entity) -> Option<Ref<'_, T>> {
+        let set = Ref::map(self.storage::<T>()?.borrow(), |s| s.as_any().downcast_ref::<SparseSet<T>>().expect("storage type"));
+        Ref::filter_map(set, |s| s.dense(entity).map(|d| &s.data[d])).ok()
+    }
+
+    /// Marks it changed if written through.
+    pub fn get_mut<T: 'static>(&mut self, entity: Entity) -> Option<Mut<'_, T>> {
+        let tick = self.change_tick;
+        let set = self.storages.get_mut(&TypeId::of::<T>())?.get_mut().as_any_mut().downcast_mut::<SparseSet<T>>().expect("storage type");
+        let d = set.dense(entity)?;
+        Some(Mut { value: &mut set.data[d], changed: &mut set.ticks[d].changed, tick })
+    }
+
+    pub fn query<Q: QueryData>(&self) -> Query<'_, Q> {
+        Query::new(self)
+    }
+
+    pub fn query_filtered<Q: QueryData, F: QueryFilter>(&self) -> Query<'_, Q, F> {
+        Query::new(self)
+    }
+
+    /// Adds or replaces the `R` resource, returning the old one.
+    pub fn insert_resource<R: 'static>(&mut self, value: R) -> Option<R> {
+        let old = self.resources.insert(TypeId::of::<R>(), RefCell::new(Box::new(value)))?;
+        old.into_inner().downcast().ok().map(|r| *r)
+    }
+
+    pub fn remove_resource<R: 'static>(&mut self) -> Option<R> {
+        let old = self.resources.remove(&TypeId::of::<R>())?;
+        old.into_inner().downcast().ok().map(|r| *r)
+    }
+
+    pub fn has_resource<R: 'static>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<R>())
+    }
+
+    /// Panics if it's borrowed mutably.
+    pub fn resource<R: 'static>(&self) -> Option<Ref<'_, R>> {
+        let cell = self.resources.get(&TypeId::of::<R>())?;
+        Some(Ref::map(cell.borrow(), |r| r.downcast_ref().expect("resource type")))
+    }
+
+    /// Panics if it's borrowed.
+    pub fn resource_mut<R: 'static>(&self) -> Option<RefMut<'_, R>> {
+        let cell = self.resources.get(&TypeId::of::<R>())?;
+        Some(RefMut::map(cell.borrow_mut(), |r| r.downcast_mut().expect("resource type")))
+    }
+
+    /// Queues `f` to run on the world at the next `apply_deferred`, for spawning, despawning and
+    /// inserting from inside a query.
+    pub fn defer(&self, f: impl FnOnce(&mut World) + 'static) {
+        self.deferred.borrow_mut().push(Box::new(f));
+    }
+
+    /// Runs what was deferred, in order, including anything deferred meanwhile.
+    pub fn apply_deferred(&mut self) {
+        loop {
+            let commands = std::mem::take(self.deferred.get_mut());
+            if commands.is_empty() {
+                return;
+            }
+            for command in commands {
+                command(self);
+            }
+        }
+    }
+
+    /// Stamped on components as they're added or changed.
+    pub fn change_tick(&self) -> u32 {
+        self.change_tick
+    }
+
+    /// `Added`/`Changed` filters match what happened after this.
+    pub fn last_change_tick(&self) -> u32 {
+        self.last_change_tick
+    }
+
+    /// Makes everything up to now count as seen.
+    pub fn clear_trackers(&mut self) {
+        self.last_change_tick = self.change_tick;
+        self.change_tick += 1;
+    }
+
+    pub(crate) fn set_last_change_tick(&mut self, tick: u32) {
+        self.last_change_tick = tick;
+    }
+
+    pub(crate) fn increment_change_tick(&mut self) {
+        self.change_tick += 1;
+    }
+
+    pub(crate) fn storage<T: 'static>(&self) -> Option<&RefCell<Box<dyn Storage>>> {
+        self.storages.get(&TypeId::of::<T>())
+    }
+
+    fn set_mut<T: 'static>(&mut self) -> &mut SparseSet<T> {
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| RefCell::new(Box::new(SparseSet::<T>::default())))
+            .get_mut()
+            .as_any_mut()
+            .downcast_mut()
+            .expect("storage type")
+    }
+}
@@ -0,0 +1,92 @@
+//! View frusta, extracted from a view-projection matrix, and what's inside them.
+
+use glam::{Mat4, Vec2, Vec3, Vec4};
+
+use crate::bounds::BoundingVolume;
+use crate::camera::Camera;
+
+/// `normal·p + d = 0`; the normal points to the positive side.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plane {
+    pub normal: Vec3,
+    pub d: f32,
+}
+
+impl Plane {
+    /// Normalized so `distance` is in world units. Without a normal, everything ends up on the
+    /// side `w` is on, infinitely far.
+    pub fn from_vec4(v: Vec4) -> Self {
+        let len = v.truncate().length();
+        if len == 0.0 {
+            return Self { normal: Vec3::ZERO, d: f32::INFINITY.copysign(v.w) };
+        }
+        let v = v / len;
+        Self { normal: v.truncate(), d: v.w }
+    }
+
+    /// Signed, positive on the side the normal points to.
+    pub fn distance(&self, p: Vec3) -> f32 {
+        self.normal.dot(p) + self.d
+    }
+}
+
+/// Where a volume is relative to a frustum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Intersection {
+    Outside,
+    Intersecting,
+    Inside,
+}
+
+/// Six planes facing inwards: left, right, bottom, top, near, far.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    pub planes: [Plane; 6],
+}
+
+impl Frustum {
+    /// For 0..1 depth, which covers reverse-Z, infinite far planes and oblique near planes too.
+    pub fn from_view_proj(m: Mat4) -> Self {
+        let (r0, r1, r2, r3) = (m.row(0), m.row(1), m.row(2), m.row(3));
+        Self {
+            planes: [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r2, r3 - r2].map(Plane::from_vec4),
+        }
+    }
+
+    pub fn from_camera(camera: &Camera, viewport: Vec2) -> Self {
+        Self::from_view_proj(camera.view_proj_viewport(viewport))
+    }
+
+    pub fn contains_point(&self, p: Vec3) -> bool {
+        self.planes.iter().all(|plane| plane.distance(p) >= 0.0)
+    }
+
+    /// Conservative: volumes near a corner may be `Intersecting` while just outside.
+    pub fn classify(&self, volume: &impl BoundingVolume) -> Intersection {
+        let center = volume.center();
+        let mut result = Intersection::Inside;
+        for plane in &self.planes {
+            let distance = plane.distance(center);
+            let radius = volume.radius_along(plane.normal);
+            if distance < -radius {
+                return Intersection::Outside;
+            }
+            if distance < radius {
+                result = Intersection::Intersecting;
+            }
+        }
+        result
+    }
+
+    pub fn intersects(&self, volume: &impl BoundingVolume) -> bool {
+        self.classify(volume) != Intersection::Outside
+    }
+
+    /// Drops the `items` whose world-space volume is outside; those without one are kept.
+    /// Returns how many went.
+    pub fn cull<T, V: BoundingVolume>(&self, items: &mut Vec<T>, volume: impl Fn(&T) -> Option<V>) -> usize {
+        let before = items.len();
+        items.retain(|item| volume(item).is_none_or(|v| self.intersects(&v)));
+        before - items.len()
+    }
+}
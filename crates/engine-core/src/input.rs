@@ -0,0 +1,94 @@
+use std::collections::HashSet;
+
+use glam::Vec2;
+
+/// Keys the engine knows by name; platforms map their own key codes onto these.
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub enum Key {
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Up, Down, Left, Right,
+    Space, Shift, Ctrl, Alt, Tab, Enter, Escape,
+}
+
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub enum MouseButton { Left, Right, Middle }
+
+/// What's held this frame plus what moved since the last; the platform layer feeds it events
+/// and calls `end_frame` once everything has read it.
+#[derive(Clone, Debug, Default)]
+pub struct InputState {
+    keys: HashSet<Key>,
+    buttons: HashSet<MouseButton>,
+    cursor: Option<Vec2>,
+    mouse_delta: Vec2,
+    scroll: f32,
+}
+
+impl InputState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_key(&mut self, key: Key, down: bool) {
+        if down { self.keys.insert(key); } else { self.keys.remove(&key); }
+    }
+
+    pub fn set_button(&mut self, button: MouseButton, down: bool) {
+        if down { self.buttons.insert(button); } else { self.buttons.remove(&button); }
+    }
+
+    /// Cursor position in pixels; movement since the previous position adds to `mouse_delta`.
+    pub fn set_cursor(&mut self, position: Vec2) {
+        if let Some(prev) = self.cursor {
+            self.mouse_delta += position - prev;
+        }
+        self.cursor = Some(position);
+    }
+
+    /// Raw mouse motion in pixels, for platforms that report it apart from the cursor.
+    pub fn add_mouse_delta(&mut self, delta: Vec2) {
+        self.mouse_delta += delta;
+    }
+
+    /// In lines (notches); positive scrolls up / away from the user.
+    pub fn add_scroll(&mut self, lines: f32) {
+        self.scroll += lines;
+    }
+
+    /// Releases everything, e.g. when the window loses focus and won't see the key-ups.
+    pub fn release_all(&mut self) {
+        self.keys.clear();
+        self.buttons.clear();
+    }
+
+    pub fn is_down(&self, key: Key) -> bool {
+        self.keys.contains(&key)
+    }
+
+    pub fn is_button_down(&self, button: MouseButton) -> bool {
+        self.buttons.contains(&button)
+    }
+
+    pub fn cursor(&self) -> Option<Vec2> {
+        self.cursor
+    }
+
+    pub fn mouse_delta(&self) -> Vec2 {
+        self.mouse_delta
+    }
+
+    pub fn scroll(&self) -> f32 {
+        self.scroll
+    }
+
+    /// -1, 0 or 1 from a pair of opposing keys.
+    pub fn axis(&self, negative: Key, positive: Key) -> f32 {
+        f32::from(u8::from(self.is_down(positive))) - f32::from(u8::from(self.is_down(negative)))
+    }
+
+    /// Clears the per-frame deltas.
+    pub fn end_frame(&mut self) {
+        self.mouse_delta = Vec2::ZERO;
+        self.scroll = 0.0;
+    }
+}
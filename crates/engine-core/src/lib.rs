@@ -1,2 +1,16 @@
+pub mod bounds;
 pub mod camera;
+pub mod controller;
+pub mod ecs;
+pub mod frustum;
+pub mod input;
+pub mod ray;
+pub mod transform;
+pub use bounds::{Aabb, BoundingVolume, Obb, Sphere};
 pub use camera::{Camera, CameraUBO};
+pub use controller::{CameraController, FirstPersonController, FlyController, MoveKeys, OrbitController};
+pub use ecs::{Entity, World};
+pub use frustum::{Frustum, Intersection, Plane};
+pub use input::{InputState, Key, MouseButton};
+pub use ray::{MeshHit, Ray, TriangleHit};
+pub use transform::Transform;
@@ -0,0 +1,104 @@
+//! Rays, mostly from the cursor, and what they hit.
+
+use glam::{Mat4, Vec2, Vec3};
+
+use crate::bounds::Aabb;
+use crate::camera::Camera;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    /// Not necessarily unit length; distances `t` are in multiples of it.
+    pub dir: Vec3,
+}
+
+/// A ray hitting a triangle at `ray.at(t)`, `u` and `v` of the way along its second and third
+/// corners.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TriangleHit {
+    pub t: f32,
+    pub u: f32,
+    pub v: f32,
+}
+
+/// The closest triangle a ray hits in a mesh; `triangle` counts from the first index (or vertex).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MeshHit {
+    pub triangle: usize,
+    pub hit: TriangleHit,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, dir: Vec3) -> Self {
+        Self { origin, dir }
+    }
+
+    /// Through pixel `screen` (from the top-left, as `Camera::world_to_screen` gives it),
+    /// starting on the near plane with a unit direction. Parallel rays for orthographic cameras.
+    pub fn from_screen(camera: &Camera, screen: Vec2, viewport: Vec2) -> Self {
+        let near = if camera.reverse_z { 1.0 } else { 0.0 };
+        let origin = camera.screen_to_world(screen, near, viewport);
+        // Halfway is finite even with an infinite far plane.
+        let further = camera.screen_to_world(screen, 0.5, viewport);
+        Self::new(origin, (further - origin).normalize())
+    }
+
+    pub fn at(&self, t: f32) -> Vec3 {
+        self.origin + self.dir * t
+    }
+
+    /// Into the space `transform` maps to. `t` means the same point on either ray, so hits on
+    /// the result apply to this one as they are.
+    pub fn transformed(&self, transform: &Mat4) -> Ray {
+        Ray::new(transform.transform_point3(self.origin), transform.transform_vector3(self.dir))
+    }
+
+    /// Where the ray enters the box; 0 when it starts inside.
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        let inv = self.dir.recip();
+        let (t0, t1) = ((aabb.min - self.origin) * inv, (aabb.max - self.origin) * inv);
+        let near = t0.min(t1).max_element().max(0.0);
+        let far = t0.max(t1).min_element();
+        (near <= far).then_some(near)
+    }
+
+    /// Both faces; hits behind the origin don't count.
+    pub fn intersect_triangle(&self, a: Vec3, b: Vec3, c: Vec3) -> Option<TriangleHit> {
+        // Möller–Trumbore.
+        let (e1, e2) = (b - a, c - a);
+        let p = self.dir.cross(e2);
+        let det = e1.dot(p);
+        if det.abs() < f32::EPSILON * e1.length_squared().max(e2.length_squared()) {
+            return None;
+        }
+        let s = (self.origin - a) / det;
+        let u = s.dot(p);
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = s.cross(e1);
+        let v = self.dir.dot(q);
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = e2.dot(q);
+        (t >= 0.0).then_some(TriangleHit { t, u, v })
+    }
+
+    /// Closest hit on a triangle list placed by `transform`; without `indices` every three
+    /// positions make a triangle.
+    pub fn intersect_mesh(&self, positions: &[Vec3], indices: Option<&[u32]>, transform: &Mat4) -> Option<MeshHit> {
+        let local = self.transformed(&transform.inverse());
+        let corner = |i: usize| match indices {
+            Some(idx) => positions.get(*idx.get(i)? as usize).copied(),
+            None => positions.get(i).copied(),
+        };
+        let count = indices.map_or(positions.len(), <[u32]>::len) / 3;
+        (0..count)
+            .filter_map(|tri| {
+                let (a, b, c) = (corner(tri * 3)?, corner(tri * 3 + 1)?, corner(tri * 3 + 2)?);
+                Some(MeshHit { triangle: tri, hit: local.intersect_triangle(a, b, c)? })
+            })
+            .min_by(|x, y| x.hit.t.total_cmp(&y.hit.t))
+    }
+}
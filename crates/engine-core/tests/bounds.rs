@@ -0,0 +1,93 @@
+use engine_core::{Aabb, BoundingVolume, Camera, Frustum, Intersection, Obb, Sphere};
+use glam::{Mat3, Mat4, Quat, Vec2, Vec3};
+
+const VIEWPORT: Vec2 = Vec2::new(800.0, 600.0);
+
+/// Looking down -Z from the origin, 60° vertically, from 0.1 to 100.
+fn camera() -> Camera {
+    Camera::new(Vec3::ZERO, Vec3::NEG_Z)
+}
+
+#[test]
+fn frustum_classifies_volumes() {
+    let cameras = [
+        camera(),
+        camera().with_reverse_z(true),
+        camera().with_reverse_z(true).with_infinite_far(),
+        Camera::orthographic(Vec3::ZERO, Vec3::NEG_Z, 20.0),
+    ];
+    for camera in &cameras {
+        let frustum = Frustum::from_camera(camera, VIEWPORT);
+        let sphere = |x: f32, z: f32, r: f32| frustum.classify(&Sphere::new(Vec3::new(x, 0.0, z), r));
+        assert_eq!(sphere(0.0, -10.0, 1.0), Intersection::Inside);
+        assert_eq!(sphere(0.0, 5.0, 1.0), Intersection::Outside, "behind");
+        assert_eq!(sphere(0.0, 0.5, 1.0), Intersection::Intersecting, "across the near plane");
+        assert_eq!(sphere(40.0, -10.0, 1.0), Intersection::Outside, "off to the side");
+        assert!(frustum.contains_point(Vec3::new(0.0, 0.0, -50.0)));
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, 1.0)));
+    }
+    let finite = Frustum::from_camera(&camera(), VIEWPORT);
+    let infinite = Frustum::from_camera(&camera().with_infinite_far(), VIEWPORT);
+    let far = Aabb::from_center_half_extents(Vec3::new(0.0, 0.0, -500.0), Vec3::ONE);
+    assert_eq!(finite.classify(&far), Intersection::Outside);
+    assert_eq!(infinite.classify(&far), Intersection::Inside);
+}
+
+#[test]
+fn oriented_boxes_are_tighter_than_their_aabb() {
+    // A long thin box along the right side of the view, just outside: its AABB pokes in.
+    let frustum = Frustum::from_camera(&camera(), Vec2::splat(100.0));
+    let local = Aabb::from_center_half_extents(Vec3::ZERO, Vec3::new(3.0, 0.05, 0.05));
+    let outward = Vec3::new(30f32.to_radians().cos(), 0.0, 30f32.to_radians().sin());
+    let on_edge = Vec3::new(10.0 * 30f32.to_radians().tan(), 0.0, -10.0);
+    let transform = Mat4::from_rotation_translation(Quat::from_rotation_y(60f32.to_radians()), on_edge + outward * 0.3);
+    assert!(frustum.intersects(&local.transformed(&transform)));
+    assert!(!frustum.intersects(&Obb::from_aabb(&local, &transform)));
+
+    let obb = Obb::from_aabb(&local, &transform);
+    let aabb = local.transformed(&transform);
+    for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+        assert!((obb.radius_along(axis) - aabb.radius_along(axis)).abs() < 1e-5);
+    }
+    assert!(obb.contains_point(transform.transform_point3(Vec3::new(1.9, 0.0, 0.0))));
+    assert!(!obb.contains_point(transform.transform_point3(Vec3::new(0.0, 0.1, 0.0))));
+}
+
+#[test]
+fn volume_pair_tests() {
+    let unit = Aabb::from_center_half_extents(Vec3::ZERO, Vec3::ONE);
+    assert!(unit.intersects(&Aabb::from_center_half_extents(Vec3::new(1.9, 0.0, 0.0), Vec3::ONE)));
+    assert!(!unit.intersects(&Aabb::from_center_half_extents(Vec3::new(2.1, 0.0, 0.0), Vec3::ONE)));
+    // Past the corner along the diagonal but within reach of its faces' planes.
+    assert!(!unit.intersects_sphere(&Sphere::new(Vec3::splat(1.5), 0.8)));
+    assert!(unit.intersects_sphere(&Sphere::new(Vec3::new(1.5, 0.0, 0.0), 0.6)));
+
+    let points = [Vec3::new(-1.0, 0.0, 0.0), Vec3::new(3.0, 1.0, 0.0), Vec3::new(1.0, -1.0, 2.0)];
+    let sphere = Sphere::from_points(points).expect("points");
+    assert!(points.iter().all(|&p| sphere.contains_point(p + (sphere.center - p) * 1e-4)));
+    assert_eq!(Aabb::from_points([]), None);
+
+    // Two turned cubes whose AABBs overlap while they don't.
+    let diamond = |x: f32| {
+        let rotation = Mat3::from_rotation_z(45f32.to_radians()) * Mat3::from_rotation_x(45f32.to_radians());
+        Obb::new(Vec3::new(x, 0.0, 0.0), rotation)
+    };
+    assert!(diamond(0.0).intersects(&diamond(1.5)));
+    assert!(!diamond(0.0).intersects(&diamond(3.0)));
+    let aabb = |x: f32| Aabb::from_center_half_extents(Vec3::new(x, 0.0, 0.0), Vec3::splat(diamond(0.0).radius_along(Vec3::X)));
+    assert!(aabb(0.0).intersects(&aabb(3.0)));
+}
+
+#[test]
+fn cull_drops_only_bounded_items_out_of_view() {
+    let frustum = Frustum::from_camera(&camera(), VIEWPORT);
+    let mut items = vec![
+        Some(Vec3::new(0.0, 0.0, -5.0)),
+        Some(Vec3::new(0.0, 0.0, 5.0)),
+        None,
+        Some(Vec3::new(0.0, 100.0, -5.0)),
+    ];
+    let culled = frustum.cull(&mut items, |p| p.map(|c| Sphere::new(c, 0.5)));
+    assert_eq!(culled, 2);
+    assert_eq!(items, [Some(Vec3::new(0.0, 0.0, -5.0)), None]);
+}
@@ -0,0 +1,86 @@
+use engine_core::camera::Projection;
+use engine_core::Camera;
+use glam::{Mat4, Vec2, Vec3, Vec4};
+
+const VIEWPORT: Vec2 = Vec2::new(800.0, 600.0);
+
+fn assert_close(a: Vec3, b: Vec3, eps: f32) {
+    assert!(a.distance(b) < eps, "{a} vs {b}");
+}
+
+#[test]
+fn perspective_matches_the_usual_matrix() {
+    let camera = Camera::new(Vec3::new(1.0, 2.0, 3.0), Vec3::ZERO);
+    let expected = Mat4::perspective_rh(camera.fov_y, 800.0 / 600.0, camera.z_near, camera.z_far);
+    assert!(camera.projection_viewport(VIEWPORT).abs_diff_eq(expected, 1e-5));
+    assert!(camera.projection(800.0 / 600.0).abs_diff_eq(expected, 1e-5));
+
+    // A symmetric frustum is the same thing.
+    let h = camera.z_near * (camera.fov_y * 0.5).tan();
+    let w = h * 800.0 / 600.0;
+    let frustum = camera.clone().with_projection(Projection::Frustum { left: -w, right: w, bottom: -h, top: h });
+    assert!(frustum.projection_viewport(VIEWPORT).abs_diff_eq(expected, 1e-5));
+}
+
+#[test]
+fn screen_conversions_round_trip() {
+    let eye = Vec3::new(3.0, 4.0, 10.0);
+    let cameras = [
+        Camera::new(eye, Vec3::ZERO),
+        Camera::new(eye, Vec3::ZERO).with_reverse_z(true).with_infinite_far(),
+        Camera::new(eye, Vec3::ZERO).with_zoom(2.0).with_origin(Vec2::new(0.25, 0.75)),
+        Camera::orthographic(eye, Vec3::ZERO, 10.0).with_reverse_z(true),
+        Camera::new(eye, Vec3::ZERO).with_projection(Projection::Frustum { left: -0.02, right: 0.1, bottom: -0.05, top: 0.05 }),
+        Camera::pixel_2d(Vec2::new(40.0, 30.0)),
+    ];
+    for (i, camera) in cameras.iter().enumerate() {
+        let point = Vec3::new(0.5, -0.25, 1.0);
+        let screen = camera.world_to_screen(point, VIEWPORT).unwrap_or_else(|| panic!("camera {i}"));
+        assert!((0.0..=1.0).contains(&screen.z), "camera {i}: {screen}");
+        assert_close(camera.screen_to_world(screen.truncate(), screen.z, VIEWPORT), point, 1e-3);
+    }
+    assert_eq!(cameras[0].world_to_screen(Vec3::new(6.0, 8.0, 20.0), VIEWPORT), None, "behind the camera");
+}
+
+#[test]
+fn origin_and_zoom_place_the_view() {
+    let camera = Camera::orthographic(Vec3::new(0.0, 0.0, 5.0), Vec3::ZERO, 10.0);
+    let top = camera.world_to_screen(Vec3::new(0.0, 5.0, 0.0), VIEWPORT).expect("in view");
+    assert_close(top, Vec3::new(400.0, 0.0, top.z), 1e-3);
+
+    // The view axis at the top-left corner.
+    let corner = camera.clone().with_origin(Vec2::ZERO).world_to_screen(Vec3::ZERO, VIEWPORT).expect("in view");
+    assert_close(corner.truncate().extend(0.0), Vec3::ZERO, 1e-3);
+
+    let zoomed = camera.with_zoom(2.0).world_to_screen(Vec3::new(0.0, 2.5, 0.0), VIEWPORT).expect("in view");
+    assert_close(zoomed.truncate().extend(0.0), Vec3::new(400.0, 0.0, 0.0), 1e-3);
+}
+
+#[test]
+fn pixel_mode_is_one_unit_per_pixel() {
+    // Odd sizes and fractional positions still land texels on whole pixels.
+    let viewport = Vec2::new(801.0, 601.0);
+    let camera = Camera::pixel_2d(Vec2::new(100.3, 50.0));
+    let a = camera.world_to_screen(Vec3::new(110.0, 50.0, 0.0), viewport).expect("in view");
+    let b = camera.world_to_screen(Vec3::new(111.0, 47.0, 0.0), viewport).expect("in view");
+    assert!((b.x - a.x - 1.0).abs() < 1e-3 && (b.y - a.y - 3.0).abs() < 1e-3, "{a} {b}");
+    assert!((a.x - a.x.round()).abs() < 1e-3 && (a.y - a.y.round()).abs() < 1e-3, "{a}");
+
+    let zoomed = Camera::pixel_2d(Vec2::ZERO).with_zoom(3.0);
+    let a = zoomed.world_to_screen(Vec3::ZERO, viewport).expect("in view");
+    let b = zoomed.world_to_screen(Vec3::new(1.0, 0.0, 0.0), viewport).expect("in view");
+    assert!((b.x - a.x - 3.0).abs() < 1e-3);
+}
+
+#[test]
+fn clip_plane_replaces_the_near_plane() {
+    // A mirror at z = 0 seen from z = 5: only what's beyond it is kept.
+    let camera = Camera::new(Vec3::new(0.0, 0.0, 5.0), Vec3::ZERO).with_clip_plane(Some(Vec4::new(0.0, 0.0, -1.0, 0.0)));
+    for reverse in [false, true] {
+        let camera = camera.clone().with_reverse_z(reverse);
+        let depth = |z: f32| camera.world_to_screen(Vec3::new(0.0, 0.0, z), VIEWPORT).expect("in view").z;
+        let inside = |d: f32| (0.0..=1.0).contains(&d);
+        assert!(inside(depth(-1.0)), "{}", depth(-1.0));
+        assert!(!inside(depth(1.0)), "{}", depth(1.0));
+    }
+}
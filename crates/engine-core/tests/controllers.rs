@@ -0,0 +1,83 @@
+use engine_core::{Camera, CameraController, FirstPersonController, FlyController, InputState, Key, MouseButton, OrbitController};
+use glam::{Vec2, Vec3};
+
+/// One second at `fps`, with `first` applied to the first frame's input and `held` to every frame's.
+fn run(controller: &mut impl CameraController, fps: u32, first: impl Fn(&mut InputState), held: impl Fn(&mut InputState)) -> Camera {
+    let mut camera = Camera::new(Vec3::new(0.0, 0.0, 5.0), Vec3::ZERO);
+    let mut input = InputState::new();
+    for frame in 0..fps {
+        held(&mut input);
+        if frame == 0 {
+            first(&mut input);
+        }
+        controller.update(&mut camera, &input, 1.0 / fps as f32);
+        input.end_frame();
+    }
+    camera
+}
+
+fn assert_close(a: Vec3, b: Vec3, eps: f32) {
+    assert!(a.distance(b) < eps, "{a} vs {b}");
+}
+
+#[test]
+fn orbit_smoothing_is_frame_rate_independent() {
+    let camera = Camera::new(Vec3::new(0.0, 0.0, 5.0), Vec3::ZERO);
+    let drag = |input: &mut InputState| {
+        input.add_mouse_delta(Vec2::new(100.0, -40.0));
+        input.add_scroll(2.0);
+    };
+    let hold = |input: &mut InputState| input.set_button(MouseButton::Left, true);
+    let mut results = Vec::new();
+    for fps in [5, 30, 144] {
+        let mut orbit = OrbitController::from_camera(&camera).with_smoothing(3.0);
+        results.push(run(&mut orbit, fps, drag, hold));
+    }
+    // Same gap left after a second, whatever the steps.
+    for cam in &results[1..] {
+        assert_close(cam.position, results[0].position, 1e-3);
+    }
+    // Dragging turns the world with the mouse, so the camera went left and down, and closer in.
+    let settled = run(&mut OrbitController::from_camera(&camera).with_smoothing(f32::INFINITY), 1, drag, hold);
+    assert!(settled.position.x < 0.0 && settled.position.y < 0.0);
+    assert!((settled.position.length() - 5.0 * 0.81).abs() < 1e-3, "{}", settled.position);
+    // Still catching up at the lowest rate.
+    assert!(results[0].position.distance(settled.position) > 0.01);
+}
+
+#[test]
+fn fly_accelerates_and_drifts_to_a_stop() {
+    let forward = |input: &mut InputState| input.set_key(Key::W, true);
+    let mut positions = Vec::new();
+    for fps in [10, 60, 240] {
+        let mut fly = FlyController::new(Vec3::ZERO, 0.0, 0.0);
+        let cam = run(&mut fly, fps, |_| {}, forward);
+        assert!((fly.velocity().length() - fly.acceleration / fly.damping).abs() < 0.01);
+        positions.push(cam.position);
+    }
+    for p in &positions[1..] {
+        assert_close(*p, positions[0], 1e-3);
+    }
+    assert!(positions[0].z < -3.0, "{}", positions[0]);
+
+    let mut fly = FlyController::new(Vec3::ZERO, 0.0, 0.0);
+    run(&mut fly, 60, |_| {}, forward);
+    run(&mut fly, 60, |_| {}, |_| {});
+    assert!(fly.velocity().length() < 0.01);
+
+    // Looks only while the button is down.
+    let mut fly = FlyController::new(Vec3::ZERO, 0.0, 0.0);
+    run(&mut fly, 1, |input| input.add_mouse_delta(Vec2::new(200.0, 0.0)), |_| {});
+    assert_eq!(fly.yaw, 0.0);
+}
+
+#[test]
+fn first_person_clamps_pitch_and_walks_level() {
+    let mut fp = FirstPersonController::new(Vec3::ZERO, 0.0).with_look_button(None);
+    let look_down = |input: &mut InputState| input.add_mouse_delta(Vec2::new(0.0, 10_000.0));
+    let cam = run(&mut fp, 60, look_down, |input| input.set_key(Key::W, true));
+    assert_eq!(fp.pitch, -fp.max_pitch);
+    assert!((cam.target - cam.position).y < -0.9);
+    // Walked a second's worth straight ahead, not into the ground.
+    assert_close(cam.position, Vec3::new(0.0, 0.0, -fp.speed), 1e-3);
+}
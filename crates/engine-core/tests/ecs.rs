@@ -0,0 +1,129 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use engine_core::ecs::{Added, Changed, Entity, Schedule, Stage, With, Without, World};
+use engine_core::Transform;
+use glam::Vec3;
+
+#[derive(Debug, PartialEq)]
+struct Health(i32);
+struct Enemy;
+
+#[test]
+fn despawned_handles_go_stale() {
+    let mut world = World::new();
+    let a = world.spawn((Health(3), Enemy));
+    let b = world.spawn(());
+    assert_eq!(world.len(), 2);
+    assert_eq!(*world.get::<Health>(a).unwrap(), Health(3));
+    assert!(world.has::<Enemy>(a) && !world.has::<Enemy>(b));
+
+    assert!(world.despawn(a));
+    assert!(!world.despawn(a), "already gone");
+    assert!(world.get::<Health>(a).is_none());
+    let c = world.spawn((Health(5),));
+    assert_eq!(c.index(), a.index(), "slot reused");
+    assert_ne!(c, a);
+    assert!(!world.is_alive(a) && world.is_alive(c));
+    assert!(!world.insert(a, Enemy), "stale handle");
+    assert!(!world.has::<Enemy>(c), "components didn't survive the old entity");
+    assert_eq!(world.entity_at(a.index()), Some(c));
+    assert_eq!(Entity::from_bits(c.to_bits()), c);
+
+    assert_eq!(world.remove::<Health>(c), Some(Health(5)));
+    assert!(world.is_alive(c));
+}
+
+#[test]
+fn queries_filter_and_fetch_optional_components() {
+    let mut world = World::new();
+    let player = world.spawn((Health(10), Transform::from_translation(Vec3::X)));
+    let grunt = world.spawn((Health(3), Enemy));
+    let boss = world.spawn((Health(50), Enemy, Transform::IDENTITY));
+    world.spawn((Transform::IDENTITY,));
+
+    assert_eq!(world.query::<&Health>().count(), 3);
+    assert_eq!(world.query_filtered::<&Health, With<Enemy>>().entities(), [grunt, boss]);
+    assert_eq!(world.query_filtered::<&Health, Without<Enemy>>().entities(), [player]);
+    assert_eq!(world.query::<(&Health, &Transform)>().entities(), [player, boss]);
+
+    let mut seen = Vec::new();
+    world.query::<(&Health, Option<&Transform>)>().for_each(|e, (h, t)| seen.push((e, h.0, t.is_some())));
+    assert_eq!(seen, [(player, 10, true), (grunt, 3, false), (boss, 50, true)]);
+
+    world.query_filtered::<&mut Health, With<Enemy>>().for_each(|_, mut h| h.0 -= 1);
+    assert_eq!(world.get::<Health>(grunt).unwrap().0, 2);
+    assert_eq!(world.get::<Health>(player).unwrap().0, 10);
+    assert_eq!(world.query::<&Health>().get(boss, |h| h.0), Some(49));
+    assert_eq!(world.query_filtered::<&Health, Without<Enemy>>().get(boss, |h| h.0), None);
+}
+
+#[test]
+fn systems_see_changes_since_they_last_ran() {
+    let mut world = World::new();
+    let a = world.spawn((Health(1),));
+    let log = Rc::new(RefCell::new(Vec::new()));
+
+    let mut schedule = Schedule::new();
+    let seen = log.clone();
+    schedule.add_system(Stage::PostUpdate, "watch", move |world| {
+        let added = world.query_filtered::<&Health, Added<Health>>().count();
+        let changed = world.query_filtered::<&Health, Changed<Health>>().count();
+        seen.borrow_mut().push((added, changed));
+    });
+    schedule.add_system(Stage::Update, "heal", move |world| {
+        world.query_filtered::<&mut Health, Without<Enemy>>().for_each(|_, mut h| h.0 += 1);
+    });
+
+    schedule.run(&mut world);
+    assert_eq!(log.borrow().last(), Some(&(1, 1)), "added before the first run");
+    world.insert(a, Enemy);
+    schedule.run(&mut world);
+    assert_eq!(log.borrow().last(), Some(&(0, 0)), "enemies aren't healed");
+    world.get_mut::<Health>(a).unwrap().0 = 7;
+    schedule.run(&mut world);
+    assert_eq!(log.borrow().last(), Some(&(0, 1)));
+    // Reading through `get_mut` isn't a change.
+    assert_eq!(world.get_mut::<Health>(a).unwrap().0, 7);
+    schedule.run(&mut world);
+    assert_eq!(log.borrow().last(), Some(&(0, 0)));
+}
+
+#[test]
+fn stages_resources_and_deferred_commands() {
+    struct Spawned(Vec<Entity>);
+
+    let mut world = World::new();
+    world.insert_resource(Spawned(Vec::new()));
+    world.spawn((Health(0),));
+    let order = Rc::new(RefCell::new(Vec::new()));
+    let mut schedule = Schedule::new();
+    for (stage, name) in [(Stage::Last, "last"), (Stage::First, "first"), (Stage::Update, "update")] {
+        let order = order.clone();
+        schedule.add_system(stage, name, move |_| order.borrow_mut().push(name));
+    }
+    schedule.add_system(Stage::Update, "split", |world| {
+        world.query::<&Health>().for_each(|e, h| {
+            let hp = h.0;
+            world.defer(move |world| {
+                let spawned = world.spawn((Health(hp + 1),));
+                world.resource_mut::<Spawned>().unwrap().0.push(spawned);
+                world.despawn(e);
+            });
+        });
+    });
+    assert_eq!(
+        schedule.systems().map(|(_, name)| name).collect::<Vec<_>>(),
+        ["first", "update", "split", "last"]
+    );
+
+    schedule.run(&mut world);
+    schedule.run(&mut world);
+    assert_eq!(*order.borrow(), ["first", "update", "last", "first", "update", "last"]);
+    assert_eq!(world.len(), 1);
+    let spawned = world.resource::<Spawned>().unwrap().0.clone();
+    assert_eq!(spawned.len(), 2);
+    assert_eq!(world.get::<Health>(spawned[1]).unwrap().0, 2);
+    assert!(world.remove_resource::<Spawned>().is_some());
+    assert!(!world.has_resource::<Spawned>());
+}
@@ -0,0 +1,75 @@
+use engine_core::{Aabb, Camera, Ray};
+use glam::{Mat4, Vec2, Vec3};
+
+const VIEWPORT: Vec2 = Vec2::new(800.0, 600.0);
+
+fn assert_close(a: Vec3, b: Vec3, eps: f32) {
+    assert!(a.distance(b) < eps, "{a} vs {b}");
+}
+
+#[test]
+fn rays_from_the_cursor_go_through_what_it_points_at() {
+    let eye = Vec3::new(2.0, 3.0, 8.0);
+    let cameras = [
+        Camera::new(eye, Vec3::ZERO),
+        Camera::new(eye, Vec3::ZERO).with_reverse_z(true).with_infinite_far(),
+        Camera::orthographic(eye, Vec3::ZERO, 6.0),
+    ];
+    for camera in &cameras {
+        let point = Vec3::new(0.5, -1.0, 0.25);
+        let screen = camera.world_to_screen(point, VIEWPORT).expect("in view").truncate();
+        let ray = Ray::from_screen(camera, screen, VIEWPORT);
+        assert!((ray.dir.length() - 1.0).abs() < 1e-5);
+        let t = (point - ray.origin).dot(ray.dir);
+        assert!(t > 0.0);
+        assert_close(ray.at(t), point, 1e-3);
+    }
+    let center = Ray::from_screen(&cameras[0], VIEWPORT * 0.5, VIEWPORT);
+    assert_close(center.dir, -eye.normalize(), 1e-4);
+
+    // Orthographic rays are parallel.
+    let a = Ray::from_screen(&cameras[2], Vec2::ZERO, VIEWPORT);
+    let b = Ray::from_screen(&cameras[2], VIEWPORT, VIEWPORT);
+    assert_close(a.dir, b.dir, 1e-5);
+    assert!(a.origin.distance(b.origin) > 1.0);
+}
+
+#[test]
+fn ray_against_boxes_and_triangles() {
+    let unit = Aabb::from_center_half_extents(Vec3::ZERO, Vec3::ONE);
+    let ray = Ray::new(Vec3::new(0.5, 0.5, 5.0), Vec3::NEG_Z);
+    assert_eq!(ray.intersect_aabb(&unit), Some(4.0));
+    assert_eq!(Ray::new(Vec3::ZERO, Vec3::X).intersect_aabb(&unit), Some(0.0), "starts inside");
+    assert_eq!(Ray::new(Vec3::new(0.5, 0.5, 5.0), Vec3::Z).intersect_aabb(&unit), None, "behind");
+    assert_eq!(Ray::new(Vec3::new(2.0, 0.0, 5.0), Vec3::NEG_Z).intersect_aabb(&unit), None);
+
+    let (a, b, c) = (Vec3::new(0.0, 0.0, -2.0), Vec3::new(2.0, 0.0, -2.0), Vec3::new(0.0, 2.0, -2.0));
+    let hit = Ray::new(Vec3::new(0.5, 0.25, 0.0), Vec3::NEG_Z).intersect_triangle(a, b, c).expect("hit");
+    assert!((hit.t - 2.0).abs() < 1e-5 && (hit.u - 0.25).abs() < 1e-5 && (hit.v - 0.125).abs() < 1e-5, "{hit:?}");
+    // Either face, but not behind or beside.
+    assert!(Ray::new(Vec3::new(0.5, 0.25, -4.0), Vec3::Z).intersect_triangle(a, b, c).is_some());
+    assert!(Ray::new(Vec3::new(0.5, 0.25, -4.0), Vec3::NEG_Z).intersect_triangle(a, b, c).is_none());
+    assert!(Ray::new(Vec3::new(1.5, 1.5, 0.0), Vec3::NEG_Z).intersect_triangle(a, b, c).is_none());
+    assert!(Ray::new(Vec3::new(0.5, 0.25, -2.0), Vec3::X).intersect_triangle(a, b, c).is_none(), "edge on");
+}
+
+#[test]
+fn ray_against_a_placed_mesh_finds_the_closest_triangle() {
+    // Two quads facing +z, at z = 0 and z = -1, in the mesh's own space.
+    let positions = [
+        Vec3::new(-1.0, -1.0, 0.0), Vec3::new(1.0, -1.0, 0.0), Vec3::new(1.0, 1.0, 0.0), Vec3::new(-1.0, 1.0, 0.0),
+        Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, -1.0), Vec3::new(-1.0, 1.0, -1.0),
+    ];
+    let indices = [4, 5, 6, 4, 6, 7, 0, 1, 2, 0, 2, 3];
+    let transform = Mat4::from_scale_rotation_translation(Vec3::splat(2.0), glam::Quat::IDENTITY, Vec3::new(10.0, 0.0, 0.0));
+    let ray = Ray::new(Vec3::new(10.5, 1.0, 5.0), Vec3::NEG_Z);
+
+    let hit = ray.intersect_mesh(&positions, Some(&indices), &transform).expect("hit");
+    assert_eq!(hit.triangle, 3);
+    assert!((hit.hit.t - 5.0).abs() < 1e-4, "{hit:?}");
+    assert_close(ray.at(hit.hit.t), Vec3::new(10.5, 1.0, 0.0), 1e-4);
+
+    let unindexed: Vec<Vec3> = indices.iter().map(|&i| positions[i as usize]).collect();
+    assert_eq!(ray.intersect_mesh(&unindexed, None, &transform), Some(hit));
+    assert_eq!(ray.intersect_mesh(&positions, Some(&indices), &Mat4::IDENTITY), None);
+}
@@ -0,0 +1,327 @@
+use std::path::Path;
+
+use image::{ImageBuffer, Luma, Rgba32FImage, RgbaImage};
+
+pub type DepthImage = ImageBuffer<Luma<f32>, Vec<f32>>;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CaptureOptions {
+    pub depth: bool,
+}
+
+pub struct FrameCapture {
+    pub color: RgbaImage,
+    /// Format the color target had; sRGB formats are stored as-is, float formats get sRGB-encoded.
+    pub format: wgpu::TextureFormat,
+    pub depth: Option<DepthImage>,
+}
+
+#[derive(Debug)]
+pub enum CaptureError {
+    UnsupportedFormat(wgpu::TextureFormat),
+    Map(wgpu::BufferAsyncError),
+    Poll(wgpu::PollError),
+    Image(image::ImageError),
+    /// Window surfaces are gone after `present`; call `Renderer::request_capture` before rendering.
+    NotRecorded,
+    /// The texture wasn't created with `COPY_SRC`, as with surfaces that don't support it.
+    NotCopyable,
+    /// `save_depth_exr` on a capture taken without `CaptureOptions::depth`.
+    NoDepth,
+}
+
+impl std::fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CaptureError::UnsupportedFormat(fmt) => write!(f, "cannot capture texture format {fmt:?}"),
+            CaptureError::Map(e) => write!(f, "readback buffer mapping failed: {e}"),
+            CaptureError::Poll(e) => write!(f, "device poll failed: {e}"),
+            CaptureError::Image(e) => write!(f, "image encoding failed: {e}"),
+            CaptureError::NotRecorded => write!(f, "no frame was recorded for capture; call request_capture before rendering"),
+            CaptureError::NotCopyable => write!(f, "texture cannot be copied from; the surface does not support COPY_SRC"),
+            CaptureError::NoDepth => write!(f, "no depth was captured; capture with CaptureOptions::depth"),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+impl From<image::ImageError> for CaptureError {
+    fn from(e: image::ImageError) -> Self { CaptureError::Image(e) }
+}
+
+impl FrameCapture {
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), CaptureError> {
+        self.color.save_with_format(path, image::ImageFormat::Png)?;
+        Ok(())
+    }
+
+    /// EXR wants linear values, so sRGB-encoded pixels are decoded first.
+    pub fn save_exr(&self, path: impl AsRef<Path>) -> Result<(), CaptureError> {
+        let encoded = !matches!(self.format, wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Bgra8Unorm);
+        let to_linear = |c: u8| if encoded { srgb_to_linear(c) } else { c as f32 / 255.0 };
+        let linear = Rgba32FImage::from_fn(self.color.width(), self.color.height(), |x, y| {
+            let p = self.color.get_pixel(x, y).0;
+            image::Rgba([to_linear(p[0]), to_linear(p[1]), to_linear(p[2]), p[3] as f32 / 255.0])
+        });
+        linear.save_with_format(path, image::ImageFormat::OpenExr)?;
+        Ok(())
+    }
+
+    pub fn save_depth_exr(&self, path: impl AsRef<Path>) -> Result<(), CaptureError> {
+        let Some(depth) = &self.depth else { return Err(CaptureError::NoDepth) };
+        let rgb = image::Rgb32FImage::from_fn(depth.width(), depth.height(), |x, y| {
+            let d = depth.get_pixel(x, y).0[0];
+            image::Rgb([d, d, d])
+        });
+        rgb.save_with_format(path, image::ImageFormat::OpenExr)?;
+        Ok(())
+    }
+}
+
+/// Texture -> buffer copy recorded into an encoder, read back once the encoder is submitted.
+pub(crate) struct Readback {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    bytes_per_pixel: u32,
+    padded_bytes_per_row: u32,
+    format: wgpu::TextureFormat,
+}
+
+pub(crate) fn encode_readback(
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    texture: &wgpu::Texture,
+    aspect: wgpu::TextureAspect,
+) -> Result<Readback, CaptureError> {
+    if !texture.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+        return Err(CaptureError::NotCopyable);
+    }
+    // Depth32FloatStencil8's depth aspect copies out as plain Depth32Float.
+    let format = texture.format().aspect_specific_format(aspect).unwrap_or(texture.format());
+    let bytes_per_pixel = format
+        .block_copy_size(Some(aspect))
+        .ok_or(CaptureError::UnsupportedFormat(format))?;
+    let (width, height) = (texture.width(), texture.height());
+
+    let unpadded = width * bytes_per_pixel;
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded_bytes_per_row = unpadded.div_ceil(align) * align;
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("readback"),
+        size: padded_bytes_per_row as u64 * height as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    encoder.copy_texture_to_buffer(
+        wgpu::TexelCopyTextureInfo {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect,
+        },
+        wgpu::TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(height),
+            },
+        },
+        wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+    );
+
+    Ok(Readback { buffer, width, height, bytes_per_pixel, padded_bytes_per_row, format })
+}
+
+impl Readback {
+    /// Maps the buffer and strips the row padding. Blocks until the GPU is done.
+    fn read(self, device: &wgpu::Device) -> Result<(Vec<u8>, u32, u32, wgpu::TextureFormat), CaptureError> {
+        let slice = self.buffer.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |res| { let _ = tx.send(res); });
+        device.poll(wgpu::PollType::Wait).map_err(CaptureError::Poll)?;
+        rx.recv().expect("map_async callback dropped").map_err(CaptureError::Map)?;
+
+        let row = (self.width * self.bytes_per_pixel) as usize;
+        let mut out = Vec::with_capacity(row * self.height as usize);
+        {
+            let data = slice.get_mapped_range();
+            for chunk in data.chunks(self.padded_bytes_per_row as usize).take(self.height as usize) {
+                out.extend_from_slice(&chunk[..row]);
+            }
+        }
+        self.buffer.unmap();
+        Ok((out, self.width, self.height, self.format))
+    }
+
+    pub(crate) fn into_color(self, device: &wgpu::Device) -> Result<RgbaImage, CaptureError> {
+        let (bytes, width, height, format) = self.read(device)?;
+        use wgpu::TextureFormat as F;
+        let rgba = match format {
+            F::Rgba8Unorm | F::Rgba8UnormSrgb => bytes,
+            F::Bgra8Unorm | F::Bgra8UnormSrgb => {
+                let mut b = bytes;
+                b.chunks_exact_mut(4).for_each(|px| px.swap(0, 2));
+                b
+            }
+            F::Rgba16Float => bytes
+                .chunks_exact(2)
+                .enumerate()
+                .map(|(i, h)| {
+                    let v = half::f16::from_le_bytes([h[0], h[1]]).to_f32();
+                    let v = if i % 4 == 3 { v } else { linear_to_srgb(v) };
+                    (v.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+                })
+                .collect(),
+            other => return Err(CaptureError::UnsupportedFormat(other)),
+        };
+        Ok(RgbaImage::from_raw(width, height, rgba).expect("readback size mismatch"))
+    }
+
+    pub(crate) fn into_depth(self, device: &wgpu::Device) -> Result<DepthImage, CaptureError> {
+        let (bytes, width, height, format) = self.read(device)?;
+        // Rgba8Unorm is the packed copy from `encode_depth_readback`, same bytes as Depth32Float.
+        if !matches!(format, wgpu::TextureFormat::Depth32Float | wgpu::TextureFormat::Rgba8Unorm) {
+            return Err(CaptureError::UnsupportedFormat(format));
+        }
+        let data: Vec<f32> = bytemuck::pod_collect_to_vec(&bytes);
+        Ok(DepthImage::from_raw(width, height, data).expect("readback size mismatch"))
+    }
+}
+
+/// Depth readback. Where the adapter can't copy depth textures to buffers (GL), the depth
+/// bits are first packed into an `Rgba8Unorm` texture and that one is read back instead. The same
+/// goes for multisampled depth, which can't be copied at all; it reads back sample 0. Either way
+/// `depth` has to be bindable.
+pub(crate) fn encode_depth_readback(
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    depth: &wgpu::Texture,
+    downlevel: wgpu::DownlevelFlags,
+) -> Result<Readback, CaptureError> {
+    let multisampled = depth.sample_count() > 1;
+    if !multisampled && downlevel.contains(wgpu::DownlevelFlags::DEPTH_TEXTURE_AND_BUFFER_COPIES) {
+        return encode_readback(device, encoder, depth, wgpu::TextureAspect::DepthOnly);
+    }
+
+    let target = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("depth_copy"),
+        size: depth.size(),
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+
+    // textureLoad's last argument is the mip level for one, the sample index for the other.
+    let wgsl = if multisampled {
+        DEPTH_COPY_WGSL.replace("texture_2d<f32>", "texture_multisampled_2d<f32>")
+    } else {
+        DEPTH_COPY_WGSL.to_string()
+    };
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("depth_copy"),
+        source: wgpu::ShaderSource::Wgsl(wgsl.into()),
+    });
+    let bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("depth_copy_bgl"),
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                // Plain float binding: GLSL has no textureLoad on depth samplers.
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled,
+            },
+            count: None,
+        }],
+    });
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("depth_copy_layout"),
+        bind_group_layouts: &[&bgl],
+        push_constant_ranges: &[],
+    });
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("depth_copy"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &module,
+            entry_point: Some("vs_main"),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &module,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::TextureFormat::Rgba8Unorm.into())],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    });
+
+    let depth_view = depth.create_view(&wgpu::TextureViewDescriptor {
+        aspect: wgpu::TextureAspect::DepthOnly,
+        ..Default::default()
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("depth_copy_bg"),
+        layout: &bgl,
+        entries: &[wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&depth_view) }],
+    });
+
+    {
+        let target_view = target.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("depth_copy"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &target_view,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: wgpu::StoreOp::Store },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rp.set_pipeline(&pipeline);
+        rp.set_bind_group(0, &bind_group, &[]);
+        rp.draw(0..3, 0..1);
+    }
+
+    encode_readback(device, encoder, &target, wgpu::TextureAspect::All)
+}
+
+const DEPTH_COPY_WGSL: &str = r#"
+@group(0) @binding(0) var depth_tex: texture_2d<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> @builtin(position) vec4<f32> {
+  let uv = vec2<f32>(f32((i << 1u) & 2u), f32(i & 2u));
+  return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+  let d = textureLoad(depth_tex, vec2<i32>(pos.xy), 0).r;
+  return unpack4x8unorm(bitcast<u32>(d));
+}
+"#;
+
+fn srgb_to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+}
@@ -1,12 +1,92 @@
 use winit::window::Window;
 
+use crate::msaa::MsaaError;
+use crate::types::GResult;
+
+/// Where frames end up: a window swapchain or a texture owned by the context.
+pub enum RenderTarget {
+    Surface(wgpu::Surface<'static>),
+    Offscreen(wgpu::Texture),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct HeadlessConfig {
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+    /// Skip hardware adapters and go straight for the software one (llvmpipe, WARP, ...).
+    pub force_fallback_adapter: bool,
+}
+
+impl Default for HeadlessConfig {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 720,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            force_fallback_adapter: false,
+        }
+    }
+}
+
+/// Color target acquired for one frame. For surfaces it has to be presented,
+/// offscreen frames simply stay in the context's texture.
+pub struct Frame {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    surface_texture: Option<wgpu::SurfaceTexture>,
+}
+
+impl Frame {
+    pub fn present(self) {
+        if let Some(st) = self.surface_texture {
+            st.present();
+        }
+    }
+}
+
 pub struct GfxContext {
     pub instance: wgpu::Instance,
-    pub surface: wgpu::Surface<'static>,
+    pub adapter: wgpu::Adapter,
+    pub target: RenderTarget,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub config: wgpu::SurfaceConfiguration,
+    pub depth_texture: wgpu::Texture,
     pub depth_view: wgpu::TextureView,
+    sample_count: u32,
+}
+
+impl GfxContext {
+    /// `DEPTH_STENCIL_FORMAT` where supported, otherwise `DEPTH_FORMAT`.
+    pub fn depth_format(&self) -> wgpu::TextureFormat {
+        self.depth_texture.format()
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// For a scene rendering into `color` (the surface format, or `HDR_FORMAT`).
+    pub fn supported_sample_counts(&self, color: wgpu::TextureFormat) -> Vec<u32> {
+        crate::msaa::supported_sample_counts(&self.adapter, &self.device, color, self.depth_format())
+    }
+
+    /// Recreates the depth target at `samples` per pixel. Pipelines have to match it.
+    pub fn set_sample_count(&mut self, samples: u32, color: wgpu::TextureFormat) -> Result<(), MsaaError> {
+        let supported = self.supported_sample_counts(color);
+        if !supported.contains(&samples) {
+            return Err(MsaaError::Unsupported { requested: samples, supported });
+        }
+        self.sample_count = samples;
+        self.create_depth();
+        Ok(())
+    }
+
+    fn create_depth(&mut self) {
+        let (width, height, backend) = (self.config.width, self.config.height, self.adapter.get_info().backend);
+        (self.depth_texture, self.depth_view) = crate::depth::create_depth(&self.device, width, height, self.sample_count, backend);
+    }
 }
 
 impl GfxContext {
@@ -26,22 +106,20 @@ impl GfxContext {
             force_fallback_adapter: false,
         })).expect("No adapter found");
 
-        let (device, queue) = pollster::block_on(adapter.request_device(
-            &wgpu::DeviceDescriptor {
-                label: Some("device"),
-                required_features: wgpu::Features::empty(),
-                required_limits: wgpu::Limits::default(),
-                memory_hints: Default::default(),
-                trace: wgpu::Trace::Off,
-            }
-        )).expect("request_device failed");
+        let (device, queue) = request_device(&adapter);
 
         let caps = surface.get_capabilities(&adapter);
         let format = caps.formats.iter().copied().find(|f| f.is_srgb()).unwrap_or(caps.formats[0]);
         let size = window.inner_size();
 
+        // COPY_SRC lets `Renderer::capture_frame` read the swapchain image back.
+        let mut usage = wgpu::TextureUsages::RENDER_ATTACHMENT;
+        if caps.usages.contains(wgpu::TextureUsages::COPY_SRC) {
+            usage |= wgpu::TextureUsages::COPY_SRC;
+        }
+
         let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage,
             format,
             width: size.width.max(1),
             height: size.height.max(1),
@@ -52,16 +130,135 @@ impl GfxContext {
         };
         surface.configure(&device, &config);
 
-        let depth_view = crate::depth::create_depth_view(&device, config.width, config.height);
+        let (depth_texture, depth_view) = crate::depth::create_depth(&device, config.width, config.height, 1, adapter.get_info().backend);
 
-        Self { instance, surface, device, queue, config, depth_view }
+        Self {
+            instance, adapter, target: RenderTarget::Surface(surface), device, queue, config,
+            depth_texture, depth_view, sample_count: 1,
+        }
+    }
+
+    /// Context without a window: frames are rendered into an owned texture.
+    /// Falls back to a software adapter when no hardware one is available.
+    pub fn new_headless(cfg: &HeadlessConfig) -> Self {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+
+        let request = |force_fallback_adapter| {
+            pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                compatible_surface: None,
+                force_fallback_adapter,
+            }))
+        };
+        let adapter = if cfg.force_fallback_adapter {
+            request(true)
+        } else {
+            request(false).or_else(|_| request(true))
+        }.expect("No adapter found");
+
+        let (device, queue) = request_device(&adapter);
+
+        // Not used for presenting, but keeps size/format in the same place as the windowed path.
+        let config = wgpu::SurfaceConfiguration {
+            usage: offscreen_usage(),
+            format: cfg.format,
+            width: cfg.width.max(1),
+            height: cfg.height.max(1),
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+
+        let texture = create_offscreen_texture(&device, &config);
+        let (depth_texture, depth_view) = crate::depth::create_depth(&device, config.width, config.height, 1, adapter.get_info().backend);
+
+        Self {
+            instance, adapter, target: RenderTarget::Offscreen(texture), device, queue, config,
+            depth_texture, depth_view, sample_count: 1,
+        }
+    }
+
+    pub fn is_headless(&self) -> bool {
+        matches!(self.target, RenderTarget::Offscreen(_))
     }
 
     pub fn resize(&mut self, width: u32, height: u32) {
         if width == 0 || height == 0 { return; }
         self.config.width = width;
         self.config.height = height;
-        self.surface.configure(&self.device, &self.config);
-        self.depth_view = crate::depth::create_depth_view(&self.device, width, height);
+        match &mut self.target {
+            RenderTarget::Surface(surface) => surface.configure(&self.device, &self.config),
+            RenderTarget::Offscreen(texture) => *texture = create_offscreen_texture(&self.device, &self.config),
+        }
+        self.create_depth();
     }
+
+    /// The offscreen color texture, if this context is headless.
+    pub fn offscreen_texture(&self) -> Option<&wgpu::Texture> {
+        match &self.target {
+            RenderTarget::Offscreen(texture) => Some(texture),
+            RenderTarget::Surface(_) => None,
+        }
+    }
+
+    pub fn acquire_frame(&self) -> GResult<Frame> {
+        match &self.target {
+            RenderTarget::Surface(surface) => {
+                let st = surface.get_current_texture()?;
+                let view = st.texture.create_view(&wgpu::TextureViewDescriptor::default());
+                Ok(Frame { texture: st.texture.clone(), view, surface_texture: Some(st) })
+            }
+            RenderTarget::Offscreen(texture) => {
+                let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                Ok(Frame { texture: texture.clone(), view, surface_texture: None })
+            }
+        }
+    }
+}
+
+fn request_device(adapter: &wgpu::Adapter) -> (wgpu::Device, wgpu::Queue) {
+    // Software and GL adapters don't always reach the default limits.
+    let required_limits = if wgpu::Limits::default().check_limits(&adapter.limits()) {
+        wgpu::Limits::default()
+    } else {
+        wgpu::Limits::downlevel_defaults().using_resolution(adapter.limits())
+    };
+
+    // Optional extras, taken where available since they can't be enabled after the fact: the
+    // driver pipeline cache, a depth format with stencil, line/point polygon modes, and the
+    // adapter's own format capabilities (MSAA counts beyond 1 and 4).
+    let wanted = wgpu::Features::PIPELINE_CACHE
+        | wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+        | wgpu::Features::DEPTH32FLOAT_STENCIL8
+        | wgpu::Features::POLYGON_MODE_LINE
+        | wgpu::Features::POLYGON_MODE_POINT;
+    let required_features = adapter.features() & wanted;
+
+    pollster::block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: Some("device"),
+            required_features,
+            required_limits,
+            memory_hints: Default::default(),
+            trace: wgpu::Trace::Off,
+        }
+    )).expect("request_device failed")
+}
+
+fn offscreen_usage() -> wgpu::TextureUsages {
+    wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::TEXTURE_BINDING
+}
+
+fn create_offscreen_texture(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("offscreen_color"),
+        size: wgpu::Extent3d { width: config.width, height: config.height, depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: config.format,
+        usage: config.usage,
+        view_formats: &[],
+    })
 }
@@ -1,17 +1,32 @@
-use wgpu::TextureView;
+use wgpu::{Texture, TextureView};
 
-use crate::types::DEPTH_FORMAT;
+use crate::types::{DEPTH_FORMAT, DEPTH_STENCIL_FORMAT};
 
-pub fn create_depth_view(device: &wgpu::Device, width: u32, height: u32) -> TextureView {
+/// `DEPTH_STENCIL_FORMAT` if the device has it enabled, else plain `DEPTH_FORMAT`.
+pub fn depth_format(device: &wgpu::Device) -> wgpu::TextureFormat {
+    if device.features().contains(wgpu::Features::DEPTH32FLOAT_STENCIL8) { DEPTH_STENCIL_FORMAT } else { DEPTH_FORMAT }
+}
+
+/// Multisampled depth can't be copied out, so it drops `COPY_SRC`. On GL it can't be bound
+/// either: a multisampled attachment that's also a texture there keeps the pass from resolving.
+/// Captures draw the depth again at 1x instead (`Renderer::capture_frame`).
+pub fn create_depth(device: &wgpu::Device, width: u32, height: u32, samples: u32, backend: wgpu::Backend) -> (Texture, TextureView) {
+    let mut usage = wgpu::TextureUsages::RENDER_ATTACHMENT;
+    if samples == 1 {
+        usage |= wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::TEXTURE_BINDING;
+    } else if backend != wgpu::Backend::Gl {
+        usage |= wgpu::TextureUsages::TEXTURE_BINDING;
+    }
     let depth = device.create_texture(&wgpu::TextureDescriptor {
         label: Some("depth"),
         size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
         mip_level_count: 1,
-        sample_count: 1,
+        sample_count: samples,
         dimension: wgpu::TextureDimension::D2,
-        format: DEPTH_FORMAT,
-        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format: depth_format(device),
+        usage,
         view_formats: &[],
     });
-    depth.create_view(&wgpu::TextureViewDescriptor::default())
+    let view = depth.create_view(&wgpu::TextureViewDescriptor::default());
+    (depth, view)
 }
@@ -0,0 +1,178 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Bump when the manifest layout or anything hashed into a permutation id changes.
+const MANIFEST_VERSION: u32 = 2;
+const MANIFEST_FILE: &str = "pipelines.manifest";
+/// Saves in a row an entry can go unbuilt before it's dropped from the manifest.
+const MAX_UNUSED_RUNS: u32 = 8;
+
+/// A permutation that has been compiled before, and against which source.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PermutationEntry {
+    pub src_name: String,
+    pub source_hash: u64,
+    /// Runs in a row that saved without building it.
+    pub unused_runs: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CacheStats {
+    /// Built with the same source as last time.
+    pub hits: u32,
+    /// Never built before on this adapter.
+    pub misses: u32,
+    /// Built before, but the source has changed since.
+    pub invalidated: u32,
+}
+
+/// Pipeline cache that survives restarts: a manifest of compiled permutations (per adapter and
+/// driver) plus the driver's own `wgpu::PipelineCache` blob where the backend has one (Vulkan).
+///
+/// Both are only written by `save`; a manifest from another adapter, driver or version is ignored
+/// (and replaced), and entries not built for `MAX_UNUSED_RUNS` runs are dropped.
+pub struct DiskCache {
+    dir: PathBuf,
+    adapter: String,
+    entries: HashMap<u64, PermutationEntry>,
+    /// Ids recorded since `open`.
+    used: HashSet<u64>,
+    driver_cache: Option<wgpu::PipelineCache>,
+    driver_file: Option<PathBuf>,
+    stats: CacheStats,
+}
+
+impl DiskCache {
+    pub fn open(dir: impl AsRef<Path>, device: &wgpu::Device, adapter: &wgpu::AdapterInfo) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+        let fingerprint = adapter_fingerprint(adapter);
+
+        let entries = match std::fs::read_to_string(dir.join(MANIFEST_FILE)) {
+            Ok(text) => parse_manifest(&text, &fingerprint).unwrap_or_default(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e),
+        };
+
+        let driver_file = device.features().contains(wgpu::Features::PIPELINE_CACHE)
+            .then(|| wgpu::util::pipeline_cache_key(adapter))
+            .flatten()
+            .map(|name| dir.join(name));
+        let driver_cache = driver_file.as_ref().map(|file| {
+            let data = std::fs::read(file).ok();
+            // SAFETY: the blob is only ever written from `get_data` of a cache on this adapter
+            // (the file name is keyed on it), and `fallback` covers driver updates.
+            unsafe {
+                device.create_pipeline_cache(&wgpu::PipelineCacheDescriptor {
+                    label: Some("disk_pipeline_cache"),
+                    data: data.as_deref(),
+                    fallback: true,
+                })
+            }
+        });
+
+        Ok(Self {
+            dir,
+            adapter: fingerprint,
+            entries,
+            used: HashSet::new(),
+            driver_cache,
+            driver_file,
+            stats: CacheStats::default(),
+        })
+    }
+
+    pub fn dir(&self) -> &Path { &self.dir }
+
+    /// For `RenderPipelineDescriptor::cache`; `None` where the backend has no driver cache.
+    pub fn driver_cache(&self) -> Option<&wgpu::PipelineCache> { self.driver_cache.as_ref() }
+
+    pub fn stats(&self) -> CacheStats { self.stats }
+
+    pub fn entries(&self) -> impl Iterator<Item = (u64, &PermutationEntry)> {
+        self.entries.iter().map(|(id, e)| (*id, e))
+    }
+
+    /// Whether `id` was compiled before from this exact source.
+    pub fn is_warm(&self, id: u64, source_hash: u64) -> bool {
+        self.entries.get(&id).is_some_and(|e| e.source_hash == source_hash)
+    }
+
+    /// Notes a freshly built pipeline; an entry with older source is replaced.
+    pub(crate) fn record(&mut self, id: u64, src_name: &str, source_hash: u64) {
+        self.used.insert(id);
+        match self.entries.get_mut(&id) {
+            Some(e) if e.source_hash == source_hash => self.stats.hits += 1,
+            Some(e) => {
+                e.source_hash = source_hash;
+                e.src_name = src_name.to_string();
+                self.stats.invalidated += 1;
+            }
+            None => {
+                self.entries.insert(id, PermutationEntry { src_name: src_name.to_string(), source_hash, unused_runs: 0 });
+                self.stats.misses += 1;
+            }
+        }
+    }
+
+    /// Writes the manifest and driver blob (via temp files, so a crash mid-save can't leave a
+    /// half-written cache behind). Entries not built since `open` count one more unused run.
+    pub fn save(&self) -> io::Result<()> {
+        let mut ids: Vec<_> = self.entries.keys().copied().collect();
+        ids.sort_unstable();
+        let mut text = format!("version {MANIFEST_VERSION} {}\nadapter {}\n", env!("CARGO_PKG_VERSION"), self.adapter);
+        for id in ids {
+            let e = &self.entries[&id];
+            let unused = if self.used.contains(&id) { 0 } else { e.unused_runs + 1 };
+            if unused <= MAX_UNUSED_RUNS {
+                text.push_str(&format!("p {id:016x} {:016x} {unused} {}\n", e.source_hash, e.src_name));
+            }
+        }
+        write_atomic(&self.dir.join(MANIFEST_FILE), text.as_bytes())?;
+
+        if let (Some(cache), Some(file)) = (&self.driver_cache, &self.driver_file) {
+            if let Some(data) = cache.get_data() {
+                write_atomic(file, &data)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Everything that should invalidate compiled pipelines when it changes.
+fn adapter_fingerprint(info: &wgpu::AdapterInfo) -> String {
+    format!(
+        "{:?}|{:04x}|{:04x}|{}|{}|{}",
+        info.backend, info.vendor, info.device, info.name, info.driver, info.driver_info,
+    )
+    .replace(['\n', '\r'], " ")
+}
+
+/// `None` if the manifest is from another version or adapter, or unreadable.
+fn parse_manifest(text: &str, adapter: &str) -> Option<HashMap<u64, PermutationEntry>> {
+    let mut lines = text.lines();
+    let version = lines.next()?.strip_prefix("version ")?;
+    if version != format!("{MANIFEST_VERSION} {}", env!("CARGO_PKG_VERSION")) {
+        return None;
+    }
+    if lines.next()?.strip_prefix("adapter ")? != adapter {
+        return None;
+    }
+    let mut entries = HashMap::new();
+    for line in lines {
+        let mut parts = line.strip_prefix("p ")?.splitn(4, ' ');
+        let id = u64::from_str_radix(parts.next()?, 16).ok()?;
+        let source_hash = u64::from_str_radix(parts.next()?, 16).ok()?;
+        let unused_runs = parts.next()?.parse().ok()?;
+        let src_name = parts.next()?.to_string();
+        entries.insert(id, PermutationEntry { src_name, source_hash, unused_runs });
+    }
+    Some(entries)
+}
+
+fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, data)?;
+    std::fs::rename(&tmp, path)
+}
@@ -0,0 +1,508 @@
+//! Frame graph: passes declare what they read and write, the graph orders them, drops the ones
+//! nothing depends on, and backs their transient textures with pooled, aliased allocations.
+
+use std::collections::{BinaryHeap, HashMap};
+use std::cmp::Reverse;
+
+/// The frame's color target (swapchain image or offscreen texture).
+pub const FRAME: &str = "frame";
+/// The scene's depth (and stencil) target. Multisampled along with the scene.
+pub const DEPTH: &str = "depth";
+/// The scene's linear color while HDR is on, tonemapped into `FRAME`.
+pub const HDR: &str = "hdr";
+/// The finished image the post-process stack starts from, in place of `FRAME` while it's active.
+pub const LDR: &str = "ldr";
+/// The built-in pass drawing submitted `DrawItem`s into `FRAME` (or `HDR`, or `LDR`) and `DEPTH`.
+pub const SCENE_PASS: &str = "scene";
+/// Built-in HDR pass metering `HDR` for auto exposure.
+pub const EXPOSURE_PASS: &str = "exposure";
+/// Built-in HDR pass mapping `HDR` into `FRAME` (or `LDR`).
+pub const TONEMAP_PASS: &str = "tonemap";
+/// The UI overlay; never part of the graph, always drawn after it.
+pub const UI_PASS: &str = "ui";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextureSize {
+    /// Same size as the frame.
+    Frame,
+    /// Frame size times this, rounded up.
+    Scaled(f32),
+    Fixed(u32, u32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureDesc {
+    pub size: TextureSize,
+    pub format: wgpu::TextureFormat,
+    pub samples: u32,
+    pub usage: wgpu::TextureUsages,
+}
+
+impl TextureDesc {
+    /// Frame-sized, single-sample, usable as attachment and for sampling.
+    pub fn new(format: wgpu::TextureFormat) -> Self {
+        Self {
+            size: TextureSize::Frame,
+            format,
+            samples: 1,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        }
+    }
+
+    pub fn with_size(mut self, size: TextureSize) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn with_samples(mut self, samples: u32) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    /// Added to the default usages.
+    pub fn with_usage(mut self, usage: wgpu::TextureUsages) -> Self {
+        self.usage |= usage;
+        self
+    }
+
+    fn extent(&self, frame: (u32, u32)) -> (u32, u32) {
+        match self.size {
+            TextureSize::Frame => frame,
+            TextureSize::Scaled(s) => (
+                ((frame.0 as f32 * s).ceil() as u32).max(1),
+                ((frame.1 as f32 * s).ceil() as u32).max(1),
+            ),
+            TextureSize::Fixed(w, h) => (w.max(1), h.max(1)),
+        }
+    }
+}
+
+/// A graph resource, valid for the frame whose `setup` handed it out.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct ResourceId(u32);
+
+/// A pass added with `Renderer::add_pass`. `setup` runs every frame before anything is
+/// recorded, so declarations can follow the frame size or sample count.
+pub trait GraphPass {
+    /// Unique among the renderer's passes; other passes refer to it by this in `before`/`after`.
+    fn name(&self) -> &str;
+    fn setup(&mut self, builder: &mut PassBuilder);
+    fn execute(&mut self, ctx: &mut PassContext);
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GraphError {
+    /// Read or written, but neither created nor imported by any pass.
+    Missing { pass: String, resource: String },
+    /// Created or imported by more than one pass.
+    Duplicate { resource: String },
+    /// `before`/`after` names a pass that doesn't exist.
+    UnknownPass { pass: String, other: String },
+    DuplicatePass(String),
+    /// Passes that depend on each other; none of them can go first.
+    Cycle(Vec<String>),
+}
+
+impl std::fmt::Display for GraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraphError::Missing { pass, resource } => write!(f, "pass '{pass}' uses '{resource}', which no pass creates"),
+            GraphError::Duplicate { resource } => write!(f, "'{resource}' is created by more than one pass"),
+            GraphError::UnknownPass { pass, other } => write!(f, "pass '{pass}' is ordered against unknown pass '{other}'"),
+            GraphError::DuplicatePass(name) => write!(f, "more than one pass is named '{name}'"),
+            GraphError::Cycle(passes) => write!(f, "passes depend on each other: {}", passes.join(", ")),
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+/// How the last frame's graph came out.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GraphStats {
+    /// Passes in the order they ran, `UI_PASS` included when drawn.
+    pub order: Vec<String>,
+    /// Passes skipped because nothing used what they write.
+    pub culled: Vec<String>,
+    /// Transient textures declared by the passes that ran.
+    pub transients: usize,
+    /// Textures actually backing them; lower than `transients` when lifetimes didn't overlap.
+    pub allocated: usize,
+}
+
+enum Source {
+    Frame,
+    Transient(TextureDesc),
+    Texture(wgpu::Texture),
+    Buffer(wgpu::Buffer),
+}
+
+struct Resource {
+    name: String,
+    /// Creating pass and what backs it; `None` until some pass creates or imports it.
+    source: Option<(usize, Source)>,
+}
+
+#[derive(Default)]
+pub(crate) struct PassDecl {
+    name: String,
+    reads: Vec<ResourceId>,
+    writes: Vec<ResourceId>,
+    before: Vec<String>,
+    after: Vec<String>,
+}
+
+/// Collects the declarations of every pass in a frame.
+pub struct PassBuilder<'a> {
+    graph: &'a mut GraphBuilder,
+    pass: usize,
+}
+
+impl PassBuilder<'_> {
+    pub fn frame_size(&self) -> (u32, u32) { self.graph.frame_size }
+
+    pub fn frame_format(&self) -> wgpu::TextureFormat { self.graph.frame_format }
+
+    /// The scene's sample count; `DEPTH` has it too.
+    pub fn samples(&self) -> u32 { self.graph.samples }
+
+    /// A texture that only lives for this frame, between its first and last use. The creating
+    /// pass writes it first.
+    pub fn create_texture(&mut self, name: &str, desc: TextureDesc) -> ResourceId {
+        let id = self.define(name, Source::Transient(desc));
+        self.graph.passes[self.pass].writes.push(id);
+        id
+    }
+
+    /// A texture owned elsewhere, for this or other passes to `read`/`write`. Passes writing
+    /// imported resources are never culled.
+    pub fn import_texture(&mut self, name: &str, texture: &wgpu::Texture) -> ResourceId {
+        self.define(name, Source::Texture(texture.clone()))
+    }
+
+    pub fn import_buffer(&mut self, name: &str, buffer: &wgpu::Buffer) -> ResourceId {
+        self.define(name, Source::Buffer(buffer.clone()))
+    }
+
+    /// Runs after every pass writing `name` (other than this one).
+    pub fn read(&mut self, name: &str) -> ResourceId {
+        let id = self.graph.intern(name);
+        self.graph.passes[self.pass].reads.push(id);
+        id
+    }
+
+    /// Passes writing the same resource run in the order they were added.
+    pub fn write(&mut self, name: &str) -> ResourceId {
+        let id = self.graph.intern(name);
+        self.graph.passes[self.pass].writes.push(id);
+        id
+    }
+
+    /// Orders this pass before `pass` even without a resource between them.
+    pub fn before(&mut self, pass: &str) {
+        self.graph.passes[self.pass].before.push(pass.to_string());
+    }
+
+    pub fn after(&mut self, pass: &str) {
+        self.graph.passes[self.pass].after.push(pass.to_string());
+    }
+
+    fn define(&mut self, name: &str, source: Source) -> ResourceId {
+        let id = self.graph.intern(name);
+        let res = &mut self.graph.resources[id.0 as usize];
+        if res.source.is_some() {
+            self.graph.duplicates.push(name.to_string());
+        } else {
+            res.source = Some((self.pass, source));
+        }
+        id
+    }
+}
+
+/// One frame's worth of declarations.
+pub(crate) struct GraphBuilder {
+    frame_size: (u32, u32),
+    frame_format: wgpu::TextureFormat,
+    samples: u32,
+    resources: Vec<Resource>,
+    names: HashMap<String, ResourceId>,
+    passes: Vec<PassDecl>,
+    duplicates: Vec<String>,
+}
+
+impl GraphBuilder {
+    /// `FRAME` and `DEPTH` come pre-imported; the frame itself is only needed for `allocate`.
+    pub(crate) fn new(frame_size: (u32, u32), frame_format: wgpu::TextureFormat, depth: &wgpu::Texture) -> Self {
+        let mut graph = Self {
+            frame_size,
+            frame_format,
+            samples: depth.sample_count(),
+            resources: Vec::new(),
+            names: HashMap::new(),
+            passes: Vec::new(),
+            duplicates: Vec::new(),
+        };
+        for (name, source) in [(FRAME, Source::Frame), (DEPTH, Source::Texture(depth.clone()))] {
+            let id = graph.intern(name);
+            graph.resources[id.0 as usize].source = Some((usize::MAX, source));
+        }
+        graph
+    }
+
+    pub(crate) fn add_pass(&mut self, name: &str) -> PassBuilder<'_> {
+        self.passes.push(PassDecl { name: name.to_string(), ..Default::default() });
+        let pass = self.passes.len() - 1;
+        PassBuilder { graph: self, pass }
+    }
+
+    fn intern(&mut self, name: &str) -> ResourceId {
+        if let Some(id) = self.names.get(name) {
+            return *id;
+        }
+        let id = ResourceId(self.resources.len() as u32);
+        self.resources.push(Resource { name: name.to_string(), source: None });
+        self.names.insert(name.to_string(), id);
+        id
+    }
+
+    /// Orders passes by their dependencies and drops the ones nothing uses.
+    pub(crate) fn compile(self) -> Result<CompiledGraph, GraphError> {
+        if let Some(resource) = self.duplicates.first() {
+            return Err(GraphError::Duplicate { resource: resource.clone() });
+        }
+        let n = self.passes.len();
+        let mut by_name = HashMap::new();
+        for (i, p) in self.passes.iter().enumerate() {
+            if by_name.insert(p.name.as_str(), i).is_some() {
+                return Err(GraphError::DuplicatePass(p.name.clone()));
+            }
+            if let Some(id) = p.reads.iter().chain(&p.writes).find(|id| self.resources[id.0 as usize].source.is_none()) {
+                return Err(GraphError::Missing { pass: p.name.clone(), resource: self.resources[id.0 as usize].name.clone() });
+            }
+        }
+
+        // Writers of a resource chain in the order they were added, its creator first; readers
+        // that don't also write it wait for all of them.
+        let mut edges = vec![Vec::new(); n];
+        for (r, res) in self.resources.iter().enumerate() {
+            let id = ResourceId(r as u32);
+            let creator = res.source.as_ref().map(|(pass, _)| *pass);
+            let mut writers: Vec<usize> = (0..n).filter(|&i| self.passes[i].writes.contains(&id)).collect();
+            writers.sort_by_key(|&i| (Some(i) != creator, i));
+            for w in writers.windows(2) {
+                edges[w[0]].push(w[1]);
+            }
+            for (i, p) in self.passes.iter().enumerate() {
+                if p.reads.contains(&id) && !writers.contains(&i) {
+                    writers.iter().for_each(|&w| edges[w].push(i));
+                }
+            }
+        }
+        for (i, p) in self.passes.iter().enumerate() {
+            let lookup = |other: &String| by_name.get(other.as_str()).copied()
+                .ok_or_else(|| GraphError::UnknownPass { pass: p.name.clone(), other: other.clone() });
+            for other in &p.before {
+                edges[i].push(lookup(other)?);
+            }
+            for other in &p.after {
+                edges[lookup(other)?].push(i);
+            }
+        }
+
+        // Kahn's algorithm, preferring the pass added first whenever there's a choice.
+        let mut incoming = vec![0; n];
+        edges.iter().flatten().for_each(|&to| incoming[to] += 1);
+        let mut ready: BinaryHeap<_> = (0..n).filter(|&i| incoming[i] == 0).map(Reverse).collect();
+        let mut order = Vec::with_capacity(n);
+        while let Some(Reverse(i)) = ready.pop() {
+            order.push(i);
+            for &to in &edges[i] {
+                incoming[to] -= 1;
+                if incoming[to] == 0 {
+                    ready.push(Reverse(to));
+                }
+            }
+        }
+        if order.len() < n {
+            let stuck = (0..n).filter(|i| !order.contains(i)).map(|i| self.passes[i].name.clone()).collect();
+            return Err(GraphError::Cycle(stuck));
+        }
+
+        // Passes writing anything outside the graph stay, and so does whatever they read from.
+        let imported = |id: &ResourceId| !matches!(self.resources[id.0 as usize].source, Some((_, Source::Transient(_))));
+        let mut live = vec![false; n];
+        for &i in order.iter().rev() {
+            let p = &self.passes[i];
+            live[i] = p.writes.iter().any(imported)
+                || order.iter().any(|&j| live[j] && j != i && p.writes.iter().any(|w| self.passes[j].reads.contains(w)));
+        }
+
+        let (order, culled): (Vec<usize>, Vec<usize>) = order.into_iter().partition(|&i| live[i]);
+        Ok(CompiledGraph { builder: self, order, culled })
+    }
+}
+
+/// A compiled frame: passes by the index they were added at, in the order they run.
+pub(crate) struct CompiledGraph {
+    builder: GraphBuilder,
+    pub(crate) order: Vec<usize>,
+    pub(crate) culled: Vec<usize>,
+}
+
+impl CompiledGraph {
+    pub(crate) fn pass_name(&self, i: usize) -> &str { &self.builder.passes[i].name }
+
+    pub(crate) fn transients(&self) -> usize { self.lifetimes().len() }
+
+    /// Transient textures as (first, last) position in `order`, ordered by first use.
+    fn lifetimes(&self) -> Vec<(ResourceId, TextureDesc, usize, usize)> {
+        let mut out = Vec::new();
+        for (r, res) in self.builder.resources.iter().enumerate() {
+            let Some((_, Source::Transient(desc))) = &res.source else { continue };
+            let id = ResourceId(r as u32);
+            let uses: Vec<usize> = self.order.iter().enumerate()
+                .filter(|(_, &i)| self.builder.passes[i].reads.contains(&id) || self.builder.passes[i].writes.contains(&id))
+                .map(|(pos, _)| pos)
+                .collect();
+            if let (Some(&first), Some(&last)) = (uses.first(), uses.last()) {
+                out.push((id, *desc, first, last));
+            }
+        }
+        out.sort_by_key(|&(_, _, first, _)| first);
+        out
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+struct PoolKey {
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+    samples: u32,
+    usage: wgpu::TextureUsages,
+}
+
+/// Transient texture storage, reused across frames; textures a frame didn't need are dropped.
+#[derive(Default)]
+pub(crate) struct TexturePool {
+    free: Vec<(PoolKey, wgpu::Texture, wgpu::TextureView)>,
+}
+
+/// What each `ResourceId` is backed by for the frame being recorded.
+pub(crate) struct FrameResources {
+    textures: Vec<Option<(wgpu::Texture, wgpu::TextureView)>>,
+    buffers: Vec<Option<wgpu::Buffer>>,
+}
+
+/// Handed to `GraphPass::execute`, with the resources its `setup` declared.
+pub struct PassContext<'a> {
+    pub device: &'a wgpu::Device,
+    pub queue: &'a wgpu::Queue,
+    pub encoder: &'a mut wgpu::CommandEncoder,
+    resources: &'a FrameResources,
+}
+
+impl<'a> PassContext<'a> {
+    pub(crate) fn new(
+        device: &'a wgpu::Device,
+        queue: &'a wgpu::Queue,
+        encoder: &'a mut wgpu::CommandEncoder,
+        resources: &'a FrameResources,
+    ) -> Self {
+        Self { device, queue, encoder, resources }
+    }
+
+    /// # Panics
+    /// If `id` isn't a texture of this frame's graph.
+    pub fn texture(&self, id: ResourceId) -> &wgpu::Texture {
+        &self.resources.texture(id).0
+    }
+
+    /// # Panics
+    /// If `id` isn't a texture of this frame's graph.
+    pub fn view(&self, id: ResourceId) -> &wgpu::TextureView {
+        &self.resources.texture(id).1
+    }
+
+    /// # Panics
+    /// If `id` isn't a buffer of this frame's graph.
+    pub fn buffer(&self, id: ResourceId) -> &wgpu::Buffer {
+        self.resources.buffers.get(id.0 as usize).and_then(Option::as_ref).expect("not a buffer of this frame")
+    }
+}
+
+impl FrameResources {
+    pub(crate) fn texture(&self, id: ResourceId) -> &(wgpu::Texture, wgpu::TextureView) {
+        self.textures.get(id.0 as usize).and_then(Option::as_ref).expect("not a texture of this frame")
+    }
+}
+
+impl TexturePool {
+    /// Backs every transient, handing a texture on once its last user has run. Returns the
+    /// resources and how many distinct textures were used.
+    pub(crate) fn allocate(
+        &mut self,
+        device: &wgpu::Device,
+        graph: &CompiledGraph,
+        frame: (&wgpu::Texture, &wgpu::TextureView),
+    ) -> (FrameResources, usize) {
+        let count = graph.builder.resources.len();
+        let mut res = FrameResources { textures: vec![None; count], buffers: vec![None; count] };
+        for (r, resource) in graph.builder.resources.iter().enumerate() {
+            match &resource.source {
+                Some((_, Source::Frame)) => res.textures[r] = Some((frame.0.clone(), frame.1.clone())),
+                Some((_, Source::Texture(t))) => {
+                    res.textures[r] = Some((t.clone(), t.create_view(&wgpu::TextureViewDescriptor::default())));
+                }
+                Some((_, Source::Buffer(b))) => res.buffers[r] = Some(b.clone()),
+                _ => {}
+            }
+        }
+
+        // (key, texture, view, used this frame); whatever is left unused at the end is dropped.
+        let mut available: Vec<_> = std::mem::take(&mut self.free).into_iter().map(|(k, t, v)| (k, t, v, false)).collect();
+        let mut in_use: Vec<(usize, PoolKey, wgpu::Texture, wgpu::TextureView)> = Vec::new();
+        let mut allocated = 0;
+        for (id, desc, first, last) in graph.lifetimes() {
+            // Anything whose last user runs before this one's first can be handed on.
+            let (done, still): (Vec<_>, Vec<_>) = in_use.into_iter().partition(|(end, ..)| *end < first);
+            in_use = still;
+            available.extend(done.into_iter().map(|(_, k, t, v)| (k, t, v, true)));
+
+            let (width, height) = desc.extent(graph.builder.frame_size);
+            let key = PoolKey { width, height, format: desc.format, samples: desc.samples, usage: desc.usage };
+            let (texture, view) = match available.iter().position(|(k, ..)| *k == key) {
+                Some(i) => {
+                    let (_, t, v, used) = available.swap_remove(i);
+                    allocated += usize::from(!used);
+                    (t, v)
+                }
+                None => {
+                    allocated += 1;
+                    create_texture(device, &graph.builder.resources[id.0 as usize].name, key)
+                }
+            };
+            res.textures[id.0 as usize] = Some((texture.clone(), view.clone()));
+            in_use.push((last, key, texture, view));
+        }
+        self.free = in_use.into_iter().map(|(_, k, t, v)| (k, t, v))
+            .chain(available.into_iter().filter(|(.., used)| *used).map(|(k, t, v, _)| (k, t, v)))
+            .collect();
+        (res, allocated)
+    }
+}
+
+fn create_texture(device: &wgpu::Device, name: &str, key: PoolKey) -> (wgpu::Texture, wgpu::TextureView) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(name),
+        size: wgpu::Extent3d { width: key.width, height: key.height, depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count: key.samples,
+        dimension: wgpu::TextureDimension::D2,
+        format: key.format,
+        usage: key.usage,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    (texture, view)
+}
@@ -0,0 +1,93 @@
+use std::marker::PhantomData;
+
+/// Typed index into a `Pool`. Stale handles (to removed slots) never alias new resources.
+pub struct Handle<T> {
+    index: u32,
+    generation: u32,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self { *self }
+}
+impl<T> Copy for Handle<T> {}
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool { self.index == other.index && self.generation == other.generation }
+}
+impl<T> Eq for Handle<T> {}
+impl<T> std::hash::Hash for Handle<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+impl<T> std::fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Handle({}v{})", self.index, self.generation)
+    }
+}
+
+impl<T> Handle<T> {
+    pub fn index(&self) -> u32 { self.index }
+
+    /// Placeholder that never resolves in any pool.
+    pub(crate) fn dangling() -> Self {
+        Self { index: u32::MAX, generation: u32::MAX, _marker: PhantomData }
+    }
+}
+
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+pub struct Pool<T> {
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
+}
+
+impl<T> Default for Pool<T> {
+    fn default() -> Self { Self { slots: Vec::new(), free: Vec::new() } }
+}
+
+impl<T> Pool<T> {
+    pub fn insert(&mut self, value: T) -> Handle<T> {
+        let index = match self.free.pop() {
+            Some(i) => {
+                self.slots[i as usize].value = Some(value);
+                i
+            }
+            None => {
+                self.slots.push(Slot { generation: 0, value: Some(value) });
+                self.slots.len() as u32 - 1
+            }
+        };
+        Handle { index, generation: self.slots[index as usize].generation, _marker: PhantomData }
+    }
+
+    pub fn get(&self, h: Handle<T>) -> Option<&T> {
+        self.slots.get(h.index as usize)
+            .filter(|s| s.generation == h.generation)
+            .and_then(|s| s.value.as_ref())
+    }
+
+    pub fn get_mut(&mut self, h: Handle<T>) -> Option<&mut T> {
+        self.slots.get_mut(h.index as usize)
+            .filter(|s| s.generation == h.generation)
+            .and_then(|s| s.value.as_mut())
+    }
+
+    pub fn remove(&mut self, h: Handle<T>) -> Option<T> {
+        let slot = self.slots.get_mut(h.index as usize).filter(|s| s.generation == h.generation)?;
+        let value = slot.value.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(h.index);
+        Some(value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Handle<T>, &T)> {
+        self.slots.iter().enumerate().filter_map(|(i, s)| {
+            s.value.as_ref().map(|v| (Handle { index: i as u32, generation: s.generation, _marker: PhantomData }, v))
+        })
+    }
+}
@@ -0,0 +1,524 @@
+use shader_core::{Overrides, RenderState, WgslSource};
+
+use crate::graph::{GraphPass, PassBuilder, PassContext, ResourceId, TextureDesc, EXPOSURE_PASS, FRAME, HDR, LDR, TONEMAP_PASS};
+
+/// Format the scene renders into while HDR is on.
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Graph buffer holding the exposure the tonemapper applies.
+const EXPOSURE: &str = "exposure";
+
+/// How scene radiance is mapped to the display range.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Tonemapper {
+    /// Narkowicz's fit of the ACES filmic curve.
+    #[default]
+    Aces,
+    /// `c / (1 + c)` per channel; never clips, but flattens highlights.
+    Reinhard,
+    /// Sobotka's AgX (polynomial approximation); bright saturated colors go to white.
+    AgX,
+}
+
+impl Tonemapper {
+    pub const ALL: [Tonemapper; 3] = [Tonemapper::Aces, Tonemapper::Reinhard, Tonemapper::AgX];
+
+    /// Value of the tonemap shader's `TONEMAPPER` override.
+    fn id(self) -> u32 {
+        match self {
+            Tonemapper::Aces => 0,
+            Tonemapper::Reinhard => 1,
+            Tonemapper::AgX => 2,
+        }
+    }
+}
+
+/// Metering for `Exposure::Auto`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AutoExposure {
+    /// log2 luminance range the histogram covers; pixels outside count toward its ends.
+    pub min_log_lum: f32,
+    pub max_log_lum: f32,
+    /// In stops, on top of exposing the average to middle gray.
+    pub compensation: f32,
+    /// Adaptation rate per second; `f32::INFINITY` adapts within a frame.
+    pub speed: f32,
+}
+
+impl Default for AutoExposure {
+    fn default() -> Self {
+        Self { min_log_lum: -8.0, max_log_lum: 6.0, compensation: 0.0, speed: 1.5 }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Exposure {
+    /// Scales the scene by `2^ev`.
+    Manual { ev: f32 },
+    /// Meters the scene's luminance histogram on the GPU. Without compute shaders this acts like
+    /// `Manual` at `compensation`.
+    Auto(AutoExposure),
+}
+
+impl Default for Exposure {
+    fn default() -> Self { Exposure::Auto(AutoExposure::default()) }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct HdrSettings {
+    pub tonemapper: Tonemapper,
+    pub exposure: Exposure,
+}
+
+impl HdrSettings {
+    pub fn with_tonemapper(mut self, tonemapper: Tonemapper) -> Self {
+        self.tonemapper = tonemapper;
+        self
+    }
+
+    pub fn with_exposure(mut self, exposure: Exposure) -> Self {
+        self.exposure = exposure;
+        self
+    }
+}
+
+/// Matches `Exposure` in the shaders; `lum` is the adapted average luminance, 0 until metered.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct ExposureUbo {
+    scale: f32,
+    lum: f32,
+    _pad: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct MeterParams {
+    min_log_lum: f32,
+    log_lum_range: f32,
+    /// Fraction of the way to the metered luminance to move this frame.
+    adapt: f32,
+    key: f32,
+    pixel_count: u32,
+    _pad: [u32; 3],
+}
+
+/// The passes turning the scene's `HDR` target into the frame.
+pub(crate) struct Hdr {
+    settings: HdrSettings,
+    /// `None` without compute shaders.
+    exposure: Option<ExposurePass>,
+    tonemap: TonemapPass,
+}
+
+impl Hdr {
+    /// `compute` is whether the adapter can run the metering shaders. Tonemapping waits for
+    /// `set_pipeline`; `bgl` is what that pipeline is laid out with (`bind_group_layout`).
+    pub(crate) fn new(device: &wgpu::Device, bgl: wgpu::BindGroupLayout, settings: HdrSettings, compute: bool) -> Self {
+        let mut usage = wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST;
+        if compute {
+            usage |= wgpu::BufferUsages::STORAGE;
+        }
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("exposure"),
+            size: std::mem::size_of::<ExposureUbo>() as u64,
+            usage,
+            mapped_at_creation: false,
+        });
+        let mut hdr = Self {
+            settings,
+            exposure: compute.then(|| ExposurePass::new(device, buffer.clone())),
+            tonemap: TonemapPass { buffer, bgl, manual: None, post: false, ids: None, pipeline: None },
+        };
+        hdr.set(settings);
+        hdr
+    }
+
+    pub(crate) fn settings(&self) -> HdrSettings {
+        self.settings
+    }
+
+    pub(crate) fn set(&mut self, settings: HdrSettings) {
+        self.settings = settings;
+        self.tonemap.manual = match (settings.exposure, &mut self.exposure) {
+            (Exposure::Manual { ev }, _) => Some(ev.exp2()),
+            (Exposure::Auto(auto), None) => Some(auto.compensation.exp2()),
+            (Exposure::Auto(auto), Some(exposure)) => {
+                exposure.auto = auto;
+                None
+            }
+        };
+    }
+
+    /// Built from `tonemap_desc` for the current tonemapper.
+    pub(crate) fn set_pipeline(&mut self, pipeline: wgpu::RenderPipeline) {
+        self.tonemap.pipeline = Some(pipeline);
+    }
+
+    /// In the order they're added to the graph. With `post`, tonemapping goes to `LDR` for the
+    /// post-process stack to pick up.
+    pub(crate) fn passes(&mut self, post: bool) -> Vec<&mut dyn GraphPass> {
+        self.tonemap.post = post;
+        let mut passes: Vec<&mut dyn GraphPass> = Vec::new();
+        if let (None, Some(exposure)) = (self.tonemap.manual, &mut self.exposure) {
+            passes.push(exposure);
+        }
+        passes.push(&mut self.tonemap);
+        passes
+    }
+}
+
+const METER_WGSL: &str = r#"
+struct Exposure { scale: f32, lum: f32, pad: vec2<f32> }
+struct Params { min_log_lum: f32, log_lum_range: f32, adapt: f32, key: f32, pixel_count: u32 }
+
+@group(0) @binding(0) var hdr: texture_2d<f32>;
+@group(0) @binding(1) var<storage, read_write> histogram: array<atomic<u32>, 256>;
+@group(0) @binding(2) var<uniform> params: Params;
+@group(0) @binding(3) var<storage, read_write> exposure: Exposure;
+
+var<workgroup> bins: array<atomic<u32>, 256>;
+var<workgroup> weighted: array<f32, 256>;
+
+// Bin 0 is for (near) black, the rest spread over the log2 luminance range.
+fn bin(c: vec3<f32>) -> u32 {
+  let lum = dot(c, vec3<f32>(0.2126, 0.7152, 0.0722));
+  if (lum < 0.0001) { return 0u; }
+  let t = clamp((log2(lum) - params.min_log_lum) / params.log_lum_range, 0.0, 1.0);
+  return u32(t * 254.0 + 1.0);
+}
+
+@compute @workgroup_size(16, 16)
+fn build(@builtin(global_invocation_id) id: vec3<u32>, @builtin(local_invocation_index) i: u32) {
+  atomicStore(&bins[i], 0u);
+  workgroupBarrier();
+  let size = textureDimensions(hdr);
+  if (id.x < size.x && id.y < size.y) {
+    atomicAdd(&bins[bin(textureLoad(hdr, vec2<i32>(id.xy), 0).rgb)], 1u);
+  }
+  workgroupBarrier();
+  atomicAdd(&histogram[i], atomicLoad(&bins[i]));
+}
+
+@compute @workgroup_size(256)
+fn average(@builtin(local_invocation_index) i: u32) {
+  let count = atomicLoad(&histogram[i]);
+  weighted[i] = f32(count) * f32(i);
+  atomicStore(&histogram[i], 0u);
+  workgroupBarrier();
+  for (var stride = 128u; stride > 0u; stride = stride >> 1u) {
+    if (i < stride) { weighted[i] += weighted[i + stride]; }
+    workgroupBarrier();
+  }
+  if (i == 0u) {
+    // `count` is bin 0 here; black pixels don't drag the average down.
+    let lit = max(f32(params.pixel_count) - f32(count), 1.0);
+    let avg = exp2((weighted[0] / lit - 1.0) / 254.0 * params.log_lum_range + params.min_log_lum);
+    var lum = avg;
+    if (exposure.lum > 0.0) { lum = mix(exposure.lum, avg, params.adapt); }
+    exposure.lum = lum;
+    exposure.scale = params.key / lum;
+  }
+}
+"#;
+
+/// Meters `HDR` into the exposure buffer: a luminance histogram, then its adapted average.
+struct ExposurePass {
+    auto: AutoExposure,
+    buffer: wgpu::Buffer,
+    histogram: wgpu::Buffer,
+    params: wgpu::Buffer,
+    bgl: wgpu::BindGroupLayout,
+    build: wgpu::ComputePipeline,
+    average: wgpu::ComputePipeline,
+    last: Option<std::time::Instant>,
+    hdr: Option<ResourceId>,
+}
+
+impl ExposurePass {
+    fn new(device: &wgpu::Device, buffer: wgpu::Buffer) -> Self {
+        let storage = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("exposure_meter"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                storage(1),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage(3),
+            ],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("exposure_meter"),
+            bind_group_layouts: &[&bgl],
+            push_constant_ranges: &[],
+        });
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("exposure_meter"),
+            source: wgpu::ShaderSource::Wgsl(METER_WGSL.into()),
+        });
+        let pipeline = |entry: &str| device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(entry),
+            layout: Some(&layout),
+            module: &module,
+            entry_point: Some(entry),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        let buffer_of = |label, size: usize, usage| device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: size as u64,
+            usage,
+            mapped_at_creation: false,
+        });
+        Self {
+            auto: AutoExposure::default(),
+            histogram: buffer_of("luminance_histogram", 256 * 4, wgpu::BufferUsages::STORAGE),
+            params: buffer_of("meter_params", std::mem::size_of::<MeterParams>(), wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST),
+            buffer,
+            build: pipeline("build"),
+            average: pipeline("average"),
+            bgl,
+            last: None,
+            hdr: None,
+        }
+    }
+}
+
+impl GraphPass for ExposurePass {
+    fn name(&self) -> &str { EXPOSURE_PASS }
+
+    fn setup(&mut self, b: &mut PassBuilder) {
+        self.hdr = Some(b.read(HDR));
+        b.write(EXPOSURE);
+    }
+
+    fn execute(&mut self, ctx: &mut PassContext) {
+        let now = std::time::Instant::now();
+        let dt = self.last.map_or(0.0, |t| (now - t).as_secs_f32());
+        self.last = Some(now);
+
+        let hdr = ctx.texture(self.hdr.expect("set up"));
+        let (width, height) = (hdr.width(), hdr.height());
+        // An infinite speed over a zero `dt` is NaN, and means adapting at once as well.
+        let adapt = (1.0 - (-dt * self.auto.speed).exp()).clamp(0.0, 1.0);
+        let params = MeterParams {
+            min_log_lum: self.auto.min_log_lum,
+            log_lum_range: (self.auto.max_log_lum - self.auto.min_log_lum).max(f32::EPSILON),
+            adapt: if adapt.is_nan() { 1.0 } else { adapt },
+            key: 0.18 * self.auto.compensation.exp2(),
+            pixel_count: width * height,
+            _pad: [0; 3],
+        };
+        ctx.queue.write_buffer(&self.params, 0, bytemuck::bytes_of(&params));
+
+        let bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("exposure_meter"),
+            layout: &self.bgl,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(ctx.view(self.hdr.expect("set up"))) },
+                wgpu::BindGroupEntry { binding: 1, resource: self.histogram.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: self.params.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: self.buffer.as_entire_binding() },
+            ],
+        });
+        // Separate passes so the histogram is complete before it's averaged.
+        for (pipeline, groups) in [(&self.build, (width.div_ceil(16), height.div_ceil(16))), (&self.average, (1, 1))] {
+            let mut pass = ctx.encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some(EXPOSURE_PASS), timestamp_writes: None });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(groups.0, groups.1, 1);
+        }
+    }
+}
+
+const TONEMAP_WGSL: &str = r#"
+struct Exposure { scale: f32, lum: f32, pad: vec2<f32> }
+
+@group(0) @binding(0) var hdr: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> exposure: Exposure;
+
+override TONEMAPPER: u32 = 0u;
+// Set when the target doesn't encode sRGB itself.
+override ENCODE_SRGB: bool = false;
+
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> @builtin(position) vec4<f32> {
+  let uv = vec2<f32>(f32((i << 1u) & 2u), f32(i & 2u));
+  return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+fn aces(x: vec3<f32>) -> vec3<f32> {
+  return clamp(x * (2.51 * x + 0.03) / (x * (2.43 * x + 0.59) + 0.14), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+fn reinhard(x: vec3<f32>) -> vec3<f32> {
+  return x / (1.0 + x);
+}
+
+fn agx_contrast(x: vec3<f32>) -> vec3<f32> {
+  let x2 = x * x;
+  let x4 = x2 * x2;
+  return 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232;
+}
+
+fn agx(x: vec3<f32>) -> vec3<f32> {
+  let inset = mat3x3<f32>(
+    vec3<f32>(0.842479062253094, 0.0423282422610123, 0.0423756549057051),
+    vec3<f32>(0.0784335999999992, 0.878468636469772, 0.0784336),
+    vec3<f32>(0.0792237451477643, 0.0791661274605434, 0.879142973793104),
+  );
+  let outset = mat3x3<f32>(
+    vec3<f32>(1.19687900512017, -0.0528968517574562, -0.0529716355144438),
+    vec3<f32>(-0.0980208811401368, 1.15190312990417, -0.0980434501171241),
+    vec3<f32>(-0.0990297440797205, -0.0989611768448433, 1.15107367264116),
+  );
+  let min_ev = -12.47393;
+  let max_ev = 4.026069;
+  var v = clamp(log2(max(inset * x, vec3<f32>(1e-10))), vec3<f32>(min_ev), vec3<f32>(max_ev));
+  v = outset * agx_contrast((v - min_ev) / (max_ev - min_ev));
+  // The curve ends in a 2.2 display encoding; undo it so the target's encoding applies.
+  return pow(clamp(v, vec3<f32>(0.0), vec3<f32>(1.0)), vec3<f32>(2.2));
+}
+
+fn srgb(x: vec3<f32>) -> vec3<f32> {
+  return select(1.055 * pow(x, vec3<f32>(1.0 / 2.4)) - 0.055, x * 12.92, x <= vec3<f32>(0.0031308));
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+  let c = max(textureLoad(hdr, vec2<i32>(pos.xy), 0).rgb * exposure.scale, vec3<f32>(0.0));
+  var mapped: vec3<f32>;
+  if (TONEMAPPER == 1u) {
+    mapped = reinhard(c);
+  } else if (TONEMAPPER == 2u) {
+    mapped = agx(c);
+  } else {
+    mapped = aces(c);
+  }
+  if (ENCODE_SRGB) { mapped = srgb(mapped); }
+  return vec4<f32>(mapped, 1.0);
+}
+"#;
+
+/// What the tonemap pipeline is built from, drawing into `format`.
+pub(crate) fn tonemap_desc(tonemapper: Tonemapper, format: wgpu::TextureFormat) -> (WgslSource, RenderState<wgpu::TextureFormat>, Overrides) {
+    let mut overrides = Overrides::default();
+    overrides.set_u32("TONEMAPPER", tonemapper.id());
+    overrides.set_bool("ENCODE_SRGB", !format.is_srgb());
+    (WgslSource::new("hdr/tonemap.wgsl", TONEMAP_WGSL), RenderState::new(format).without_depth(), overrides)
+}
+
+/// The group the tonemap pipeline is laid out with: `HDR` (0) and the exposure (1).
+pub(crate) fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("tonemap_bgl"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+    })
+}
+
+/// Maps `HDR` into the frame (or `LDR`).
+struct TonemapPass {
+    buffer: wgpu::Buffer,
+    bgl: wgpu::BindGroupLayout,
+    /// Scale written each frame in place of the metered one.
+    manual: Option<f32>,
+    /// Writes `LDR` rather than the frame.
+    post: bool,
+    ids: Option<(ResourceId, ResourceId)>,
+    pipeline: Option<wgpu::RenderPipeline>,
+}
+
+impl GraphPass for TonemapPass {
+    fn name(&self) -> &str { TONEMAP_PASS }
+
+    fn setup(&mut self, b: &mut PassBuilder) {
+        b.import_buffer(EXPOSURE, &self.buffer);
+        b.read(EXPOSURE);
+        let target = match self.post {
+            true => b.create_texture(LDR, TextureDesc::new(b.frame_format())),
+            false => b.write(FRAME),
+        };
+        self.ids = Some((b.read(HDR), target));
+    }
+
+    fn execute(&mut self, ctx: &mut PassContext) {
+        let (hdr, frame) = self.ids.expect("set up");
+        if let Some(scale) = self.manual {
+            // A zero `lum` makes metering start over from scratch if it's switched back on.
+            let ubo = ExposureUbo { scale, lum: 0.0, _pad: [0.0; 2] };
+            ctx.queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&ubo));
+        }
+        let pipeline = self.pipeline.as_ref().expect("built by Renderer::set_hdr");
+        let bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(TONEMAP_PASS),
+            layout: &self.bgl,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(ctx.view(hdr)) },
+                wgpu::BindGroupEntry { binding: 1, resource: self.buffer.as_entire_binding() },
+            ],
+        });
+        let target = ctx.view(frame).clone();
+        let mut rp = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(TONEMAP_PASS),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &target,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: wgpu::StoreOp::Store },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rp.set_pipeline(pipeline);
+        rp.set_bind_group(0, &bind_group, &[]);
+        rp.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,68 @@
+use bytemuck::{Pod, Zeroable};
+
+use crate::handle::Handle;
+
+/// Per-instance vertex stream: the model matrix as four columns at locations 4..=7.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct InstanceData {
+    pub model: [[f32; 4]; 4],
+}
+
+impl InstanceData {
+    pub fn new(model: glam::Mat4) -> Self {
+        Self { model: model.to_cols_array_2d() }
+    }
+
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRS: [wgpu::VertexAttribute; 4] = wgpu::vertex_attr_array![
+            4 => Float32x4, 5 => Float32x4, 6 => Float32x4, 7 => Float32x4
+        ];
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<InstanceData>() as u64,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &ATTRS,
+        }
+    }
+}
+
+pub struct InstanceBuffer {
+    pub buffer: wgpu::Buffer,
+    pub count: u32,
+    capacity: u32,
+}
+
+pub type InstanceHandle = Handle<InstanceBuffer>;
+
+impl InstanceBuffer {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, instances: &[InstanceData]) -> Self {
+        let capacity = (instances.len() as u32).max(1);
+        let buffer = Self::create(device, capacity);
+        if !instances.is_empty() {
+            queue.write_buffer(&buffer, 0, bytemuck::cast_slice(instances));
+        }
+        Self { buffer, count: instances.len() as u32, capacity }
+    }
+
+    fn create(device: &wgpu::Device, capacity: u32) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("instances"),
+            size: capacity as u64 * std::mem::size_of::<InstanceData>() as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Replaces the contents, reallocating only when the buffer has to grow.
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, instances: &[InstanceData]) {
+        let needed = instances.len() as u32;
+        if needed > self.capacity {
+            self.capacity = needed.next_power_of_two();
+            self.buffer = Self::create(device, self.capacity);
+        }
+        if !instances.is_empty() {
+            queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(instances));
+        }
+        self.count = needed;
+    }
+}
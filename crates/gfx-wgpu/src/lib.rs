@@ -1,14 +1,50 @@
 mod context;
 mod depth;
+mod msaa;
+mod hdr;
+mod post;
+mod load;
+pub mod graph;
 mod renderer;
 mod types;
 mod camera_bind;
 mod ui;
 mod pipeline_cache;
+mod disk_cache;
+mod capture;
+mod handle;
+mod mesh;
+mod object_bind;
+mod instance;
+mod texture;
+mod sampler;
+mod mipmap;
+mod material;
+mod reflect;
+mod state;
+mod picking;
+mod world;
 
 pub use renderer::Renderer;
-pub use types::{Vertex, DEPTH_FORMAT};
-pub use engine_core::{Camera, CameraUBO};
+pub use types::{Vertex, TexVertex, DEPTH_FORMAT, DEPTH_STENCIL_FORMAT};
+pub use engine_core::{Aabb, Camera, CameraUBO};
 pub use ui::UiLayer;
-pub use pipeline_cache::PipelineCache;
-pub use context::GfxContext;
\ No newline at end of file
+pub use pipeline_cache::{LayoutKey, MaterialLayout, MaterialLayoutKey, Pipeline, PipelineCache, PipelineError};
+pub use disk_cache::{CacheStats, DiskCache, PermutationEntry};
+pub use msaa::MsaaError;
+pub use hdr::{AutoExposure, Exposure, HdrSettings, Tonemapper, HDR_FORMAT};
+pub use load::{LoadOp, LoadOps};
+pub use post::{Bloom, ChromaticAberration, ColorGrading, ColorLut, Fxaa, FxaaQuality, PostEffect, PostEntry, PostStack, Smaa, SmaaQuality, Vignette};
+pub use graph::{GraphError, GraphPass, GraphStats, PassBuilder, PassContext, ResourceId, TextureDesc, TextureSize};
+pub use context::{GfxContext, HeadlessConfig, Frame, RenderTarget};
+pub use capture::{CaptureOptions, CaptureError, FrameCapture, DepthImage};
+pub use handle::{Handle, Pool};
+pub use mesh::{CullStats, DrawItem, Indices, Mesh, MeshHandle, PipelineHandle};
+pub use object_bind::ObjectUBO;
+pub use instance::{InstanceBuffer, InstanceData, InstanceHandle};
+pub use texture::{ColorSpace, Texture, TextureError, TextureHandle, TextureOptions};
+pub use sampler::{SamplerDesc, SamplerFilter, WrapMode};
+pub use material::{Material, MaterialDesc, MaterialHandle, MaterialParam, MaterialTexture};
+pub use picking::{PickError, PickHit, PICK_FORMAT};
+pub use world::{Hidden, Renderable};
+pub use reflect::bind_group_layout_entries;
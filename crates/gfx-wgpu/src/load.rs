@@ -0,0 +1,79 @@
+/// How a target starts out when a pass begins.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoadOp<T> {
+    Clear(T),
+    /// Keep what the target held. Pooled transients (the `HDR`/`LDR` targets, multisampled
+    /// color) hold nothing meaningful; the frame and depth keep the previous frame.
+    Load,
+    /// Contents are about to be overwritten anyway. wgpu has no such op yet, so this clears;
+    /// on tiled GPUs that costs about the same.
+    DontCare,
+}
+
+impl<T: Default> LoadOp<T> {
+    fn into_wgpu(self) -> wgpu::LoadOp<T> {
+        match self {
+            LoadOp::Clear(v) => wgpu::LoadOp::Clear(v),
+            LoadOp::Load => wgpu::LoadOp::Load,
+            LoadOp::DontCare => wgpu::LoadOp::Clear(T::default()),
+        }
+    }
+}
+
+/// How the scene pass loads its color, depth and stencil targets (see `Renderer::set_load_ops`
+/// and `Renderer::request_load_ops`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LoadOps {
+    pub color: LoadOp<wgpu::Color>,
+    /// Cleared values are standard depth (1 = far), and flipped under reverse-Z.
+    pub depth: LoadOp<f32>,
+    pub stencil: LoadOp<u32>,
+}
+
+impl Default for LoadOps {
+    fn default() -> Self {
+        Self {
+            color: LoadOp::Clear(wgpu::Color { r: 0.05, g: 0.06, b: 0.1, a: 1.0 }),
+            depth: LoadOp::Clear(1.0),
+            stencil: LoadOp::Clear(0),
+        }
+    }
+}
+
+impl LoadOps {
+    pub fn with_color(mut self, color: LoadOp<wgpu::Color>) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_clear_color(self, color: wgpu::Color) -> Self {
+        self.with_color(LoadOp::Clear(color))
+    }
+
+    pub fn with_depth(mut self, depth: LoadOp<f32>) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    pub fn with_stencil(mut self, stencil: LoadOp<u32>) -> Self {
+        self.stencil = stencil;
+        self
+    }
+
+    pub(crate) fn color_op(&self) -> wgpu::LoadOp<wgpu::Color> {
+        self.color.into_wgpu()
+    }
+
+    pub(crate) fn depth_op(&self, reverse_z: bool) -> wgpu::LoadOp<f32> {
+        match self.depth {
+            LoadOp::Clear(d) if reverse_z => wgpu::LoadOp::Clear(1.0 - d),
+            // Whatever is fastest to clear to.
+            LoadOp::DontCare => wgpu::LoadOp::Clear(if reverse_z { 0.0 } else { 1.0 }),
+            op => op.into_wgpu(),
+        }
+    }
+
+    pub(crate) fn stencil_op(&self) -> wgpu::LoadOp<u32> {
+        self.stencil.into_wgpu()
+    }
+}
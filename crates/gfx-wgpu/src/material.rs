@@ -0,0 +1,169 @@
+use shader_core::{Overrides, RenderState, ShaderReflection, WgslSource};
+
+use crate::handle::Handle;
+use crate::mesh::PipelineHandle;
+use crate::pipeline_cache::{MaterialLayoutKey, PipelineError};
+use crate::sampler::SamplerDesc;
+use crate::texture::TextureHandle;
+
+/// Value of one field in a material's uniform block.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MaterialParam {
+    F32(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+}
+
+impl MaterialParam {
+    /// (size, align) following WGSL uniform layout rules.
+    fn layout(&self) -> (u64, u64) {
+        match self {
+            MaterialParam::F32(_) => (4, 4),
+            MaterialParam::Vec2(_) => (8, 8),
+            MaterialParam::Vec3(_) => (12, 16),
+            MaterialParam::Vec4(_) => (16, 16),
+        }
+    }
+
+    fn bytes(&self) -> &[u8] {
+        match self {
+            MaterialParam::F32(v) => bytemuck::bytes_of(v),
+            MaterialParam::Vec2(v) => bytemuck::cast_slice(v),
+            MaterialParam::Vec3(v) => bytemuck::cast_slice(v),
+            MaterialParam::Vec4(v) => bytemuck::cast_slice(v),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MaterialTexture {
+    pub texture: TextureHandle,
+    pub sampler: SamplerDesc,
+}
+
+/// Everything needed to build a material. The group 2 layout comes from the shader, which has
+/// to declare exactly:
+///
+/// - binding 0: `var<uniform>` struct with `params` as fields, in order (only if there are any)
+/// - binding 1 + 2i: `texture_2d<f32>` for `textures[i]`
+/// - binding 2 + 2i: `sampler` for `textures[i]`
+#[derive(Clone)]
+pub struct MaterialDesc {
+    pub shader: WgslSource,
+    pub state: RenderState<wgpu::TextureFormat>,
+    pub overrides: Overrides,
+    pub vertex_layouts: Vec<wgpu::VertexBufferLayout<'static>>,
+    pub params: Vec<(String, MaterialParam)>,
+    pub textures: Vec<MaterialTexture>,
+}
+
+impl MaterialDesc {
+    pub fn new(
+        shader: WgslSource,
+        state: RenderState<wgpu::TextureFormat>,
+        vertex_layouts: &[wgpu::VertexBufferLayout<'static>],
+    ) -> Self {
+        Self {
+            shader,
+            state,
+            overrides: Overrides::default(),
+            vertex_layouts: vertex_layouts.to_vec(),
+            params: Vec::new(),
+            textures: Vec::new(),
+        }
+    }
+    pub fn with_overrides(mut self, overrides: Overrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+    pub fn with_param(mut self, name: &str, value: MaterialParam) -> Self {
+        self.params.push((name.to_string(), value));
+        self
+    }
+    pub fn with_texture(mut self, texture: TextureHandle, sampler: SamplerDesc) -> Self {
+        self.textures.push(MaterialTexture { texture, sampler });
+        self
+    }
+
+    /// Byte offset of every param plus the padded size of the whole block.
+    pub(crate) fn param_offsets(&self) -> (Vec<u64>, u64) {
+        let mut offsets = Vec::with_capacity(self.params.len());
+        let mut end = 0u64;
+        for (_, p) in &self.params {
+            let (size, align) = p.layout();
+            let offset = end.next_multiple_of(align);
+            offsets.push(offset);
+            end = offset + size;
+        }
+        (offsets, end.next_multiple_of(16))
+    }
+
+    /// Group 2 as the shader behind `refl` declares it.
+    pub fn layout_key_for(refl: &ShaderReflection) -> MaterialLayoutKey {
+        MaterialLayoutKey { entries: crate::reflect::bind_group_layout_entries(refl, 2) }
+    }
+
+    /// The shader's group 2 layout, or `PipelineError::Layout` if `params` and `textures` don't fit it.
+    pub fn layout_key(&self) -> Result<MaterialLayoutKey, PipelineError> {
+        let key = Self::layout_key_for(&self.shader.check()?);
+        self.check_layout(&key).map_err(|msg| PipelineError::Layout(format!("{}: {msg}", self.shader.name)))?;
+        Ok(key)
+    }
+
+    fn check_layout(&self, key: &MaterialLayoutKey) -> Result<(), String> {
+        let entry = |binding: u32| key.entries.iter().find(|e| e.binding == binding).map(|e| e.ty);
+        let (offsets, padded) = self.param_offsets();
+        let used = offsets.last().zip(self.params.last()).map_or(0, |(o, (_, p))| o + p.layout().0);
+        match entry(0) {
+            None if self.params.is_empty() => {}
+            None => return Err("params given, but binding 0 isn't declared".into()),
+            Some(_) if self.params.is_empty() => return Err("binding 0 is declared, but no params are given".into()),
+            Some(wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, min_binding_size, .. }) => {
+                let size = min_binding_size.map_or(0, |s| s.get());
+                if size < used || size > padded {
+                    return Err(format!("params take {used} bytes, but the uniform at binding 0 takes {size}"));
+                }
+            }
+            Some(_) => return Err("binding 0 isn't a uniform buffer".into()),
+        }
+        let textures = self.textures.len() as u32;
+        for i in 0..textures {
+            match entry(1 + 2 * i) {
+                Some(wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { .. },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                }) => {}
+                _ => return Err(format!("texture {i} needs a texture_2d<f32> at binding {}", 1 + 2 * i)),
+            }
+            if !matches!(entry(2 + 2 * i), Some(wgpu::BindingType::Sampler(_))) {
+                return Err(format!("texture {i} needs a sampler at binding {}", 2 + 2 * i));
+            }
+        }
+        match key.entries.iter().find(|e| e.binding > 2 * textures) {
+            Some(e) => Err(format!("binding {} is declared, but only {textures} textures are given", e.binding)),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn param_bytes(&self) -> Vec<u8> {
+        let (offsets, size) = self.param_offsets();
+        let mut bytes = vec![0u8; size as usize];
+        for ((_, p), offset) in self.params.iter().zip(offsets) {
+            let data = p.bytes();
+            bytes[offset as usize..offset as usize + data.len()].copy_from_slice(data);
+        }
+        bytes
+    }
+}
+
+pub struct Material {
+    pub desc: MaterialDesc,
+    pub pipeline: PipelineHandle,
+    pub(crate) layout_key: MaterialLayoutKey,
+    pub(crate) params_buffer: Option<wgpu::Buffer>,
+    pub(crate) bind_group: wgpu::BindGroup,
+}
+
+pub type MaterialHandle = Handle<Material>;
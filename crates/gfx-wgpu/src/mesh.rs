@@ -0,0 +1,143 @@
+use engine_core::Aabb;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+
+use crate::handle::Handle;
+use crate::instance::InstanceHandle;
+use crate::material::MaterialHandle;
+use crate::pipeline_cache::Pipeline;
+use crate::texture::TextureHandle;
+
+pub enum Indices<'a> {
+    U16(&'a [u16]),
+    U32(&'a [u32]),
+}
+
+pub struct IndexBuffer {
+    pub buffer: wgpu::Buffer,
+    pub format: wgpu::IndexFormat,
+    pub count: u32,
+}
+
+pub struct Mesh {
+    pub vbuf: wgpu::Buffer,
+    pub vcount: u32,
+    pub index: Option<IndexBuffer>,
+    /// Local-space bounds for culling (`Renderer::set_mesh_bounds`); unbounded meshes are always drawn.
+    pub bounds: Option<Aabb>,
+}
+
+pub type MeshHandle = Handle<Mesh>;
+pub type PipelineHandle = Handle<Pipeline>;
+
+/// One draw of a mesh with a pipeline. `transform` ends up in the object UBO (group 1).
+/// With `instances` set, the instance buffer is bound to vertex slot 1 and the mesh is drawn
+/// once per instance; the shader then applies both matrices. `texture` is bound at group 2.
+/// With `material` set, the material's pipeline and bind group are used instead.
+/// `stencil_ref` is what stencil `Replace` writes and the stencil test compares against.
+/// `bounds` replaces the mesh's for culling, in the same space; instanced draws are only culled
+/// with it, and it then has to cover every instance. `pick_id` is what `Renderer::pick` reports
+/// for it; 0 picks as nothing, though it still hides what's behind it.
+#[derive(Clone, Copy, Debug)]
+pub struct DrawItem {
+    pub mesh: MeshHandle,
+    pub pipeline: PipelineHandle,
+    pub transform: glam::Mat4,
+    pub instances: Option<InstanceHandle>,
+    pub texture: Option<TextureHandle>,
+    pub material: Option<MaterialHandle>,
+    pub stencil_ref: u32,
+    pub bounds: Option<Aabb>,
+    pub pick_id: u32,
+}
+
+impl DrawItem {
+    pub fn new(mesh: MeshHandle, pipeline: PipelineHandle) -> Self {
+        Self { mesh, pipeline, transform: glam::Mat4::IDENTITY, instances: None, texture: None, material: None, stencil_ref: 0, bounds: None, pick_id: 0 }
+    }
+    /// The pipeline is resolved from the material on `Renderer::submit`.
+    pub fn from_material(mesh: MeshHandle, material: MaterialHandle) -> Self {
+        Self { material: Some(material), ..Self::new(mesh, PipelineHandle::dangling()) }
+    }
+    pub fn with_transform(mut self, transform: glam::Mat4) -> Self {
+        self.transform = transform;
+        self
+    }
+    pub fn with_instances(mut self, instances: InstanceHandle) -> Self {
+        self.instances = Some(instances);
+        self
+    }
+    pub fn with_texture(mut self, texture: TextureHandle) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+    pub fn with_stencil_ref(mut self, stencil_ref: u32) -> Self {
+        self.stencil_ref = stencil_ref;
+        self
+    }
+    pub fn with_bounds(mut self, bounds: Aabb) -> Self {
+        self.bounds = Some(bounds);
+        self
+    }
+    pub fn with_pick_id(mut self, id: u32) -> Self {
+        self.pick_id = id;
+        self
+    }
+    /// The local bounds culling goes by, given the mesh's.
+    pub(crate) fn cull_bounds(&self, mesh: Option<&Mesh>) -> Option<Aabb> {
+        match self.instances {
+            Some(_) => self.bounds,
+            None => self.bounds.or(mesh?.bounds),
+        }
+    }
+}
+
+/// How many draws the last frame culled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CullStats {
+    /// Draws queued for the frame.
+    pub submitted: usize,
+    /// Of those, the ones skipped for being out of view.
+    pub culled: usize,
+}
+
+impl CullStats {
+    pub fn drawn(&self) -> usize {
+        self.submitted - self.culled
+    }
+}
+
+impl Mesh {
+    pub fn new<V: bytemuck::Pod>(device: &wgpu::Device, vertices: &[V], indices: Option<Indices>) -> Self {
+        let vbuf = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("mesh_vbuf"),
+            contents: bytemuck::cast_slice(vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let index = indices.map(|idx| {
+            let (contents, format, count): (&[u8], _, _) = match idx {
+                Indices::U16(i) => (bytemuck::cast_slice(i), wgpu::IndexFormat::Uint16, i.len()),
+                Indices::U32(i) => (bytemuck::cast_slice(i), wgpu::IndexFormat::Uint32, i.len()),
+            };
+            let buffer = device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("mesh_ibuf"),
+                contents,
+                usage: wgpu::BufferUsages::INDEX,
+            });
+            IndexBuffer { buffer, format, count: count as u32 }
+        });
+
+        Self { vbuf, vcount: vertices.len() as u32, index, bounds: None }
+    }
+
+    pub fn draw(&self, rp: &mut wgpu::RenderPass<'_>, instances: std::ops::Range<u32>) {
+        rp.set_vertex_buffer(0, self.vbuf.slice(..));
+        match &self.index {
+            Some(ib) => {
+                rp.set_index_buffer(ib.buffer.slice(..), ib.format);
+                rp.draw_indexed(0..ib.count, 0, instances);
+            }
+            None => rp.draw(0..self.vcount, instances),
+        }
+    }
+}
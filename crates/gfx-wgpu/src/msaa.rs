@@ -0,0 +1,41 @@
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MsaaError {
+    /// The color or depth format can't be rendered (and resolved) at that many samples.
+    Unsupported { requested: u32, supported: Vec<u32> },
+}
+
+impl std::fmt::Display for MsaaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MsaaError::Unsupported { requested, supported } => {
+                write!(f, "{requested}x MSAA is not supported here (supported: {supported:?})")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MsaaError {}
+
+/// Sample counts both formats can be rendered at, the color one resolved, ascending.
+/// Always contains 1.
+pub fn supported_sample_counts(
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+    color: wgpu::TextureFormat,
+    depth: wgpu::TextureFormat,
+) -> Vec<u32> {
+    // Without this feature the device only allows what WebGPU guarantees, whatever the adapter says.
+    let features = |format: wgpu::TextureFormat| {
+        if device.features().contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES) {
+            adapter.get_texture_format_features(format)
+        } else {
+            format.guaranteed_format_features(device.features())
+        }
+    };
+    let (color, depth) = (features(color), features(depth));
+    let resolvable = color.flags.contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE);
+    [1, 2, 4, 8, 16]
+        .into_iter()
+        .filter(|&n| n == 1 || (resolvable && color.flags.sample_count_supported(n) && depth.flags.sample_count_supported(n)))
+        .collect()
+}
@@ -0,0 +1,84 @@
+use bytemuck::{Pod, Zeroable};
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct ObjectUBO { pub model: [[f32; 4]; 4] }
+
+/// Per-draw uniforms in one buffer, addressed with dynamic offsets (group 1).
+pub struct ObjectBind<T = ObjectUBO> {
+    pub bgl: wgpu::BindGroupLayout,
+    pub buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+    pub stride: u64,
+    capacity: u64,
+    _slot: std::marker::PhantomData<T>,
+}
+
+impl<T: Pod> ObjectBind<T> {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("object_bgl"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<T>() as u64),
+                },
+                count: None,
+            }],
+        });
+
+        let align = device.limits().min_uniform_buffer_offset_alignment as u64;
+        let stride = (std::mem::size_of::<T>() as u64).next_multiple_of(align);
+        let capacity = 64;
+        let (buffer, bind_group) = Self::create(device, &bgl, stride, capacity);
+
+        Self { bgl, buffer, bind_group, stride, capacity, _slot: std::marker::PhantomData }
+    }
+
+    fn create(device: &wgpu::Device, bgl: &wgpu::BindGroupLayout, stride: u64, capacity: u64) -> (wgpu::Buffer, wgpu::BindGroup) {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("object_ubo"),
+            size: stride * capacity,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("object_bg"),
+            layout: bgl,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<T>() as u64),
+                }),
+            }],
+        });
+        (buffer, bind_group)
+    }
+
+    /// Uploads one slot per object, growing the buffer when needed.
+    pub fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, objects: &[T]) {
+        if objects.is_empty() { return; }
+        let needed = objects.len() as u64;
+        if needed > self.capacity {
+            self.capacity = needed.next_power_of_two();
+            (self.buffer, self.bind_group) = Self::create(device, &self.bgl, self.stride, self.capacity);
+        }
+
+        let mut data = vec![0u8; (self.stride * needed) as usize];
+        for (i, obj) in objects.iter().enumerate() {
+            let at = i * self.stride as usize;
+            data[at..at + std::mem::size_of::<T>()].copy_from_slice(bytemuck::bytes_of(obj));
+        }
+        queue.write_buffer(&self.buffer, 0, &data);
+    }
+
+    pub fn offset(&self, index: usize) -> u32 {
+        (index as u64 * self.stride) as u32
+    }
+}
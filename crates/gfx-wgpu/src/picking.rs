@@ -0,0 +1,330 @@
+//! GPU picking: the last frame's draws once more, writing their `DrawItem::pick_id` instead of a
+//! color, into the single pixel under the cursor.
+
+use std::collections::HashMap;
+
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec2, Vec4};
+
+use crate::camera_bind::CameraBind;
+use crate::instance::{InstanceBuffer, InstanceData};
+use crate::mesh::Mesh;
+use crate::object_bind::ObjectBind;
+use crate::pipeline_cache::Pipeline;
+
+/// Object IDs are rendered in this format.
+pub const PICK_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+
+/// What's under the cursor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PickHit {
+    /// The `pick_id` of the draw; typically an entity.
+    pub id: u32,
+    /// As the depth buffer has it, for `Camera::screen_to_world`.
+    pub depth: f32,
+}
+
+#[derive(Debug)]
+pub enum PickError {
+    Map(wgpu::BufferAsyncError),
+    Poll(wgpu::PollError),
+}
+
+impl std::fmt::Display for PickError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PickError::Map(e) => write!(f, "pick readback mapping failed: {e}"),
+            PickError::Poll(e) => write!(f, "device poll failed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for PickError {}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub(crate) struct PickUBO {
+    model: [[f32; 4]; 4],
+    id: u32,
+    _pad: [u32; 3],
+}
+
+/// One draw from the last frame.
+pub(crate) struct PickDraw<'a> {
+    pub mesh: &'a Mesh,
+    pub pipeline: &'a Pipeline,
+    pub instances: Option<&'a InstanceBuffer>,
+    pub transform: Mat4,
+    pub id: u32,
+}
+
+/// Everything the pick pipeline takes from the scene pipeline it stands in for.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct PickKey {
+    primitive: wgpu::PrimitiveState,
+    position: wgpu::VertexAttribute,
+    stride: u64,
+    instanced: bool,
+    reverse_z: bool,
+}
+
+impl PickKey {
+    /// `None` when the pipeline has no float position at location 0 to go by.
+    fn new(draw: &PickDraw, reverse_z: bool) -> Option<Self> {
+        let layout = draw.pipeline.vertex_layouts.first()?;
+        let position = *layout.attributes.iter().find(|a| a.shader_location == 0)?;
+        if !matches!(position.format, wgpu::VertexFormat::Float32x2 | wgpu::VertexFormat::Float32x3 | wgpu::VertexFormat::Float32x4) {
+            return None;
+        }
+        Some(Self {
+            primitive: crate::state::primitive(&draw.pipeline.state),
+            position,
+            stride: layout.array_stride,
+            instanced: draw.instances.is_some(),
+            reverse_z,
+        })
+    }
+
+    fn entry_point(&self) -> &'static str {
+        match (self.position.format, self.instanced) {
+            (wgpu::VertexFormat::Float32x2, false) => "vs_2d",
+            (wgpu::VertexFormat::Float32x2, true) => "vs_2d_instanced",
+            (wgpu::VertexFormat::Float32x3, false) => "vs_3d",
+            (wgpu::VertexFormat::Float32x3, true) => "vs_3d_instanced",
+            (_, false) => "vs_4d",
+            (_, true) => "vs_4d_instanced",
+        }
+    }
+}
+
+pub(crate) struct Picker {
+    camera: CameraBind,
+    objects: ObjectBind<PickUBO>,
+    layout: wgpu::PipelineLayout,
+    module: wgpu::ShaderModule,
+    pipelines: HashMap<PickKey, wgpu::RenderPipeline>,
+    /// Single-pixel IDs, depth and the depth attachment. Depth is written out as color bits too,
+    /// which read back where depth textures don't and render where float targets can't (GL).
+    targets: [(wgpu::Texture, wgpu::TextureView); 3],
+    readback: wgpu::Buffer,
+}
+
+impl Picker {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        let camera = CameraBind::new(device);
+        let objects = ObjectBind::new(device);
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("pick_layout"),
+            bind_group_layouts: &[&camera.bgl, &objects.bgl],
+            push_constant_ranges: &[],
+        });
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("pick"),
+            source: wgpu::ShaderSource::Wgsl(PICK_WGSL.into()),
+        });
+        let target = |label, format, usage| {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d { width: 1, height: 1, depth_or_array_layers: 1 },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&Default::default());
+            (texture, view)
+        };
+        let color = wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC;
+        let targets = [
+            target("pick_ids", PICK_FORMAT, color),
+            target("pick_depth", PICK_FORMAT, color),
+            target("pick_depth_attachment", crate::types::DEPTH_FORMAT, wgpu::TextureUsages::RENDER_ATTACHMENT),
+        ];
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("pick_readback"),
+            size: 8,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        Self { camera, objects, layout, module, pipelines: HashMap::new(), targets, readback }
+    }
+
+    fn pipeline(&mut self, device: &wgpu::Device, key: PickKey) {
+        let (layout, module) = (&self.layout, &self.module);
+        self.pipelines.entry(key).or_insert_with(|| {
+            let attributes = [wgpu::VertexAttribute { shader_location: 0, ..key.position }];
+            let mut buffers = vec![wgpu::VertexBufferLayout {
+                array_stride: key.stride,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &attributes,
+            }];
+            if key.instanced {
+                buffers.push(InstanceData::layout());
+            }
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("pick"),
+                layout: Some(layout),
+                vertex: wgpu::VertexState {
+                    module,
+                    entry_point: Some(key.entry_point()),
+                    buffers: &buffers,
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(PICK_FORMAT.into()), Some(PICK_FORMAT.into())],
+                    compilation_options: Default::default(),
+                }),
+                primitive: key.primitive,
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: crate::types::DEPTH_FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: if key.reverse_z { wgpu::CompareFunction::Greater } else { wgpu::CompareFunction::Less },
+                    stencil: Default::default(),
+                    bias: Default::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            })
+        });
+    }
+
+    /// Renders `draws` as `view_proj` sees them into the pixel under `cursor` and reads it back.
+    pub(crate) fn pick(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        (view_proj, reverse_z): (Mat4, bool),
+        (cursor, viewport): (Vec2, Vec2),
+        draws: &[PickDraw],
+    ) -> Result<Option<PickHit>, PickError> {
+        // Blow the pixel up to fill the whole clip space.
+        let center = ((cursor.floor() + 0.5) / viewport * 2.0 - 1.0) * Vec2::new(1.0, -1.0);
+        let zoom = Mat4::from_cols(
+            Vec4::new(viewport.x, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, viewport.y, 0.0, 0.0),
+            Vec4::Z,
+            (-center * viewport).extend(0.0).extend(1.0),
+        );
+        let camera = engine_core::CameraUBO { view_proj: (zoom * view_proj).to_cols_array_2d() };
+        queue.write_buffer(&self.camera.buffer, 0, bytemuck::bytes_of(&camera));
+
+        let keyed: Vec<(PickKey, &PickDraw)> = draws.iter()
+            .filter_map(|d| Some((PickKey::new(d, reverse_z)?, d)))
+            .collect();
+        for (key, _) in &keyed {
+            self.pipeline(device, *key);
+        }
+        let ubos: Vec<PickUBO> = keyed.iter()
+            .map(|(_, d)| PickUBO { model: d.transform.to_cols_array_2d(), id: d.id, _pad: [0; 3] })
+            .collect();
+        self.objects.write(device, queue, &ubos);
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("pick") });
+        {
+            let clear = |value| wgpu::Operations { load: wgpu::LoadOp::Clear(value), store: wgpu::StoreOp::Store };
+            let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("pick"),
+                color_attachments: &[
+                    Some(wgpu::RenderPassColorAttachment { view: &self.targets[0].1, resolve_target: None, ops: clear(wgpu::Color::TRANSPARENT) }),
+                    Some(wgpu::RenderPassColorAttachment { view: &self.targets[1].1, resolve_target: None, ops: clear(wgpu::Color::TRANSPARENT) }),
+                ],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.targets[2].1,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(if reverse_z { 0.0 } else { 1.0 }),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            rp.set_bind_group(0, &self.camera.bind_group, &[]);
+            for (i, (key, d)) in keyed.iter().enumerate() {
+                rp.set_pipeline(&self.pipelines[key]);
+                rp.set_bind_group(1, &self.objects.bind_group, &[self.objects.offset(i)]);
+                match d.instances {
+                    Some(inst) => {
+                        rp.set_vertex_buffer(1, inst.buffer.slice(..));
+                        d.mesh.draw(&mut rp, 0..inst.count);
+                    }
+                    None => d.mesh.draw(&mut rp, 0..1),
+                }
+            }
+        }
+        for (i, (texture, _)) in self.targets[..2].iter().enumerate() {
+            encoder.copy_texture_to_buffer(
+                texture.as_image_copy(),
+                wgpu::TexelCopyBufferInfo {
+                    buffer: &self.readback,
+                    layout: wgpu::TexelCopyBufferLayout { offset: i as u64 * 4, bytes_per_row: None, rows_per_image: None },
+                },
+                wgpu::Extent3d { width: 1, height: 1, depth_or_array_layers: 1 },
+            );
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = self.readback.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |res| { let _ = tx.send(res); });
+        device.poll(wgpu::PollType::Wait).map_err(PickError::Poll)?;
+        rx.recv().expect("map_async callback dropped").map_err(PickError::Map)?;
+        let (id, depth) = {
+            let data = slice.get_mapped_range();
+            let words: &[u32] = bytemuck::cast_slice(&data);
+            (words[0], f32::from_bits(words[1]))
+        };
+        self.readback.unmap();
+        Ok((id != 0).then_some(PickHit { id, depth }))
+    }
+}
+
+const PICK_WGSL: &str = r#"
+struct CameraUBO { view_proj: mat4x4<f32> };
+@group(0) @binding(0) var<uniform> cam: CameraUBO;
+
+struct PickUBO { model: mat4x4<f32>, id: u32 };
+@group(1) @binding(0) var<uniform> obj: PickUBO;
+
+struct VsOut {
+  @builtin(position) pos: vec4<f32>,
+  @location(0) @interpolate(flat) id: u32,
+};
+
+struct Instance {
+  @location(4) m0: vec4<f32>,
+  @location(5) m1: vec4<f32>,
+  @location(6) m2: vec4<f32>,
+  @location(7) m3: vec4<f32>,
+};
+
+fn place(local: vec4<f32>) -> VsOut {
+  return VsOut(cam.view_proj * obj.model * local, obj.id);
+}
+
+fn instance(i: Instance) -> mat4x4<f32> {
+  return mat4x4<f32>(i.m0, i.m1, i.m2, i.m3);
+}
+
+@vertex fn vs_2d(@location(0) p: vec2<f32>) -> VsOut { return place(vec4<f32>(p, 0.0, 1.0)); }
+@vertex fn vs_3d(@location(0) p: vec3<f32>) -> VsOut { return place(vec4<f32>(p, 1.0)); }
+@vertex fn vs_4d(@location(0) p: vec4<f32>) -> VsOut { return place(p); }
+@vertex fn vs_2d_instanced(@location(0) p: vec2<f32>, i: Instance) -> VsOut { return place(instance(i) * vec4<f32>(p, 0.0, 1.0)); }
+@vertex fn vs_3d_instanced(@location(0) p: vec3<f32>, i: Instance) -> VsOut { return place(instance(i) * vec4<f32>(p, 1.0)); }
+@vertex fn vs_4d_instanced(@location(0) p: vec4<f32>, i: Instance) -> VsOut { return place(instance(i) * p); }
+
+struct FsOut {
+  @location(0) id: u32,
+  @location(1) depth: u32,
+};
+
+@fragment
+fn fs_main(in: VsOut) -> FsOut {
+  return FsOut(in.id, bitcast<u32>(in.pos.z));
+}
+"#;
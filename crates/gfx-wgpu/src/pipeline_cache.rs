@@ -1,87 +1,263 @@
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
-use shader_core::{WgslSource, RenderState, ShaderKey, Topology, Overrides};
+use shader_core::{
+    DiagnosticKind, OverrideError, Overrides, PreprocessError, RenderState, ShaderDiagnostic, ShaderKey, ShaderReflection,
+    WgslSource,
+};
 use wgpu::{Device, PipelineLayout, TextureFormat};
 
+use crate::disk_cache::DiskCache;
+
+#[derive(Debug)]
+pub enum PipelineError {
+    /// Loading or expanding the source failed (only on reload).
+    Preprocess(PreprocessError),
+    /// The WGSL didn't parse or validate, or wgpu rejected the module or pipeline.
+    Compile(ShaderDiagnostic),
+    Overrides(OverrideError),
+    /// The handle doesn't name a live pipeline.
+    StaleHandle,
+    /// A material names a texture that was removed (or never existed).
+    StaleTexture,
+    /// A material doesn't fit the group 2 layout its shader declares.
+    Layout(String),
+    /// A shader file couldn't be watched, so edits to it won't be hot reloaded.
+    Watch(std::path::PathBuf, shader_core::WatchError),
+}
+
+impl std::fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PipelineError::Preprocess(e) => write!(f, "{e}"),
+            PipelineError::Compile(e) => write!(f, "{e}"),
+            PipelineError::Overrides(e) => write!(f, "{e}"),
+            PipelineError::StaleHandle => write!(f, "pipeline handle is stale or unknown"),
+            PipelineError::StaleTexture => write!(f, "texture handle is stale or unknown"),
+            PipelineError::Layout(msg) => write!(f, "{msg}"),
+            PipelineError::Watch(path, e) => write!(f, "can't watch {} for changes: {e}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for PipelineError {}
+
+impl From<PreprocessError> for PipelineError {
+    fn from(e: PreprocessError) -> Self { PipelineError::Preprocess(e) }
+}
+
+impl From<ShaderDiagnostic> for PipelineError {
+    fn from(e: ShaderDiagnostic) -> Self { PipelineError::Compile(e) }
+}
+
+impl From<OverrideError> for PipelineError {
+    fn from(e: OverrideError) -> Self { PipelineError::Overrides(e) }
+}
+
+/// Which pipeline layout a pipeline was built against. Part of the cache key, since the same
+/// shader permutation compiled against different layouts gives different pipelines.
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+pub enum LayoutKey {
+    /// camera (0) + object (1)
+    Scene,
+    /// camera (0) + object (1) + texture/sampler (2)
+    Textured,
+    /// camera (0) + object (1) + material group (2)
+    Material(MaterialLayoutKey),
+    /// A post-process effect: source texture (0), sampler (1), params (2), aux texture (3)
+    Post,
+    /// Tonemapping: HDR texture (0), exposure (1)
+    Tonemap,
+}
+
+/// A material's bind group layout, as its shader declares group 2 (see `MaterialDesc::layout_key_for`).
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+pub struct MaterialLayoutKey {
+    pub entries: Vec<wgpu::BindGroupLayoutEntry>,
+}
+
+pub struct MaterialLayout {
+    pub bgl: wgpu::BindGroupLayout,
+    pub layout: PipelineLayout,
+}
+
+/// A built pipeline plus everything needed to build it again.
+pub struct Pipeline {
+    pub raw: wgpu::RenderPipeline,
+    pub layout: LayoutKey,
+    pub src: WgslSource,
+    pub state: RenderState<TextureFormat>,
+    pub overrides: Overrides,
+    pub vertex_layouts: Vec<wgpu::VertexBufferLayout<'static>>,
+}
+
 pub struct PipelineCache {
-    map: HashMap<ShaderKey<TextureFormat>, wgpu::RenderPipeline>,
+    map: HashMap<(LayoutKey, ShaderKey<TextureFormat>), wgpu::RenderPipeline>,
+    material_layouts: HashMap<MaterialLayoutKey, MaterialLayout>,
+    /// By `ShaderKey::source_hash`, so cache hits can still check their overrides.
+    reflections: HashMap<u64, ShaderReflection>,
+    disk: Option<DiskCache>,
+    depth_format: TextureFormat,
+}
+
+impl Default for PipelineCache {
+    fn default() -> Self { Self::new() }
 }
 
 impl PipelineCache {
-    pub fn new() -> Self { Self { map: HashMap::new() } }
+    pub fn new() -> Self {
+        Self {
+            map: HashMap::new(),
+            material_layouts: HashMap::new(),
+            reflections: HashMap::new(),
+            disk: None,
+            depth_format: crate::types::DEPTH_FORMAT,
+        }
+    }
+
+    /// Depth attachment format pipelines with `RenderState::depth` are built for.
+    pub fn with_depth_format(mut self, format: TextureFormat) -> Self {
+        self.depth_format = format;
+        self
+    }
+
+    /// Persist compiled permutations (and the driver cache, where there is one) through `disk`.
+    pub fn set_disk_cache(&mut self, disk: DiskCache) { self.disk = Some(disk); }
+    pub fn disk_cache(&self) -> Option<&DiskCache> { self.disk.as_ref() }
+
+    /// Bind group layout for the material group plus the full pipeline layout, with the
+    /// renderer's shared groups (`scene_bgls`) in front.
+    pub fn material_layout(
+        &mut self,
+        device: &Device,
+        key: &MaterialLayoutKey,
+        scene_bgls: &[&wgpu::BindGroupLayout],
+    ) -> &MaterialLayout {
+        self.material_layouts.entry(key.clone()).or_insert_with(|| {
+            let bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("material_bgl"),
+                entries: &key.entries,
+            });
+            let mut groups = scene_bgls.to_vec();
+            groups.push(&bgl);
+            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("material_layout"),
+                bind_group_layouts: &groups,
+                push_constant_ranges: &[],
+            });
+            MaterialLayout { bgl, layout }
+        })
+    }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn get_or_create(
         &mut self,
         key: ShaderKey<TextureFormat>,
+        layout_key: LayoutKey,
         device: &Device,
         layout: &PipelineLayout,
         src: &WgslSource,
         state: &RenderState<TextureFormat>,
         overrides: &Overrides,
         vertex_layouts: &[wgpu::VertexBufferLayout<'static>],
-    ) -> &wgpu::RenderPipeline {
-        self.map.entry(key.clone()).or_insert_with(|| {
-            let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: Some(src.name),
-                source: wgpu::ShaderSource::Wgsl(src.code.into()),
-            });
+    ) -> Result<&wgpu::RenderPipeline, PipelineError> {
+        let id = key.permutation_id(&layout_key);
+        let source_hash = key.source_hash;
+
+        // Checked up front: naga gives us positions, and wgpu panics on unknown or mistyped constants.
+        // Hits are checked too: conversions can map a mistyped value onto a valid permutation's key.
+        let reflection = match self.reflections.entry(source_hash) {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => e.insert(src.check()?),
+        };
+        let constants = overrides.validate(&src.name, reflection)?;
+        let entry = match self.map.entry((layout_key, key)) {
+            Entry::Occupied(e) => return Ok(e.into_mut()),
+            Entry::Vacant(e) => e,
+        };
+        let constants: Vec<(&str, f64)> = constants.iter().map(|(k, v)| (k.as_str(), *v)).collect();
 
-            let topo = match state.topo {
-                Topology::TriangleList  => wgpu::PrimitiveTopology::TriangleList,
-                Topology::TriangleStrip => wgpu::PrimitiveTopology::TriangleStrip,
-                Topology::LineList      => wgpu::PrimitiveTopology::LineList,
+        // Anything wgpu still objects to lands in the scopes instead of the uncaptured-error panic.
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(&src.name),
+            source: wgpu::ShaderSource::Wgsl(src.code.clone()),
+        });
+        if let Some(err) = pollster::block_on(device.pop_error_scope()) {
+            return Err(module_diagnostic(src, &module, &err).into());
+        }
+
+        if state.stencil.is_enabled() {
+            let msg = if !state.depth {
+                Some("stencil state set, but depth is off and the stencil lives in the depth buffer".to_string())
+            } else if !self.depth_format.has_stencil_aspect() {
+                Some(format!("stencil state set, but the depth buffer ({:?}) has no stencil", self.depth_format))
+            } else {
+                None
             };
+            if let Some(msg) = msg {
+                return Err(src.diagnostic(DiagnosticKind::Pipeline, msg, None).into());
+            }
+        }
+        let targets = crate::state::color_targets(state);
 
-            // --- NEW: z HashMap -> posortowana vec i wycinek (&[(&str, f64)])
-            // kopiujemy, sortujemy po kluczu żeby hash/klucz był deterministyczny
-            let mut pairs_owned: Vec<(String, f64)> =
-                overrides.map.iter().map(|(k,v)| (k.clone(), *v)).collect();
-            pairs_owned.sort_by(|a,b| a.0.cmp(&b.0));
-            // teraz robimy wektor referencji (&str, f64); ważne: odnosi się do pairs_owned
-            let pairs_ref: Vec<(&str, f64)> =
-                pairs_owned.iter().map(|(k,v)| (k.as_str(), *v)).collect();
-
-            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some(&format!("pso:{}", src.name)),
-                layout: Some(layout),
-                vertex: wgpu::VertexState {
-                    module: &module,
-                    entry_point: Some("vs_main"),
-                    buffers: vertex_layouts,
-                    compilation_options: wgpu::PipelineCompilationOptions {
-                        constants: &pairs_ref,
-                        zero_initialize_workgroup_memory: true,
-                        ..Default::default()
-                    },
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(&format!("pso:{}", src.name)),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: Some("vs_main"),
+                buffers: vertex_layouts,
+                compilation_options: wgpu::PipelineCompilationOptions {
+                    constants: &constants,
+                    zero_initialize_workgroup_memory: true,
                 },
-                fragment: Some(wgpu::FragmentState {
-                    module: &module,
-                    entry_point: Some("fs_main"),
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: state.format,
-                        blend: Some(wgpu::BlendState::REPLACE),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                    compilation_options: wgpu::PipelineCompilationOptions {
-                        constants: &pairs_ref,
-                        zero_initialize_workgroup_memory: true,
-                        ..Default::default()
-                    },
-                }),
-                primitive: wgpu::PrimitiveState { topology: topo, ..Default::default() },
-                depth_stencil: if state.depth {
-                    Some(wgpu::DepthStencilState {
-                        format: crate::types::DEPTH_FORMAT,
-                        depth_write_enabled: true,
-                        depth_compare: wgpu::CompareFunction::Less,
-                        stencil: wgpu::StencilState::default(),
-                        bias: wgpu::DepthBiasState::default(),
-                    })
-                } else { None },
-                multisample: wgpu::MultisampleState { count: state.msaa, ..Default::default() },
-                multiview: None,
-                cache: None,
-            })
-        })
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: Some("fs_main"),
+                targets: &targets,
+                compilation_options: wgpu::PipelineCompilationOptions {
+                    constants: &constants,
+                    zero_initialize_workgroup_memory: true,
+                },
+            }),
+            primitive: crate::state::primitive(state),
+            depth_stencil: crate::state::depth_stencil(state, self.depth_format),
+            multisample: wgpu::MultisampleState { count: state.msaa, ..Default::default() },
+            multiview: None,
+            cache: self.disk.as_ref().and_then(|d| d.driver_cache()),
+        });
+        if let Some(err) = pollster::block_on(device.pop_error_scope()) {
+            return Err(src.diagnostic(DiagnosticKind::Pipeline, error_message(&err), None).into());
+        }
+        if let Some(disk) = &mut self.disk {
+            disk.record(id, &src.name, source_hash);
+        }
+        Ok(entry.insert(pipeline))
     }
+}
 
+/// Prefers the module's own compilation messages, which carry positions.
+fn module_diagnostic(src: &WgslSource, module: &wgpu::ShaderModule, err: &wgpu::Error) -> ShaderDiagnostic {
+    let info = pollster::block_on(module.get_compilation_info());
+    let msg = info.messages.into_iter().find(|m| m.message_type == wgpu::CompilationMessageType::Error);
+    match msg {
+        Some(m) => {
+            let position = m.location.map(|l| (l.line_number, l.line_position));
+            src.diagnostic(DiagnosticKind::Validation, m.message, position)
+        }
+        None => src.diagnostic(DiagnosticKind::Pipeline, error_message(err), None),
+    }
+}
+
+/// The error's cause chain on one line, without wgpu's "Validation Error / Caused by" framing.
+fn error_message(err: &wgpu::Error) -> String {
+    let mut parts = Vec::new();
+    let mut cur = std::error::Error::source(err);
+    while let Some(e) = cur {
+        parts.push(e.to_string());
+        cur = e.source();
+    }
+    if parts.is_empty() { err.to_string() } else { parts.join(": ") }
 }
@@ -0,0 +1,953 @@
+//! Full-screen effects over the finished (tonemapped) image, run in stack order. Each is drawn
+//! with pipelines from the `PipelineCache`, its compile-time choices passed as `Overrides`.
+//!
+//! Effects assume an sRGB frame format, which `GfxContext` picks wherever the surface has one.
+
+use std::sync::Arc;
+
+use shader_core::{BlendMode, Overrides, RenderState, WgslSource};
+
+use crate::graph::{GraphPass, PassBuilder, PassContext, ResourceId, TextureDesc, TextureSize, FRAME, LDR};
+use crate::hdr::HDR_FORMAT;
+
+/// Glow around bright areas, blurred through a chain of half-size copies.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bloom {
+    /// Brightness where the glow starts; `knee` softens the cut-off.
+    pub threshold: f32,
+    pub knee: f32,
+    pub intensity: f32,
+    /// Upsampling filter radius, in texels of each level.
+    pub radius: f32,
+    /// Number of half-size levels, 1..=8; more spreads the glow wider.
+    pub levels: u32,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Self { threshold: 0.8, knee: 0.2, intensity: 0.6, radius: 1.0, levels: 5 }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum FxaaQuality {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+/// Fast approximate antialiasing: blurs along edges found by luma contrast.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Fxaa {
+    pub quality: FxaaQuality,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SmaaQuality {
+    Low,
+    #[default]
+    Medium,
+    High,
+    Ultra,
+}
+
+/// Subpixel morphological antialiasing: finds edges, traces how far each one runs to guess the
+/// shape it came from, and blends across it by how much of each pixel that shape covers. Sharper
+/// than FXAA on text and fine detail.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Smaa {
+    pub quality: SmaaQuality,
+}
+
+/// Darkens the image toward its edges.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Vignette {
+    pub intensity: f32,
+    /// Distance from the center (1 = middle of an edge) where darkening starts.
+    pub radius: f32,
+    pub smoothness: f32,
+    /// Circular regardless of aspect; otherwise it follows the screen's shape.
+    pub rounded: bool,
+}
+
+impl Default for Vignette {
+    fn default() -> Self {
+        Self { intensity: 0.4, radius: 0.6, smoothness: 0.6, rounded: true }
+    }
+}
+
+/// Splits colors apart toward the edges, like a cheap lens.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChromaticAberration {
+    /// Red/blue offset at the corners, in pixels.
+    pub intensity: f32,
+    /// Taps across the spectrum, at least 3; more smears instead of splitting.
+    pub samples: u32,
+}
+
+impl Default for ChromaticAberration {
+    fn default() -> Self {
+        Self { intensity: 4.0, samples: 3 }
+    }
+}
+
+/// A 3D color lookup table, sRGB-encoded in and out.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorLut {
+    size: u32,
+    /// `size` slices side by side: `size * size` wide, `size` high. Blue picks the slice.
+    rgba: Vec<u8>,
+}
+
+impl ColorLut {
+    pub fn identity(size: u32) -> Self {
+        Self::from_fn(size, |c| c)
+    }
+
+    /// Samples `f` at every entry; colors are 0..1.
+    pub fn from_fn(size: u32, f: impl Fn([f32; 3]) -> [f32; 3]) -> Self {
+        assert!(size >= 2, "a LUT needs at least 2 entries per axis");
+        let max = (size - 1) as f32;
+        let mut rgba = Vec::with_capacity((size * size * size * 4) as usize);
+        for g in 0..size {
+            for b in 0..size {
+                for r in 0..size {
+                    let c = f([r as f32 / max, g as f32 / max, b as f32 / max]);
+                    rgba.extend(c.map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8));
+                    rgba.push(255);
+                }
+            }
+        }
+        Self { size, rgba }
+    }
+
+    /// A LUT laid out as a strip of slices (the common `N² × N` PNG). `None` if it isn't one.
+    pub fn from_strip(width: u32, height: u32, rgba: Vec<u8>) -> Option<Self> {
+        let valid = height >= 2 && width == height * height && rgba.len() == (width * height * 4) as usize;
+        valid.then_some(Self { size: height, rgba })
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+}
+
+/// Grades the image through a LUT.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorGrading {
+    pub lut: Arc<ColorLut>,
+    /// Blend between the original (0) and graded (1) color.
+    pub intensity: f32,
+}
+
+impl Default for ColorGrading {
+    fn default() -> Self {
+        Self { lut: Arc::new(ColorLut::identity(16)), intensity: 1.0 }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PostEffect {
+    Bloom(Bloom),
+    Fxaa(Fxaa),
+    Smaa(Smaa),
+    Vignette(Vignette),
+    ChromaticAberration(ChromaticAberration),
+    ColorGrading(ColorGrading),
+}
+
+impl PostEffect {
+    /// Also the effect's graph pass name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PostEffect::Bloom(_) => "bloom",
+            PostEffect::Fxaa(_) => "fxaa",
+            PostEffect::Smaa(_) => "smaa",
+            PostEffect::Vignette(_) => "vignette",
+            PostEffect::ChromaticAberration(_) => "chromatic_aberration",
+            PostEffect::ColorGrading(_) => "color_grading",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PostEffect::Bloom(_) => "Bloom",
+            PostEffect::Fxaa(_) => "FXAA",
+            PostEffect::Smaa(_) => "SMAA",
+            PostEffect::Vignette(_) => "Vignette",
+            PostEffect::ChromaticAberration(_) => "Chromatic aberration",
+            PostEffect::ColorGrading(_) => "Color grading",
+        }
+    }
+
+    /// Sliders for the runtime parameters; returns whether any moved.
+    fn ui(&mut self, ui: &dyn ui_core::Ui) -> bool {
+        let mut changed = false;
+        match self {
+            PostEffect::Bloom(b) => {
+                changed |= ui.slider_f32("Bloom threshold", 0.0..=2.0, &mut b.threshold);
+                changed |= ui.slider_f32("Bloom intensity", 0.0..=2.0, &mut b.intensity);
+                changed |= ui.slider_f32("Bloom radius", 0.5..=3.0, &mut b.radius);
+            }
+            PostEffect::Fxaa(f) => {
+                if ui.button(&format!("FXAA quality: {:?}", f.quality)) {
+                    f.quality = match f.quality {
+                        FxaaQuality::Low => FxaaQuality::Medium,
+                        FxaaQuality::Medium => FxaaQuality::High,
+                        FxaaQuality::High => FxaaQuality::Low,
+                    };
+                    changed = true;
+                }
+            }
+            PostEffect::Smaa(s) => {
+                if ui.button(&format!("SMAA quality: {:?}", s.quality)) {
+                    s.quality = match s.quality {
+                        SmaaQuality::Low => SmaaQuality::Medium,
+                        SmaaQuality::Medium => SmaaQuality::High,
+                        SmaaQuality::High => SmaaQuality::Ultra,
+                        SmaaQuality::Ultra => SmaaQuality::Low,
+                    };
+                    changed = true;
+                }
+            }
+            PostEffect::Vignette(v) => {
+                changed |= ui.slider_f32("Vignette intensity", 0.0..=1.0, &mut v.intensity);
+                changed |= ui.slider_f32("Vignette radius", 0.0..=1.5, &mut v.radius);
+                changed |= ui.checkbox("Round vignette", &mut v.rounded);
+            }
+            PostEffect::ChromaticAberration(c) => {
+                changed |= ui.slider_f32("Aberration (px)", 0.0..=20.0, &mut c.intensity);
+            }
+            PostEffect::ColorGrading(g) => {
+                changed |= ui.slider_f32("Grading intensity", 0.0..=1.0, &mut g.intensity);
+            }
+        }
+        changed
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PostEntry {
+    pub effect: PostEffect,
+    pub enabled: bool,
+}
+
+/// The ordered effects run after the scene (and tonemapping). Holds one of each kind.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PostStack {
+    entries: Vec<PostEntry>,
+}
+
+impl PostStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every effect at its defaults, in the usual order. SMAA is in there disabled, as an
+    /// alternative to FXAA.
+    pub fn standard() -> Self {
+        let mut stack = Self::new()
+            .with(PostEffect::Bloom(Bloom::default()))
+            .with(PostEffect::ChromaticAberration(ChromaticAberration::default()))
+            .with(PostEffect::ColorGrading(ColorGrading::default()))
+            .with(PostEffect::Vignette(Vignette::default()))
+            .with(PostEffect::Fxaa(Fxaa::default()))
+            .with(PostEffect::Smaa(Smaa::default()));
+        stack.set_enabled("smaa", false);
+        stack
+    }
+
+    pub fn with(mut self, effect: PostEffect) -> Self {
+        self.push(effect);
+        self
+    }
+
+    /// Appends `effect`, enabled; one of the same kind already in the stack is replaced in place.
+    pub fn push(&mut self, effect: PostEffect) {
+        match self.entries.iter_mut().find(|e| e.effect.name() == effect.name()) {
+            Some(entry) => entry.effect = effect,
+            None => self.entries.push(PostEntry { effect, enabled: true }),
+        }
+    }
+
+    pub fn entries(&self) -> &[PostEntry] {
+        &self.entries
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut PostEntry> {
+        self.entries.iter_mut().find(|e| e.effect.name() == name)
+    }
+
+    /// Returns whether there was such an effect.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        self.get_mut(name).map(|e| e.enabled = enabled).is_some()
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<PostEffect> {
+        let i = self.entries.iter().position(|e| e.effect.name() == name)?;
+        Some(self.entries.remove(i).effect)
+    }
+
+    /// Moves the effect at `index` one step earlier.
+    pub fn move_up(&mut self, index: usize) {
+        if index > 0 && index < self.entries.len() {
+            self.entries.swap(index - 1, index);
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.entries.iter().any(|e| e.enabled)
+    }
+
+    /// A checkbox per effect, its parameters while enabled, and buttons to reorder. Returns
+    /// whether anything changed; hand the stack back with `Renderer::set_post_stack` then.
+    pub fn ui(&mut self, ui: &dyn ui_core::Ui) -> bool {
+        let mut changed = false;
+        let mut move_up = None;
+        for (i, entry) in self.entries.iter_mut().enumerate() {
+            if i > 0 {
+                ui.separator();
+            }
+            changed |= ui.checkbox(entry.effect.label(), &mut entry.enabled);
+            if entry.enabled {
+                changed |= entry.effect.ui(ui);
+            }
+            if i > 0 && ui.button(&format!("Move {} up", entry.effect.label())) {
+                move_up = Some(i);
+            }
+        }
+        if let Some(i) = move_up {
+            self.move_up(i);
+            changed = true;
+        }
+        changed
+    }
+}
+
+/// Bindings and full-screen triangle every effect shader starts with; each declares its own
+/// `Params` (at most `PARAMS_SIZE` bytes). A macro so the shaders can `concat!` it.
+macro_rules! prelude {
+    () => { r#"
+@group(0) @binding(0) var src: texture_2d<f32>;
+@group(0) @binding(1) var samp: sampler;
+@group(0) @binding(2) var<uniform> params: Params;
+// The bloom levels while compositing, the LUT while grading.
+@group(0) @binding(3) var aux: texture_2d<f32>;
+
+struct VsOut { @builtin(position) pos: vec4<f32>, @location(0) uv: vec2<f32> }
+
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> VsOut {
+  let uv = vec2<f32>(f32((i << 1u) & 2u), f32(i & 2u));
+  return VsOut(vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0), uv);
+}
+
+fn tap(uv: vec2<f32>) -> vec3<f32> {
+  return textureSampleLevel(src, samp, uv, 0.0).rgb;
+}
+"#
+    };
+}
+
+const PARAMS_SIZE: u64 = 32;
+
+const BLOOM_WGSL: &str = concat!(prelude!(), r#"
+struct Params { threshold: f32, knee: f32, intensity: f32, radius: f32 }
+
+// 0: threshold + downsample, 1: downsample, 2: upsample (blended additively), 3: composite.
+override MODE: u32 = 0u;
+
+// 13 taps in overlapping boxes, which keeps shimmering down (Jimenez, "Next generation post processing in CoD:AW").
+fn downsample(uv: vec2<f32>) -> vec3<f32> {
+  let t = 1.0 / vec2<f32>(textureDimensions(src));
+  let a = tap(uv + t * vec2<f32>(-2.0, -2.0));
+  let b = tap(uv + t * vec2<f32>(0.0, -2.0));
+  let c = tap(uv + t * vec2<f32>(2.0, -2.0));
+  let d = tap(uv + t * vec2<f32>(-2.0, 0.0));
+  let e = tap(uv);
+  let f = tap(uv + t * vec2<f32>(2.0, 0.0));
+  let g = tap(uv + t * vec2<f32>(-2.0, 2.0));
+  let h = tap(uv + t * vec2<f32>(0.0, 2.0));
+  let i = tap(uv + t * vec2<f32>(2.0, 2.0));
+  let j = tap(uv + t * vec2<f32>(-1.0, -1.0));
+  let k = tap(uv + t * vec2<f32>(1.0, -1.0));
+  let l = tap(uv + t * vec2<f32>(-1.0, 1.0));
+  let m = tap(uv + t * vec2<f32>(1.0, 1.0));
+  return e * 0.125 + (a + c + g + i) * 0.03125 + (b + d + f + h) * 0.0625 + (j + k + l + m) * 0.125;
+}
+
+fn upsample(uv: vec2<f32>) -> vec3<f32> {
+  let t = params.radius / vec2<f32>(textureDimensions(src));
+  var sum = tap(uv) * 4.0;
+  sum += (tap(uv + vec2<f32>(-t.x, 0.0)) + tap(uv + vec2<f32>(t.x, 0.0)) + tap(uv + vec2<f32>(0.0, -t.y)) + tap(uv + vec2<f32>(0.0, t.y))) * 2.0;
+  sum += tap(uv - t) + tap(uv + t) + tap(uv + vec2<f32>(-t.x, t.y)) + tap(uv + vec2<f32>(t.x, -t.y));
+  return sum / 16.0;
+}
+
+fn threshold(c: vec3<f32>) -> vec3<f32> {
+  let bright = max(c.r, max(c.g, c.b));
+  var soft = clamp(bright - params.threshold + params.knee, 0.0, 2.0 * params.knee);
+  soft = soft * soft / (4.0 * params.knee + 0.00001);
+  return c * max(soft, bright - params.threshold) / max(bright, 0.00001);
+}
+
+@fragment
+fn fs_main(in: VsOut) -> @location(0) vec4<f32> {
+  if (MODE == 0u) { return vec4<f32>(threshold(downsample(in.uv)), 1.0); }
+  if (MODE == 1u) { return vec4<f32>(downsample(in.uv), 1.0); }
+  if (MODE == 2u) { return vec4<f32>(upsample(in.uv), 1.0); }
+  let glow = textureSampleLevel(aux, samp, in.uv, 0.0).rgb;
+  return vec4<f32>(tap(in.uv) + glow * params.intensity, 1.0);
+}
+"#);
+
+const FXAA_WGSL: &str = concat!(prelude!(), r#"
+struct Params { unused: vec4<f32> }
+
+override SPAN_MAX: f32 = 8.0;
+override EDGE_THRESHOLD: f32 = 0.166;
+const REDUCE_MUL: f32 = 0.125;
+const REDUCE_MIN: f32 = 0.0078125;
+
+// Perceptual enough for finding edges.
+fn luma(c: vec3<f32>) -> f32 {
+  return dot(sqrt(c), vec3<f32>(0.299, 0.587, 0.114));
+}
+
+@fragment
+fn fs_main(in: VsOut) -> @location(0) vec4<f32> {
+  let t = 1.0 / vec2<f32>(textureDimensions(src));
+  let m = tap(in.uv);
+  let nw = luma(tap(in.uv + vec2<f32>(-t.x, -t.y)));
+  let ne = luma(tap(in.uv + vec2<f32>(t.x, -t.y)));
+  let sw = luma(tap(in.uv + vec2<f32>(-t.x, t.y)));
+  let se = luma(tap(in.uv + vec2<f32>(t.x, t.y)));
+  let lm = luma(m);
+  let lo = min(lm, min(min(nw, ne), min(sw, se)));
+  let hi = max(lm, max(max(nw, ne), max(sw, se)));
+  if (hi - lo < max(0.0312, hi * EDGE_THRESHOLD)) {
+    return vec4<f32>(m, 1.0);
+  }
+
+  var dir = vec2<f32>(-((nw + ne) - (sw + se)), (nw + sw) - (ne + se));
+  let reduce = max((nw + ne + sw + se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+  dir = clamp(dir / (min(abs(dir.x), abs(dir.y)) + reduce), vec2<f32>(-SPAN_MAX), vec2<f32>(SPAN_MAX)) * t;
+
+  let a = 0.5 * (tap(in.uv + dir * (1.0 / 3.0 - 0.5)) + tap(in.uv + dir * (2.0 / 3.0 - 0.5)));
+  let b = a * 0.5 + 0.25 * (tap(in.uv - dir * 0.5) + tap(in.uv + dir * 0.5));
+  let lb = luma(b);
+  if (lb < lo || lb > hi) {
+    return vec4<f32>(a, 1.0);
+  }
+  return vec4<f32>(b, 1.0);
+}
+"#);
+
+const SMAA_WGSL: &str = concat!(prelude!(), r#"
+struct Params { unused: vec4<f32> }
+
+// 0: edge detection, 1: blending weights (from the edges), 2: neighborhood blending (with the
+// weights as `aux`).
+override MODE: u32 = 0u;
+override THRESHOLD: f32 = 0.1;
+override MAX_SEARCH_STEPS: i32 = 8;
+
+fn clamped(p: vec2<i32>) -> vec2<i32> {
+  return clamp(p, vec2<i32>(0), vec2<i32>(textureDimensions(src)) - 1);
+}
+
+fn texel(p: vec2<i32>) -> vec4<f32> {
+  return textureLoad(src, clamped(p), 0);
+}
+
+// The weights are the size of `src`.
+fn weight(p: vec2<i32>) -> vec4<f32> {
+  return textureLoad(aux, clamped(p), 0);
+}
+
+fn luma(p: vec2<i32>) -> f32 {
+  return dot(sqrt(texel(p).rgb), vec3<f32>(0.299, 0.587, 0.114));
+}
+
+// Edges on each pixel's left (r) and top (g): luma jumps over `THRESHOLD` that aren't much
+// smaller than the largest one around, so only the dominant of two close edges is kept.
+fn detect_edges(p: vec2<i32>) -> vec4<f32> {
+  let l = luma(p);
+  let delta = abs(l - vec2<f32>(luma(p + vec2<i32>(-1, 0)), luma(p + vec2<i32>(0, -1))));
+  var edges = step(vec2<f32>(THRESHOLD), delta);
+  if (edges.x + edges.y == 0.0) {
+    return vec4<f32>(0.0);
+  }
+  let around = abs(l - vec2<f32>(luma(p + vec2<i32>(1, 0)), luma(p + vec2<i32>(0, 1))));
+  let beyond = abs(vec2<f32>(luma(p + vec2<i32>(-1, 0)), luma(p + vec2<i32>(0, -1)))
+    - vec2<f32>(luma(p + vec2<i32>(-2, 0)), luma(p + vec2<i32>(0, -2))));
+  let most = max(max(delta.x, delta.y), max(max(around.x, around.y), max(beyond.x, beyond.y)));
+  edges *= step(vec2<f32>(most), 2.0 * delta);
+  return vec4<f32>(edges, 0.0, 1.0);
+}
+
+// Height of the silhouette an edge run of `len` pixels stands for, `t` pixels from its start,
+// with +0.5 a whole half pixel onto the side being shaded. `s0` and `s1` tell which way the
+// run's ends turn: +1 toward that side, -1 away, 0 not at all. Ends turning opposite ways
+// make a Z, with one line end to end; otherwise the line meets the edge halfway.
+fn height(t: f32, len: f32, s0: f32, s1: f32) -> f32 {
+  if (s0 * s1 < 0.0) {
+    return mix(s0, s1, t / len) * 0.5;
+  }
+  let half = len * 0.5;
+  if (t < half) {
+    return s0 * 0.5 * (1.0 - t / half);
+  }
+  return s1 * 0.5 * (t - half) / half;
+}
+
+// How much of the pixel past `p`'s edge along `e` (its top for (0, -1), its left for (-1, 0))
+// `p` takes (x), and how much of `p` that pixel takes (y). `along` steps along the edge, and
+// `channel` picks the edge in the edges texture; the crossing edges at the run's ends are in
+// the other one.
+fn run_weights(p: vec2<i32>, e: vec2<i32>, along: vec2<i32>, channel: u32) -> vec2<f32> {
+  var d0 = 0;
+  for (var i = 1; i <= MAX_SEARCH_STEPS; i++) {
+    if (texel(p - along * i)[channel] < 0.5) { break; }
+    d0 = i;
+  }
+  var d1 = 0;
+  for (var i = 1; i <= MAX_SEARCH_STEPS; i++) {
+    if (texel(p + along * i)[channel] < 0.5) { break; }
+    d1 = i;
+  }
+  let cross = 1u - channel;
+  let start = p - along * d0;
+  let end = p + along * (d1 + 1);
+  let s0 = texel(start)[cross] - texel(start + e)[cross];
+  let s1 = texel(end)[cross] - texel(end + e)[cross];
+  let h = height(f32(d0) + 0.5, f32(d0 + d1 + 1), s0, s1);
+  return vec2<f32>(max(h, 0.0), max(-h, 0.0));
+}
+
+fn blending_weights(p: vec2<i32>) -> vec4<f32> {
+  let edges = texel(p).rg;
+  var w = vec4<f32>(0.0);
+  if (edges.g > 0.5) {
+    w = vec4<f32>(run_weights(p, vec2<i32>(0, -1), vec2<i32>(1, 0), 1u), w.zw);
+  }
+  if (edges.r > 0.5) {
+    w = vec4<f32>(w.xy, run_weights(p, vec2<i32>(-1, 0), vec2<i32>(0, 1), 0u));
+  }
+  return w;
+}
+
+// Mixes in the neighbors across whichever of the pixel's horizontal or vertical edges weigh more.
+fn blend(p: vec2<i32>) -> vec4<f32> {
+  let w = weight(p);
+  let up = w.x;
+  let down = weight(p + vec2<i32>(0, 1)).y;
+  let left = w.z;
+  let right = weight(p + vec2<i32>(1, 0)).w;
+  let c = texel(p).rgb;
+  if (up + down >= left + right) {
+    let mixed = c * (1.0 - up - down) + texel(p + vec2<i32>(0, -1)).rgb * up + texel(p + vec2<i32>(0, 1)).rgb * down;
+    return vec4<f32>(mixed, 1.0);
+  }
+  let mixed = c * (1.0 - left - right) + texel(p + vec2<i32>(-1, 0)).rgb * left + texel(p + vec2<i32>(1, 0)).rgb * right;
+  return vec4<f32>(mixed, 1.0);
+}
+
+@fragment
+fn fs_main(in: VsOut) -> @location(0) vec4<f32> {
+  let p = vec2<i32>(in.pos.xy);
+  if (MODE == 0u) { return detect_edges(p); }
+  if (MODE == 1u) { return blending_weights(p); }
+  return blend(p);
+}
+"#);
+
+const VIGNETTE_WGSL: &str = concat!(prelude!(), r#"
+struct Params { intensity: f32, radius: f32, smoothness: f32, pad: f32 }
+
+override ROUNDED: bool = true;
+
+@fragment
+fn fs_main(in: VsOut) -> @location(0) vec4<f32> {
+  var d = (in.uv - 0.5) * 2.0;
+  if (ROUNDED) {
+    let size = vec2<f32>(textureDimensions(src));
+    d.x *= size.x / size.y;
+  }
+  let dark = smoothstep(params.radius, params.radius + params.smoothness, length(d));
+  return vec4<f32>(tap(in.uv) * (1.0 - params.intensity * dark), 1.0);
+}
+"#);
+
+const ABERRATION_WGSL: &str = concat!(prelude!(), r#"
+struct Params { intensity: f32, pad: vec3<f32> }
+
+override SAMPLES: u32 = 3u;
+
+@fragment
+fn fs_main(in: VsOut) -> @location(0) vec4<f32> {
+  // Reaches `intensity` pixels at the corners.
+  let offset = (in.uv - 0.5) * 1.41421 * params.intensity / vec2<f32>(textureDimensions(src));
+  var sum = vec3<f32>(0.0);
+  var weights = vec3<f32>(0.0);
+  for (var i = 0u; i < SAMPLES; i++) {
+    // Red at t = 0 (pushed out), green in the middle, blue at t = 1 (pulled in).
+    let t = f32(i) / f32(SAMPLES - 1u);
+    let w = clamp(1.0 - abs(t * 2.0 - vec3<f32>(0.0, 1.0, 2.0)), vec3<f32>(0.0), vec3<f32>(1.0));
+    sum += tap(in.uv - offset * (t * 2.0 - 1.0)) * w;
+    weights += w;
+  }
+  return vec4<f32>(sum / weights, 1.0);
+}
+"#);
+
+const GRADING_WGSL: &str = concat!(prelude!(), r#"
+struct Params { intensity: f32, pad: vec3<f32> }
+
+override LUT_SIZE: u32 = 16u;
+
+fn to_srgb(x: vec3<f32>) -> vec3<f32> {
+  return select(1.055 * pow(x, vec3<f32>(1.0 / 2.4)) - 0.055, x * 12.92, x <= vec3<f32>(0.0031308));
+}
+
+fn to_linear(x: vec3<f32>) -> vec3<f32> {
+  return select(pow((x + 0.055) / 1.055, vec3<f32>(2.4)), x / 12.92, x <= vec3<f32>(0.04045));
+}
+
+// Bilinear within the two nearest blue slices, then between them.
+fn lookup(c: vec3<f32>) -> vec3<f32> {
+  let n = f32(LUT_SIZE);
+  let blue = c.b * (n - 1.0);
+  let s0 = floor(blue);
+  let s1 = min(s0 + 1.0, n - 1.0);
+  let xy = c.rg * (n - 1.0) + 0.5;
+  let a = textureSampleLevel(aux, samp, vec2<f32>((s0 * n + xy.x) / (n * n), xy.y / n), 0.0).rgb;
+  let b = textureSampleLevel(aux, samp, vec2<f32>((s1 * n + xy.x) / (n * n), xy.y / n), 0.0).rgb;
+  return mix(a, b, blue - s0);
+}
+
+@fragment
+fn fs_main(in: VsOut) -> @location(0) vec4<f32> {
+  let c = clamp(tap(in.uv), vec3<f32>(0.0), vec3<f32>(1.0));
+  let graded = to_linear(lookup(to_srgb(c)));
+  return vec4<f32>(mix(c, graded, params.intensity), 1.0);
+}
+"#);
+
+/// What SMAA's edges and blending weights are kept in.
+const SMAA_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// What `effect`'s pipelines are built from, in the order its pass uses them. They all draw
+/// into `format`, except bloom's downsampled levels and SMAA's edges and weights.
+pub(crate) fn pipeline_descs(effect: &PostEffect, format: wgpu::TextureFormat) -> Vec<(WgslSource, RenderState<wgpu::TextureFormat>, Overrides)> {
+    let state = RenderState::new(format).without_depth();
+    let mut overrides = Overrides::default();
+    match effect {
+        PostEffect::Bloom(_) => {
+            let src = WgslSource::new("post/bloom.wgsl", BLOOM_WGSL);
+            let level = RenderState::new(HDR_FORMAT).without_depth();
+            [level, level, level.with_blend(BlendMode::Additive), state]
+                .into_iter()
+                .enumerate()
+                .map(|(mode, state)| {
+                    let mut overrides = Overrides::default();
+                    overrides.set_u32("MODE", mode as u32);
+                    (src.clone(), state, overrides)
+                })
+                .collect()
+        }
+        PostEffect::Fxaa(f) => {
+            let (span, threshold) = match f.quality {
+                FxaaQuality::Low => (4.0, 0.25),
+                FxaaQuality::Medium => (8.0, 0.166),
+                FxaaQuality::High => (16.0, 0.125),
+            };
+            overrides.set_f32("SPAN_MAX", span);
+            overrides.set_f32("EDGE_THRESHOLD", threshold);
+            vec![(WgslSource::new("post/fxaa.wgsl", FXAA_WGSL), state, overrides)]
+        }
+        PostEffect::Smaa(s) => {
+            let (threshold, steps) = match s.quality {
+                SmaaQuality::Low => (0.15, 4),
+                SmaaQuality::Medium => (0.1, 8),
+                SmaaQuality::High => (0.1, 16),
+                SmaaQuality::Ultra => (0.05, 32),
+            };
+            let src = WgslSource::new("post/smaa.wgsl", SMAA_WGSL);
+            let data = RenderState::new(SMAA_FORMAT).without_depth();
+            [data, data, state]
+                .into_iter()
+                .enumerate()
+                .map(|(mode, state)| {
+                    let mut overrides = Overrides::default();
+                    overrides.set_u32("MODE", mode as u32);
+                    overrides.set_f32("THRESHOLD", threshold);
+                    overrides.set_i32("MAX_SEARCH_STEPS", steps);
+                    (src.clone(), state, overrides)
+                })
+                .collect()
+        }
+        PostEffect::Vignette(v) => {
+            overrides.set_bool("ROUNDED", v.rounded);
+            vec![(WgslSource::new("post/vignette.wgsl", VIGNETTE_WGSL), state, overrides)]
+        }
+        PostEffect::ChromaticAberration(c) => {
+            overrides.set_u32("SAMPLES", c.samples.max(3));
+            vec![(WgslSource::new("post/chromatic_aberration.wgsl", ABERRATION_WGSL), state, overrides)]
+        }
+        PostEffect::ColorGrading(g) => {
+            overrides.set_u32("LUT_SIZE", g.lut.size());
+            vec![(WgslSource::new("post/color_grading.wgsl", GRADING_WGSL), state, overrides)]
+        }
+    }
+}
+
+/// The group every effect pipeline is laid out with (see `PRELUDE`).
+pub(crate) fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    let texture = |binding| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    };
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("post_bgl"),
+        entries: &[
+            texture(0),
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            texture(3),
+        ],
+    })
+}
+
+/// GPU side of the stack: a pass per enabled effect.
+pub(crate) struct Post {
+    stack: PostStack,
+    pub(crate) passes: Vec<EffectPass>,
+    bgl: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    /// Bound as `aux` by effects that don't use it.
+    blank: wgpu::TextureView,
+}
+
+impl Post {
+    /// `sampler` should filter linearly and clamp.
+    pub(crate) fn new(device: &wgpu::Device, bgl: wgpu::BindGroupLayout, sampler: wgpu::Sampler) -> Self {
+        let blank = create_texture(device, "post_blank", 1, 1).create_view(&wgpu::TextureViewDescriptor::default());
+        Self { stack: PostStack::default(), passes: Vec::new(), bgl, sampler, blank }
+    }
+
+    pub(crate) fn stack(&self) -> &PostStack {
+        &self.stack
+    }
+
+    /// `pipelines` has those of `pipeline_descs` for each enabled effect, in order.
+    pub(crate) fn set(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, stack: PostStack, pipelines: Vec<Vec<wgpu::RenderPipeline>>) {
+        let old = std::mem::take(&mut self.passes);
+        let enabled = stack.entries.iter().filter(|e| e.enabled);
+        for (entry, pipelines) in enabled.zip(pipelines) {
+            // Kept across changes; only the values are written again.
+            let params = old.iter()
+                .find(|p| p.effect.name() == entry.effect.name())
+                .map(|p| p.params.clone())
+                .unwrap_or_else(|| device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some(entry.effect.name()),
+                    size: PARAMS_SIZE,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }));
+            queue.write_buffer(&params, 0, bytemuck::cast_slice(&params_of(&entry.effect)));
+
+            let aux = match &entry.effect {
+                // Uploaded again only when it's a different LUT.
+                PostEffect::ColorGrading(g) => old.iter()
+                    .find_map(|p| p.lut.as_ref().filter(|(lut, _)| Arc::ptr_eq(lut, &g.lut)).map(|(_, view)| view.clone()))
+                    .unwrap_or_else(|| upload_lut(device, queue, &g.lut)),
+                _ => self.blank.clone(),
+            };
+            let lut = match &entry.effect {
+                PostEffect::ColorGrading(g) => Some((g.lut.clone(), aux.clone())),
+                _ => None,
+            };
+            self.passes.push(EffectPass {
+                effect: entry.effect.clone(),
+                pipelines,
+                params,
+                aux,
+                lut,
+                bgl: self.bgl.clone(),
+                sampler: self.sampler.clone(),
+                input: String::new(),
+                output: String::new(),
+                ids: None,
+                levels: Vec::new(),
+            });
+        }
+        self.stack = stack;
+    }
+}
+
+/// Chains `passes` from `LDR` to `FRAME`, through a transient between each two.
+pub(crate) fn chain(passes: &mut [EffectPass]) -> impl Iterator<Item = &mut dyn GraphPass> {
+    let last = passes.len().saturating_sub(1);
+    passes.iter_mut().enumerate().map(move |(i, pass)| {
+        pass.input = if i == 0 { LDR.to_string() } else { format!("post_{}", i - 1) };
+        pass.output = if i == last { FRAME.to_string() } else { format!("post_{i}") };
+        pass as &mut dyn GraphPass
+    })
+}
+
+fn params_of(effect: &PostEffect) -> [f32; 8] {
+    let mut p = [0.0; 8];
+    match effect {
+        PostEffect::Bloom(b) => p[..4].copy_from_slice(&[b.threshold, b.knee, b.intensity, b.radius]),
+        PostEffect::Fxaa(_) | PostEffect::Smaa(_) => {}
+        PostEffect::Vignette(v) => p[..3].copy_from_slice(&[v.intensity, v.radius, v.smoothness]),
+        PostEffect::ChromaticAberration(c) => p[0] = c.intensity,
+        PostEffect::ColorGrading(g) => p[0] = g.intensity,
+    }
+    p
+}
+
+fn create_texture(device: &wgpu::Device, label: &str, width: u32, height: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    })
+}
+
+fn upload_lut(device: &wgpu::Device, queue: &wgpu::Queue, lut: &ColorLut) -> wgpu::TextureView {
+    let (width, height) = (lut.size * lut.size, lut.size);
+    let texture = create_texture(device, "color_lut", width, height);
+    queue.write_texture(
+        texture.as_image_copy(),
+        &lut.rgba,
+        wgpu::TexelCopyBufferLayout { offset: 0, bytes_per_row: Some(width * 4), rows_per_image: None },
+        texture.size(),
+    );
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+pub(crate) struct EffectPass {
+    effect: PostEffect,
+    pipelines: Vec<wgpu::RenderPipeline>,
+    params: wgpu::Buffer,
+    aux: wgpu::TextureView,
+    lut: Option<(Arc<ColorLut>, wgpu::TextureView)>,
+    bgl: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    input: String,
+    output: String,
+    ids: Option<(ResourceId, ResourceId)>,
+    /// Bloom's half-size chain, largest first; SMAA's edges, then its blending weights.
+    levels: Vec<ResourceId>,
+}
+
+impl EffectPass {
+    fn draw(&self, ctx: &mut PassContext, pipeline: usize, src: ResourceId, aux: Option<ResourceId>, target: ResourceId, load: wgpu::LoadOp<wgpu::Color>) {
+        let bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(self.effect.name()),
+            layout: &self.bgl,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(ctx.view(src)) },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(&self.sampler) },
+                wgpu::BindGroupEntry { binding: 2, resource: self.params.as_entire_binding() },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(aux.map_or(&self.aux, |id| ctx.view(id))),
+                },
+            ],
+        });
+        let view = ctx.view(target).clone();
+        let mut rp = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(self.effect.name()),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations { load, store: wgpu::StoreOp::Store },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rp.set_pipeline(&self.pipelines[pipeline]);
+        rp.set_bind_group(0, &bind_group, &[]);
+        rp.draw(0..3, 0..1);
+    }
+}
+
+impl GraphPass for EffectPass {
+    fn name(&self) -> &str { self.effect.name() }
+
+    fn setup(&mut self, b: &mut PassBuilder) {
+        let input = b.read(&self.input);
+        let output = if self.output == FRAME {
+            b.write(FRAME)
+        } else {
+            b.create_texture(&self.output, TextureDesc::new(b.frame_format()))
+        };
+        self.ids = Some((input, output));
+        self.levels = match &self.effect {
+            PostEffect::Bloom(bloom) => (1..=bloom.levels.clamp(1, 8) as i32)
+                .map(|k| {
+                    let desc = TextureDesc::new(HDR_FORMAT).with_size(TextureSize::Scaled(0.5f32.powi(k)));
+                    b.create_texture(&format!("bloom_{}", k - 1), desc)
+                })
+                .collect(),
+            PostEffect::Smaa(_) => ["smaa_edges", "smaa_weights"]
+                .map(|name| b.create_texture(name, TextureDesc::new(SMAA_FORMAT)))
+                .to_vec(),
+            _ => Vec::new(),
+        };
+    }
+
+    fn execute(&mut self, ctx: &mut PassContext) {
+        let (input, output) = self.ids.expect("set up");
+        let clear = wgpu::LoadOp::Clear(wgpu::Color::BLACK);
+        if let PostEffect::Smaa(_) = self.effect {
+            let (edges, weights) = (self.levels[0], self.levels[1]);
+            self.draw(ctx, 0, input, None, edges, clear);
+            self.draw(ctx, 1, edges, None, weights, clear);
+            self.draw(ctx, 2, input, Some(weights), output, clear);
+            return;
+        }
+        let Some(&first) = self.levels.first() else {
+            self.draw(ctx, 0, input, None, output, clear);
+            return;
+        };
+        self.draw(ctx, 0, input, None, first, clear);
+        for pair in self.levels.windows(2) {
+            self.draw(ctx, 1, pair[0], None, pair[1], clear);
+        }
+        for pair in self.levels.windows(2).rev() {
+            self.draw(ctx, 2, pair[1], None, pair[0], wgpu::LoadOp::Load);
+        }
+        self.draw(ctx, 3, input, Some(first), output, clear);
+    }
+}
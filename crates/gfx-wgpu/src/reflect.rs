@@ -0,0 +1,52 @@
+use shader_core::{BindingType, ShaderReflection, ShaderStage, TextureDimension, TextureSample};
+
+/// Layout entries for bind group `group` as declared by the shader. Float textures are assumed
+/// filterable, since WGSL doesn't say.
+pub fn bind_group_layout_entries(refl: &ShaderReflection, group: u32) -> Vec<wgpu::BindGroupLayoutEntry> {
+    refl.group(group)
+        .filter_map(|b| {
+            let ty = match &b.ty {
+                BindingType::Uniform { size } => wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(*size as u64),
+                },
+                BindingType::Storage { size, read_only } => wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: *read_only },
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(*size as u64),
+                },
+                BindingType::Texture { dimension, sample, multisampled } => wgpu::BindingType::Texture {
+                    sample_type: match sample {
+                        TextureSample::Float => wgpu::TextureSampleType::Float { filterable: !multisampled },
+                        TextureSample::Sint => wgpu::TextureSampleType::Sint,
+                        TextureSample::Uint => wgpu::TextureSampleType::Uint,
+                        TextureSample::Depth => wgpu::TextureSampleType::Depth,
+                    },
+                    view_dimension: view_dimension(*dimension),
+                    multisampled: *multisampled,
+                },
+                BindingType::Sampler { comparison: true } => wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                BindingType::Sampler { comparison: false } => wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                BindingType::StorageTexture { .. } | BindingType::Other => return None,
+            };
+            let visibility = b.stages.iter().fold(wgpu::ShaderStages::NONE, |v, s| v | match s {
+                ShaderStage::Vertex => wgpu::ShaderStages::VERTEX,
+                ShaderStage::Fragment => wgpu::ShaderStages::FRAGMENT,
+                ShaderStage::Compute => wgpu::ShaderStages::COMPUTE,
+            });
+            Some(wgpu::BindGroupLayoutEntry { binding: b.binding, visibility, ty, count: None })
+        })
+        .collect()
+}
+
+fn view_dimension(dim: TextureDimension) -> wgpu::TextureViewDimension {
+    match dim {
+        TextureDimension::D1 => wgpu::TextureViewDimension::D1,
+        TextureDimension::D2 => wgpu::TextureViewDimension::D2,
+        TextureDimension::D2Array => wgpu::TextureViewDimension::D2Array,
+        TextureDimension::D3 => wgpu::TextureViewDimension::D3,
+        TextureDimension::Cube => wgpu::TextureViewDimension::Cube,
+        TextureDimension::CubeArray => wgpu::TextureViewDimension::CubeArray,
+    }
+}
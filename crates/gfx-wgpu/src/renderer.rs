@@ -1,94 +1,193 @@
-use wgpu::util::{BufferInitDescriptor, DeviceExt};
-use crate::depth::create_depth_view;
-use crate::types::{Vertex, GResult};
-use crate::pipeline_cache::PipelineCache;
-use crate::context::GfxContext;
+use engine_core::{Aabb, Entity, Frustum, Obb, World};
+use wgpu::util::DeviceExt;
+
+use crate::types::GResult;
+use crate::pipeline_cache::{LayoutKey, MaterialLayoutKey, Pipeline, PipelineCache, PipelineError};
+use crate::context::{Frame, GfxContext, HeadlessConfig};
 use crate::camera_bind::CameraBind;
+use crate::object_bind::{ObjectBind, ObjectUBO};
+use crate::handle::Pool;
+use crate::mesh::{CullStats, DrawItem, Indices, Mesh, MeshHandle, PipelineHandle};
+use crate::instance::{InstanceBuffer, InstanceData, InstanceHandle};
+use crate::mipmap::MipGenerator;
+use crate::sampler::{SamplerCache, SamplerDesc};
+use crate::texture::{texture_bgl, TexelData, Texture, TextureError, TextureHandle, TextureOptions};
+use crate::material::{Material, MaterialDesc, MaterialHandle, MaterialParam};
+use crate::msaa::MsaaError;
+use crate::hdr::{Hdr, HdrSettings, HDR_FORMAT};
+use crate::post::{self, EffectPass, Post, PostStack};
+use crate::load::LoadOps;
+use crate::picking::{PickDraw, PickError, PickHit, Picker};
+use crate::graph::{CompiledGraph, GraphBuilder, GraphError, GraphPass, GraphStats, PassContext, ResourceId, TextureDesc, TexturePool};
+use crate::capture::{encode_depth_readback, encode_readback, CaptureError, CaptureOptions, FrameCapture, Readback};
+
+type UiBackendBox = dyn ui_core::UiBackend<
+    Device = wgpu::Device,
+    Queue = wgpu::Queue,
+    Encoder = wgpu::CommandEncoder,
+    View = wgpu::TextureView
+>;
 
 pub struct Renderer {
     pub ctx: GfxContext,
 
-    pipeline: Option<wgpu::RenderPipeline>,
-    vbuf: wgpu::Buffer,
-    vcount: u32,
+    meshes: Pool<Mesh>,
+    instances: Pool<InstanceBuffer>,
+    pipelines: Pool<Pipeline>,
+    textures: Pool<Texture>,
+    materials: Pool<Material>,
+    draw_list: Vec<DrawItem>,
 
     cam: CameraBind,
+    objects: ObjectBind,
 
     pipeline_cache: PipelineCache,
     pipeline_layout: wgpu::PipelineLayout,
+    textured_layout: wgpu::PipelineLayout,
+    texture_bgl: wgpu::BindGroupLayout,
+    samplers: SamplerCache,
+    mips: MipGenerator,
+
+    ui: Box<UiBackendBox>,
+
+    capture_request: Option<CaptureOptions>,
+    captured: Option<Result<FrameCapture, CaptureError>>,
+
+    shader_watcher: Option<shader_core::ShaderWatcher>,
+    /// Failed reloads; the pipeline keeps running its last good version meanwhile.
+    shader_errors: Vec<(PipelineHandle, PipelineError)>,
 
-    ui: Box<dyn ui_core::UiBackend<
-        Device = wgpu::Device,
-        Queue = wgpu::Queue,
-        Encoder = wgpu::CommandEncoder,
-        View = wgpu::TextureView
-    >>,
+    passes: Vec<Box<dyn GraphPass>>,
+    transients: TexturePool,
+    graph_stats: GraphStats,
+    /// Why the last frame fell back to the scene pass alone.
+    graph_error: Option<GraphError>,
+    /// Tonemapping and exposure passes, while the scene renders into `HDR_FORMAT`.
+    hdr: Option<Hdr>,
+    post: Post,
+    post_layout: wgpu::PipelineLayout,
+    tonemap_bgl: wgpu::BindGroupLayout,
+    tonemap_layout: wgpu::PipelineLayout,
+    /// For frames without a `request_load_ops`.
+    load_ops: LoadOps,
+    load_request: Option<LoadOps>,
+    /// What the last frame's scene pass loaded with, for drawing its depth again.
+    frame_load_ops: LoadOps,
+    /// Depth runs from 1 (near) to 0 (far); scene pipelines' compares are flipped to match.
+    reverse_z: bool,
+    /// From the last camera upload; draws outside its frustum are skipped while `culling` is on.
+    view_proj: Option<glam::Mat4>,
+    culling: bool,
+    cull_stats: CullStats,
+    picker: Picker,
+    /// What the last frame drew, for `pick`.
+    last_draws: Vec<DrawItem>,
+    /// Entities `submit_world` queued for the next frame: pick id `i + 1` is `[i]`.
+    pick_entities: Vec<Entity>,
+    /// The same for the last frame, for `pick_entity`.
+    last_pick_entities: Vec<Entity>,
+}
+
+/// Where the scene pass draws this frame.
+struct SceneTargets {
+    color: ResourceId,
+    /// Set when `color` is multisampled.
+    resolve: Option<ResourceId>,
 }
 
 impl Renderer {
     pub fn new(window: &winit::window::Window) -> Self {
         let ctx = GfxContext::new(window);
-        
-        // Adapter + device
-        let adapter = pollster::block_on(ctx.instance.request_adapter(&wgpu::RequestAdapterOptions {
-            power_preference: wgpu::PowerPreference::HighPerformance,
-            compatible_surface: Some(&ctx.surface),
-            force_fallback_adapter: false,
-        })).expect("No adapter found");
-
-        // Swapchain config
-        let caps = ctx.surface.get_capabilities(&adapter);
-        let format = caps.formats.iter().copied().find(|f| f.is_srgb()).unwrap_or(caps.formats[0]);
-
-        let size = window.inner_size();
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format,
-            width: size.width.max(1),
-            height: size.height.max(1),
-            present_mode: caps.present_modes[0],
-            alpha_mode: caps.alpha_modes[0],
-            view_formats: vec![],
-            desired_maximum_frame_latency: 2,
-        };
-        ctx.surface.configure(&ctx.device, &config);
+        let ui = Box::new(crate::ui::UiLayer::new(window, &ctx.device, &ctx.queue, ctx.config.format));
+        Self::with_context(ctx, ui)
+    }
+
+    pub fn new_headless(cfg: &HeadlessConfig) -> Self {
+        let ctx = GfxContext::new_headless(cfg);
+        let ui = Box::new(crate::ui::UiLayer::new_headless(
+            &ctx.device, &ctx.queue, ctx.config.format, ctx.config.width, ctx.config.height,
+        ));
+        Self::with_context(ctx, ui)
+    }
 
+    fn with_context(ctx: GfxContext, ui: Box<UiBackendBox>) -> Self {
         let cam = CameraBind::new(&ctx.device);
+        let objects = ObjectBind::new(&ctx.device);
 
         // Shader + pipeline
         let pipeline_layout = ctx.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("pipeline_layout"),
-            bind_group_layouts: &[&cam.bgl],
+            bind_group_layouts: &[&cam.bgl, &objects.bgl],
             push_constant_ranges: &[],
         });
 
-        let pipeline_cache = PipelineCache::new();
-
-        // Vertex buffer
-        let verts: [Vertex; 3] = [
-            Vertex { pos: [-0.6, -0.5], col: [1.0, 0.2, 0.2] },
-            Vertex { pos: [ 0.6, -0.5], col: [0.2, 1.0, 0.2] },
-            Vertex { pos: [ 0.0,  0.6], col: [0.2, 0.2, 1.0] },
-        ];
-
-        let vbuf = ctx.device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("vbuf"),
-            contents: bytemuck::cast_slice(&verts),
-            usage: wgpu::BufferUsages::VERTEX,
+        let texture_bgl = texture_bgl(&ctx.device);
+        let textured_layout = ctx.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("textured_layout"),
+            bind_group_layouts: &[&cam.bgl, &objects.bgl, &texture_bgl],
+            push_constant_ranges: &[],
         });
+        let mut samplers = SamplerCache::new(ctx.adapter.get_downlevel_capabilities().flags);
+        let mips = MipGenerator::new(&ctx.device);
 
-        let vcount = verts.len() as u32;
+        let pipeline_cache = PipelineCache::new().with_depth_format(ctx.depth_format());
 
-        let ui = Box::new(crate::ui::UiLayer::new(window, &ctx.device, &ctx.queue, ctx.config.format));
+        let post_bgl = post::bind_group_layout(&ctx.device);
+        let post_layout = ctx.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("post_layout"),
+            bind_group_layouts: &[&post_bgl],
+            push_constant_ranges: &[],
+        });
+        let post = Post::new(&ctx.device, post_bgl, samplers.get(&ctx.device, SamplerDesc::LINEAR_CLAMP).clone());
+        let tonemap_bgl = crate::hdr::bind_group_layout(&ctx.device);
+        let tonemap_layout = ctx.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("tonemap_layout"),
+            bind_group_layouts: &[&tonemap_bgl],
+            push_constant_ranges: &[],
+        });
+        let picker = Picker::new(&ctx.device);
 
         Self {
             ctx,
-            pipeline: None,
-            vbuf, vcount,
+            meshes: Pool::default(),
+            instances: Pool::default(),
+            pipelines: Pool::default(),
+            textures: Pool::default(),
+            materials: Pool::default(),
+            draw_list: Vec::new(),
             cam,
+            objects,
             pipeline_cache,
             pipeline_layout,
-            ui
+            textured_layout,
+            texture_bgl,
+            samplers,
+            mips,
+            ui,
+            capture_request: None,
+            captured: None,
+            shader_watcher: None,
+            shader_errors: Vec::new(),
+            passes: Vec::new(),
+            transients: TexturePool::default(),
+            graph_stats: GraphStats::default(),
+            graph_error: None,
+            hdr: None,
+            post,
+            post_layout,
+            tonemap_bgl,
+            tonemap_layout,
+            load_ops: LoadOps::default(),
+            load_request: None,
+            frame_load_ops: LoadOps::default(),
+            reverse_z: false,
+            view_proj: None,
+            culling: true,
+            cull_stats: CullStats::default(),
+            picker,
+            last_draws: Vec::new(),
+            pick_entities: Vec::new(),
+            last_pick_entities: Vec::new(),
         }
     }
 
@@ -96,144 +195,649 @@ impl Renderer {
         self.ui.handle_window_event(window, id, event);
     }
 
+    pub fn msaa(&self) -> u32 {
+        self.ctx.sample_count()
+    }
+
+    /// Sample counts `set_msaa` accepts on this adapter, ascending. Can change with `set_hdr`.
+    pub fn supported_msaa(&self) -> Vec<u32> {
+        self.ctx.supported_sample_counts(self.scene_format())
+    }
+
+    /// Switches the scene to `samples` per pixel and rebuilds every pipeline to match, keeping
+    /// handles valid.
+    pub fn set_msaa(&mut self, samples: u32) -> Result<(), MsaaError> {
+        if samples == self.ctx.sample_count() {
+            return Ok(());
+        }
+        self.ctx.set_sample_count(samples, self.scene_format())?;
+        self.rebuild_scene_pipelines();
+        Ok(())
+    }
+
+    /// Renders the scene into an `HDR_FORMAT` target and tonemaps it into the frame with
+    /// `settings`, or straight into the frame with `None`. Toggling rebuilds every pipeline like
+    /// `set_msaa`, and drops back to 1x if the new format can't do the current sample count.
+    pub fn set_hdr(&mut self, settings: Option<HdrSettings>) {
+        match (&mut self.hdr, settings) {
+            (Some(hdr), Some(settings)) => hdr.set(settings),
+            (None, None) => {}
+            (_, settings) => {
+                let compute = self.ctx.adapter.get_downlevel_capabilities().flags.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS);
+                self.hdr = settings.map(|s| Hdr::new(&self.ctx.device, self.tonemap_bgl.clone(), s, compute));
+                if !self.supported_msaa().contains(&self.ctx.sample_count()) {
+                    self.ctx.set_sample_count(1, self.scene_format()).expect("1x is always supported");
+                }
+                self.rebuild_scene_pipelines();
+            }
+        }
+        if let Some(tonemapper) = settings.map(|s| s.tonemapper) {
+            let (src, state, overrides) = crate::hdr::tonemap_desc(tonemapper, self.ctx.config.format);
+            let pipeline = self.create_pipeline(LayoutKey::Tonemap, &src, &state, &overrides, &[]).expect("built-in tonemap shader");
+            self.hdr.as_mut().expect("just set").set_pipeline(pipeline.raw);
+        }
+    }
+
+    pub fn hdr(&self) -> Option<HdrSettings> {
+        self.hdr.as_ref().map(Hdr::settings)
+    }
+
+    pub fn post_stack(&self) -> &PostStack {
+        self.post.stack()
+    }
+
+    /// Runs the enabled effects of `stack`, in order, over the finished frame (after tonemapping,
+    /// before the UI). If a pipeline fails to build, the current stack stays.
+    pub fn set_post_stack(&mut self, stack: PostStack) -> Result<(), PipelineError> {
+        let format = self.ctx.config.format;
+        let mut pipelines = Vec::new();
+        for entry in stack.entries().iter().filter(|e| e.enabled) {
+            let mut built = Vec::new();
+            for (src, state, overrides) in post::pipeline_descs(&entry.effect, format) {
+                built.push(self.create_pipeline(LayoutKey::Post, &src, &state, &overrides, &[])?.raw);
+            }
+            pipelines.push(built);
+        }
+        self.post.set(&self.ctx.device, &self.ctx.queue, stack, pipelines);
+        Ok(())
+    }
+
+    pub fn load_ops(&self) -> LoadOps {
+        self.load_ops
+    }
+
+    /// How the scene pass starts each frame, from the next one on, unless that frame has its
+    /// own from `request_load_ops`.
+    pub fn set_load_ops(&mut self, ops: LoadOps) {
+        self.load_ops = ops;
+    }
+
+    /// How the scene pass starts the next rendered frame only; later ones go back to `load_ops`.
+    pub fn request_load_ops(&mut self, ops: LoadOps) {
+        self.load_request = Some(ops);
+    }
+
+    pub fn reverse_z(&self) -> bool {
+        self.reverse_z
+    }
+
+    /// Clears depth to 0 and flips every scene pipeline's depth compare (`Less` to `Greater`),
+    /// rebuilding them like `set_msaa`. Pair it with `Camera::reverse_z` for the projection.
+    pub fn set_reverse_z(&mut self, on: bool) {
+        if on == self.reverse_z {
+            return;
+        }
+        self.reverse_z = on;
+        let handles: Vec<PipelineHandle> = self.pipelines.iter().map(|(h, _)| h).collect();
+        for handle in handles {
+            if let Some(p) = self.pipelines.get_mut(handle) {
+                p.state.depth_compare = p.state.depth_compare.reversed();
+            }
+        }
+        self.rebuild_scene_pipelines();
+    }
+
+    /// The color format scene pipelines render to: the frame's, or `HDR_FORMAT` (see `set_hdr`).
+    pub fn scene_format(&self) -> wgpu::TextureFormat {
+        if self.hdr.is_some() { HDR_FORMAT } else { self.ctx.config.format }
+    }
+
+    /// `state` with the scene's color format and sample count.
+    fn scene_state(&self, state: &shader_core::RenderState<wgpu::TextureFormat>) -> shader_core::RenderState<wgpu::TextureFormat> {
+        shader_core::RenderState { format: self.scene_format(), ..state.with_msaa(self.ctx.sample_count()) }
+    }
+
+    /// A caller's `state`, written for standard depth, the way scene pipelines are built with it.
+    fn new_scene_state(&self, state: &shader_core::RenderState<wgpu::TextureFormat>) -> shader_core::RenderState<wgpu::TextureFormat> {
+        let mut state = self.scene_state(state);
+        if self.reverse_z {
+            state.depth_compare = state.depth_compare.reversed();
+        }
+        state
+    }
+
+    /// After the scene's format or sample count changed. Pipelines that fail are reported in
+    /// `shader_errors` and skipped while drawing until a later rebuild succeeds.
+    fn rebuild_scene_pipelines(&mut self) {
+        let handles: Vec<PipelineHandle> = self.pipelines.iter().map(|(h, _)| h).collect();
+        for handle in handles {
+            let Some(old) = self.pipelines.get(handle) else { continue };
+            let (src, layout_key, state, overrides, vertex_layouts) = (
+                old.src.clone(), old.layout.clone(), self.scene_state(&old.state), old.overrides.clone(), old.vertex_layouts.clone(),
+            );
+            let result = self.create_pipeline(layout_key, &src, &state, &overrides, &vertex_layouts);
+            self.replace_pipeline(handle, result);
+        }
+    }
+
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if new_size.width == 0 || new_size.height == 0 { return; }
-        self.ctx.config.width = new_size.width;
-        self.ctx.config.height = new_size.height;
-        self.ctx.surface.configure(&self.ctx.device, &self.ctx.config);
-        self.ctx.depth_view = create_depth_view(&self.ctx.device, self.ctx.config.width, self.ctx.config.height);
+        self.ctx.resize(new_size.width, new_size.height);
+        self.ui.resize(new_size.width, new_size.height);
     }
 
-    pub fn render(&mut self) -> GResult<()> {
-        let frame = self.ctx.surface.get_current_texture()?;
-        let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let mut encoder = self.ctx.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("encoder") });
+    /// `resolve_target` is set when `color` is multisampled.
+    fn encode_scene(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        (color, view): &(wgpu::Texture, wgpu::TextureView),
+        resolve_target: Option<&wgpu::TextureView>,
+        ops: LoadOps,
+    ) {
+        self.frame_load_ops = ops;
+        // Group by pipeline to keep state changes down, blended pipelines after opaque ones so
+        // they blend over them; the sort is stable so submit order is kept within a pipeline.
+        let mut draws = std::mem::take(&mut self.draw_list);
+        let submitted = draws.len();
+        let culled = match (self.culling, self.view_proj) {
+            (true, Some(view_proj)) => Frustum::from_view_proj(view_proj).cull(&mut draws, |d| {
+                d.cull_bounds(self.meshes.get(d.mesh)).map(|b| Obb::from_aabb(&b, &d.transform))
+            }),
+            _ => 0,
+        };
+        self.cull_stats = CullStats { submitted, culled };
+        let blended = |d: &DrawItem| self.pipelines.get(d.pipeline)
+            .is_some_and(|p| p.state.targets().iter().flatten().any(|t| t.blend != shader_core::BlendMode::Replace));
+        draws.sort_by_key(|d| (blended(d), d.pipeline.index()));
+
+        let ubos: Vec<ObjectUBO> = draws.iter()
+            .map(|d| ObjectUBO { model: d.transform.to_cols_array_2d() })
+            .collect();
+        self.objects.write(&self.ctx.device, &self.ctx.queue, &ubos);
 
-        {
-            let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("triangle"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color { r: 0.05, g: 0.06, b: 0.1, a: 1.0 }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.ctx.depth_view,
-                    depth_ops: Some(wgpu::Operations { load: wgpu::LoadOp::Clear(1.0), store: wgpu::StoreOp::Store }),
-                    stencil_ops: None,
-                }),
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
-            if let Some(ref p) = self.pipeline {
-                rp.set_pipeline(p);
-                rp.set_bind_group(0, &self.cam.bind_group, &[]);
-                rp.set_vertex_buffer(0, self.vbuf.slice(..));
-                rp.draw(0..self.vcount, 0..1);
+        // Multisampled color only lives until it's resolved.
+        let store = if resolve_target.is_some() { wgpu::StoreOp::Discard } else { wgpu::StoreOp::Store };
+        let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("scene"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target,
+                ops: wgpu::Operations { load: ops.color_op(), store },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.ctx.depth_view,
+                depth_ops: Some(wgpu::Operations { load: ops.depth_op(self.reverse_z), store: wgpu::StoreOp::Store }),
+                stencil_ops: self.ctx.depth_format().has_stencil_aspect()
+                    .then_some(wgpu::Operations { load: ops.stencil_op(), store: wgpu::StoreOp::Store }),
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        let (samples, format) = (color.sample_count(), color.format());
+        // Only left behind by a failed `set_msaa`/`set_hdr` rebuild, and would fail validation.
+        self.encode_draws(&mut rp, &draws, |_, p| (p.state.msaa == samples && p.state.format == format).then_some(&p.raw));
+        drop(rp);
+        self.last_draws = draws;
+        self.last_pick_entities = std::mem::take(&mut self.pick_entities);
+    }
+
+    /// Draws `draws`, as sorted by `encode_scene` for the object UBOs it wrote, with whatever
+    /// pipeline `raw` gives for each; those it gives none for are skipped.
+    fn encode_draws<'a>(
+        &'a self,
+        rp: &mut wgpu::RenderPass<'_>,
+        draws: &[DrawItem],
+        raw: impl Fn(PipelineHandle, &'a Pipeline) -> Option<&'a wgpu::RenderPipeline>,
+    ) {
+        rp.set_bind_group(0, &self.cam.bind_group, &[]);
+
+        let mut bound = None;
+        let mut stencil_ref = 0;
+        for (i, d) in draws.iter().enumerate() {
+            let (Some(pipeline), Some(mesh)) = (self.pipelines.get(d.pipeline), self.meshes.get(d.mesh)) else {
+                continue;
+            };
+            let Some(pipeline) = raw(d.pipeline, pipeline) else { continue };
+            if bound != Some(d.pipeline) {
+                rp.set_pipeline(pipeline);
+                bound = Some(d.pipeline);
+            }
+            if d.stencil_ref != stencil_ref {
+                rp.set_stencil_reference(d.stencil_ref);
+                stencil_ref = d.stencil_ref;
+            }
+            rp.set_bind_group(1, &self.objects.bind_group, &[self.objects.offset(i)]);
+            if let Some(mat) = d.material.and_then(|m| self.materials.get(m)) {
+                rp.set_bind_group(2, &mat.bind_group, &[]);
+            } else if let Some(tex) = d.texture.and_then(|t| self.textures.get(t)) {
+                rp.set_bind_group(2, &tex.bind_group, &[]);
+            }
+            match d.instances {
+                Some(h) => {
+                    let Some(inst) = self.instances.get(h) else { continue };
+                    rp.set_vertex_buffer(1, inst.buffer.slice(..));
+                    mesh.draw(rp, 0..inst.count);
+                }
+                None => mesh.draw(rp, 0..1),
             }
         }
+    }
+
+    /// The last frame's depth drawn again at 1x, for capturing where the multisampled depth
+    /// buffer can't be read (GL, see `depth::create_depth`). Uses single-sampled builds of the
+    /// draws' pipelines, which the pipeline cache keeps for the next capture.
+    fn encode_depth_replay(&mut self, encoder: &mut wgpu::CommandEncoder) -> wgpu::Texture {
+        let (width, height) = (self.ctx.depth_texture.width(), self.ctx.depth_texture.height());
+        let (depth, depth_view) = crate::depth::create_depth(&self.ctx.device, width, height, 1, self.ctx.adapter.get_info().backend);
+        let color = self.ctx.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("depth_replay_color"),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.scene_format(),
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let color_view = color.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut raw = std::collections::HashMap::new();
+        let handles: Vec<PipelineHandle> = self.last_draws.iter().map(|d| d.pipeline).collect();
+        for handle in handles {
+            let Some(p) = self.pipelines.get(handle).filter(|_| !raw.contains_key(&handle)) else { continue };
+            let (src, layout_key, state, overrides, vertex_layouts) = (
+                p.src.clone(), p.layout.clone(), p.state.with_msaa(1), p.overrides.clone(), p.vertex_layouts.clone(),
+            );
+            if let Ok(p) = self.create_pipeline(layout_key, &src, &state, &overrides, &vertex_layouts) {
+                raw.insert(handle, p.raw);
+            }
+        }
+
+        // The multisampled depth can't be loaded into this one either, so a `Load` starts clear.
+        let depth_op = match self.frame_load_ops.depth_op(self.reverse_z) {
+            wgpu::LoadOp::Load => wgpu::LoadOp::Clear(if self.reverse_z { 0.0 } else { 1.0 }),
+            op => op,
+        };
+        let stencil_op = match self.frame_load_ops.stencil_op() {
+            wgpu::LoadOp::Load => wgpu::LoadOp::Clear(0),
+            op => op,
+        };
+        let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("depth_replay"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &color_view,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: wgpu::StoreOp::Discard },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &depth_view,
+                depth_ops: Some(wgpu::Operations { load: depth_op, store: wgpu::StoreOp::Store }),
+                stencil_ops: depth.format().has_stencil_aspect()
+                    .then_some(wgpu::Operations { load: stencil_op, store: wgpu::StoreOp::Store }),
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        self.encode_draws(&mut rp, &self.last_draws, |h, _| raw.get(&h));
+        drop(rp);
+        depth
+    }
+
+    pub fn upload_mesh<V: bytemuck::Pod>(&mut self, vertices: &[V], indices: Option<Indices>) -> MeshHandle {
+        self.meshes.insert(Mesh::new(&self.ctx.device, vertices, indices))
+    }
+
+    pub fn remove_mesh(&mut self, mesh: MeshHandle) {
+        self.meshes.remove(mesh);
+    }
+
+    pub fn upload_instances(&mut self, instances: &[InstanceData]) -> InstanceHandle {
+        self.instances.insert(InstanceBuffer::new(&self.ctx.device, &self.ctx.queue, instances))
+    }
+
+    pub fn update_instances(&mut self, handle: InstanceHandle, instances: &[InstanceData]) {
+        if let Some(buf) = self.instances.get_mut(handle) {
+            buf.update(&self.ctx.device, &self.ctx.queue, instances);
+        }
+    }
+
+    pub fn remove_instances(&mut self, handle: InstanceHandle) {
+        self.instances.remove(handle);
+    }
+
+    pub fn load_texture(&mut self, bytes: &[u8], opts: TextureOptions) -> Result<TextureHandle, TextureError> {
+        let data = TexelData::decode(bytes, opts.color_space)?;
+        Ok(self.add_texture(&data, opts))
+    }
+
+    pub fn load_texture_file(&mut self, path: impl AsRef<std::path::Path>, opts: TextureOptions) -> Result<TextureHandle, TextureError> {
+        let data = TexelData::load(path, opts.color_space)?;
+        Ok(self.add_texture(&data, opts))
+    }
+
+    /// `rgba` holds `width * height` pixels, row by row.
+    pub fn create_texture_rgba8(&mut self, width: u32, height: u32, rgba: &[u8], opts: TextureOptions) -> Result<TextureHandle, TextureError> {
+        if width == 0 || height == 0 || rgba.len() as u64 != width as u64 * height as u64 * 4 {
+            return Err(TextureError::Size { width, height, len: rgba.len() });
+        }
+        let data = TexelData::from_rgba8(width, height, rgba.to_vec(), opts.color_space);
+        Ok(self.add_texture(&data, opts))
+    }
+
+    fn add_texture(&mut self, data: &TexelData, opts: TextureOptions) -> TextureHandle {
+        // Mips are blitted on the GPU, which needs the format to be renderable here.
+        let renderable = self.ctx.adapter
+            .get_texture_format_features(data.format)
+            .allowed_usages
+            .contains(wgpu::TextureUsages::RENDER_ATTACHMENT);
+        let mips = (opts.mipmaps && renderable).then_some(&mut self.mips);
+
+        let (texture, view) = Texture::upload(&self.ctx.device, &self.ctx.queue, mips, data);
+        let sampler = self.samplers.get(&self.ctx.device, opts.sampler);
+        let bind_group = Texture::make_bind_group(&self.ctx.device, &self.texture_bgl, &view, sampler);
+
+        self.textures.insert(Texture {
+            texture,
+            view,
+            format: data.format,
+            width: data.width,
+            height: data.height,
+            bind_group,
+            sampler: opts.sampler,
+        })
+    }
+
+    pub fn set_texture_sampler(&mut self, handle: TextureHandle, desc: SamplerDesc) {
+        let Some(tex) = self.textures.get_mut(handle) else { return };
+        let sampler = self.samplers.get(&self.ctx.device, desc);
+        tex.bind_group = Texture::make_bind_group(&self.ctx.device, &self.texture_bgl, &tex.view, sampler);
+        tex.sampler = desc;
+    }
+
+    pub fn texture(&self, handle: TextureHandle) -> Option<&Texture> {
+        self.textures.get(handle)
+    }
+
+    pub fn remove_texture(&mut self, handle: TextureHandle) {
+        self.textures.remove(handle);
+    }
+
+    /// Layout of group 2 in textured pipelines: `texture_2d<f32>` at binding 0, sampler at 1.
+    pub fn texture_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.texture_bgl
+    }
+
+    /// Queues draws for the next `render*` call. The list is consumed by that frame.
+    pub fn submit(&mut self, items: impl IntoIterator<Item = DrawItem>) {
+        let materials = &self.materials;
+        self.draw_list.extend(items.into_iter().map(|mut d| {
+            if let Some(mat) = d.material.and_then(|m| materials.get(m)) {
+                d.pipeline = mat.pipeline;
+            }
+            d
+        }));
+    }
+
+    /// Queues a draw for every `Renderable` entity in `world` without `Hidden`, picked as that
+    /// entity (see `pick_entity`). Returns how many.
+    ///
+    /// Their pick ids count up from 1 through the frame, replacing the items' own; don't give
+    /// draws `submit`ted for the same frame ids of their own if they're to be told apart.
+    pub fn submit_world(&mut self, world: &World) -> usize {
+        let items = crate::world::draw_list(world);
+        let count = items.len();
+        let first = self.pick_entities.len();
+        self.pick_entities.extend(items.iter().map(|(e, _)| *e));
+        self.submit(items.into_iter().enumerate().map(|(i, (_, item))| {
+            let id = u32::try_from(first + i + 1).expect("fewer than u32::MAX entities drawn a frame");
+            item.with_pick_id(id)
+        }));
+        count
+    }
+
+    fn finish_frame(&mut self, mut encoder: wgpu::CommandEncoder, frame: Frame) {
+        let pending = self.capture_request.take().map(|opts| self.encode_capture(&mut encoder, &frame.texture, opts));
 
         self.ctx.queue.submit(std::iter::once(encoder.finish()));
+
+        if let Some(pending) = pending {
+            self.captured = Some(pending.and_then(|(color, depth)| self.read_capture(color, depth)));
+        }
         frame.present();
-        Ok(())
     }
 
-    pub fn render_with<F>(&mut self, mut extra_pass: F) -> GResult<()>
-    where
-        F: FnMut(&mut wgpu::CommandEncoder, &wgpu::TextureView),
-    {
-        let frame = self.ctx.surface.get_current_texture()?;
-        let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
+    fn encode_capture(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        color: &wgpu::Texture,
+        opts: CaptureOptions,
+    ) -> Result<(Readback, Option<Readback>), CaptureError> {
+        let color = encode_readback(&self.ctx.device, encoder, color, wgpu::TextureAspect::All)?;
+        let depth = if opts.depth {
+            let downlevel = self.ctx.adapter.get_downlevel_capabilities().flags;
+            let replayed = (!self.ctx.depth_texture.usage().contains(wgpu::TextureUsages::TEXTURE_BINDING))
+                .then(|| self.encode_depth_replay(encoder));
+            let depth = replayed.as_ref().unwrap_or(&self.ctx.depth_texture);
+            Some(encode_depth_readback(&self.ctx.device, encoder, depth, downlevel)?)
+        } else { None };
+        Ok((color, depth))
+    }
+
+    fn read_capture(&self, color: Readback, depth: Option<Readback>) -> Result<FrameCapture, CaptureError> {
+        Ok(FrameCapture {
+            format: self.ctx.config.format,
+            color: color.into_color(&self.ctx.device)?,
+            depth: depth.map(|d| d.into_depth(&self.ctx.device)).transpose()?,
+        })
+    }
+
+    /// Records a readback of the next rendered frame, picked up with `capture_frame`. Headless
+    /// renderers don't need it (their target stays readable) and ignore it.
+    pub fn request_capture(&mut self, opts: CaptureOptions) {
+        if self.ctx.offscreen_texture().is_none() {
+            self.capture_request = Some(opts);
+        }
+    }
+
+    /// Returns the pixels of the last rendered frame.
+    ///
+    /// Headless renderers read their offscreen target directly. Window surfaces can't be read
+    /// after presenting, so there the capture has to be armed with `request_capture` first, and
+    /// it fails with `CaptureError::NotCopyable` on surfaces that can't be copied from.
+    pub fn capture_frame(&mut self, opts: CaptureOptions) -> Result<FrameCapture, CaptureError> {
+        if let Some(texture) = self.ctx.offscreen_texture().cloned() {
+            let mut encoder = self.ctx.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("capture") });
+            let (color, depth) = self.encode_capture(&mut encoder, &texture, opts)?;
+            self.ctx.queue.submit(std::iter::once(encoder.finish()));
+            return self.read_capture(color, depth);
+        }
+        self.captured.take().unwrap_or(Err(CaptureError::NotRecorded))
+    }
+
+    /// Adds a pass to every following frame's graph. Passes can come in any order; use
+    /// `check_graph` once they're all in.
+    pub fn add_pass(&mut self, pass: impl GraphPass + 'static) {
+        self.passes.push(Box::new(pass));
+    }
+
+    /// Compiles the graph as the next frame would, without recording anything.
+    pub fn check_graph(&mut self) -> Result<(), GraphError> {
+        let size = (self.ctx.config.width, self.ctx.config.height);
+        let mut hdr = self.hdr.take();
+        let mut effects = std::mem::take(&mut self.post.passes);
+        let mut passes = std::mem::take(&mut self.passes);
+        let (has_hdr, has_post) = (hdr.is_some(), !effects.is_empty());
+        let result = self.build_graph(size, has_hdr, has_post, &mut graph_nodes(hdr.as_mut(), &mut effects, &mut passes)).map(drop);
+        (self.hdr, self.post.passes, self.passes) = (hdr, effects, passes);
+        result
+    }
+
+    /// Returns whether a pass by that name was there.
+    pub fn remove_pass(&mut self, name: &str) -> bool {
+        let len = self.passes.len();
+        self.passes.retain(|p| p.name() != name);
+        self.passes.len() != len
+    }
+
+    pub fn graph_stats(&self) -> &GraphStats {
+        &self.graph_stats
+    }
+
+    /// Set when the last frame's graph didn't compile; that frame then ran the built-in passes alone.
+    pub fn graph_error(&self) -> Option<&GraphError> {
+        self.graph_error.as_ref()
+    }
+
+    /// Declares the scene pass, then `nodes` (graph pass `i + 1` is `nodes[i]`). The HDR and
+    /// post-process passes are among `nodes` while they're out of `self`, hence `hdr` and `post`.
+    fn build_graph(
+        &self,
+        frame_size: (u32, u32),
+        hdr: bool,
+        post: bool,
+        nodes: &mut [&mut dyn GraphPass],
+    ) -> Result<(CompiledGraph, SceneTargets), GraphError> {
+        use crate::graph::{DEPTH, FRAME, HDR, LDR, SCENE_PASS};
+
+        let mut graph = GraphBuilder::new(frame_size, self.ctx.config.format, &self.ctx.depth_texture);
+        let scene = {
+            let mut b = graph.add_pass(SCENE_PASS);
+            let (target, format) = match (hdr, post) {
+                (true, _) => (b.create_texture(HDR, TextureDesc::new(HDR_FORMAT)), HDR_FORMAT),
+                (false, true) => (b.create_texture(LDR, TextureDesc::new(b.frame_format())), b.frame_format()),
+                (false, false) => (b.write(FRAME), b.frame_format()),
+            };
+            b.write(DEPTH);
+            match b.samples() {
+                1 => SceneTargets { color: target, resolve: None },
+                samples => {
+                    // Only ever resolved; also being a texture keeps it from resolving on GL.
+                    let desc = TextureDesc { usage: wgpu::TextureUsages::RENDER_ATTACHMENT, ..TextureDesc::new(format).with_samples(samples) };
+                    SceneTargets { color: b.create_texture("scene_msaa", desc), resolve: Some(target) }
+                }
+            }
+        };
+        for pass in nodes {
+            pass.setup(&mut graph.add_pass(pass.name()));
+        }
+        Ok((graph.compile()?, scene))
+    }
+
+    /// Starts a frame and records its graph.
+    fn begin_frame(&mut self) -> GResult<(Frame, wgpu::CommandEncoder)> {
+        if self.shader_watcher.as_ref().is_some_and(|w| w.has_events()) {
+            self.reload_shaders();
+        }
+        let frame = self.ctx.acquire_frame()?;
         let mut encoder = self.ctx.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("encoder") });
 
-        {
-            let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("triangle"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color { r: 0.05, g: 0.06, b: 0.1, a: 1.0 }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.ctx.depth_view,
-                    depth_ops: Some(wgpu::Operations { load: wgpu::LoadOp::Clear(1.0), store: wgpu::StoreOp::Store }),
-                    stencil_ops: None,
-                }),
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
-            if let Some(ref p) = self.pipeline {
-                rp.set_pipeline(p);
-                rp.set_bind_group(0, &self.cam.bind_group, &[]);
-                rp.set_vertex_buffer(0, self.vbuf.slice(..));
-                rp.draw(0..self.vcount, 0..1);
+        let mut hdr = self.hdr.take();
+        let mut effects = std::mem::take(&mut self.post.passes);
+        let mut passes = std::mem::take(&mut self.passes);
+        self.record_graph(&mut encoder, &frame, hdr.as_mut(), &mut effects, &mut passes);
+        (self.hdr, self.post.passes, self.passes) = (hdr, effects, passes);
+        Ok((frame, encoder))
+    }
+
+    fn record_graph(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        frame: &Frame,
+        hdr: Option<&mut Hdr>,
+        effects: &mut [EffectPass],
+        passes: &mut [Box<dyn GraphPass>],
+    ) {
+        let size = (frame.texture.width(), frame.texture.height());
+        let (has_hdr, has_post, added) = (hdr.is_some(), !effects.is_empty(), passes.len());
+        let mut nodes = graph_nodes(hdr, effects, passes);
+        let (graph, scene) = match self.build_graph(size, has_hdr, has_post, &mut nodes) {
+            Ok(g) => { self.graph_error = None; g }
+            Err(e) => {
+                self.graph_error = Some(e);
+                nodes.truncate(nodes.len() - added);
+                self.build_graph(size, has_hdr, has_post, &mut nodes).expect("built-in passes alone always compile")
+            }
+        };
+        let (resources, allocated) = self.transients.allocate(&self.ctx.device, &graph, (&frame.texture, &frame.view));
+
+        for &i in &graph.order {
+            if i == 0 {
+                let resolve = scene.resolve.map(|id| &resources.texture(id).1);
+                let ops = self.load_request.take().unwrap_or(self.load_ops);
+                self.encode_scene(encoder, resources.texture(scene.color), resolve, ops);
+            } else {
+                let mut ctx = PassContext::new(&self.ctx.device, &self.ctx.queue, encoder, &resources);
+                nodes[i - 1].execute(&mut ctx);
             }
-            rp.set_bind_group(0, &self.cam.bind_group, &[]);
-            rp.set_vertex_buffer(0, self.vbuf.slice(..));
-            rp.draw(0..self.vcount, 0..1);
         }
+        self.graph_stats = GraphStats {
+            order: graph.order.iter().map(|&i| graph.pass_name(i).to_string()).collect(),
+            culled: graph.culled.iter().map(|&i| graph.pass_name(i).to_string()).collect(),
+            transients: graph.transients(),
+            allocated,
+        };
+    }
 
-        extra_pass(&mut encoder, &view);
+    pub fn render(&mut self) -> GResult<()> {
+        let (frame, encoder) = self.begin_frame()?;
+        self.finish_frame(encoder, frame);
+        Ok(())
+    }
 
-        self.ctx.queue.submit(std::iter::once(encoder.finish()));
-        frame.present();
+    /// `extra_pass` runs after the graph; prefer `add_pass` for anything that outlives a frame.
+    pub fn render_with<F>(&mut self, mut extra_pass: F) -> GResult<()>
+    where
+        F: FnMut(&mut wgpu::CommandEncoder, &wgpu::TextureView),
+    {
+        let (frame, mut encoder) = self.begin_frame()?;
+        extra_pass(&mut encoder, &frame.view);
+        self.finish_frame(encoder, frame);
         Ok(())
     }
 
-    pub fn render_with_ui<F>(&mut self, window: &winit::window::Window, mut ui_build: F) -> GResult<()>
+    /// Draws the UI over the frame once the whole graph has run.
+    /// `window` may be `None` for headless renderers.
+    pub fn render_with_ui<F>(&mut self, window: Option<&winit::window::Window>, mut ui_build: F) -> GResult<()>
     where
         F: for<'a> FnMut(&'a dyn ui_core::Ui),
     {
-        let frame = self.ctx.surface.get_current_texture()?;
-        let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let mut encoder = self.ctx.device.create_command_encoder(
-            &wgpu::CommandEncoderDescriptor { label: Some("encoder") }
-        );
+        let (frame, mut encoder) = self.begin_frame()?;
 
-        {
-            let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("triangle"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color { r: 0.05, g: 0.06, b: 0.1, a: 1.0 }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.ctx.depth_view,
-                    depth_ops: Some(wgpu::Operations { load: wgpu::LoadOp::Clear(1.0), store: wgpu::StoreOp::Store }),
-                    stencil_ops: None,
-                }),
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
-            if let Some(ref p) = self.pipeline {
-                rp.set_pipeline(p);
-                rp.set_bind_group(0, &self.cam.bind_group, &[]);
-                rp.set_vertex_buffer(0, self.vbuf.slice(..));
-                rp.draw(0..self.vcount, 0..1);
+        let errors: Vec<String> = self.shader_errors.iter().map(|(_, e)| e.to_string())
+            .chain(self.graph_error.iter().map(|e| format!("render graph: {e}")))
+            .collect();
+        let mut build = |ui: &dyn ui_core::Ui| {
+            ui_build(ui);
+            if !errors.is_empty() {
+                ui.window("Shader errors", [520.0, 240.0], &mut |ui| {
+                    for (i, e) in errors.iter().enumerate() {
+                        if i > 0 { ui.separator(); }
+                        ui.text(e);
+                    }
+                });
             }
-        }
-
+        };
         self.ui.build_and_render(
             window,
             &self.ctx.device,
             &self.ctx.queue,
             &mut encoder,
-            &view,
-            &mut ui_build,
+            &frame.view,
+            &mut build,
         );
+        self.graph_stats.order.push(crate::graph::UI_PASS.to_string());
 
-        self.ctx.queue.submit(std::iter::once(encoder.finish()));
-        frame.present();
+        self.finish_frame(encoder, frame);
         Ok(())
     }
 
@@ -241,52 +845,385 @@ impl Renderer {
         self.ctx.config.width as f32 / self.ctx.config.height as f32
     }
 
+    /// Frame size in pixels, as `Camera`'s `*_viewport` methods and screen conversions take it.
+    pub fn viewport(&self) -> glam::Vec2 {
+        glam::Vec2::new(self.ctx.config.width as f32, self.ctx.config.height as f32)
+    }
+
+    /// Also what draws are culled against.
     pub fn update_camera_ubo(&mut self, ubo: &crate::CameraUBO) {
         self.ctx.queue.write_buffer(&self.cam.buffer, 0, bytemuck::bytes_of(ubo));
+        self.view_proj = Some(glam::Mat4::from_cols_array_2d(&ubo.view_proj));
+    }
+
+    /// Local-space bounds `mesh`'s draws are culled by; `None` draws it whatever the view.
+    pub fn set_mesh_bounds(&mut self, mesh: MeshHandle, bounds: Option<Aabb>) {
+        if let Some(m) = self.meshes.get_mut(mesh) {
+            m.bounds = bounds;
+        }
+    }
+
+    pub fn culling(&self) -> bool {
+        self.culling
+    }
+
+    /// On by default; draws without bounds are never culled either way.
+    pub fn set_culling(&mut self, on: bool) {
+        self.culling = on;
+    }
+
+    pub fn cull_stats(&self) -> CullStats {
+        self.cull_stats
+    }
+
+    /// The draw under `cursor` (in pixels from the top-left) in the last frame, as the current
+    /// camera sees it. Renders that frame's draws again into a single pixel and waits for it.
+    /// Draws go by their pipeline's position at location 0 and the usual camera and model
+    /// transforms; those without a float position there can't be picked.
+    pub fn pick(&mut self, cursor: glam::Vec2) -> Result<Option<PickHit>, PickError> {
+        let viewport = self.viewport();
+        let Some(view_proj) = self.view_proj else { return Ok(None) };
+        if !(cursor.cmpge(glam::Vec2::ZERO).all() && cursor.cmplt(viewport).all()) {
+            return Ok(None);
+        }
+        let draws: Vec<PickDraw> = self.last_draws.iter()
+            .filter_map(|d| Some(PickDraw {
+                mesh: self.meshes.get(d.mesh)?,
+                pipeline: self.pipelines.get(d.pipeline)?,
+                instances: match d.instances {
+                    Some(h) => Some(self.instances.get(h)?),
+                    None => None,
+                },
+                transform: d.transform,
+                id: d.pick_id,
+            }))
+            .collect();
+        self.picker.pick(&self.ctx.device, &self.ctx.queue, (view_proj, self.reverse_z), (cursor, viewport), &draws)
     }
 
+    /// `pick`, for a frame drawn with `submit_world`: the entity under `cursor` and the hit.
+    /// Entities despawned since (even if their slot went to a new one) and draws without a pick
+    /// id pick as nothing.
+    pub fn pick_entity(&mut self, world: &World, cursor: glam::Vec2) -> Result<Option<(Entity, PickHit)>, PickError> {
+        Ok(self.pick(cursor)?.and_then(|hit| {
+            let entity = *self.last_pick_entities.get(hit.id.checked_sub(1)? as usize)?;
+            world.is_alive(entity).then_some((entity, hit))
+        }))
+    }
+
+    /// Rebuilds the pipeline behind `handle` with new overrides/topology, keeping the handle valid.
+    /// On error the old pipeline stays in place; on success its entry in `shader_errors` goes.
     pub fn rebuild_pipeline(
         &mut self,
+        handle: PipelineHandle,
         shader_src: &shader_core::WgslSource,
         overrides: shader_core::Overrides,
         topo: shader_core::Topology,
-    ) {
-        let state = shader_core::RenderState {
-            format: self.ctx.config.format,
-            depth: true,
-            msaa: 1,
-            topo,
-        };
-        let key = shader_core::ShaderKey::new(shader_src, state, &overrides);
-        let p = self.pipeline_cache.get_or_create(
-            key,
-            &self.ctx.device,
-            &self.pipeline_layout,
-            shader_src,
-            &state,
-            &overrides,
-            &[crate::types::Vertex::layout()],
-        ).clone();
-        self.pipeline = Some(p);
+    ) -> Result<(), PipelineError> {
+        let old = self.pipelines.get(handle).ok_or(PipelineError::StaleHandle)?;
+        let (layout_key, vertex_layouts) = (old.layout.clone(), old.vertex_layouts.clone());
+        let state = shader_core::RenderState { topo, ..old.state };
+        let p = self.create_pipeline(layout_key, shader_src, &state, &overrides, &vertex_layouts)?;
+        self.replace_pipeline(handle, Ok(p));
+        Ok(())
     }
 
+    /// `state.format` and `state.msaa` are replaced with the scene's (see `scene_format`, `set_msaa`),
+    /// and the depth compare is flipped under `set_reverse_z`.
     pub fn build_pipeline(
         &mut self,
         shader_src: &shader_core::WgslSource,
         state: &shader_core::RenderState<wgpu::TextureFormat>,
         overrides: &shader_core::Overrides,
         vertex_layouts: &[wgpu::VertexBufferLayout<'static>],
-    ) {
+    ) -> Result<PipelineHandle, PipelineError> {
+        let state = self.new_scene_state(state);
+        let p = self.create_pipeline(LayoutKey::Scene, shader_src, &state, overrides, vertex_layouts)?;
+        Ok(self.add_pipeline(p))
+    }
+
+    /// Like `build_pipeline`, but the layout has the texture group (see `texture_bind_group_layout`)
+    /// at index 2; draw items using it should set `texture`.
+    pub fn build_textured_pipeline(
+        &mut self,
+        shader_src: &shader_core::WgslSource,
+        state: &shader_core::RenderState<wgpu::TextureFormat>,
+        overrides: &shader_core::Overrides,
+        vertex_layouts: &[wgpu::VertexBufferLayout<'static>],
+    ) -> Result<PipelineHandle, PipelineError> {
+        let state = self.new_scene_state(state);
+        let p = self.create_pipeline(LayoutKey::Textured, shader_src, &state, overrides, vertex_layouts)?;
+        Ok(self.add_pipeline(p))
+    }
+
+    /// Compiles a permutation into the pipeline cache (and the disk cache, if open) without
+    /// making a handle, so a later `build_*`/`create_material` with the same inputs is a hit.
+    /// `state` is used as given, so several formats and sample counts can be warmed up.
+    pub fn precompile(
+        &mut self,
+        layout: LayoutKey,
+        shader_src: &shader_core::WgslSource,
+        state: &shader_core::RenderState<wgpu::TextureFormat>,
+        overrides: &shader_core::Overrides,
+        vertex_layouts: &[wgpu::VertexBufferLayout<'static>],
+    ) -> Result<(), PipelineError> {
+        self.create_pipeline(layout, shader_src, state, overrides, vertex_layouts).map(drop)
+    }
+
+    pub fn pipeline(&self, handle: PipelineHandle) -> Option<&Pipeline> {
+        self.pipelines.get(handle)
+    }
+
+    /// Starts watching the files behind every pipeline's source (see `WgslSource::dependencies`).
+    /// Changed shaders are then recompiled at the start of the next `render*` call.
+    pub fn watch_shaders(&mut self) -> Result<(), shader_core::WatchError> {
+        let mut watcher = shader_core::ShaderWatcher::new()?;
+        for (_, p) in self.pipelines.iter() {
+            for dep in p.src.dependencies() {
+                watcher.watch(&dep)?;
+            }
+        }
+        self.shader_watcher = Some(watcher);
+        Ok(())
+    }
+
+    /// Recompiles pipelines whose shader files changed on disk. Pipelines that fail keep their
+    /// previous version; the error is kept in `shader_errors` (and shown by `render_with_ui`)
+    /// until a later reload of that pipeline succeeds. Returns how many pipelines were rebuilt.
+    pub fn reload_shaders(&mut self) -> usize {
+        let Some(watcher) = &self.shader_watcher else { return 0 };
+        let changed = watcher.changed();
+        if changed.is_empty() {
+            return 0;
+        }
+
+        let affected: Vec<PipelineHandle> = self.pipelines.iter()
+            .filter(|(_, p)| p.src.dependencies().iter().any(|d| changed.contains(&shader_core::canonical(d))))
+            .map(|(h, _)| h)
+            .collect();
+
+        let mut rebuilt = 0;
+        for handle in affected {
+            let Some(old) = self.pipelines.get(handle) else { continue };
+            let (src, layout_key, state, overrides, vertex_layouts) = (
+                old.src.clone(), old.layout.clone(), old.state, old.overrides.clone(), old.vertex_layouts.clone(),
+            );
+            let result = src.reload()
+                .map_err(PipelineError::from)
+                .and_then(|src| self.create_pipeline(layout_key, &src, &state, &overrides, &vertex_layouts));
+            rebuilt += usize::from(self.replace_pipeline(handle, result));
+        }
+        rebuilt
+    }
+
+    /// Swaps in a rebuilt pipeline, or records why it couldn't be rebuilt. Returns whether it was.
+    fn replace_pipeline(&mut self, handle: PipelineHandle, result: Result<Pipeline, PipelineError>) -> bool {
+        self.shader_errors.retain(|(h, _)| *h != handle);
+        match result {
+            Ok(p) => {
+                if let Some(slot) = self.pipelines.get_mut(handle) {
+                    *slot = p;
+                }
+                self.watch_sources(handle);
+                true
+            }
+            Err(e) => {
+                self.shader_errors.push((handle, e));
+                false
+            }
+        }
+    }
+
+    fn add_pipeline(&mut self, p: Pipeline) -> PipelineHandle {
+        let handle = self.pipelines.insert(p);
+        self.watch_sources(handle);
+        handle
+    }
+
+    /// Watches the pipeline's shader files under `watch_shaders`. Those that can't be watched
+    /// end up in `shader_errors`, since they won't reload.
+    fn watch_sources(&mut self, handle: PipelineHandle) {
+        let (Some(watcher), Some(p)) = (&mut self.shader_watcher, self.pipelines.get(handle)) else { return };
+        for dep in p.src.dependencies() {
+            if let Err(e) = watcher.watch(&dep) {
+                self.shader_errors.push((handle, PipelineError::Watch(dep, e)));
+            }
+        }
+    }
+
+    pub fn shader_errors(&self) -> &[(PipelineHandle, PipelineError)] {
+        &self.shader_errors
+    }
+
+    /// Keeps compiled pipelines in `dir` across runs. Open it before building pipelines so they
+    /// can use the driver cache; call `save_pipeline_cache` before exiting.
+    pub fn open_pipeline_cache(&mut self, dir: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        let disk = crate::disk_cache::DiskCache::open(dir, &self.ctx.device, &self.ctx.adapter.get_info())?;
+        self.pipeline_cache.set_disk_cache(disk);
+        Ok(())
+    }
+
+    /// No-op without `open_pipeline_cache`.
+    pub fn save_pipeline_cache(&self) -> std::io::Result<()> {
+        self.pipeline_cache.disk_cache().map_or(Ok(()), |d| d.save())
+    }
+
+    pub fn pipeline_cache_stats(&self) -> Option<crate::disk_cache::CacheStats> {
+        self.pipeline_cache.disk_cache().map(|d| d.stats())
+    }
+
+    fn pipeline_layout_for(&mut self, key: &LayoutKey) -> wgpu::PipelineLayout {
+        match key {
+            LayoutKey::Scene => self.pipeline_layout.clone(),
+            LayoutKey::Textured => self.textured_layout.clone(),
+            LayoutKey::Post => self.post_layout.clone(),
+            LayoutKey::Tonemap => self.tonemap_layout.clone(),
+            LayoutKey::Material(mk) => self.pipeline_cache
+                .material_layout(&self.ctx.device, mk, &[&self.cam.bgl, &self.objects.bgl])
+                .layout
+                .clone(),
+        }
+    }
+
+    fn create_pipeline(
+        &mut self,
+        layout_key: LayoutKey,
+        shader_src: &shader_core::WgslSource,
+        state: &shader_core::RenderState<wgpu::TextureFormat>,
+        overrides: &shader_core::Overrides,
+        vertex_layouts: &[wgpu::VertexBufferLayout<'static>],
+    ) -> Result<Pipeline, PipelineError> {
+        let layout = self.pipeline_layout_for(&layout_key);
         let key = shader_core::ShaderKey::new(shader_src, *state, overrides);
-        let p = self.pipeline_cache.get_or_create(
+        let raw = self.pipeline_cache.get_or_create(
             key,
+            layout_key.clone(),
             &self.ctx.device,
-            &self.pipeline_layout,
+            &layout,
             shader_src,
             state,
             overrides,
             vertex_layouts,
-        ).clone();
-        self.pipeline = Some(p);
+        )?.clone();
+        Ok(Pipeline {
+            raw,
+            layout: layout_key,
+            src: shader_src.clone(),
+            state: *state,
+            overrides: overrides.clone(),
+            vertex_layouts: vertex_layouts.to_vec(),
+        })
+    }
+
+    /// Builds the material's pipeline (sharing layouts and pipelines with other materials of the
+    /// same shape) and its group 2 bind group. Draw it with `DrawItem::from_material`.
+    pub fn create_material(&mut self, desc: MaterialDesc) -> Result<MaterialHandle, PipelineError> {
+        let layout_key = desc.layout_key()?;
+        let params_buffer = (!desc.params.is_empty()).then(|| {
+            self.ctx.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("material_params"),
+                contents: &desc.param_bytes(),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            })
+        });
+        let bind_group = self.material_bind_group(&desc, &layout_key, params_buffer.as_ref())?;
+
+        let p = self.create_pipeline(
+            LayoutKey::Material(layout_key.clone()),
+            &desc.shader,
+            &self.new_scene_state(&desc.state),
+            &desc.overrides,
+            &desc.vertex_layouts,
+        )?;
+        let pipeline = self.add_pipeline(p);
+        Ok(self.materials.insert(Material { desc, pipeline, layout_key, params_buffer, bind_group }))
+    }
+
+    fn material_bind_group(
+        &mut self,
+        desc: &MaterialDesc,
+        layout_key: &MaterialLayoutKey,
+        params: Option<&wgpu::Buffer>,
+    ) -> Result<wgpu::BindGroup, PipelineError> {
+        let bound: Vec<(wgpu::TextureView, wgpu::Sampler)> = desc.textures.iter()
+            .map(|t| {
+                let tex = self.textures.get(t.texture).ok_or(PipelineError::StaleTexture)?;
+                Ok((tex.view.clone(), self.samplers.get(&self.ctx.device, t.sampler).clone()))
+            })
+            .collect::<Result<_, PipelineError>>()?;
+
+        let mut entries = Vec::new();
+        if let Some(buf) = params {
+            entries.push(wgpu::BindGroupEntry { binding: 0, resource: buf.as_entire_binding() });
+        }
+        for (i, (view, sampler)) in bound.iter().enumerate() {
+            let i = i as u32;
+            entries.push(wgpu::BindGroupEntry { binding: 1 + 2 * i, resource: wgpu::BindingResource::TextureView(view) });
+            entries.push(wgpu::BindGroupEntry { binding: 2 + 2 * i, resource: wgpu::BindingResource::Sampler(sampler) });
+        }
+        let bgl = &self.pipeline_cache
+            .material_layout(&self.ctx.device, layout_key, &[&self.cam.bgl, &self.objects.bgl])
+            .bgl;
+        Ok(self.ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("material_bg"),
+            layout: bgl,
+            entries: &entries,
+        }))
+    }
+
+    pub fn material(&self, handle: MaterialHandle) -> Option<&Material> {
+        self.materials.get(handle)
+    }
+
+    /// Updates a uniform param in place. Returns `false` if the material or param doesn't exist,
+    /// or the value's type differs from the one the material was created with.
+    pub fn set_material_param(&mut self, handle: MaterialHandle, name: &str, value: MaterialParam) -> bool {
+        let Some(mat) = self.materials.get_mut(handle) else { return false };
+        let Some(slot) = mat.desc.params.iter_mut().find(|(n, _)| n == name) else { return false };
+        if std::mem::discriminant(&slot.1) != std::mem::discriminant(&value) {
+            return false;
+        }
+        slot.1 = value;
+        if let Some(buf) = &mat.params_buffer {
+            self.ctx.queue.write_buffer(buf, 0, &mat.desc.param_bytes());
+        }
+        true
     }
+
+    /// Swaps texture `index` of a material, rebuilding its bind group. Returns `false` if the
+    /// material, the slot or the texture doesn't exist.
+    pub fn set_material_texture(&mut self, handle: MaterialHandle, index: usize, texture: TextureHandle, sampler: SamplerDesc) -> bool {
+        let Some(mat) = self.materials.get(handle) else { return false };
+        if index >= mat.desc.textures.len() {
+            return false;
+        }
+        let mut desc = mat.desc.clone();
+        desc.textures[index].texture = texture;
+        desc.textures[index].sampler = sampler;
+        let (layout_key, params) = (mat.layout_key.clone(), mat.params_buffer.clone());
+        let Ok(bind_group) = self.material_bind_group(&desc, &layout_key, params.as_ref()) else { return false };
+        let mat = self.materials.get_mut(handle).expect("checked above");
+        mat.desc = desc;
+        mat.bind_group = bind_group;
+        true
+    }
+
+    pub fn remove_material(&mut self, handle: MaterialHandle) {
+        if let Some(mat) = self.materials.remove(handle) {
+            self.pipelines.remove(mat.pipeline);
+        }
+    }
+}
+
+/// The built-in HDR and post-process passes, then the added ones.
+fn graph_nodes<'a>(
+    hdr: Option<&'a mut Hdr>,
+    effects: &'a mut [EffectPass],
+    passes: &'a mut [Box<dyn GraphPass>],
+) -> Vec<&'a mut dyn GraphPass> {
+    let post = !effects.is_empty();
+    let mut nodes = hdr.map(|h| h.passes(post)).unwrap_or_default();
+    nodes.extend(post::chain(effects));
+    nodes.extend(passes.iter_mut().map(|p| p.as_mut() as &mut dyn GraphPass));
+    nodes
 }
@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub enum SamplerFilter {
+    Nearest,
+    /// Trilinear.
+    Linear,
+    /// Trilinear with the given max anisotropy (clamped to 1..=16).
+    Anisotropic(u16),
+}
+
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub enum WrapMode { Repeat, MirrorRepeat, ClampToEdge }
+
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub struct SamplerDesc {
+    pub filter: SamplerFilter,
+    pub wrap: WrapMode,
+}
+
+impl SamplerDesc {
+    pub const LINEAR_REPEAT: Self = Self { filter: SamplerFilter::Linear, wrap: WrapMode::Repeat };
+    pub const LINEAR_CLAMP: Self = Self { filter: SamplerFilter::Linear, wrap: WrapMode::ClampToEdge };
+    pub const NEAREST_REPEAT: Self = Self { filter: SamplerFilter::Nearest, wrap: WrapMode::Repeat };
+    pub const NEAREST_CLAMP: Self = Self { filter: SamplerFilter::Nearest, wrap: WrapMode::ClampToEdge };
+    pub const ANISO_REPEAT: Self = Self { filter: SamplerFilter::Anisotropic(16), wrap: WrapMode::Repeat };
+}
+
+impl Default for SamplerDesc {
+    fn default() -> Self { Self::LINEAR_REPEAT }
+}
+
+/// Samplers are small but not free; share one per distinct description.
+pub struct SamplerCache {
+    map: HashMap<SamplerDesc, wgpu::Sampler>,
+    anisotropy_supported: bool,
+}
+
+impl SamplerCache {
+    pub fn new(downlevel: wgpu::DownlevelFlags) -> Self {
+        Self {
+            map: HashMap::new(),
+            anisotropy_supported: downlevel.contains(wgpu::DownlevelFlags::ANISOTROPIC_FILTERING),
+        }
+    }
+
+    pub fn get(&mut self, device: &wgpu::Device, desc: SamplerDesc) -> &wgpu::Sampler {
+        let anisotropy_supported = self.anisotropy_supported;
+        self.map.entry(desc).or_insert_with(|| {
+            let address_mode = match desc.wrap {
+                WrapMode::Repeat => wgpu::AddressMode::Repeat,
+                WrapMode::MirrorRepeat => wgpu::AddressMode::MirrorRepeat,
+                WrapMode::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+            };
+            let (filter, anisotropy_clamp) = match desc.filter {
+                SamplerFilter::Nearest => (wgpu::FilterMode::Nearest, 1),
+                SamplerFilter::Linear => (wgpu::FilterMode::Linear, 1),
+                SamplerFilter::Anisotropic(n) if anisotropy_supported => (wgpu::FilterMode::Linear, n.clamp(1, 16)),
+                SamplerFilter::Anisotropic(_) => (wgpu::FilterMode::Linear, 1),
+            };
+            device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("sampler"),
+                address_mode_u: address_mode,
+                address_mode_v: address_mode,
+                address_mode_w: address_mode,
+                mag_filter: filter,
+                min_filter: filter,
+                mipmap_filter: filter,
+                anisotropy_clamp,
+                ..Default::default()
+            })
+        })
+    }
+}
@@ -0,0 +1,133 @@
+//! `shader_core::RenderState` to wgpu descriptors.
+
+use shader_core::{
+    BlendFactor, BlendMode, BlendOp, CompareFunction, CullMode, FrontFace, PolygonMode, RenderState, StencilFace,
+    StencilOp, Topology,
+};
+use wgpu::TextureFormat;
+
+pub(crate) fn color_targets(state: &RenderState<TextureFormat>) -> Vec<Option<wgpu::ColorTargetState>> {
+    state.targets().into_iter()
+        .map(|t| t.map(|t| wgpu::ColorTargetState {
+            format: t.format,
+            blend: blend_state(t.blend),
+            write_mask: wgpu::ColorWrites::ALL,
+        }))
+        .collect()
+}
+
+pub(crate) fn primitive(state: &RenderState<TextureFormat>) -> wgpu::PrimitiveState {
+    wgpu::PrimitiveState {
+        topology: match state.topo {
+            Topology::TriangleList  => wgpu::PrimitiveTopology::TriangleList,
+            Topology::TriangleStrip => wgpu::PrimitiveTopology::TriangleStrip,
+            Topology::LineList      => wgpu::PrimitiveTopology::LineList,
+        },
+        front_face: match state.front_face {
+            FrontFace::Ccw => wgpu::FrontFace::Ccw,
+            FrontFace::Cw => wgpu::FrontFace::Cw,
+        },
+        cull_mode: match state.cull {
+            CullMode::None => None,
+            CullMode::Front => Some(wgpu::Face::Front),
+            CullMode::Back => Some(wgpu::Face::Back),
+        },
+        polygon_mode: match state.polygon {
+            PolygonMode::Fill => wgpu::PolygonMode::Fill,
+            PolygonMode::Line => wgpu::PolygonMode::Line,
+            PolygonMode::Point => wgpu::PolygonMode::Point,
+        },
+        ..Default::default()
+    }
+}
+
+/// `None` without depth. Stencil state then, or on a `depth_format` without stencil, is rejected
+/// before this is called.
+pub(crate) fn depth_stencil(state: &RenderState<TextureFormat>, depth_format: TextureFormat) -> Option<wgpu::DepthStencilState> {
+    if !state.depth {
+        return None;
+    }
+    let face = |f: StencilFace| wgpu::StencilFaceState {
+        compare: compare(f.compare),
+        fail_op: stencil_op(f.fail_op),
+        depth_fail_op: stencil_op(f.depth_fail_op),
+        pass_op: stencil_op(f.pass_op),
+    };
+    Some(wgpu::DepthStencilState {
+        format: depth_format,
+        depth_write_enabled: state.depth_write,
+        depth_compare: compare(state.depth_compare),
+        stencil: wgpu::StencilState {
+            front: face(state.stencil.front),
+            back: face(state.stencil.back),
+            read_mask: state.stencil.read_mask,
+            write_mask: state.stencil.write_mask,
+        },
+        bias: wgpu::DepthBiasState {
+            constant: state.depth_bias.constant,
+            slope_scale: state.depth_bias.slope_scale,
+            clamp: state.depth_bias.clamp,
+        },
+    })
+}
+
+fn blend_state(mode: BlendMode) -> Option<wgpu::BlendState> {
+    let component = |c: shader_core::BlendComponent| wgpu::BlendComponent {
+        src_factor: blend_factor(c.src),
+        dst_factor: blend_factor(c.dst),
+        operation: match c.op {
+            BlendOp::Add => wgpu::BlendOperation::Add,
+            BlendOp::Subtract => wgpu::BlendOperation::Subtract,
+            BlendOp::ReverseSubtract => wgpu::BlendOperation::ReverseSubtract,
+            BlendOp::Min => wgpu::BlendOperation::Min,
+            BlendOp::Max => wgpu::BlendOperation::Max,
+        },
+    };
+    // `REPLACE` spelled out rather than `None`, same as before blend modes existed.
+    let Some((color, alpha)) = mode.components() else { return Some(wgpu::BlendState::REPLACE) };
+    Some(wgpu::BlendState { color: component(color), alpha: component(alpha) })
+}
+
+fn blend_factor(f: BlendFactor) -> wgpu::BlendFactor {
+    match f {
+        BlendFactor::Zero => wgpu::BlendFactor::Zero,
+        BlendFactor::One => wgpu::BlendFactor::One,
+        BlendFactor::Src => wgpu::BlendFactor::Src,
+        BlendFactor::OneMinusSrc => wgpu::BlendFactor::OneMinusSrc,
+        BlendFactor::SrcAlpha => wgpu::BlendFactor::SrcAlpha,
+        BlendFactor::OneMinusSrcAlpha => wgpu::BlendFactor::OneMinusSrcAlpha,
+        BlendFactor::Dst => wgpu::BlendFactor::Dst,
+        BlendFactor::OneMinusDst => wgpu::BlendFactor::OneMinusDst,
+        BlendFactor::DstAlpha => wgpu::BlendFactor::DstAlpha,
+        BlendFactor::OneMinusDstAlpha => wgpu::BlendFactor::OneMinusDstAlpha,
+        BlendFactor::SrcAlphaSaturated => wgpu::BlendFactor::SrcAlphaSaturated,
+        BlendFactor::Constant => wgpu::BlendFactor::Constant,
+        BlendFactor::OneMinusConstant => wgpu::BlendFactor::OneMinusConstant,
+    }
+}
+
+pub(crate) fn compare(f: CompareFunction) -> wgpu::CompareFunction {
+    match f {
+        CompareFunction::Never => wgpu::CompareFunction::Never,
+        CompareFunction::Less => wgpu::CompareFunction::Less,
+        CompareFunction::Equal => wgpu::CompareFunction::Equal,
+        CompareFunction::LessEqual => wgpu::CompareFunction::LessEqual,
+        CompareFunction::Greater => wgpu::CompareFunction::Greater,
+        CompareFunction::NotEqual => wgpu::CompareFunction::NotEqual,
+        CompareFunction::GreaterEqual => wgpu::CompareFunction::GreaterEqual,
+        CompareFunction::Always => wgpu::CompareFunction::Always,
+    }
+}
+
+fn stencil_op(op: StencilOp) -> wgpu::StencilOperation {
+    match op {
+        StencilOp::Keep => wgpu::StencilOperation::Keep,
+        StencilOp::Zero => wgpu::StencilOperation::Zero,
+        StencilOp::Replace => wgpu::StencilOperation::Replace,
+        StencilOp::Invert => wgpu::StencilOperation::Invert,
+        StencilOp::IncrementClamp => wgpu::StencilOperation::IncrementClamp,
+        StencilOp::DecrementClamp => wgpu::StencilOperation::DecrementClamp,
+        StencilOp::IncrementWrap => wgpu::StencilOperation::IncrementWrap,
+        StencilOp::DecrementWrap => wgpu::StencilOperation::DecrementWrap,
+    }
+}
@@ -0,0 +1,189 @@
+use std::path::Path;
+
+use crate::handle::Handle;
+use crate::mipmap::MipGenerator;
+use crate::sampler::SamplerDesc;
+
+/// How 8-bit texel data should be interpreted. Albedo/UI art is sRGB; normal maps,
+/// roughness etc. are linear. HDR images are always linear float.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ColorSpace { Srgb, Linear }
+
+#[derive(Clone, Copy, Debug)]
+pub struct TextureOptions {
+    pub color_space: ColorSpace,
+    pub mipmaps: bool,
+    pub sampler: SamplerDesc,
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        Self { color_space: ColorSpace::Srgb, mipmaps: true, sampler: SamplerDesc::default() }
+    }
+}
+
+#[derive(Debug)]
+pub enum TextureError {
+    Io(std::io::Error),
+    Decode(image::ImageError),
+    /// Raw texels that don't fill a non-empty `width` x `height` image exactly.
+    Size { width: u32, height: u32, len: usize },
+}
+
+impl std::fmt::Display for TextureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextureError::Io(e) => write!(f, "failed to read texture: {e}"),
+            TextureError::Decode(e) => write!(f, "failed to decode texture: {e}"),
+            TextureError::Size { width, height, len } => write!(f, "{len} bytes of texels don't make a {width}x{height} texture"),
+        }
+    }
+}
+
+impl std::error::Error for TextureError {}
+
+pub struct Texture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub format: wgpu::TextureFormat,
+    pub width: u32,
+    pub height: u32,
+    /// Texture + sampler bind group for `texture_bgl` (group 2).
+    pub bind_group: wgpu::BindGroup,
+    pub sampler: SamplerDesc,
+}
+
+pub type TextureHandle = Handle<Texture>;
+
+/// Decoded texels ready for upload.
+pub(crate) struct TexelData {
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+    pub bytes: Vec<u8>,
+}
+
+impl TexelData {
+    pub fn from_rgba8(width: u32, height: u32, rgba: Vec<u8>, space: ColorSpace) -> Self {
+        let format = match space {
+            ColorSpace::Srgb => wgpu::TextureFormat::Rgba8UnormSrgb,
+            ColorSpace::Linear => wgpu::TextureFormat::Rgba8Unorm,
+        };
+        Self { width, height, format, bytes: rgba }
+    }
+
+    /// PNG/JPEG become RGBA8 in the requested color space, HDR/EXR become `Rgba16Float`.
+    pub fn decode(bytes: &[u8], space: ColorSpace) -> Result<Self, TextureError> {
+        let img = image::load_from_memory(bytes).map_err(TextureError::Decode)?;
+        let (width, height) = (img.width(), img.height());
+        let is_float = matches!(
+            img.color(),
+            image::ColorType::Rgb32F | image::ColorType::Rgba32F
+        );
+        if is_float {
+            let texels: Vec<u8> = img.into_rgba32f()
+                .into_raw()
+                .into_iter()
+                .flat_map(|v| half::f16::from_f32(v).to_le_bytes())
+                .collect();
+            return Ok(Self { width, height, format: wgpu::TextureFormat::Rgba16Float, bytes: texels });
+        }
+        Ok(Self::from_rgba8(width, height, img.into_rgba8().into_raw(), space))
+    }
+
+    pub fn load(path: impl AsRef<Path>, space: ColorSpace) -> Result<Self, TextureError> {
+        let bytes = std::fs::read(path).map_err(TextureError::Io)?;
+        Self::decode(&bytes, space)
+    }
+}
+
+pub fn texture_bgl(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("texture_bgl"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+    })
+}
+
+impl Texture {
+    pub(crate) fn upload(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mips: Option<&mut MipGenerator>,
+        data: &TexelData,
+    ) -> (wgpu::Texture, wgpu::TextureView) {
+        let mip_level_count = if mips.is_some() { data.width.max(data.height).max(1).ilog2() + 1 } else { 1 };
+        let mut usage = wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST;
+        if mip_level_count > 1 {
+            usage |= wgpu::TextureUsages::RENDER_ATTACHMENT;
+        }
+
+        let size = wgpu::Extent3d { width: data.width, height: data.height, depth_or_array_layers: 1 };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("texture"),
+            size,
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: data.format,
+            usage,
+            view_formats: &[],
+        });
+
+        let bytes_per_texel = data.format.block_copy_size(None).expect("uncompressed format");
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &data.bytes,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(data.width * bytes_per_texel),
+                rows_per_image: Some(data.height),
+            },
+            size,
+        );
+
+        if let (Some(mips), true) = (mips, mip_level_count > 1) {
+            mips.generate(device, queue, &texture);
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        (texture, view)
+    }
+
+    pub(crate) fn make_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        view: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("texture_bg"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(view) },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(sampler) },
+            ],
+        })
+    }
+}
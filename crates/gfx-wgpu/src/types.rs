@@ -1,6 +1,8 @@
 use bytemuck::{Pod, Zeroable};
 
 pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+/// Used instead of `DEPTH_FORMAT` where the adapter has it, so pipelines can use stencil.
+pub const DEPTH_STENCIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32FloatStencil8;
 
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
@@ -23,4 +25,25 @@ impl Vertex {
     }
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct TexVertex {
+    pub pos: [f32; 3],
+    pub uv: [f32; 2],
+}
+
+impl TexVertex {
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        use std::mem::size_of;
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<TexVertex>() as u64,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute { shader_location: 0, offset: 0, format: wgpu::VertexFormat::Float32x3 },
+                wgpu::VertexAttribute { shader_location: 1, offset: 12, format: wgpu::VertexFormat::Float32x2 },
+            ],
+        }
+    }
+}
+
 pub type GResult<T> = Result<T, wgpu::SurfaceError>;
@@ -31,7 +31,7 @@ impl<'a> ui_core::Ui for UiAdapter<'a> {
 
 pub struct UiLayer {
     pub imgui: Context,
-    platform: WinitPlatform,
+    platform: Option<WinitPlatform>,
     renderer: ImGuiRenderer,
 }
 
@@ -57,7 +57,28 @@ impl UiLayer {
         let config = RendererConfig { texture_format: surface_format, ..Default::default() };
         let renderer = ImGuiRenderer::new(&mut imgui, device, queue, config);
 
-        Self { imgui, platform, renderer }
+        Self { imgui, platform: Some(platform), renderer }
+    }
+
+    pub fn new_headless(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        surface_format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let mut imgui = Context::create();
+        imgui.set_ini_filename(None);
+        imgui.io_mut().display_size = [width as f32, height as f32];
+        imgui.io_mut().display_framebuffer_scale = [1.0, 1.0];
+        imgui.fonts().add_font(&[imgui::FontSource::DefaultFontData {
+            config: Some(imgui::FontConfig { size_pixels: 13.0, ..Default::default() }),
+        }]);
+
+        let config = RendererConfig { texture_format: surface_format, ..Default::default() };
+        let renderer = ImGuiRenderer::new(&mut imgui, device, queue, config);
+
+        Self { imgui, platform: None, renderer }
     }
 }
 
@@ -73,20 +94,24 @@ impl ui_core::UiBackend for UiLayer {
         window_id: winit::window::WindowId,
         event: &WindowEvent,
     ) {
-        let ev: Event<()> = Event::WindowEvent { window_id, event: event.clone() };
-        self.platform.handle_event(self.imgui.io_mut(), window, &ev);
+        if let Some(platform) = &mut self.platform {
+            let ev: Event<()> = Event::WindowEvent { window_id, event: event.clone() };
+            platform.handle_event(self.imgui.io_mut(), window, &ev);
+        }
     }
 
     fn build_and_render(
         &mut self,
-        window: &Window,
+        window: Option<&Window>,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         encoder: &mut wgpu::CommandEncoder,
         view: &wgpu::TextureView,
         build: &mut dyn for<'a> FnMut(&'a dyn ui_core::Ui), // <- HRTB
     ) {
-        self.platform.prepare_frame(self.imgui.io_mut(), window).ok();
+        if let (Some(platform), Some(window)) = (&mut self.platform, window) {
+            platform.prepare_frame(self.imgui.io_mut(), window).ok();
+        }
 
         let frame = self.imgui.frame();
         let ui_ref: &ImUi = &*frame;
@@ -94,7 +119,9 @@ impl ui_core::UiBackend for UiLayer {
 
         build(&adapter as &dyn ui_core::Ui);
 
-        self.platform.prepare_render(ui_ref, window);
+        if let (Some(platform), Some(window)) = (&mut self.platform, window) {
+            platform.prepare_render(ui_ref, window);
+        }
         let draw_data = self.imgui.render();
 
         let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
@@ -109,6 +136,12 @@ impl ui_core::UiBackend for UiLayer {
             occlusion_query_set: None,
         });
 
-        self.renderer.render(&draw_data, queue, device, &mut rpass).expect("imgui render");
+        self.renderer.render(draw_data, queue, device, &mut rpass).expect("imgui render");
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        if self.platform.is_none() {
+            self.imgui.io_mut().display_size = [width as f32, height as f32];
+        }
     }
 }
@@ -0,0 +1,26 @@
+use engine_core::ecs::{Entity, Without, World};
+use engine_core::Transform;
+
+use crate::mesh::DrawItem;
+
+/// Draws its entity with `Renderer::submit_world`. With a `Transform` on the entity too, the
+/// item's own transform is applied first, relative to it.
+#[derive(Clone, Copy, Debug)]
+pub struct Renderable(pub DrawItem);
+
+/// Keeps a `Renderable` entity from being drawn.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Hidden;
+
+/// Every drawn `Renderable` with its entity.
+pub(crate) fn draw_list(world: &World) -> Vec<(Entity, DrawItem)> {
+    let mut items = Vec::new();
+    world.query_filtered::<(&Renderable, Option<&Transform>), Without<Hidden>>().for_each(|e, (r, t)| {
+        let mut item = r.0;
+        if let Some(t) = t {
+            item.transform = t.matrix() * item.transform;
+        }
+        items.push((e, item));
+    });
+    items
+}
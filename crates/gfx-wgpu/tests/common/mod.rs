@@ -0,0 +1,149 @@
+//! Golden-image harness: renders headlessly, compares against `tests/golden/<name>.png`.
+//!
+//! Set `GOLDEN_BLESS=1` to (re)write the references instead of comparing. On mismatch the
+//! actual image and a diff mask are written to `<target>/tmp/golden/`.
+
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+
+use gfx_wgpu::{CaptureOptions, HeadlessConfig, Renderer};
+use image::{Rgba, RgbaImage};
+
+pub const WIDTH: u32 = 256;
+pub const HEIGHT: u32 = 192;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Tolerance {
+    /// Per-pixel perceptual (YIQ) difference in 0..1 above which a pixel counts as different.
+    pub pixel: f32,
+    /// Fraction of pixels allowed to differ.
+    pub max_fraction: f32,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self { Self { pixel: 0.05, max_fraction: 0.001 } }
+}
+
+pub fn headless_renderer() -> Renderer {
+    Renderer::new_headless(&HeadlessConfig {
+        width: WIDTH,
+        height: HEIGHT,
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        force_fallback_adapter: std::env::var_os("GOLDEN_HW_ADAPTER").is_none(),
+    })
+}
+
+pub fn capture(renderer: &mut Renderer) -> RgbaImage {
+    renderer.capture_frame(CaptureOptions::default()).expect("capture failed").color
+}
+
+pub fn center(img: &RgbaImage) -> [u8; 4] {
+    img.get_pixel(img.width() / 2, img.height() / 2).0
+}
+
+/// The middle pixel of the last rendered frame.
+pub fn center_pixel(renderer: &mut Renderer) -> [u8; 4] {
+    center(&capture(renderer))
+}
+
+/// The demo's shaders, read from disk.
+pub fn demo_shader(name: &str) -> shader_core::WgslSource {
+    shader_core::ShaderLibrary::new()
+        .add_root(concat!(env!("CARGO_MANIFEST_DIR"), "/../../demos/triangle/shaders"))
+        .compose(name, &shader_core::Defines::default())
+        .unwrap_or_else(|e| panic!("{e}"))
+}
+
+pub fn triangle_src() -> shader_core::WgslSource {
+    demo_shader("triangle.wgsl")
+}
+
+pub fn triangle_mesh(renderer: &mut Renderer) -> gfx_wgpu::MeshHandle {
+    let verts = [
+        gfx_wgpu::Vertex { pos: [-0.6, -0.5], col: [1.0, 0.2, 0.2] },
+        gfx_wgpu::Vertex { pos: [ 0.6, -0.5], col: [0.2, 1.0, 0.2] },
+        gfx_wgpu::Vertex { pos: [ 0.0,  0.6], col: [0.2, 0.2, 1.0] },
+    ];
+    renderer.upload_mesh(&verts, None)
+}
+
+fn reference_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{name}.png"))
+}
+
+fn output_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("golden")
+}
+
+fn blessing() -> bool {
+    std::env::var("GOLDEN_BLESS").is_ok_and(|v| v != "0")
+}
+
+/// YIQ color distance (as in pixelmatch), normalized to 0..1.
+fn perceptual_delta(a: &Rgba<u8>, b: &Rgba<u8>) -> f32 {
+    let yiq = |p: &Rgba<u8>| {
+        // Blend against white so alpha differences show up too.
+        let a = p.0[3] as f32 / 255.0;
+        let [r, g, b] = [0, 1, 2].map(|i| 255.0 + (p.0[i] as f32 - 255.0) * a);
+        (
+            r * 0.298_895_3 + g * 0.586_622_5 + b * 0.114_482_2,
+            r * 0.595_977_9 - g * 0.274_176_4 - b * 0.321_801_5,
+            r * 0.211_470_2 - g * 0.522_617_4 + b * 0.311_147_2,
+        )
+    };
+    let (y1, i1, q1) = yiq(a);
+    let (y2, i2, q2) = yiq(b);
+    let (dy, di, dq) = (y1 - y2, i1 - i2, q1 - q2);
+    (0.5053 * dy * dy + 0.299 * di * di + 0.1957 * dq * dq) / 35215.0
+}
+
+pub fn assert_golden(name: &str, actual: &RgbaImage, tol: Tolerance) {
+    let reference = reference_path(name);
+
+    if blessing() {
+        actual.save(&reference).expect("write reference");
+        eprintln!("blessed {}", reference.display());
+        return;
+    }
+
+    let expected = match image::open(&reference) {
+        Ok(img) => img.to_rgba8(),
+        Err(e) => panic!("missing reference {} ({e}); run with GOLDEN_BLESS=1 to create it", reference.display()),
+    };
+
+    let out = output_dir();
+    if expected.dimensions() != actual.dimensions() {
+        std::fs::create_dir_all(&out).ok();
+        actual.save(out.join(format!("{name}.actual.png"))).ok();
+        panic!("{name}: size {:?} differs from reference {:?}", actual.dimensions(), expected.dimensions());
+    }
+
+    let mut diff = RgbaImage::new(actual.width(), actual.height());
+    let mut bad = 0usize;
+    for (x, y, a) in actual.enumerate_pixels() {
+        let e = expected.get_pixel(x, y);
+        if perceptual_delta(a, e) > tol.pixel {
+            bad += 1;
+            diff.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+        } else {
+            // Faded copy of the reference so the mismatches are easy to place.
+            let l = (e.0[0] as u32 + e.0[1] as u32 + e.0[2] as u32) / 3;
+            let l = (l / 4 + 192) as u8;
+            diff.put_pixel(x, y, Rgba([l, l, l, 255]));
+        }
+    }
+
+    let fraction = bad as f32 / (actual.width() * actual.height()) as f32;
+    if fraction > tol.max_fraction {
+        std::fs::create_dir_all(&out).ok();
+        actual.save(out.join(format!("{name}.actual.png"))).ok();
+        diff.save(out.join(format!("{name}.diff.png"))).ok();
+        panic!(
+            "{name}: {bad} pixels ({:.3}%) differ from the reference (allowed {:.3}%), see {}",
+            fraction * 100.0,
+            tol.max_fraction * 100.0,
+            out.display(),
+        );
+    }
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use gfx_wgpu::{Aabb, Camera, CullStats, DrawItem, InstanceData, Renderer};
+use glam::{Mat4, Vec3};
+use shader_core::{Overrides, RenderState};
+
+use common::{center_pixel, headless_renderer};
+
+fn stats(submitted: usize, culled: usize) -> CullStats {
+    CullStats { submitted, culled }
+}
+
+/// Renders the demo triangle at each of `offsets`, plus an instanced copy at the origin when asked.
+fn render(renderer: &mut Renderer, offsets: &[Vec3], instanced: Option<Option<Aabb>>) {
+    let mesh = common::triangle_mesh(renderer);
+    let bounds = Aabb::new(Vec3::new(-0.6, -0.5, 0.0), Vec3::new(0.6, 0.6, 0.0));
+    renderer.set_mesh_bounds(mesh, Some(bounds));
+    let state = RenderState::new(renderer.ctx.config.format);
+    let pipeline = renderer
+        .build_pipeline(&common::triangle_src(), &state, &Overrides::default(), &[gfx_wgpu::Vertex::layout()])
+        .expect("pipeline");
+    renderer.submit(offsets.iter().map(|&o| DrawItem::new(mesh, pipeline).with_transform(Mat4::from_translation(o))));
+    if let Some(item_bounds) = instanced {
+        let inst_src = common::demo_shader("instanced.wgsl");
+        let layouts = [gfx_wgpu::Vertex::layout(), InstanceData::layout()];
+        let inst_pipeline = renderer.build_pipeline(&inst_src, &state, &Overrides::default(), &layouts).expect("pipeline");
+        let instances = renderer.upload_instances(&[InstanceData::new(Mat4::IDENTITY)]);
+        let mut item = DrawItem::new(mesh, inst_pipeline).with_instances(instances);
+        item.bounds = item_bounds;
+        renderer.submit([item]);
+    }
+    renderer.render().expect("render");
+}
+
+#[test]
+fn draws_out_of_view_are_culled() {
+    let mut renderer = headless_renderer();
+    let camera = Camera::new(Vec3::new(0.0, 0.0, 2.0), Vec3::ZERO);
+    renderer.update_camera_ubo(&camera.make_ubo(renderer.aspect()));
+    let behind = Vec3::new(0.0, 0.0, 5.0);
+    let aside = Vec3::new(20.0, 0.0, 0.0);
+
+    render(&mut renderer, &[Vec3::ZERO, behind, aside], None);
+    assert_eq!(renderer.cull_stats(), stats(3, 2));
+    let background = [63, 69, 89];
+    assert_ne!(center_pixel(&mut renderer)[..3], background, "the one in view is drawn");
+
+    // Instanced draws only go by their own bounds.
+    render(&mut renderer, &[], Some(None));
+    assert_eq!(renderer.cull_stats(), stats(1, 0));
+    render(&mut renderer, &[], Some(Some(Aabb::from_center_half_extents(behind, Vec3::ONE))));
+    assert_eq!(renderer.cull_stats(), stats(1, 1));
+
+    renderer.set_culling(false);
+    render(&mut renderer, &[Vec3::ZERO, behind, aside], None);
+    assert_eq!(renderer.cull_stats(), stats(3, 0));
+    assert!(renderer.shader_errors().is_empty());
+}
+
+#[test]
+fn unbounded_meshes_are_always_drawn() {
+    let mut renderer = headless_renderer();
+    renderer.update_camera_ubo(&Camera::new(Vec3::new(0.0, 0.0, 2.0), Vec3::ZERO).make_ubo(renderer.aspect()));
+    let mesh = common::triangle_mesh(&mut renderer);
+    let state = RenderState::new(renderer.ctx.config.format);
+    let pipeline = renderer
+        .build_pipeline(&common::triangle_src(), &state, &Overrides::default(), &[gfx_wgpu::Vertex::layout()])
+        .expect("pipeline");
+    renderer.submit([DrawItem::new(mesh, pipeline).with_transform(Mat4::from_translation(Vec3::new(0.0, 0.0, 5.0)))]);
+    renderer.render().expect("render");
+    assert_eq!(renderer.cull_stats(), stats(1, 0));
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use std::process::Command;
+
+use common::{headless_renderer, triangle_src};
+use gfx_wgpu::CacheStats;
+
+fn cache_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("disk_cache").join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+/// Fresh renderer on `dir`, builds `src` once and returns what the cache made of it.
+fn build_once(dir: &std::path::Path, src: &shader_core::WgslSource) -> CacheStats {
+    let mut renderer = headless_renderer();
+    renderer.open_pipeline_cache(dir).expect("open cache");
+    let state = shader_core::RenderState::new(renderer.ctx.config.format);
+    renderer
+        .build_pipeline(src, &state, &shader_core::Overrides::default(), &[gfx_wgpu::Vertex::layout()])
+        .expect("pipeline");
+    renderer.save_pipeline_cache().expect("save cache");
+    renderer.pipeline_cache_stats().expect("stats")
+}
+
+#[test]
+fn permutations_persist_across_runs() {
+    let dir = cache_dir("persist");
+    let src = triangle_src();
+
+    assert_eq!(build_once(&dir, &src), CacheStats { hits: 0, misses: 1, invalidated: 0 });
+    assert_eq!(build_once(&dir, &src), CacheStats { hits: 1, misses: 0, invalidated: 0 });
+
+    // Same file and permutation, different code.
+    let mut edited = src.clone();
+    edited.code = format!("{}\n// edited\n", src.code).into();
+    assert_eq!(build_once(&dir, &edited), CacheStats { hits: 0, misses: 0, invalidated: 1 });
+    assert_eq!(build_once(&dir, &edited), CacheStats { hits: 1, misses: 0, invalidated: 0 });
+}
+
+#[test]
+fn foreign_manifests_are_ignored() {
+    let dir = cache_dir("foreign");
+    let src = triangle_src();
+    build_once(&dir, &src);
+
+    let manifest = dir.join("pipelines.manifest");
+    let text = std::fs::read_to_string(&manifest).unwrap();
+    let other_adapter: String = text
+        .lines()
+        .map(|l| if l.starts_with("adapter ") { "adapter Vulkan|10de|2684|Some GPU|driver|1.0" } else { l })
+        .collect::<Vec<_>>()
+        .join("\n");
+    std::fs::write(&manifest, other_adapter).unwrap();
+    assert_eq!(build_once(&dir, &src).misses, 1);
+
+    let old_version = text.replacen("version ", "version 0", 1);
+    std::fs::write(&manifest, old_version).unwrap();
+    assert_eq!(build_once(&dir, &src).misses, 1);
+}
+
+/// Child half of `permutations_persist_across_processes`; does nothing when run on its own.
+#[test]
+fn build_in_child_process() {
+    let Some(dir) = std::env::var_os("DISK_CACHE_CHILD_DIR") else { return };
+    let stats = build_once(std::path::Path::new(&dir), &triangle_src());
+    println!("stats {} {} {}", stats.hits, stats.misses, stats.invalidated);
+}
+
+#[test]
+fn permutations_persist_across_processes() {
+    // Every process hashes afresh, so this is what catches ids that only hold within one run.
+    let dir = cache_dir("processes");
+    let run = || {
+        let out = Command::new(std::env::current_exe().unwrap())
+            .args(["build_in_child_process", "--exact", "--nocapture", "--test-threads=1"])
+            .env("DISK_CACHE_CHILD_DIR", &dir)
+            .output()
+            .expect("spawn test binary");
+        assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+        let stdout = String::from_utf8_lossy(&out.stdout).into_owned();
+        stdout.lines().find_map(|l| Some(l.split_once("stats ")?.1)).expect("child stats").to_string()
+    };
+    assert_eq!(run(), "0 1 0");
+    assert_eq!(run(), "1 0 0");
+    let manifest = std::fs::read_to_string(dir.join("pipelines.manifest")).unwrap();
+    assert_eq!(manifest.lines().filter(|l| l.starts_with("p ")).count(), 1);
+}
+
+#[test]
+fn unused_permutations_are_dropped() {
+    let dir = cache_dir("unused");
+    let src = triangle_src();
+    let mut other = src.clone();
+    other.name = "other.wgsl".into();
+    build_once(&dir, &src);
+
+    let manifest = dir.join("pipelines.manifest");
+    let entries = || -> Vec<(String, u32)> {
+        std::fs::read_to_string(&manifest).unwrap().lines()
+            .filter_map(|l| l.strip_prefix("p "))
+            .map(|l| {
+                let parts: Vec<_> = l.splitn(4, ' ').collect();
+                (parts[3].to_string(), parts[2].parse().unwrap())
+            })
+            .collect()
+    };
+    build_once(&dir, &other);
+    let mut seen = entries();
+    seen.sort();
+    assert_eq!(seen, [("other.wgsl".to_string(), 0), (src.name.to_string(), 1)]);
+
+    // One run short of the limit, then one more without building it.
+    let text = std::fs::read_to_string(&manifest).unwrap().replace(" 1 ", " 8 ");
+    std::fs::write(&manifest, text).unwrap();
+    build_once(&dir, &other);
+    assert_eq!(entries(), [("other.wgsl".to_string(), 0)]);
+}
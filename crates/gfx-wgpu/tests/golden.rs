@@ -0,0 +1,222 @@
+mod common;
+
+use common::{assert_golden, capture, demo_shader, headless_renderer, triangle_mesh, triangle_src, Tolerance};
+
+fn triangle_overrides(fog: bool) -> shader_core::Overrides {
+    let mut ov = shader_core::Overrides::default();
+    ov.set_bool("USE_FOG", fog);
+    ov.set_f32("TINT_R", 1.0);
+    ov.set_f32("TINT_G", 0.9);
+    ov.set_f32("TINT_B", 0.9);
+    ov
+}
+
+fn triangle_pipeline(renderer: &mut gfx_wgpu::Renderer, fog: bool) -> gfx_wgpu::PipelineHandle {
+    let state = shader_core::RenderState::new(renderer.ctx.config.format);
+    renderer.build_pipeline(&triangle_src(), &state, &triangle_overrides(fog), &[gfx_wgpu::Vertex::layout()])
+        .expect("triangle pipeline")
+}
+
+fn render_triangle(fog: bool, angle: f32) -> image::RgbaImage {
+    let mut renderer = headless_renderer();
+    let pipeline = triangle_pipeline(&mut renderer, fog);
+    let mesh = triangle_mesh(&mut renderer);
+
+    let camera = gfx_wgpu::Camera::new(glam::Vec3::new(1.5, 1.5, 2.5), glam::Vec3::ZERO);
+    renderer.update_camera_ubo(&camera.make_ubo(renderer.aspect()));
+
+    renderer.submit([gfx_wgpu::DrawItem::new(mesh, pipeline).with_transform(glam::Mat4::from_rotation_y(angle))]);
+    renderer.render().expect("render");
+    capture(&mut renderer)
+}
+
+#[test]
+fn triangle() {
+    assert_golden("triangle", &render_triangle(true, 0.0), Tolerance::default());
+}
+
+#[test]
+fn triangle_no_fog_rotated() {
+    assert_golden("triangle_no_fog_rotated", &render_triangle(false, 0.7), Tolerance::default());
+}
+
+#[test]
+fn clear_only() {
+    let mut renderer = headless_renderer();
+    renderer.render().expect("render");
+    assert_golden("clear_only", &capture(&mut renderer), Tolerance::default());
+}
+
+#[test]
+fn indexed_meshes() {
+    let mut renderer = headless_renderer();
+    let pipeline = triangle_pipeline(&mut renderer, false);
+
+    let quad = [
+        gfx_wgpu::Vertex { pos: [-0.5, -0.5], col: [1.0, 0.8, 0.2] },
+        gfx_wgpu::Vertex { pos: [ 0.5, -0.5], col: [0.2, 0.8, 1.0] },
+        gfx_wgpu::Vertex { pos: [ 0.5,  0.5], col: [0.9, 0.2, 0.9] },
+        gfx_wgpu::Vertex { pos: [-0.5,  0.5], col: [0.2, 1.0, 0.3] },
+    ];
+    let quad16 = renderer.upload_mesh(&quad, Some(gfx_wgpu::Indices::U16(&[0, 1, 2, 0, 2, 3])));
+    let quad32 = renderer.upload_mesh(&quad, Some(gfx_wgpu::Indices::U32(&[0, 1, 2, 0, 2, 3])));
+    let tri = triangle_mesh(&mut renderer);
+
+    let camera = gfx_wgpu::Camera::new(glam::Vec3::new(0.0, 0.0, 4.0), glam::Vec3::ZERO);
+    renderer.update_camera_ubo(&camera.make_ubo(renderer.aspect()));
+
+    renderer.submit([
+        gfx_wgpu::DrawItem::new(quad16, pipeline).with_transform(glam::Mat4::from_translation(glam::Vec3::new(-1.2, 0.0, 0.0))),
+        gfx_wgpu::DrawItem::new(quad32, pipeline).with_transform(glam::Mat4::from_translation(glam::Vec3::new(1.2, 0.0, 0.0))),
+        gfx_wgpu::DrawItem::new(tri, pipeline).with_transform(glam::Mat4::from_scale_rotation_translation(
+            glam::Vec3::splat(0.8),
+            glam::Quat::from_rotation_z(0.4),
+            glam::Vec3::new(0.0, 0.3, 0.5),
+        )),
+    ]);
+    renderer.render().expect("render");
+    assert_golden("indexed_meshes", &capture(&mut renderer), Tolerance::default());
+}
+
+#[test]
+fn instanced_grid() {
+    let mut renderer = headless_renderer();
+    let src = demo_shader("instanced.wgsl");
+    let state = shader_core::RenderState::new(renderer.ctx.config.format);
+    let pipeline = renderer.build_pipeline(
+        &src,
+        &state,
+        &shader_core::Overrides::default(),
+        &[gfx_wgpu::Vertex::layout(), gfx_wgpu::InstanceData::layout()],
+    ).expect("instanced pipeline");
+    let mesh = triangle_mesh(&mut renderer);
+
+    let grid: Vec<_> = (0..1000)
+        .map(|i| {
+            let (x, y) = ((i % 40) as f32, (i / 40) as f32);
+            gfx_wgpu::InstanceData::new(
+                glam::Mat4::from_translation(glam::Vec3::new(x * 0.1 - 1.95, y * 0.1 - 1.2, 0.0))
+                    * glam::Mat4::from_rotation_z(i as f32 * 0.1)
+                    * glam::Mat4::from_scale(glam::Vec3::splat(0.06)),
+            )
+        })
+        .collect();
+    let instances = renderer.upload_instances(&grid);
+
+    let camera = gfx_wgpu::Camera::new(glam::Vec3::new(0.0, 0.0, 3.0), glam::Vec3::ZERO);
+    renderer.update_camera_ubo(&camera.make_ubo(renderer.aspect()));
+
+    renderer.submit([gfx_wgpu::DrawItem::new(mesh, pipeline)
+        .with_transform(glam::Mat4::from_rotation_x(-0.3))
+        .with_instances(instances)]);
+    renderer.render().expect("render");
+    assert_golden("instanced_grid", &capture(&mut renderer), Tolerance::default());
+}
+
+#[test]
+fn textured_floor() {
+    let mut renderer = headless_renderer();
+    let src = demo_shader("textured.wgsl");
+    let state = shader_core::RenderState::new(renderer.ctx.config.format);
+    let pipeline = renderer.build_textured_pipeline(
+        &src,
+        &state,
+        &shader_core::Overrides::default(),
+        &[gfx_wgpu::TexVertex::layout()],
+    ).expect("textured pipeline");
+
+    // Round-trip through PNG so the decode path is covered too.
+    let checker = image::RgbaImage::from_fn(64, 64, |x, y| {
+        if (x / 8 + y / 8) % 2 == 0 { image::Rgba([230, 120, 40, 255]) } else { image::Rgba([30, 40, 90, 255]) }
+    });
+    let mut png = Vec::new();
+    checker.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png).expect("encode png");
+
+    let mipped = renderer.load_texture(&png, gfx_wgpu::TextureOptions::default()).expect("load texture");
+    let nearest = renderer.load_texture(&png, gfx_wgpu::TextureOptions {
+        mipmaps: false,
+        sampler: gfx_wgpu::SamplerDesc::NEAREST_REPEAT,
+        ..Default::default()
+    }).expect("load texture");
+
+    let plane = |x0: f32, x1: f32| [
+        gfx_wgpu::TexVertex { pos: [x0, 0.0, -20.0], uv: [0.0, 0.0] },
+        gfx_wgpu::TexVertex { pos: [x0, 0.0, 0.0], uv: [0.0, 20.0] },
+        gfx_wgpu::TexVertex { pos: [x1, 0.0, 0.0], uv: [2.0, 20.0] },
+        gfx_wgpu::TexVertex { pos: [x1, 0.0, -20.0], uv: [2.0, 0.0] },
+    ];
+    let indices = [0u16, 1, 2, 0, 2, 3];
+    let left = renderer.upload_mesh(&plane(-2.0, -0.05), Some(gfx_wgpu::Indices::U16(&indices)));
+    let right = renderer.upload_mesh(&plane(0.05, 2.0), Some(gfx_wgpu::Indices::U16(&indices)));
+
+    let camera = gfx_wgpu::Camera::new(glam::Vec3::new(0.0, 0.6, 1.0), glam::Vec3::new(0.0, 0.0, -4.0));
+    renderer.update_camera_ubo(&camera.make_ubo(renderer.aspect()));
+
+    renderer.submit([
+        gfx_wgpu::DrawItem::new(left, pipeline).with_texture(mipped),
+        gfx_wgpu::DrawItem::new(right, pipeline).with_texture(nearest),
+    ]);
+    renderer.render().expect("render");
+    assert_golden("textured_floor", &capture(&mut renderer), Tolerance::default());
+}
+
+#[test]
+fn materials() {
+    let mut renderer = headless_renderer();
+    let src = demo_shader("material.wgsl");
+    let state = shader_core::RenderState::new(renderer.ctx.config.format);
+
+    let checker = |a: [u8; 4], b: [u8; 4]| -> Vec<u8> {
+        (0..16 * 16).flat_map(|i| if ((i % 16) / 4 + (i / 16) / 4) % 2 == 0 { a } else { b }).collect()
+    };
+    let opts = gfx_wgpu::TextureOptions { mipmaps: false, ..Default::default() };
+    let grey = renderer.create_texture_rgba8(16, 16, &checker([220, 220, 220, 255], [60, 60, 60, 255]), opts).expect("texture");
+    let warm = renderer.create_texture_rgba8(16, 16, &checker([240, 160, 60, 255], [90, 30, 20, 255]), opts).expect("texture");
+
+    // Same shader and layout, so both share one pipeline; only the bind groups differ.
+    let base = gfx_wgpu::MaterialDesc::new(src, state, &[gfx_wgpu::TexVertex::layout()]);
+    let tinted = renderer.create_material(base.clone()
+        .with_param("tint", gfx_wgpu::MaterialParam::Vec4([0.4, 0.8, 1.0, 1.0]))
+        .with_param("brightness", gfx_wgpu::MaterialParam::F32(0.2))
+        .with_texture(grey, gfx_wgpu::SamplerDesc::NEAREST_CLAMP)).expect("material");
+    let plain = renderer.create_material(base.clone()
+        .with_param("tint", gfx_wgpu::MaterialParam::Vec4([1.0, 1.0, 1.0, 1.0]))
+        .with_param("brightness", gfx_wgpu::MaterialParam::F32(1.0))
+        .with_texture(grey, gfx_wgpu::SamplerDesc::NEAREST_CLAMP)).expect("material");
+
+    assert!(renderer.set_material_param(tinted, "brightness", gfx_wgpu::MaterialParam::F32(1.0)));
+    assert!(!renderer.set_material_param(tinted, "brightness", gfx_wgpu::MaterialParam::Vec2([1.0, 1.0])));
+    assert!(!renderer.set_material_param(tinted, "missing", gfx_wgpu::MaterialParam::F32(1.0)));
+    assert!(renderer.set_material_texture(plain, 0, warm, gfx_wgpu::SamplerDesc::NEAREST_CLAMP));
+    assert!(!renderer.set_material_texture(plain, 1, grey, gfx_wgpu::SamplerDesc::NEAREST_CLAMP));
+
+    // A removed texture is refused, and the material keeps the one it had.
+    let removed = renderer.create_texture_rgba8(16, 16, &checker([0, 0, 0, 255], [0, 0, 0, 255]), opts).expect("texture");
+    renderer.remove_texture(removed);
+    assert!(!renderer.set_material_texture(plain, 0, removed, gfx_wgpu::SamplerDesc::NEAREST_CLAMP));
+    let err = renderer.create_material(base.clone()
+        .with_param("tint", gfx_wgpu::MaterialParam::Vec4([1.0, 1.0, 1.0, 1.0]))
+        .with_param("brightness", gfx_wgpu::MaterialParam::F32(1.0))
+        .with_texture(removed, gfx_wgpu::SamplerDesc::NEAREST_CLAMP)).unwrap_err();
+    assert!(matches!(err, gfx_wgpu::PipelineError::StaleTexture), "{err}");
+
+    let quad = |x0: f32, x1: f32| [
+        gfx_wgpu::TexVertex { pos: [x0, -0.8, 0.0], uv: [0.0, 1.0] },
+        gfx_wgpu::TexVertex { pos: [x1, -0.8, 0.0], uv: [1.0, 1.0] },
+        gfx_wgpu::TexVertex { pos: [x1, 0.8, 0.0], uv: [1.0, 0.0] },
+        gfx_wgpu::TexVertex { pos: [x0, 0.8, 0.0], uv: [0.0, 0.0] },
+    ];
+    let indices = [0u16, 1, 2, 0, 2, 3];
+    let left = renderer.upload_mesh(&quad(-1.7, -0.1), Some(gfx_wgpu::Indices::U16(&indices)));
+    let right = renderer.upload_mesh(&quad(0.1, 1.7), Some(gfx_wgpu::Indices::U16(&indices)));
+
+    let camera = gfx_wgpu::Camera::new(glam::Vec3::new(0.0, 0.0, 3.0), glam::Vec3::ZERO);
+    renderer.update_camera_ubo(&camera.make_ubo(renderer.aspect()));
+
+    renderer.submit([
+        gfx_wgpu::DrawItem::from_material(left, tinted),
+        gfx_wgpu::DrawItem::from_material(right, plain),
+    ]);
+    renderer.render().expect("render");
+    assert_golden("materials", &capture(&mut renderer), Tolerance::default());
+}
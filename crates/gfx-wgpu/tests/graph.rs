@@ -0,0 +1,220 @@
+mod common;
+
+use gfx_wgpu::graph::{FRAME, SCENE_PASS};
+use gfx_wgpu::{GraphError, GraphPass, PassBuilder, PassContext, ResourceId, TextureDesc};
+
+use common::{center_pixel, headless_renderer};
+
+/// Clears `target` to `color`, creating it unless it's the frame.
+struct Fill {
+    name: &'static str,
+    target: &'static str,
+    color: wgpu::Color,
+    before: Option<&'static str>,
+    id: Option<ResourceId>,
+}
+
+fn fill(name: &'static str, target: &'static str, color: wgpu::Color) -> Fill {
+    Fill { name, target, color, before: None, id: None }
+}
+
+impl GraphPass for Fill {
+    fn name(&self) -> &str { self.name }
+
+    fn setup(&mut self, b: &mut PassBuilder) {
+        self.id = Some(if self.target == FRAME {
+            b.write(FRAME)
+        } else {
+            b.create_texture(self.target, TextureDesc::new(b.frame_format()))
+        });
+        if let Some(pass) = self.before {
+            b.before(pass);
+        }
+    }
+
+    fn execute(&mut self, ctx: &mut PassContext) {
+        let view = ctx.view(self.id.unwrap()).clone();
+        ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(self.name),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Clear(self.color), store: wgpu::StoreOp::Store },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+    }
+}
+
+const BLIT_WGSL: &str = r#"
+@group(0) @binding(0) var src: texture_2d<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> @builtin(position) vec4<f32> {
+  let uv = vec2<f32>(f32((i << 1u) & 2u), f32(i & 2u));
+  return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+  return textureLoad(src, vec2<i32>(pos.xy), 0);
+}
+"#;
+
+/// Copies `from` into `to` (created here unless it's the frame).
+struct Blit {
+    name: &'static str,
+    from: &'static str,
+    to: &'static str,
+    ids: Option<(ResourceId, ResourceId)>,
+    pipeline: Option<(wgpu::RenderPipeline, wgpu::BindGroupLayout)>,
+}
+
+fn blit(name: &'static str, from: &'static str, to: &'static str) -> Blit {
+    Blit { name, from, to, ids: None, pipeline: None }
+}
+
+impl GraphPass for Blit {
+    fn name(&self) -> &str { self.name }
+
+    fn setup(&mut self, b: &mut PassBuilder) {
+        let from = b.read(self.from);
+        let to = if self.to == FRAME { b.write(FRAME) } else { b.create_texture(self.to, TextureDesc::new(b.frame_format())) };
+        self.ids = Some((from, to));
+    }
+
+    fn execute(&mut self, ctx: &mut PassContext) {
+        let (from, to) = self.ids.unwrap();
+        let format = ctx.texture(to).format();
+        let (pipeline, bgl) = self.pipeline.get_or_insert_with(|| {
+            let module = ctx.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("blit"),
+                source: wgpu::ShaderSource::Wgsl(BLIT_WGSL.into()),
+            });
+            let bgl = ctx.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("blit"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                }],
+            });
+            let layout = ctx.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("blit"),
+                bind_group_layouts: &[&bgl],
+                push_constant_ranges: &[],
+            });
+            let pipeline = ctx.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("blit"),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState { module: &module, entry_point: Some("vs_main"), buffers: &[], compilation_options: Default::default() },
+                fragment: Some(wgpu::FragmentState {
+                    module: &module,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(format.into())],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            });
+            (pipeline, bgl)
+        });
+        let bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("blit"),
+            layout: bgl,
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(ctx.view(from)) }],
+        });
+        let target = ctx.view(to).clone();
+        let mut rp = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(self.name),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &target,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: wgpu::StoreOp::Store },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rp.set_pipeline(pipeline);
+        rp.set_bind_group(0, &bind_group, &[]);
+        rp.draw(0..3, 0..1);
+    }
+}
+
+const RED: wgpu::Color = wgpu::Color { r: 1.0, g: 0.0, b: 0.0, a: 1.0 };
+
+#[test]
+fn passes_run_in_dependency_order() {
+    let mut renderer = headless_renderer();
+    // Added before the pass creating what it reads.
+    renderer.add_pass(blit("present", "a", FRAME));
+    renderer.add_pass(fill("fill_a", "a", RED));
+    renderer.add_pass(fill("unused", "b", RED));
+    renderer.check_graph().expect("graph");
+
+    renderer.render().expect("render");
+    let stats = renderer.graph_stats();
+    assert_eq!(stats.order, [SCENE_PASS, "fill_a", "present"]);
+    assert_eq!(stats.culled, ["unused"]);
+    assert_eq!(center_pixel(&mut renderer), [255, 0, 0, 255]);
+
+    renderer.render_with_ui(None, |ui| ui.window("overlay", [100.0, 50.0], &mut |ui| ui.text("hi"))).expect("render");
+    assert_eq!(renderer.graph_stats().order.last().map(String::as_str), Some(gfx_wgpu::graph::UI_PASS));
+
+    assert!(renderer.remove_pass("present"));
+    renderer.render().expect("render");
+    assert_eq!(renderer.graph_stats().order, [SCENE_PASS]);
+    assert_eq!(renderer.graph_stats().culled, ["fill_a", "unused"]);
+}
+
+#[test]
+fn transients_alias_when_lifetimes_do_not_overlap() {
+    let mut renderer = headless_renderer();
+    renderer.add_pass(fill("fill_a", "a", RED));
+    renderer.add_pass(blit("a_to_b", "a", "b"));
+    renderer.add_pass(blit("b_to_c", "b", "c"));
+    renderer.add_pass(blit("present", "c", FRAME));
+
+    for _ in 0..2 {
+        renderer.render().expect("render");
+        let stats = renderer.graph_stats();
+        assert_eq!((stats.transients, stats.allocated), (3, 2), "{stats:?}");
+        assert_eq!(center_pixel(&mut renderer), [255, 0, 0, 255]);
+    }
+}
+
+#[test]
+fn invalid_graphs_fall_back_to_the_scene() {
+    let mut renderer = headless_renderer();
+    renderer.add_pass(blit("present", "nowhere", FRAME));
+    let err = renderer.check_graph().unwrap_err();
+    assert_eq!(err, GraphError::Missing { pass: "present".into(), resource: "nowhere".into() });
+
+    renderer.render().expect("render");
+    assert_eq!(renderer.graph_error(), Some(&err));
+    assert_eq!(renderer.graph_stats().order, [SCENE_PASS]);
+    assert!(renderer.remove_pass("present"));
+
+    // Writes the frame after the scene, but also wants to run before it.
+    renderer.add_pass(Fill { before: Some(SCENE_PASS), ..fill("early", FRAME, RED) });
+    assert!(matches!(renderer.check_graph(), Err(GraphError::Cycle(_))));
+    assert!(renderer.remove_pass("early"));
+
+    renderer.add_pass(Fill { before: Some("shadows"), ..fill("fill", FRAME, RED) });
+    assert!(matches!(renderer.check_graph(), Err(GraphError::UnknownPass { .. })));
+    assert!(renderer.remove_pass("fill"));
+
+    renderer.render().expect("render");
+    assert!(renderer.graph_error().is_none());
+}
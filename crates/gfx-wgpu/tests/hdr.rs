@@ -0,0 +1,112 @@
+mod common;
+
+use gfx_wgpu::graph::{EXPOSURE_PASS, SCENE_PASS, TONEMAP_PASS};
+use gfx_wgpu::{AutoExposure, CaptureOptions, DrawItem, Exposure, HdrSettings, Renderer, Tonemapper, Vertex, HDR_FORMAT};
+use shader_core::{Overrides, RenderState, WgslSource};
+
+use common::headless_renderer;
+
+const FLAT: &str = "struct Out { @builtin(position) pos: vec4<f32>, @location(0) col: vec3<f32> }\n\
+    @vertex fn vs_main(@location(0) pos: vec2<f32>, @location(1) col: vec3<f32>) -> Out {\n\
+      return Out(vec4<f32>(pos, 0.5, 1.0), col);\n\
+    }\n\
+    @fragment fn fs_main(in: Out) -> @location(0) vec4<f32> { return vec4<f32>(in.col, 1.0); }\n";
+
+/// Fills the screen with `value` on every channel, which may well be over 1.
+fn fill(renderer: &mut Renderer, value: f32) -> [u8; 4] {
+    let src = WgslSource::new("flat.wgsl", FLAT);
+    let pipeline = renderer
+        .build_pipeline(&src, &RenderState::new(renderer.ctx.config.format), &Overrides::default(), &[Vertex::layout()])
+        .expect("pipeline");
+    let col = [value; 3];
+    let mesh = renderer.upload_mesh(
+        &[Vertex { pos: [-1.0, -1.0], col }, Vertex { pos: [3.0, -1.0], col }, Vertex { pos: [-1.0, 3.0], col }],
+        None,
+    );
+    renderer.submit([DrawItem::new(mesh, pipeline)]);
+    renderer.render().expect("render");
+    common::center_pixel(renderer)
+}
+
+fn manual(tonemapper: Tonemapper) -> Option<HdrSettings> {
+    Some(HdrSettings::default().with_tonemapper(tonemapper).with_exposure(Exposure::Manual { ev: 0.0 }))
+}
+
+#[test]
+fn tonemappers_keep_highlights_in_range() {
+    let mut renderer = headless_renderer();
+    assert_eq!(fill(&mut renderer, 2.0), [255; 4], "clips without HDR");
+
+    renderer.set_hdr(manual(Tonemapper::Reinhard));
+    let reinhard = fill(&mut renderer, 2.0);
+    // 2 / (1 + 2) in sRGB.
+    assert!(reinhard[..3].iter().all(|&c| c.abs_diff(213) <= 2), "{reinhard:?}");
+    assert_eq!(renderer.graph_stats().order, [SCENE_PASS, TONEMAP_PASS]);
+
+    renderer.set_hdr(manual(Tonemapper::Aces));
+    let aces = fill(&mut renderer, 2.0);
+    renderer.set_hdr(manual(Tonemapper::AgX));
+    let agx = fill(&mut renderer, 2.0);
+    for c in [aces, agx] {
+        assert!(c[0] < 255 && c[0] > reinhard[0], "{c:?}");
+    }
+    assert_ne!(aces, agx);
+
+    // Two stops down is the same as a quarter of the light.
+    renderer.set_hdr(Some(HdrSettings::default().with_tonemapper(Tonemapper::Reinhard).with_exposure(Exposure::Manual { ev: -2.0 })));
+    let darker = fill(&mut renderer, 2.0);
+    renderer.set_hdr(manual(Tonemapper::Reinhard));
+    assert_eq!(darker, fill(&mut renderer, 0.5));
+}
+
+#[test]
+fn auto_exposure_meters_the_scene() {
+    let mut renderer = headless_renderer();
+    let auto = AutoExposure { speed: f32::INFINITY, ..AutoExposure::default() };
+    renderer.set_hdr(Some(HdrSettings::default().with_exposure(Exposure::Auto(auto))));
+    if !renderer.ctx.adapter.get_downlevel_capabilities().flags.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS) {
+        eprintln!("skipping: no compute shaders");
+        return;
+    }
+    let bright = fill(&mut renderer, 8.0);
+    assert_eq!(renderer.graph_stats().order, [SCENE_PASS, EXPOSURE_PASS, TONEMAP_PASS]);
+    let dim = fill(&mut renderer, 0.25);
+    // Both end up around middle gray; histogram bins are a bit coarse.
+    for (b, d) in bright.iter().zip(dim).take(3) {
+        assert!(b.abs_diff(d) <= 4, "{bright:?} vs {dim:?}");
+    }
+    assert!(bright[0] > 60 && bright[0] < 160, "{bright:?}");
+
+    let compensated = AutoExposure { compensation: 1.0, ..auto };
+    renderer.set_hdr(Some(HdrSettings::default().with_exposure(Exposure::Auto(compensated))));
+    assert!(fill(&mut renderer, 8.0)[0] > bright[0] + 10);
+}
+
+#[test]
+fn pipelines_follow_the_scene_format() {
+    let mut renderer = headless_renderer();
+    let src = WgslSource::new("flat.wgsl", FLAT);
+    let pipeline = renderer
+        .build_pipeline(&src, &RenderState::new(renderer.ctx.config.format), &Overrides::default(), &[Vertex::layout()])
+        .expect("pipeline");
+
+    renderer.set_hdr(manual(Tonemapper::Reinhard));
+    assert_eq!(renderer.scene_format(), HDR_FORMAT);
+    assert_eq!(renderer.pipeline(pipeline).map(|p| p.state.format), Some(HDR_FORMAT));
+    let hdr = fill(&mut renderer, 2.0);
+
+    if renderer.supported_msaa().contains(&4) {
+        renderer.set_msaa(4).expect("msaa");
+        assert_eq!(fill(&mut renderer, 2.0), hdr);
+        let depth = renderer.capture_frame(CaptureOptions { depth: true }).expect("capture").depth.expect("depth");
+        let d = depth.get_pixel(depth.width() / 2, depth.height() / 2).0[0];
+        assert!((d - 0.5).abs() < 1e-3, "{d}");
+    }
+
+    renderer.set_hdr(None);
+    assert_eq!(renderer.pipeline(pipeline).map(|p| p.state.format), Some(renderer.ctx.config.format));
+    assert!(renderer.shader_errors().is_empty());
+    assert_eq!(fill(&mut renderer, 2.0), [255; 4]);
+    assert_eq!(renderer.graph_stats().order, [SCENE_PASS]);
+}
+
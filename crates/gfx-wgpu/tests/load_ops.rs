@@ -0,0 +1,96 @@
+mod common;
+
+use gfx_wgpu::{Camera, DrawItem, LoadOp, LoadOps, Renderer};
+use glam::{Mat4, Vec3};
+use shader_core::{CompareFunction, Overrides, RenderState};
+
+use common::{center_pixel, headless_renderer};
+
+const RED: wgpu::Color = wgpu::Color { r: 1.0, g: 0.0, b: 0.0, a: 1.0 };
+
+#[test]
+fn clear_color_and_load() {
+    let mut renderer = headless_renderer();
+    renderer.set_load_ops(LoadOps::default().with_clear_color(RED));
+    renderer.render().expect("render");
+    assert_eq!(center_pixel(&mut renderer), [255, 0, 0, 255]);
+
+    // The offscreen frame keeps the last one.
+    renderer.set_load_ops(LoadOps::default().with_color(LoadOp::Load));
+    renderer.render().expect("render");
+    assert_eq!(center_pixel(&mut renderer), [255, 0, 0, 255]);
+
+    renderer.set_load_ops(LoadOps::default().with_color(LoadOp::DontCare));
+    renderer.render().expect("render");
+    assert_eq!(center_pixel(&mut renderer)[..3], [0, 0, 0]);
+}
+
+#[test]
+fn requested_load_ops_last_one_frame() {
+    let mut renderer = headless_renderer();
+    renderer.render().expect("render");
+    let default = center_pixel(&mut renderer);
+
+    renderer.request_load_ops(LoadOps::default().with_clear_color(RED));
+    assert_eq!(renderer.load_ops(), LoadOps::default());
+    renderer.render().expect("render");
+    assert_eq!(center_pixel(&mut renderer), [255, 0, 0, 255]);
+    renderer.render().expect("render");
+    assert_eq!(center_pixel(&mut renderer), default);
+
+    // Over whatever `set_load_ops` says, too.
+    renderer.set_load_ops(LoadOps::default().with_color(LoadOp::Load));
+    renderer.request_load_ops(LoadOps::default().with_clear_color(RED));
+    renderer.render().expect("render");
+    renderer.render().expect("render");
+    assert_eq!(center_pixel(&mut renderer), [255, 0, 0, 255]);
+}
+
+/// A red triangle 5 units away, then a green one ten times as big and far, which projects to
+/// the same place. Returns the center pixel.
+fn near_and_far(renderer: &mut Renderer, camera: &Camera) -> [u8; 4] {
+    renderer.update_camera_ubo(&camera.make_ubo(renderer.aspect()));
+    let mesh = common::triangle_mesh(renderer);
+    let state = RenderState::new(renderer.ctx.config.format);
+    let tint = |r: f32, g: f32| Overrides::default().with("TINT_R", r).with("TINT_G", g).with("TINT_B", 0.0);
+    let red = renderer.build_pipeline(&common::triangle_src(), &state, &tint(1.0, 0.0), &[gfx_wgpu::Vertex::layout()]).expect("pipeline");
+    let green = renderer.build_pipeline(&common::triangle_src(), &state, &tint(0.0, 1.0), &[gfx_wgpu::Vertex::layout()]).expect("pipeline");
+    renderer.submit([
+        DrawItem::new(mesh, red).with_transform(Mat4::from_translation(Vec3::new(0.0, 0.0, -5.0))),
+        DrawItem::new(mesh, green).with_transform(Mat4::from_scale_rotation_translation(
+            Vec3::splat(10.0),
+            glam::Quat::IDENTITY,
+            Vec3::new(0.0, 0.0, -50.0),
+        )),
+    ]);
+    renderer.render().expect("render");
+    center_pixel(renderer)
+}
+
+#[test]
+fn reverse_z_keeps_near_geometry_in_front() {
+    let mut renderer = headless_renderer();
+    let camera = Camera::new(Vec3::ZERO, -Vec3::Z);
+    let near_wins = |c: [u8; 4]| c[0] > 0 && c[1] == 0;
+
+    assert!(near_wins(near_and_far(&mut renderer, &camera)));
+    assert!(near_wins(near_and_far(&mut renderer, &camera.clone().with_infinite_far())));
+
+    renderer.set_reverse_z(true);
+    // Only the renderer flipped: the far triangle now passes the test.
+    assert!(!near_wins(near_and_far(&mut renderer, &camera)));
+    let reversed = camera.clone().with_reverse_z(true);
+    assert!(near_wins(near_and_far(&mut renderer, &reversed)));
+    assert!(near_wins(near_and_far(&mut renderer, &reversed.with_infinite_far())));
+
+    let pipeline = renderer.build_pipeline(
+        &common::triangle_src(),
+        &RenderState::new(renderer.ctx.config.format),
+        &Overrides::default(),
+        &[gfx_wgpu::Vertex::layout()],
+    ).expect("pipeline");
+    assert_eq!(renderer.pipeline(pipeline).map(|p| p.state.depth_compare), Some(CompareFunction::Greater));
+    renderer.set_reverse_z(false);
+    assert_eq!(renderer.pipeline(pipeline).map(|p| p.state.depth_compare), Some(CompareFunction::Less));
+    assert!(renderer.shader_errors().is_empty());
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use gfx_wgpu::{CaptureOptions, DrawItem, MsaaError, Renderer, Vertex};
+use shader_core::{Overrides, RenderState, WgslSource};
+
+use common::headless_renderer;
+
+const WHITE: &str = "@vertex fn vs_main(@location(0) pos: vec2<f32>, @location(1) col: vec3<f32>) -> @builtin(position) vec4<f32> {\n\
+      return vec4<f32>(pos, 0.5, 1.0);\n\
+    }\n\
+    @fragment fn fs_main() -> @location(0) vec4<f32> { return vec4<f32>(1.0); }\n";
+
+/// Draws a flat white triangle under a UI window; returns a pixel inside each.
+fn render(renderer: &mut Renderer, draw: DrawItem) -> ([u8; 4], [u8; 4]) {
+    renderer.submit([draw]);
+    renderer.render_with_ui(None, |ui| ui.window("overlay", [120.0, 60.0], &mut |ui| ui.text("on top"))).expect("render");
+    let img = common::capture(renderer);
+    (img.get_pixel(img.width() / 2, img.height() - 20).0, img.get_pixel(70, 70).0)
+}
+
+fn setup(renderer: &mut Renderer) -> DrawItem {
+    let src = WgslSource::new("white.wgsl", WHITE);
+    let pipeline = renderer
+        .build_pipeline(&src, &RenderState::new(renderer.ctx.config.format), &Overrides::default(), &[Vertex::layout()])
+        .expect("pipeline");
+    let col = [1.0; 3];
+    let mesh = renderer.upload_mesh(
+        &[Vertex { pos: [-0.7, -0.9], col }, Vertex { pos: [0.9, -0.8], col }, Vertex { pos: [0.1, 0.3], col }],
+        None,
+    );
+    DrawItem::new(mesh, pipeline)
+}
+
+#[test]
+fn unsupported_counts_are_rejected() {
+    let mut renderer = headless_renderer();
+    let supported = renderer.supported_msaa();
+    assert_eq!(supported.first(), Some(&1));
+
+    let err = renderer.set_msaa(3).unwrap_err();
+    assert_eq!(err, MsaaError::Unsupported { requested: 3, supported });
+    assert_eq!(renderer.msaa(), 1);
+}
+
+#[test]
+fn msaa_switches_at_runtime() {
+    let mut renderer = headless_renderer();
+    if !renderer.supported_msaa().contains(&4) {
+        eprintln!("skipping: 4x MSAA unsupported");
+        return;
+    }
+    let draw = setup(&mut renderer);
+    let (scene, overlay) = render(&mut renderer, draw);
+    assert_eq!(scene, [255; 4]);
+
+    // Existing pipelines follow the switch; the scene only shows up if it was resolved (the
+    // multisampled target isn't stored), and the overlay still draws over it. A frame without
+    // the triangle in between makes sure it isn't just the previous one.
+    renderer.set_msaa(4).expect("msaa");
+    assert_eq!(renderer.pipeline(draw.pipeline).map(|p| p.state.msaa), Some(4));
+    assert!(renderer.shader_errors().is_empty());
+    renderer.render().expect("render");
+    let img = common::capture(&mut renderer);
+    assert_ne!(img.get_pixel(img.width() / 2, img.height() - 20).0, scene);
+    assert_eq!(render(&mut renderer, draw), (scene, overlay));
+
+    let capture = renderer.capture_frame(CaptureOptions { depth: true }).expect("capture");
+    let depth = capture.depth.expect("depth");
+    assert_eq!(depth.dimensions(), capture.color.dimensions());
+
+    renderer.set_msaa(1).expect("back to 1x");
+    assert_eq!(render(&mut renderer, draw), (scene, overlay));
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use gfx_wgpu::{Camera, DrawItem, InstanceData, Renderer};
+use glam::{Mat4, Quat, Vec2, Vec3};
+use shader_core::{Overrides, RenderState};
+
+use common::headless_renderer;
+
+/// The demo triangle with id 1 five units ahead, and a bigger one with id 2 behind it, which
+/// shows around it. Returns the camera.
+fn scene(renderer: &mut Renderer, reverse_z: bool) -> Camera {
+    let camera = Camera::new(Vec3::ZERO, Vec3::NEG_Z).with_reverse_z(reverse_z);
+    renderer.set_reverse_z(reverse_z);
+    renderer.update_camera_ubo(&camera.make_ubo(renderer.aspect()));
+    let mesh = common::triangle_mesh(renderer);
+    let state = RenderState::new(renderer.ctx.config.format);
+    let pipeline = renderer
+        .build_pipeline(&common::triangle_src(), &state, &Overrides::default(), &[gfx_wgpu::Vertex::layout()])
+        .expect("pipeline");
+    renderer.submit([
+        DrawItem::new(mesh, pipeline).with_transform(Mat4::from_translation(Vec3::new(0.0, 0.0, -5.0))).with_pick_id(1),
+        DrawItem::new(mesh, pipeline)
+            .with_transform(Mat4::from_scale_rotation_translation(Vec3::splat(30.0), Quat::IDENTITY, Vec3::new(0.0, 0.0, -50.0)))
+            .with_pick_id(2),
+    ]);
+    renderer.render().expect("render");
+    camera
+}
+
+#[test]
+fn picks_the_nearest_draw_and_its_depth() {
+    for reverse_z in [false, true] {
+        let mut renderer = headless_renderer();
+        let camera = scene(&mut renderer, reverse_z);
+        let viewport = renderer.viewport();
+        let center = viewport * 0.5;
+
+        let hit = renderer.pick(center).expect("pick").expect("hit");
+        assert_eq!(hit.id, 1);
+        let point = camera.screen_to_world(center, hit.depth, viewport);
+        assert!((point.z + 5.0).abs() < 1e-2, "{point}");
+
+        // Below the near one, the far one shows.
+        let below = center + Vec2::new(0.0, viewport.y * 0.17);
+        assert_eq!(renderer.pick(below).expect("pick").map(|h| h.id), Some(2));
+        assert_eq!(renderer.pick(Vec2::new(1.0, 1.0)).expect("pick"), None, "background");
+        assert_eq!(renderer.pick(viewport + 1.0).expect("pick"), None, "off screen");
+    }
+}
+
+#[test]
+fn picks_instanced_draws() {
+    let mut renderer = headless_renderer();
+    renderer.update_camera_ubo(&Camera::new(Vec3::ZERO, Vec3::NEG_Z).make_ubo(renderer.aspect()));
+    let mesh = common::triangle_mesh(&mut renderer);
+    let layouts = [gfx_wgpu::Vertex::layout(), InstanceData::layout()];
+    let pipeline = renderer
+        .build_pipeline(&common::demo_shader("instanced.wgsl"), &RenderState::new(renderer.ctx.config.format), &Overrides::default(), &layouts)
+        .expect("pipeline");
+    // Only the second instance is in front of the camera.
+    let instances = renderer.upload_instances(&[
+        InstanceData::new(Mat4::from_translation(Vec3::new(0.0, 0.0, 5.0))),
+        InstanceData::new(Mat4::from_translation(Vec3::new(0.0, 0.0, -3.0))),
+    ]);
+    renderer.submit([DrawItem::new(mesh, pipeline).with_instances(instances).with_pick_id(7)]);
+    renderer.render().expect("render");
+    assert_eq!(renderer.pick(renderer.viewport() * 0.5).expect("pick").map(|h| h.id), Some(7));
+
+    // The next frame drew nothing.
+    renderer.render().expect("render");
+    assert_eq!(renderer.pick(renderer.viewport() * 0.5).expect("pick"), None);
+}
@@ -0,0 +1,234 @@
+mod common;
+
+use common::{center_pixel, headless_renderer, triangle_src};
+
+fn state(renderer: &gfx_wgpu::Renderer) -> shader_core::RenderState<wgpu::TextureFormat> {
+    shader_core::RenderState::new(renderer.ctx.config.format)
+}
+
+#[test]
+fn bad_overrides_are_errors() {
+    let mut renderer = headless_renderer();
+    let state = state(&renderer);
+    let ov = shader_core::Overrides::default()
+        .with("USE_FOG", 1.0f32)
+        .with("TINT_Q", 1.0f32);
+
+    let err = renderer
+        .build_pipeline(&triangle_src(), &state, &ov, &[gfx_wgpu::Vertex::layout()])
+        .unwrap_err();
+    let gfx_wgpu::PipelineError::Overrides(err) = err else { panic!("expected override error, got {err}") };
+    assert_eq!(err.issues.len(), 2);
+}
+
+#[test]
+fn mistyped_overrides_fail_after_the_valid_permutation_is_cached() {
+    let mut renderer = headless_renderer();
+    let state = state(&renderer);
+    let layouts = [gfx_wgpu::Vertex::layout()];
+    let valid = shader_core::Overrides::default().with("USE_FOG", true);
+    renderer.build_pipeline(&triangle_src(), &state, &valid, &layouts).expect("pipeline");
+
+    // Both convert to the same constant as `true`, so they'd find the cached pipeline.
+    for mistyped in [shader_core::Overrides::default().with("USE_FOG", 1.0f32), shader_core::Overrides::default().with("USE_FOG", 1u32)] {
+        let err = renderer.build_pipeline(&triangle_src(), &state, &mistyped, &layouts).unwrap_err();
+        let gfx_wgpu::PipelineError::Overrides(err) = err else { panic!("expected override error, got {err}") };
+        assert!(matches!(err.issues[0].kind, shader_core::OverrideIssueKind::TypeMismatch { .. }));
+    }
+}
+
+#[test]
+fn failed_rebuild_keeps_pipeline() {
+    let mut renderer = headless_renderer();
+    let state = state(&renderer);
+    let pipeline = renderer
+        .build_pipeline(&triangle_src(), &state, &shader_core::Overrides::default(), &[gfx_wgpu::Vertex::layout()])
+        .expect("pipeline");
+
+    let bad = shader_core::Overrides::default().with("USE_FOG", 3i32);
+    assert!(renderer.rebuild_pipeline(pipeline, &triangle_src(), bad, shader_core::Topology::TriangleList).is_err());
+
+    let mesh = common::triangle_mesh(&mut renderer);
+    renderer.submit([gfx_wgpu::DrawItem::new(mesh, pipeline)]);
+    renderer.render().expect("render");
+}
+
+#[test]
+fn rebuilding_a_removed_pipeline_is_an_error() {
+    let mut renderer = headless_renderer();
+    let texture = renderer.create_texture_rgba8(1, 1, &[255; 4], gfx_wgpu::TextureOptions::default()).expect("texture");
+    let material = renderer.create_material(
+        gfx_wgpu::MaterialDesc::new(common::demo_shader("material.wgsl"), state(&renderer), &[gfx_wgpu::TexVertex::layout()])
+            .with_param("tint", gfx_wgpu::MaterialParam::Vec4([1.0; 4]))
+            .with_param("brightness", gfx_wgpu::MaterialParam::F32(1.0))
+            .with_texture(texture, gfx_wgpu::SamplerDesc::NEAREST_CLAMP),
+    ).expect("material");
+    let pipeline = renderer.material(material).expect("material").pipeline;
+    renderer.remove_material(material);
+
+    let err = renderer
+        .rebuild_pipeline(pipeline, &common::demo_shader("material.wgsl"), shader_core::Overrides::default(), shader_core::Topology::TriangleList)
+        .unwrap_err();
+    assert!(matches!(err, gfx_wgpu::PipelineError::StaleHandle), "{err}");
+}
+
+#[test]
+fn materials_must_fit_the_shader_layout() {
+    let mut renderer = headless_renderer();
+    let texture = renderer.create_texture_rgba8(1, 1, &[255; 4], gfx_wgpu::TextureOptions::default()).expect("texture");
+    let base = gfx_wgpu::MaterialDesc::new(common::demo_shader("material.wgsl"), state(&renderer), &[gfx_wgpu::TexVertex::layout()]);
+    let tint = gfx_wgpu::MaterialParam::Vec4([1.0; 4]);
+    let brightness = gfx_wgpu::MaterialParam::F32(1.0);
+
+    let cases = [
+        ("no texture", base.clone().with_param("tint", tint).with_param("brightness", brightness)),
+        ("no params", base.clone().with_texture(texture, gfx_wgpu::SamplerDesc::NEAREST_CLAMP)),
+        ("params too big", base.clone().with_param("tint", tint).with_param("brightness", brightness).with_param("extra", tint)),
+        ("extra texture", base.clone().with_param("tint", tint).with_param("brightness", brightness)
+            .with_texture(texture, gfx_wgpu::SamplerDesc::NEAREST_CLAMP)
+            .with_texture(texture, gfx_wgpu::SamplerDesc::NEAREST_CLAMP)),
+    ];
+    for (what, desc) in cases {
+        let err = renderer.create_material(desc).err().unwrap_or_else(|| panic!("{what}: accepted"));
+        assert!(matches!(err, gfx_wgpu::PipelineError::Layout(_)), "{what}: {err}");
+    }
+
+    let fits = base.with_param("tint", tint).with_param("brightness", brightness).with_texture(texture, gfx_wgpu::SamplerDesc::NEAREST_CLAMP);
+    assert_eq!(fits.layout_key().expect("layout").entries.len(), 3);
+    renderer.create_material(fits).expect("material");
+}
+
+#[test]
+fn wgsl_errors_are_diagnostics() {
+    let mut renderer = headless_renderer();
+    let state = state(&renderer);
+    let src = shader_core::WgslSource::new("broken.wgsl", "@vertex fn vs_main() -> @builtin(position) vec4<f32> {\n  return vec4<f32>(1.0;\n}\n");
+    let err = renderer
+        .build_pipeline(&src, &state, &shader_core::Overrides::default(), &[])
+        .unwrap_err();
+    let gfx_wgpu::PipelineError::Compile(diag) = err else { panic!("expected compile error, got {err}") };
+    assert_eq!(diag.kind, shader_core::DiagnosticKind::Parse);
+    assert_eq!((diag.file.as_str(), diag.line), ("broken.wgsl", 2));
+    assert_eq!(diag.snippet.as_deref(), Some("  return vec4<f32>(1.0;"));
+}
+
+#[test]
+fn pipeline_errors_do_not_panic() {
+    let mut renderer = headless_renderer();
+    let state = state(&renderer);
+    // Valid WGSL, but there's no fs_main for the pipeline to use.
+    let src = shader_core::WgslSource::new(
+        "no_fragment.wgsl",
+        "@vertex fn vs_main() -> @builtin(position) vec4<f32> { return vec4<f32>(0.0); }\n",
+    );
+    let err = renderer
+        .build_pipeline(&src, &state, &shader_core::Overrides::default(), &[])
+        .unwrap_err();
+    let gfx_wgpu::PipelineError::Compile(diag) = err else { panic!("expected compile error, got {err}") };
+    assert_eq!(diag.kind, shader_core::DiagnosticKind::Pipeline);
+    assert_eq!(diag.file, "no_fragment.wgsl");
+    assert!(diag.message.contains("fs_main"), "{}", diag.message);
+
+    // The device is still usable afterwards.
+    let ok = renderer.build_pipeline(&triangle_src(), &state, &shader_core::Overrides::default(), &[gfx_wgpu::Vertex::layout()]);
+    assert!(ok.is_ok());
+}
+
+fn solid_shader(color: &str) -> String {
+    format!(
+        "#include \"common/scene.wgsl\"\n\
+         @vertex fn vs_main(@location(0) pos: vec2<f32>, @location(1) col: vec3<f32>) -> @builtin(position) vec4<f32> {{\n\
+           return cam.view_proj * obj.model * vec4<f32>(pos, 0.0, 1.0);\n\
+         }}\n\
+         @fragment fn fs_main() -> @location(0) vec4<f32> {{ return vec4<f32>({color}, 1.0); }}\n"
+    )
+}
+
+/// Polls `reload_shaders` until `done` holds, giving the watcher ample time to notice the edit.
+fn reload_until(renderer: &mut gfx_wgpu::Renderer, done: impl Fn(&gfx_wgpu::Renderer) -> bool) {
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+    while std::time::Instant::now() < deadline {
+        renderer.reload_shaders();
+        if done(renderer) {
+            return;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    panic!("no reload within 10s");
+}
+
+#[test]
+fn hot_reload_keeps_last_good_pipeline() {
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("hot_reload");
+    std::fs::create_dir_all(dir.join("common")).unwrap();
+    std::fs::copy(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../../demos/triangle/shaders/common/scene.wgsl"),
+        dir.join("common/scene.wgsl"),
+    ).unwrap();
+    let shader_path = dir.join("solid.wgsl");
+    std::fs::write(&shader_path, solid_shader("1.0, 0.0, 0.0")).unwrap();
+
+    let mut renderer = headless_renderer();
+    let state = state(&renderer);
+    let src = shader_core::ShaderLibrary::new()
+        .add_root(&dir)
+        .compose("solid.wgsl", &shader_core::Defines::default())
+        .expect("compose");
+    let pipeline = renderer
+        .build_pipeline(&src, &state, &shader_core::Overrides::default(), &[gfx_wgpu::Vertex::layout()])
+        .expect("pipeline");
+    renderer.watch_shaders().expect("watch");
+
+    let mesh = common::triangle_mesh(&mut renderer);
+    let camera = gfx_wgpu::Camera::new(glam::Vec3::new(0.0, 0.0, 2.0), glam::Vec3::ZERO);
+    renderer.update_camera_ubo(&camera.make_ubo(renderer.aspect()));
+    let draw = gfx_wgpu::DrawItem::new(mesh, pipeline);
+
+    renderer.submit([draw]);
+    renderer.render().expect("render");
+    assert_eq!(center_pixel(&mut renderer), [255, 0, 0, 255]);
+
+    // Broken edit: error is reported, red pipeline stays.
+    std::fs::write(&shader_path, solid_shader("1.0, 0.0")).unwrap();
+    reload_until(&mut renderer, |r| !r.shader_errors().is_empty());
+    let errors = renderer.shader_errors();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].0, pipeline);
+    assert!(errors[0].1.to_string().contains("solid.wgsl"), "{}", errors[0].1);
+    renderer.submit([draw]);
+    renderer.render().expect("render");
+    assert_eq!(center_pixel(&mut renderer), [255, 0, 0, 255]);
+
+    // Fixed edit: same handle now draws green, error is gone.
+    std::fs::write(&shader_path, solid_shader("0.0, 1.0, 0.0")).unwrap();
+    reload_until(&mut renderer, |r| r.shader_errors().is_empty());
+    renderer.submit([draw]);
+    renderer.render().expect("render");
+    assert_eq!(center_pixel(&mut renderer), [0, 255, 0, 255]);
+
+    // Broken again, then rebuilt by hand from the red source: that clears the error too.
+    std::fs::write(&shader_path, solid_shader("0.0, 1.0")).unwrap();
+    reload_until(&mut renderer, |r| !r.shader_errors().is_empty());
+    renderer.rebuild_pipeline(pipeline, &src, shader_core::Overrides::default(), shader_core::Topology::TriangleList).expect("rebuild");
+    assert!(renderer.shader_errors().is_empty());
+    renderer.submit([draw]);
+    renderer.render().expect("render");
+    assert_eq!(center_pixel(&mut renderer), [255, 0, 0, 255]);
+}
+
+#[test]
+fn unwatchable_shader_files_are_reported() {
+    let mut renderer = headless_renderer();
+    renderer.watch_shaders().expect("watch");
+    let mut src = triangle_src();
+    let missing = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("no_such_dir/triangle.wgsl");
+    src.origin = shader_core::SourceOrigin::File(missing.clone());
+    let pipeline = renderer
+        .build_pipeline(&src, &state(&renderer), &shader_core::Overrides::default(), &[gfx_wgpu::Vertex::layout()])
+        .expect("pipeline");
+
+    let errors = renderer.shader_errors();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].0, pipeline);
+    assert!(matches!(&errors[0].1, gfx_wgpu::PipelineError::Watch(path, _) if *path == missing), "{}", errors[0].1);
+}
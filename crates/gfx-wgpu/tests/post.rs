@@ -0,0 +1,150 @@
+mod common;
+
+use std::sync::Arc;
+
+use gfx_wgpu::graph::{SCENE_PASS, TONEMAP_PASS};
+use gfx_wgpu::{
+    Bloom, ColorGrading, ColorLut, DrawItem, Exposure, HdrSettings, PostEffect, PostStack, Renderer, Smaa, SmaaQuality,
+    Vertex, Vignette,
+};
+use image::RgbaImage;
+use shader_core::{Overrides, RenderState, WgslSource};
+
+use common::{center, headless_renderer};
+
+const FLAT: &str = "struct Out { @builtin(position) pos: vec4<f32>, @location(0) col: vec3<f32> }\n\
+    @vertex fn vs_main(@location(0) pos: vec2<f32>, @location(1) col: vec3<f32>) -> Out {\n\
+      return Out(vec4<f32>(pos, 0.5, 1.0), col);\n\
+    }\n\
+    @fragment fn fs_main(in: Out) -> @location(0) vec4<f32> { return vec4<f32>(in.col, 1.0); }\n";
+
+/// Draws a `value` gray square reaching `half` from the center (1 covers the screen).
+fn square(renderer: &mut Renderer, half: f32, value: f32) -> RgbaImage {
+    let src = WgslSource::new("flat.wgsl", FLAT);
+    let pipeline = renderer
+        .build_pipeline(&src, &RenderState::new(renderer.ctx.config.format), &Overrides::default(), &[Vertex::layout()])
+        .expect("pipeline");
+    let col = [value; 3];
+    let v = |x: f32, y: f32| Vertex { pos: [x * half, y * half], col };
+    let mesh = renderer.upload_mesh(&[v(-1.0, -1.0), v(1.0, -1.0), v(1.0, 1.0), v(-1.0, -1.0), v(1.0, 1.0), v(-1.0, 1.0)], None);
+    renderer.submit([DrawItem::new(mesh, pipeline)]);
+    renderer.render().expect("render");
+    common::capture(renderer)
+}
+
+/// A white wedge along the bottom, its top edge rising about one pixel every three.
+fn wedge(renderer: &mut Renderer) -> RgbaImage {
+    let src = WgslSource::new("flat.wgsl", FLAT);
+    let pipeline = renderer
+        .build_pipeline(&src, &RenderState::new(renderer.ctx.config.format), &Overrides::default(), &[Vertex::layout()])
+        .expect("pipeline");
+    let col = [1.0; 3];
+    let mesh = renderer.upload_mesh(&[Vertex { pos: [-1.0, -1.0], col }, Vertex { pos: [1.0, -1.0], col }, Vertex { pos: [1.0, 0.0], col }], None);
+    renderer.submit([DrawItem::new(mesh, pipeline)]);
+    renderer.render().expect("render");
+    common::capture(renderer)
+}
+
+/// Pixels that are neither the wedge nor the background.
+fn blended(img: &RgbaImage) -> usize {
+    let (bg, fg) = (img.get_pixel(0, 0).0, [255; 4]);
+    img.pixels().filter(|p| p.0 != bg && p.0 != fg).count()
+}
+
+#[test]
+fn effects_run_in_stack_order() {
+    let mut renderer = headless_renderer();
+    renderer.set_post_stack(PostStack::standard()).expect("post");
+    square(&mut renderer, 1.0, 0.5);
+    let order = &renderer.graph_stats().order;
+    assert_eq!(order, &[SCENE_PASS, "bloom", "chromatic_aberration", "color_grading", "vignette", "fxaa"]);
+
+    renderer.set_hdr(Some(HdrSettings::default().with_exposure(Exposure::Manual { ev: 0.0 })));
+    let mut stack = renderer.post_stack().clone();
+    stack.move_up(4);
+    assert!(stack.set_enabled("bloom", false));
+    renderer.set_post_stack(stack).expect("post");
+    square(&mut renderer, 1.0, 0.5);
+    let order = &renderer.graph_stats().order;
+    assert_eq!(order, &[SCENE_PASS, TONEMAP_PASS, "chromatic_aberration", "color_grading", "fxaa", "vignette"]);
+    assert!(renderer.shader_errors().is_empty());
+
+    renderer.set_post_stack(PostStack::new()).expect("post");
+    square(&mut renderer, 1.0, 0.5);
+    assert_eq!(renderer.graph_stats().order, [SCENE_PASS, TONEMAP_PASS]);
+}
+
+#[test]
+fn vignette_darkens_the_edges() {
+    let mut renderer = headless_renderer();
+    let plain = square(&mut renderer, 1.0, 0.5);
+
+    let vignette = Vignette { intensity: 1.0, radius: 0.3, smoothness: 0.5, rounded: true };
+    renderer.set_post_stack(PostStack::new().with(PostEffect::Vignette(vignette))).expect("post");
+    let img = square(&mut renderer, 1.0, 0.5);
+    assert_eq!(center(&img), center(&plain));
+    assert_eq!(img.get_pixel(0, 0).0, [0, 0, 0, 255]);
+
+    let mut stack = renderer.post_stack().clone();
+    stack.set_enabled("vignette", false);
+    renderer.set_post_stack(stack).expect("post");
+    assert_eq!(square(&mut renderer, 1.0, 0.5), plain);
+}
+
+#[test]
+fn color_grading_looks_up_the_lut() {
+    assert!(ColorLut::from_strip(16, 4, vec![0; 16 * 4 * 4]).is_some());
+    assert!(ColorLut::from_strip(16, 5, vec![0; 16 * 5 * 4]).is_none());
+
+    let mut renderer = headless_renderer();
+    let plain = center(&square(&mut renderer, 1.0, 0.2));
+
+    let grading = |lut: ColorLut| PostEffect::ColorGrading(ColorGrading { lut: Arc::new(lut), intensity: 1.0 });
+    renderer.set_post_stack(PostStack::new().with(grading(ColorLut::identity(16)))).expect("post");
+    let identity = center(&square(&mut renderer, 1.0, 0.2));
+    assert!(identity.iter().zip(plain).all(|(a, b)| a.abs_diff(b) <= 2), "{identity:?} vs {plain:?}");
+
+    renderer.set_post_stack(PostStack::new().with(grading(ColorLut::from_fn(16, |c| c.map(|v| 1.0 - v))))).expect("post");
+    let inverted = center(&square(&mut renderer, 1.0, 0.2));
+    assert!(inverted[..3].iter().all(|&c| c.abs_diff(255 - plain[0]) <= 2), "{inverted:?} vs {plain:?}");
+}
+
+#[test]
+fn bloom_glows_around_bright_areas() {
+    let mut renderer = headless_renderer();
+    let plain = square(&mut renderer, 0.2, 1.0);
+    let bloom = Bloom { threshold: 0.5, intensity: 1.0, ..Bloom::default() };
+    renderer.set_post_stack(PostStack::new().with(PostEffect::Bloom(bloom))).expect("post");
+    let img = square(&mut renderer, 0.2, 1.0);
+
+    // Just outside the square's right edge, which reaches 60% of the way to the screen's.
+    let (x, y) = (img.width() * 65 / 100, img.height() / 2);
+    let (before, after) = (plain.get_pixel(x, y).0, img.get_pixel(x, y).0);
+    assert!(after[0] > before[0] + 20, "{before:?} vs {after:?}");
+    // The clear color is under the threshold.
+    assert_eq!(img.get_pixel(0, 0), plain.get_pixel(0, 0));
+}
+
+#[test]
+fn smaa_blends_along_slanted_edges() {
+    let mut renderer = headless_renderer();
+    let plain = wedge(&mut renderer);
+    let flat = square(&mut renderer, 0.5, 1.0);
+
+    for quality in [SmaaQuality::Low, SmaaQuality::Ultra] {
+        renderer.set_post_stack(PostStack::new().with(PostEffect::Smaa(Smaa { quality }))).expect("post");
+        let img = wedge(&mut renderer);
+        assert!(blended(&img) > blended(&plain) + img.width() as usize / 2, "{quality:?}: {} vs {}", blended(&img), blended(&plain));
+        // Away from the edge nothing moves.
+        assert_eq!(img.get_pixel(0, 0), plain.get_pixel(0, 0));
+        assert_eq!(img.get_pixel(img.width() - 1, img.height() - 1), plain.get_pixel(img.width() - 1, img.height() - 1));
+
+        // Long straight edges have nothing to smooth.
+        let img = square(&mut renderer, 0.5, 1.0);
+        let (x, y) = (img.width() / 2, img.height() / 4);
+        for dy in 0..3 {
+            assert_eq!(img.get_pixel(x, y - 1 + dy), flat.get_pixel(x, y - 1 + dy), "{quality:?}");
+        }
+    }
+    assert!(renderer.shader_errors().is_empty());
+}
@@ -0,0 +1,168 @@
+mod common;
+
+use gfx_wgpu::{CaptureOptions, DrawItem, Indices, Renderer, Vertex};
+use shader_core::{BlendMode, CompareFunction, CullMode, FrontFace, Overrides, RenderState, StencilFace, StencilOp, StencilState, WgslSource};
+
+use common::{capture, headless_renderer, HEIGHT, WIDTH};
+
+/// Positions are clip space, so the camera doesn't matter; `ALPHA` goes out with the color.
+const FLAT: &str = "override ALPHA: f32 = 1.0;\n\
+    struct VsOut { @builtin(position) pos: vec4<f32>, @location(0) col: vec3<f32> };\n\
+    @vertex fn vs_main(@location(0) pos: vec2<f32>, @location(1) col: vec3<f32>) -> VsOut {\n\
+      return VsOut(vec4<f32>(pos, 0.5, 1.0), col);\n\
+    }\n\
+    @fragment fn fs_main(in: VsOut) -> @location(0) vec4<f32> { return vec4<f32>(in.col, ALPHA); }\n";
+
+fn state(renderer: &Renderer) -> RenderState<wgpu::TextureFormat> {
+    RenderState::new(renderer.ctx.config.format)
+}
+
+fn pipeline(renderer: &mut Renderer, state: RenderState<wgpu::TextureFormat>, alpha: f32) -> gfx_wgpu::PipelineHandle {
+    let src = WgslSource::new("flat.wgsl", FLAT);
+    renderer
+        .build_pipeline(&src, &state, &Overrides::default().with("ALPHA", alpha), &[Vertex::layout()])
+        .unwrap_or_else(|e| panic!("{e}"))
+}
+
+fn quad(renderer: &mut Renderer, [x0, y0, x1, y1]: [f32; 4], col: [f32; 3]) -> gfx_wgpu::MeshHandle {
+    let verts = [
+        Vertex { pos: [x0, y0], col },
+        Vertex { pos: [x1, y0], col },
+        Vertex { pos: [x1, y1], col },
+        Vertex { pos: [x0, y1], col },
+    ];
+    renderer.upload_mesh(&verts, Some(Indices::U16(&[0, 1, 2, 0, 2, 3])))
+}
+
+/// Pixel under a clip-space point.
+fn pixel_at(img: &image::RgbaImage, x: f32, y: f32) -> [u8; 3] {
+    let px = ((x + 1.0) * 0.5 * WIDTH as f32) as u32;
+    let py = ((1.0 - y) * 0.5 * HEIGHT as f32) as u32;
+    let [r, g, b, _] = img.get_pixel(px, py).0;
+    [r, g, b]
+}
+
+fn assert_near(actual: [u8; 3], expected: [u8; 3]) {
+    assert!(
+        actual.iter().zip(expected).all(|(&a, e)| a.abs_diff(e) <= 2),
+        "got {actual:?}, expected {expected:?}",
+    );
+}
+
+#[test]
+fn blend_modes_mix_with_the_target() {
+    let mut renderer = headless_renderer();
+    let base = state(&renderer).with_depth(CompareFunction::LessEqual, false);
+    let opaque = pipeline(&mut renderer, base, 1.0);
+    let blended = [BlendMode::Alpha, BlendMode::Additive, BlendMode::Premultiplied]
+        .map(|mode| pipeline(&mut renderer, base.with_blend(mode), 0.5));
+
+    let background = quad(&mut renderer, [-1.0, -1.0, 1.0, 1.0], [0.0, 0.0, 1.0]);
+    let mut draws: Vec<_> = [-0.65, 0.0, 0.65].iter().zip(blended)
+        .map(|(&x, p)| DrawItem::new(quad(&mut renderer, [x - 0.25, -0.5, x + 0.25, 0.5], [1.0, 0.0, 0.0]), p))
+        .collect();
+    // Submitted last, but opaque draws go first.
+    draws.push(DrawItem::new(background, opaque));
+    renderer.submit(draws);
+    renderer.render().expect("render");
+    let img = capture(&mut renderer);
+
+    // Blending happens in linear space; 0.5 linear is 188 in sRGB.
+    assert_near(pixel_at(&img, -0.65, 0.0), [188, 0, 188]);
+    assert_near(pixel_at(&img, 0.0, 0.0), [188, 0, 255]);
+    assert_near(pixel_at(&img, 0.65, 0.0), [255, 0, 188]);
+    assert_near(pixel_at(&img, 0.0, 0.8), [0, 0, 255]);
+}
+
+#[test]
+fn stencil_masks_later_draws() {
+    let mut renderer = headless_renderer();
+    if renderer.ctx.depth_format() != gfx_wgpu::DEPTH_STENCIL_FORMAT {
+        eprintln!("skipping: no stencil-capable depth format");
+        return;
+    }
+    let base = state(&renderer).with_depth(CompareFunction::LessEqual, false);
+    let write = StencilFace { pass_op: StencilOp::Replace, ..StencilFace::IGNORE };
+    let test = StencilFace { compare: CompareFunction::NotEqual, ..StencilFace::IGNORE };
+    let mask = pipeline(&mut renderer, base.with_stencil(StencilState::both(write)), 1.0);
+    let cover = pipeline(&mut renderer, base.with_stencil(StencilState::both(test)), 1.0);
+
+    let hole = quad(&mut renderer, [-0.5, -0.5, 0.5, 0.5], [0.0, 1.0, 0.0]);
+    let full = quad(&mut renderer, [-1.0, -1.0, 1.0, 1.0], [1.0, 0.0, 0.0]);
+    renderer.submit([
+        DrawItem::new(full, cover).with_stencil_ref(1),
+        DrawItem::new(hole, mask).with_stencil_ref(1),
+    ]);
+    renderer.render().expect("render");
+    let img = capture(&mut renderer);
+
+    assert_near(pixel_at(&img, 0.0, 0.0), [0, 255, 0]);
+    assert_near(pixel_at(&img, 0.8, 0.8), [255, 0, 0]);
+}
+
+#[test]
+fn depth_captures_with_a_stencil_buffer() {
+    let mut renderer = headless_renderer();
+    if renderer.ctx.depth_format() != gfx_wgpu::DEPTH_STENCIL_FORMAT {
+        eprintln!("skipping: no stencil-capable depth format");
+        return;
+    }
+    let write = StencilFace { pass_op: StencilOp::Replace, ..StencilFace::IGNORE };
+    let writes_stencil = state(&renderer).with_stencil(StencilState::both(write));
+    let mask = pipeline(&mut renderer, writes_stencil, 1.0);
+    let hole = quad(&mut renderer, [-0.5, -0.5, 0.5, 0.5], [0.0, 1.0, 0.0]);
+    renderer.submit([DrawItem::new(hole, mask).with_stencil_ref(1)]);
+    renderer.render().expect("render");
+
+    // Only the depth aspect is read back, as Depth32Float.
+    let depth = renderer.capture_frame(CaptureOptions { depth: true }).expect("capture").depth.expect("depth");
+    assert!((depth.get_pixel(WIDTH / 2, HEIGHT / 2).0[0] - 0.5).abs() < 1e-3);
+    assert_eq!(depth.get_pixel(1, 1).0[0], 1.0);
+}
+
+#[test]
+fn depth_exr_needs_captured_depth() {
+    let mut renderer = headless_renderer();
+    renderer.render().expect("render");
+    let capture = renderer.capture_frame(CaptureOptions::default()).expect("capture");
+    let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("no_depth.exr");
+    let _ = std::fs::remove_file(&path);
+    assert!(matches!(capture.save_depth_exr(&path), Err(gfx_wgpu::CaptureError::NoDepth)));
+    assert!(!path.exists());
+}
+
+#[test]
+fn stencil_needs_depth() {
+    let mut renderer = headless_renderer();
+    let write = StencilFace { pass_op: StencilOp::Replace, ..StencilFace::IGNORE };
+    let no_depth = state(&renderer).without_depth().with_stencil(StencilState::both(write));
+    let err = renderer
+        .build_pipeline(&WgslSource::new("flat.wgsl", FLAT), &no_depth, &Overrides::default(), &[Vertex::layout()])
+        .unwrap_err();
+    assert!(err.to_string().contains("depth is off"), "{err}");
+}
+
+#[test]
+fn back_faces_are_culled() {
+    let mut renderer = headless_renderer();
+    let back = state(&renderer).with_cull(CullMode::Back, FrontFace::Ccw);
+    let culled = pipeline(&mut renderer, back, 1.0);
+    let flipped = pipeline(&mut renderer, back.with_cull(CullMode::Back, FrontFace::Cw), 1.0);
+
+    // Clockwise on screen.
+    let col = [1.0, 1.0, 1.0];
+    let verts = [
+        Vertex { pos: [-0.5, -0.5], col },
+        Vertex { pos: [0.0, 0.5], col },
+        Vertex { pos: [0.5, -0.5], col },
+    ];
+    let tri = renderer.upload_mesh(&verts, None);
+
+    renderer.submit([DrawItem::new(tri, culled)]);
+    renderer.render().expect("render");
+    assert_ne!(pixel_at(&capture(&mut renderer), 0.0, 0.0), [255, 255, 255]);
+
+    renderer.submit([DrawItem::new(tri, flipped)]);
+    renderer.render().expect("render");
+    assert_eq!(pixel_at(&capture(&mut renderer), 0.0, 0.0), [255, 255, 255]);
+}
@@ -0,0 +1,18 @@
+mod common;
+
+use gfx_wgpu::{TextureError, TextureOptions};
+
+use common::headless_renderer;
+
+#[test]
+fn raw_texels_must_fill_the_texture() {
+    let mut renderer = headless_renderer();
+    let opts = TextureOptions::default();
+    let texture = renderer.create_texture_rgba8(2, 2, &[255; 16], opts).expect("texture");
+    assert_eq!(renderer.texture(texture).map(|t| (t.width, t.height)), Some((2, 2)));
+
+    for (width, height, len) in [(2, 2, 15), (2, 2, 20), (0, 4, 0)] {
+        let err = renderer.create_texture_rgba8(width, height, &vec![0; len], opts).unwrap_err();
+        assert!(matches!(err, TextureError::Size { .. }), "{err}");
+    }
+}
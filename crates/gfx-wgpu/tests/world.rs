@@ -0,0 +1,70 @@
+mod common;
+
+use engine_core::ecs::World;
+use engine_core::Transform;
+use gfx_wgpu::{Camera, DrawItem, Hidden, Renderable};
+use glam::{Vec2, Vec3};
+use shader_core::{Overrides, RenderState};
+
+use common::headless_renderer;
+
+#[test]
+fn draws_and_picks_entities() {
+    let mut renderer = headless_renderer();
+    renderer.update_camera_ubo(&Camera::new(Vec3::ZERO, Vec3::NEG_Z).make_ubo(renderer.aspect()));
+    let mesh = common::triangle_mesh(&mut renderer);
+    let pipeline = renderer
+        .build_pipeline(&common::triangle_src(), &RenderState::new(renderer.ctx.config.format), &Overrides::default(), &[gfx_wgpu::Vertex::layout()])
+        .expect("pipeline");
+    let item = Renderable(DrawItem::new(mesh, pipeline));
+
+    let mut world = World::new();
+    let near = world.spawn((item, Transform::from_translation(Vec3::new(0.0, 0.0, -5.0))));
+    let far = world.spawn((item, Transform::from_translation(Vec3::new(0.0, 0.0, -50.0)).with_scale(Vec3::splat(30.0))));
+    world.spawn((Transform::IDENTITY,));
+
+    let center = renderer.viewport() * 0.5;
+    assert_eq!(renderer.submit_world(&world), 2);
+    renderer.render().expect("render");
+    let (entity, hit) = renderer.pick_entity(&world, center).expect("pick").expect("hit");
+    assert_eq!(entity, near);
+    assert!(hit.depth > 0.0 && hit.depth < 1.0);
+
+    world.insert(near, Hidden);
+    assert_eq!(renderer.submit_world(&world), 1);
+    renderer.render().expect("render");
+    assert_eq!(renderer.pick_entity(&world, center).expect("pick").map(|(e, _)| e), Some(far));
+    assert_eq!(renderer.pick_entity(&world, Vec2::ONE).expect("pick"), None, "background");
+
+    // Despawned since the frame was drawn.
+    world.despawn(far);
+    assert_eq!(renderer.pick_entity(&world, center).expect("pick"), None);
+}
+
+#[test]
+fn respawned_slots_dont_pick_as_the_old_entity() {
+    let mut renderer = headless_renderer();
+    renderer.update_camera_ubo(&Camera::new(Vec3::ZERO, Vec3::NEG_Z).make_ubo(renderer.aspect()));
+    let mesh = common::triangle_mesh(&mut renderer);
+    let pipeline = renderer
+        .build_pipeline(&common::triangle_src(), &RenderState::new(renderer.ctx.config.format), &Overrides::default(), &[gfx_wgpu::Vertex::layout()])
+        .expect("pipeline");
+    let item = (Renderable(DrawItem::new(mesh, pipeline)), Transform::from_translation(Vec3::new(0.0, 0.0, -5.0)));
+    let center = renderer.viewport() * 0.5;
+
+    let mut world = World::new();
+    let old = world.spawn(item);
+    renderer.submit_world(&world);
+    renderer.render().expect("render");
+    assert_eq!(renderer.pick_entity(&world, center).expect("pick").map(|(e, _)| e), Some(old));
+
+    world.despawn(old);
+    let new = world.spawn(item);
+    assert_eq!(new.index(), old.index());
+    assert_eq!(renderer.pick_entity(&world, center).expect("pick"), None, "drawn as the old one");
+
+    renderer.submit_world(&world);
+    renderer.render().expect("render");
+    assert_eq!(renderer.pick_entity(&world, center).expect("pick").map(|(e, _)| e), Some(new));
+}
+
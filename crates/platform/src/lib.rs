@@ -6,9 +6,11 @@ use winit::{
     window::{Window, WindowId, WindowAttributes},
 };
 
+type ResumedCallback = Box<dyn FnOnce(&Window)>;
+
 pub struct App {
     pub window: Option<Window>,
-    pub on_resumed: Option<Box<dyn FnOnce(&Window)>>,
+    pub on_resumed: Option<ResumedCallback>,
 }
 
 impl App {
@@ -34,9 +36,8 @@ impl ApplicationHandler for App {
         if let Some(win) = &self.window {
             if win.id() != id { return; }
         }
-        match event {
-            WindowEvent::CloseRequested => el.exit(),
-            _ => {}
+        if let WindowEvent::CloseRequested = event {
+            el.exit();
         }
     }
 }
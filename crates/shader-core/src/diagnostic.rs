@@ -0,0 +1,85 @@
+use crate::reflect::{ReflectError, ReflectErrorKind};
+use crate::WgslSource;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DiagnosticKind {
+    Parse,
+    Validation,
+    /// Rejected by wgpu when creating the module or pipeline (layouts, entry points, backend limits).
+    Pipeline,
+}
+
+/// A shader compile error pointing into the original file it came from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShaderDiagnostic {
+    pub kind: DiagnosticKind,
+    pub message: String,
+    pub file: String,
+    /// 1-based; 0 when the error has no position (most pipeline errors).
+    pub line: u32,
+    /// 1-based, in bytes; 0 when unknown.
+    pub column: u32,
+    /// The offending line of source, when there is one.
+    pub snippet: Option<String>,
+}
+
+impl ShaderDiagnostic {
+    pub fn has_position(&self) -> bool {
+        self.line > 0
+    }
+}
+
+impl WgslSource {
+    /// Diagnostic for a 1-based position in `code`, mapped back to the original file.
+    /// `position` is `(line, column)`; `None` pins the error on the source as a whole.
+    pub fn diagnostic(&self, kind: DiagnosticKind, message: impl Into<String>, position: Option<(u32, u32)>) -> ShaderDiagnostic {
+        let message = message.into();
+        let Some((line, column)) = position.filter(|(l, _)| *l > 0) else {
+            return ShaderDiagnostic { kind, message, file: self.name.to_string(), line: 0, column: 0, snippet: None };
+        };
+        let snippet = self.code.lines().nth(line as usize - 1).map(|l| l.trim_end().to_string());
+        let (file, line) = self.resolve_line(line);
+        ShaderDiagnostic { kind, message, file: file.to_string(), line, column, snippet }
+    }
+
+    /// `reflect` with the error turned into a diagnostic.
+    pub fn check(&self) -> Result<crate::ShaderReflection, ShaderDiagnostic> {
+        crate::ShaderReflection::from_wgsl(&self.code).map_err(|e| self.diagnose(&e))
+    }
+
+    /// Diagnostic for an error from `ShaderReflection::from_wgsl(&self.code)`.
+    fn diagnose(&self, e: &ReflectError) -> ShaderDiagnostic {
+        let kind = match e.kind {
+            ReflectErrorKind::Parse => DiagnosticKind::Parse,
+            ReflectErrorKind::Validation => DiagnosticKind::Validation,
+        };
+        self.diagnostic(kind, e.message.clone(), e.location.as_ref().map(|l| (l.line, l.column)))
+    }
+}
+
+impl std::fmt::Display for ShaderDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let what = match self.kind {
+            DiagnosticKind::Parse => "WGSL parse error",
+            DiagnosticKind::Validation => "WGSL validation error",
+            DiagnosticKind::Pipeline => "pipeline error",
+        };
+        writeln!(f, "{what}: {}", self.message)?;
+        if !self.has_position() {
+            return write!(f, "  --> {}", self.file);
+        }
+        write!(f, "  --> {}:{}:{}", self.file, self.line, self.column)?;
+        if let Some(snippet) = &self.snippet {
+            let gutter = self.line.to_string();
+            let pad = " ".repeat(gutter.len());
+            // Carets line up in bytes, so keep tabs as tabs.
+            let indent: String = snippet.bytes().take(self.column.saturating_sub(1) as usize)
+                .map(|b| if b == b'\t' { '\t' } else { ' ' })
+                .collect();
+            write!(f, "\n{pad} |\n{gutter} | {snippet}\n{pad} | {indent}^")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ShaderDiagnostic {}
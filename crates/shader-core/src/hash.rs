@@ -0,0 +1,29 @@
+use std::hash::Hasher;
+
+/// 64-bit FNV-1a. Unlike std's and ahash's hashers it isn't seeded per process, so whatever is
+/// hashed with it (permutation ids in particular) can be written to disk and compared on the
+/// next run.
+pub(crate) struct StableHasher(u64);
+
+impl StableHasher {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self(Self::OFFSET)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ b as u64).wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
@@ -1,55 +1,157 @@
-use ahash::AHasher;
-use std::collections::HashMap;
+use std::borrow::Cow;
 use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-#[derive(Clone)]
-pub struct WgslSource {
-    pub name: &'static str,
-    pub code: &'static str,
-}
+mod diagnostic;
+mod hash;
+mod overrides;
+mod preprocess;
+mod reflect;
+mod state;
+mod watch;
+
+use hash::StableHasher;
 
-#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
-pub enum Topology { TriangleList, TriangleStrip, LineList }
+pub use diagnostic::{DiagnosticKind, ShaderDiagnostic};
+pub use overrides::{OverrideError, OverrideIssue, OverrideIssueKind, OverrideValue, Overrides};
+pub use preprocess::{Defines, PreprocessError, PreprocessErrorKind, ShaderLibrary, SourceMap};
+pub use state::{
+    BlendComponent, BlendFactor, BlendMode, BlendOp, ColorTarget, CompareFunction, CullMode, DepthBias, FrontFace,
+    PolygonMode, RenderState, StencilFace, StencilOp, StencilState, Topology, MAX_COLOR_TARGETS,
+};
+pub use watch::{canonical, ShaderWatcher};
+pub use notify::Error as WatchError;
+pub use reflect::{
+    BindingInfo, BindingType, EntryPointInfo, IoVariable, OverrideInfo, ReflectError, ReflectErrorKind,
+    ScalarType, ShaderReflection, ShaderStage, SourceLocation, TextureDimension, TextureSample, ValueType,
+};
 
-#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
-pub struct RenderState<TFmt: Hash + Eq + Copy> {
-    pub format: TFmt,
-    pub depth: bool,
-    pub msaa: u32,
-    pub topo: Topology,
+/// WGSL ready for compilation. Sources coming out of `ShaderLibrary` carry the defines they
+/// were expanded with and a map back to the files they were pasted together from.
+#[derive(Clone, Debug)]
+pub struct WgslSource {
+    pub name: Cow<'static, str>,
+    pub code: Cow<'static, str>,
+    pub defines: Defines,
+    pub source_map: Option<Arc<SourceMap>>,
+    pub origin: SourceOrigin,
 }
 
-#[derive(Clone, Default)]
-pub struct Overrides {
-    pub map: HashMap<String, f64>,
+/// Where a `WgslSource` can be loaded from again.
+#[derive(Clone, Debug)]
+pub enum SourceOrigin {
+    /// Passed in as a string; reloading is a no-op.
+    Inline,
+    File(PathBuf),
+    Library { library: ShaderLibrary, path: String },
 }
 
-impl Overrides {
-    pub fn with(mut self, name: &str, value: f64) -> Self {
-        self.map.insert(name.to_string(), value);
-        self
+impl WgslSource {
+    /// Plain single-file source, usually from `include_str!`.
+    pub const fn new(name: &'static str, code: &'static str) -> Self {
+        Self {
+            name: Cow::Borrowed(name),
+            code: Cow::Borrowed(code),
+            defines: Defines { map: std::collections::BTreeMap::new() },
+            source_map: None,
+            origin: SourceOrigin::Inline,
+        }
+    }
+
+    /// Reads a single WGSL file (no preprocessing); `reload` reads it again.
+    pub fn from_file(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let code = std::fs::read_to_string(path)?;
+        Ok(Self {
+            name: Cow::Owned(path.display().to_string()),
+            code: Cow::Owned(code),
+            defines: Defines::default(),
+            source_map: None,
+            origin: SourceOrigin::File(path.to_path_buf()),
+        })
+    }
+
+    /// Files on disk this source was built from; a change to any of them calls for `reload`.
+    pub fn dependencies(&self) -> Vec<PathBuf> {
+        match &self.origin {
+            SourceOrigin::Inline => Vec::new(),
+            SourceOrigin::File(path) => vec![path.clone()],
+            SourceOrigin::Library { library, .. } => self.source_map.iter()
+                .flat_map(|m| m.files.iter())
+                .filter_map(|f| library.disk_path(f))
+                .collect(),
+        }
+    }
+
+    /// Loads and expands the source again from where it came from, with the same defines.
+    pub fn reload(&self) -> Result<Self, PreprocessError> {
+        match &self.origin {
+            SourceOrigin::Inline => Ok(self.clone()),
+            SourceOrigin::File(path) => Self::from_file(path).map_err(|e| PreprocessError {
+                file: path.display().to_string(),
+                line: 0,
+                kind: PreprocessErrorKind::Io(e.to_string()),
+            }),
+            SourceOrigin::Library { library, path } => library.compose(path, &self.defines),
+        }
+    }
+
+    /// Original file and line for a line of `code`.
+    pub fn resolve_line(&self, line: u32) -> (&str, u32) {
+        self.source_map.as_ref()
+            .and_then(|m| m.resolve(line))
+            .unwrap_or((&self.name, line))
+    }
+
+    /// Reflection of `code`; error locations point into the original files.
+    pub fn reflect(&self) -> Result<ShaderReflection, ReflectError> {
+        ShaderReflection::from_wgsl(&self.code).map_err(|mut e| {
+            if let Some(loc) = &mut e.location {
+                let (file, line) = self.resolve_line(loc.line);
+                loc.file = Some(file.to_string());
+                loc.line = line;
+            }
+            e
+        })
     }
-    pub fn set_bool(&mut self, name: &str, v: bool) { self.map.insert(name.to_string(), if v { 1.0 } else { 0.0 }); }
-    pub fn set_f32(&mut self,  name: &str, v: f32)  { self.map.insert(name.to_string(), v as f64); }
-    pub fn get_map(&self) -> &HashMap<String, f64> { &self.map }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub struct ShaderKey<TFmt: Hash + Eq + Copy> {
-    pub src_name: &'static str,
+    pub src_name: String,
     pub state: RenderState<TFmt>,
     pub consts_hash: u64,
+    /// Defines and the expanded code, so permutations of one file don't collide.
+    pub source_hash: u64,
+    /// Defines alone; together with the rest minus `source_hash` this names a permutation
+    /// independently of the code, which is what on-disk caches invalidate against.
+    pub defines_hash: u64,
 }
 
 impl<TFmt: Hash + Eq + Copy> ShaderKey<TFmt> {
     pub fn new(src: &WgslSource, state: RenderState<TFmt>, ov: &Overrides) -> Self {
-        let mut h = AHasher::default();
-        let mut pairs: Vec<_> = ov.map.iter().collect();
-        pairs.sort_by(|a,b| a.0.cmp(b.0));
-        for (k, v) in pairs {
-            k.hash(&mut h);
-            v.to_bits().hash(&mut h);
-        }
-        Self { src_name: src.name, state, consts_hash: h.finish() }
+        let mut h = StableHasher::default();
+        ov.hash_into(&mut h);
+        let consts_hash = h.finish();
+
+        let mut h = StableHasher::default();
+        src.defines.hash(&mut h);
+        let defines_hash = h.finish();
+        src.code.hash(&mut h);
+        Self { src_name: src.name.to_string(), state, consts_hash, source_hash: h.finish(), defines_hash }
+    }
+
+    /// Id of this permutation within `context` (e.g. the pipeline layout), ignoring the code
+    /// itself so an edited shader maps to the same id. The same on every run, so it can key
+    /// on-disk caches.
+    pub fn permutation_id(&self, context: &impl Hash) -> u64 {
+        let mut h = StableHasher::default();
+        context.hash(&mut h);
+        self.src_name.hash(&mut h);
+        self.state.hash(&mut h);
+        self.consts_hash.hash(&mut h);
+        self.defines_hash.hash(&mut h);
+        h.finish()
     }
 }
@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use crate::reflect::{ScalarType, ShaderReflection};
+
+/// Value for a WGSL `override` constant.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OverrideValue {
+    Bool(bool),
+    F32(f32),
+    I32(i32),
+    U32(u32),
+}
+
+impl OverrideValue {
+    /// What wgpu takes for pipeline constants.
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            OverrideValue::Bool(v) => if v { 1.0 } else { 0.0 },
+            OverrideValue::F32(v) => v as f64,
+            OverrideValue::I32(v) => v as f64,
+            OverrideValue::U32(v) => v as f64,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            OverrideValue::Bool(_) => "bool",
+            OverrideValue::F32(_) => "f32",
+            OverrideValue::I32(_) => "i32",
+            OverrideValue::U32(_) => "u32",
+        }
+    }
+
+    /// Converts to the declared type where that's lossless (ints to f32, i32 <-> u32 in range).
+    pub fn coerce(self, ty: ScalarType) -> Result<OverrideValue, OverrideIssueKind> {
+        match (self, ty) {
+            (OverrideValue::Bool(_), ScalarType::Bool)
+            | (OverrideValue::F32(_), ScalarType::F32)
+            | (OverrideValue::I32(_), ScalarType::I32)
+            | (OverrideValue::U32(_), ScalarType::U32) => Ok(self),
+            (OverrideValue::I32(v), ScalarType::F32) if v.unsigned_abs() <= 1 << 24 => Ok(OverrideValue::F32(v as f32)),
+            (OverrideValue::U32(v), ScalarType::F32) if v <= 1 << 24 => Ok(OverrideValue::F32(v as f32)),
+            (OverrideValue::I32(v), ScalarType::U32) => u32::try_from(v)
+                .map(OverrideValue::U32)
+                .map_err(|_| OverrideIssueKind::OutOfRange { value: self, expected: ty }),
+            (OverrideValue::U32(v), ScalarType::I32) => i32::try_from(v)
+                .map(OverrideValue::I32)
+                .map_err(|_| OverrideIssueKind::OutOfRange { value: self, expected: ty }),
+            (OverrideValue::I32(_) | OverrideValue::U32(_), ScalarType::F32) => {
+                Err(OverrideIssueKind::OutOfRange { value: self, expected: ty })
+            }
+            _ => Err(OverrideIssueKind::TypeMismatch { value: self, expected: ty }),
+        }
+    }
+}
+
+impl From<bool> for OverrideValue {
+    fn from(v: bool) -> Self { OverrideValue::Bool(v) }
+}
+impl From<f32> for OverrideValue {
+    fn from(v: f32) -> Self { OverrideValue::F32(v) }
+}
+impl From<i32> for OverrideValue {
+    fn from(v: i32) -> Self { OverrideValue::I32(v) }
+}
+impl From<u32> for OverrideValue {
+    fn from(v: u32) -> Self { OverrideValue::U32(v) }
+}
+
+impl std::fmt::Display for OverrideValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OverrideValue::Bool(v) => write!(f, "{v}"),
+            OverrideValue::F32(v) => write!(f, "{v}f"),
+            OverrideValue::I32(v) => write!(f, "{v}i"),
+            OverrideValue::U32(v) => write!(f, "{v}u"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Overrides {
+    pub map: HashMap<String, OverrideValue>,
+}
+
+impl Overrides {
+    pub fn with(mut self, name: &str, value: impl Into<OverrideValue>) -> Self {
+        self.map.insert(name.to_string(), value.into());
+        self
+    }
+    pub fn set_bool(&mut self, name: &str, v: bool) { self.map.insert(name.to_string(), OverrideValue::Bool(v)); }
+    pub fn set_f32(&mut self,  name: &str, v: f32)  { self.map.insert(name.to_string(), OverrideValue::F32(v)); }
+    pub fn set_i32(&mut self,  name: &str, v: i32)  { self.map.insert(name.to_string(), OverrideValue::I32(v)); }
+    pub fn set_u32(&mut self,  name: &str, v: u32)  { self.map.insert(name.to_string(), OverrideValue::U32(v)); }
+    pub fn get(&self, name: &str) -> Option<OverrideValue> { self.map.get(name).copied() }
+    pub fn get_map(&self) -> &HashMap<String, OverrideValue> { &self.map }
+
+    /// Name/value pairs sorted by name, so hashing and pipeline creation are deterministic.
+    pub fn sorted(&self) -> Vec<(&str, OverrideValue)> {
+        let mut pairs: Vec<_> = self.map.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        pairs.sort_by(|a, b| a.0.cmp(b.0));
+        pairs
+    }
+
+    /// Hashes the constants as the pipeline gets them. Conversion to the declared type is
+    /// lossless, so `SAMPLES = 4i` and `SAMPLES = 4u` hash (and build) the same.
+    pub(crate) fn hash_into<H: Hasher>(&self, h: &mut H) {
+        for (k, v) in self.sorted() {
+            k.hash(h);
+            v.as_f64().to_bits().hash(h);
+        }
+    }
+
+    /// Checks every value against the shader's `override` declarations and returns the constants
+    /// to hand to the pipeline, converted to the declared types.
+    pub fn validate(&self, shader: &str, refl: &ShaderReflection) -> Result<Vec<(String, f64)>, OverrideError> {
+        let mut issues = Vec::new();
+        let mut constants = Vec::new();
+
+        for (name, value) in self.sorted() {
+            let Some(decl) = refl.override_info(name) else {
+                let suggestion = closest(name, refl.overrides.iter().map(|o| o.name.as_str()));
+                issues.push(OverrideIssue { name: name.to_string(), kind: OverrideIssueKind::Unknown { suggestion } });
+                continue;
+            };
+            match value.coerce(decl.ty) {
+                Ok(v) => constants.push((name.to_string(), v.as_f64())),
+                Err(kind) => issues.push(OverrideIssue { name: name.to_string(), kind }),
+            }
+        }
+
+        for decl in &refl.overrides {
+            let set = self.map.contains_key(&decl.name)
+                || decl.id.is_some_and(|id| self.map.contains_key(&id.to_string()));
+            if !decl.has_default && !set {
+                issues.push(OverrideIssue { name: decl.name.clone(), kind: OverrideIssueKind::Missing { expected: decl.ty } });
+            }
+        }
+
+        if issues.is_empty() {
+            Ok(constants)
+        } else {
+            Err(OverrideError { shader: shader.to_string(), issues })
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum OverrideIssueKind {
+    /// The shader declares no such override.
+    Unknown { suggestion: Option<String> },
+    TypeMismatch { value: OverrideValue, expected: ScalarType },
+    /// Right kind of number, but it doesn't fit the declared type.
+    OutOfRange { value: OverrideValue, expected: ScalarType },
+    /// Declared without a default and not set.
+    Missing { expected: ScalarType },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OverrideIssue {
+    pub name: String,
+    pub kind: OverrideIssueKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OverrideError {
+    pub shader: String,
+    pub issues: Vec<OverrideIssue>,
+}
+
+impl std::fmt::Display for OverrideIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = &self.name;
+        match &self.kind {
+            OverrideIssueKind::Unknown { suggestion: Some(s) } => write!(f, "unknown override `{name}` (did you mean `{s}`?)"),
+            OverrideIssueKind::Unknown { suggestion: None } => write!(f, "unknown override `{name}`"),
+            OverrideIssueKind::TypeMismatch { value, expected } => write!(
+                f, "override `{name}` is declared as {} but was set to {} {value}", scalar_name(*expected), value.type_name(),
+            ),
+            OverrideIssueKind::OutOfRange { value, expected } => write!(
+                f, "override `{name}` = {value} does not fit in {}", scalar_name(*expected),
+            ),
+            OverrideIssueKind::Missing { expected } => write!(
+                f, "override `{name}: {}` has no default and must be set", scalar_name(*expected),
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for OverrideError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid overrides for {}:", self.shader)?;
+        for issue in &self.issues {
+            write!(f, "\n  {issue}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for OverrideError {}
+
+fn scalar_name(ty: ScalarType) -> &'static str {
+    match ty {
+        ScalarType::Bool => "bool",
+        ScalarType::I32 => "i32",
+        ScalarType::U32 => "u32",
+        ScalarType::I64 => "i64",
+        ScalarType::U64 => "u64",
+        ScalarType::F16 => "f16",
+        ScalarType::F32 => "f32",
+        ScalarType::F64 => "f64",
+    }
+}
+
+/// Declared name closest to `name`, if any is a plausible typo.
+fn closest<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<String> {
+    candidates
+        .map(|c| (edit_distance(&name.to_ascii_lowercase(), &c.to_ascii_lowercase()), c))
+        .filter(|(d, c)| *d <= 2.max(c.len() / 4))
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c.to_string())
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            cur[j + 1] = (prev[j] + usize::from(ca != *cb)).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
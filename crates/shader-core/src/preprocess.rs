@@ -0,0 +1,449 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::{SourceOrigin, WgslSource};
+
+/// `#define`s passed in from outside, e.g. per permutation. Ordered so permutations hash stably.
+#[derive(Clone, Debug, Default, Hash, Eq, PartialEq)]
+pub struct Defines {
+    pub map: BTreeMap<String, String>,
+}
+
+impl Defines {
+    pub fn with(mut self, name: &str, value: impl ToString) -> Self {
+        self.map.insert(name.to_string(), value.to_string());
+        self
+    }
+    /// Defined with an empty value, for `#ifdef` switches.
+    pub fn with_flag(self, name: &str) -> Self { self.with(name, "") }
+    pub fn set(&mut self, name: &str, value: impl ToString) { self.map.insert(name.to_string(), value.to_string()); }
+    pub fn remove(&mut self, name: &str) { self.map.remove(name); }
+    pub fn is_empty(&self) -> bool { self.map.is_empty() }
+}
+
+/// Where each line of expanded source came from.
+#[derive(Clone, Debug, Default)]
+pub struct SourceMap {
+    pub files: Vec<String>,
+    /// Per output line: (index into `files`, 1-based line in that file).
+    pub lines: Vec<(u32, u32)>,
+}
+
+impl SourceMap {
+    /// Original file and line of 1-based output line `line`.
+    pub fn resolve(&self, line: u32) -> Option<(&str, u32)> {
+        let (file, orig) = *self.lines.get(line.checked_sub(1)? as usize)?;
+        Some((&self.files[file as usize], orig))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PreprocessErrorKind {
+    IncludeNotFound(String),
+    UnknownDirective(String),
+    /// `#else`/`#elif`/`#endif` without a matching `#if`.
+    UnmatchedConditional(String),
+    UnterminatedConditional,
+    BadExpression(String),
+    /// `#include` nested deeper than the library allows; almost always a cycle.
+    IncludeDepth,
+    /// Reading a shader file from disk failed.
+    Io(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreprocessError {
+    pub file: String,
+    pub line: u32,
+    pub kind: PreprocessErrorKind,
+}
+
+impl std::fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: ", self.file, self.line)?;
+        match &self.kind {
+            PreprocessErrorKind::IncludeNotFound(p) => write!(f, "cannot find include \"{p}\""),
+            PreprocessErrorKind::UnknownDirective(d) => write!(f, "unknown directive #{d}"),
+            PreprocessErrorKind::UnmatchedConditional(d) => write!(f, "#{d} without #if"),
+            PreprocessErrorKind::UnterminatedConditional => write!(f, "#if without #endif"),
+            PreprocessErrorKind::BadExpression(e) => write!(f, "cannot evaluate #if expression: {e}"),
+            PreprocessErrorKind::IncludeDepth => write!(f, "#include nested too deeply (include cycle?)"),
+            PreprocessErrorKind::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for PreprocessError {}
+
+const MAX_INCLUDE_DEPTH: usize = 32;
+
+/// Virtual file system for `#include`. Embedded files win over files found under the roots.
+/// Cheap to clone; composed sources keep a clone so they can be expanded again on reload.
+#[derive(Clone, Debug, Default)]
+pub struct ShaderLibrary {
+    files: Arc<HashMap<String, Cow<'static, str>>>,
+    roots: Arc<Vec<PathBuf>>,
+}
+
+impl ShaderLibrary {
+    pub fn new() -> Self { Self::default() }
+
+    /// Registers a file under a virtual path, typically with `include_str!`.
+    pub fn add(&mut self, path: &str, code: impl Into<Cow<'static, str>>) -> &mut Self {
+        Arc::make_mut(&mut self.files).insert(normalize(path), code.into());
+        self
+    }
+
+    /// Directory searched for paths not registered with `add`.
+    pub fn add_root(&mut self, dir: impl Into<PathBuf>) -> &mut Self {
+        Arc::make_mut(&mut self.roots).push(dir.into());
+        self
+    }
+
+    pub fn roots(&self) -> &[PathBuf] { &self.roots }
+
+    pub fn load(&self, path: &str) -> Option<Cow<'static, str>> {
+        let path = normalize(path);
+        if let Some(code) = self.files.get(&path) {
+            return Some(code.clone());
+        }
+        self.roots.iter().find_map(|root| std::fs::read_to_string(root.join(&path)).ok().map(Cow::Owned))
+    }
+
+    /// Where `path` lives on disk, if it isn't embedded.
+    pub fn disk_path(&self, path: &str) -> Option<PathBuf> {
+        let path = normalize(path);
+        if self.files.contains_key(&path) {
+            return None;
+        }
+        self.roots.iter().map(|r| r.join(&path)).find(|p| p.is_file())
+    }
+
+    /// Expands the library file at `path`.
+    pub fn compose(&self, path: &str, defines: &Defines) -> Result<WgslSource, PreprocessError> {
+        let code = self.load(path).ok_or_else(|| PreprocessError {
+            file: path.to_string(),
+            line: 0,
+            kind: PreprocessErrorKind::IncludeNotFound(path.to_string()),
+        })?;
+        let mut src = self.preprocess(path, &code, defines)?;
+        src.origin = SourceOrigin::Library { library: self.clone(), path: path.to_string() };
+        Ok(src)
+    }
+
+    /// Expands `code` as if it were the file `name`, resolving includes from this library.
+    pub fn preprocess(&self, name: &str, code: &str, defines: &Defines) -> Result<WgslSource, PreprocessError> {
+        let mut pp = Preprocessor {
+            library: self,
+            defines: defines.map.clone(),
+            included: HashSet::new(),
+            out: String::new(),
+            map: SourceMap::default(),
+        };
+        pp.included.insert(normalize(name));
+        pp.file(name, code, 0)?;
+        Ok(WgslSource {
+            name: Cow::Owned(name.to_string()),
+            code: Cow::Owned(pp.out),
+            defines: defines.clone(),
+            source_map: Some(Arc::new(pp.map)),
+            origin: SourceOrigin::Inline,
+        })
+    }
+}
+
+/// `a/./b/../c.wgsl` -> `a/c.wgsl`, backslashes to slashes.
+fn normalize(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => { parts.pop(); }
+            p => parts.push(p),
+        }
+    }
+    parts.join("/")
+}
+
+fn parent(path: &str) -> &str {
+    path.rfind(['/', '\\']).map_or("", |i| &path[..i])
+}
+
+struct Cond {
+    /// This branch is being emitted.
+    active: bool,
+    /// Some earlier branch of this `#if` chain was taken.
+    taken: bool,
+    /// The enclosing block is being emitted.
+    parent: bool,
+}
+
+struct Preprocessor<'a> {
+    library: &'a ShaderLibrary,
+    defines: BTreeMap<String, String>,
+    /// Every file is pasted at most once, so shared headers can include each other freely.
+    included: HashSet<String>,
+    out: String,
+    map: SourceMap,
+}
+
+impl Preprocessor<'_> {
+    fn file(&mut self, name: &str, code: &str, depth: usize) -> Result<(), PreprocessError> {
+        let file_idx = self.map.files.len() as u32;
+        self.map.files.push(name.to_string());
+        let mut stack: Vec<Cond> = Vec::new();
+
+        for (i, line) in code.lines().enumerate() {
+            let line_no = i as u32 + 1;
+            let err = |kind| PreprocessError { file: name.to_string(), line: line_no, kind };
+            let emitting = stack.last().is_none_or(|c| c.active);
+
+            let Some(directive) = line.trim_start().strip_prefix('#') else {
+                if emitting {
+                    self.out.push_str(&self.substitute(line));
+                    self.out.push('\n');
+                    self.map.lines.push((file_idx, line_no));
+                }
+                continue;
+            };
+            let directive = directive.trim();
+            let (word, rest) = directive.split_once(char::is_whitespace).unwrap_or((directive, ""));
+            let rest = rest.trim();
+
+            match word {
+                "ifdef" | "ifndef" | "if" => {
+                    let cond = if !emitting {
+                        false
+                    } else if word == "if" {
+                        self.eval(rest).map_err(|e| err(PreprocessErrorKind::BadExpression(e)))? != 0
+                    } else {
+                        self.defines.contains_key(rest) == (word == "ifdef")
+                    };
+                    stack.push(Cond { active: cond, taken: cond, parent: emitting });
+                }
+                "elif" => {
+                    let Some(top) = stack.last() else {
+                        return Err(err(PreprocessErrorKind::UnmatchedConditional(word.to_string())));
+                    };
+                    let cond = top.parent && !top.taken
+                        && self.eval(rest).map_err(|e| err(PreprocessErrorKind::BadExpression(e)))? != 0;
+                    let top = stack.last_mut().unwrap();
+                    top.active = cond;
+                    top.taken |= cond;
+                }
+                "else" => {
+                    let Some(top) = stack.last_mut() else {
+                        return Err(err(PreprocessErrorKind::UnmatchedConditional(word.to_string())));
+                    };
+                    top.active = top.parent && !top.taken;
+                    top.taken = true;
+                }
+                "endif" => {
+                    if stack.pop().is_none() {
+                        return Err(err(PreprocessErrorKind::UnmatchedConditional(word.to_string())));
+                    }
+                }
+                _ if !emitting => {}
+                "define" => {
+                    let (key, value) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                    self.defines.insert(key.to_string(), value.trim().to_string());
+                }
+                "undef" => {
+                    self.defines.remove(rest);
+                }
+                "include" => {
+                    let target = rest.trim_matches(|c| c == '"' || c == '<' || c == '>');
+                    // Relative to the including file first, then from the library root.
+                    let relative = normalize(&format!("{}/{target}", parent(name)));
+                    let (path, code) = match self.library.load(&relative) {
+                        Some(code) => (relative, code),
+                        None => {
+                            let path = normalize(target);
+                            let code = self.library.load(&path)
+                                .ok_or_else(|| err(PreprocessErrorKind::IncludeNotFound(target.to_string())))?;
+                            (path, code)
+                        }
+                    };
+                    if depth + 1 >= MAX_INCLUDE_DEPTH {
+                        return Err(err(PreprocessErrorKind::IncludeDepth));
+                    }
+                    if self.included.insert(path.clone()) {
+                        self.file(&path, &code, depth + 1)?;
+                    }
+                }
+                other => return Err(err(PreprocessErrorKind::UnknownDirective(other.to_string()))),
+            }
+        }
+
+        if !stack.is_empty() {
+            return Err(PreprocessError {
+                file: name.to_string(),
+                line: code.lines().count() as u32,
+                kind: PreprocessErrorKind::UnterminatedConditional,
+            });
+        }
+        Ok(())
+    }
+
+    /// Replaces identifiers that are `#define`d with a value.
+    fn substitute<'l>(&self, line: &'l str) -> Cow<'l, str> {
+        if self.defines.values().all(String::is_empty) {
+            return Cow::Borrowed(line);
+        }
+        let mut out = String::with_capacity(line.len());
+        let mut rest = line;
+        while let Some(start) = rest.find(is_ident_start) {
+            out.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let end = rest.find(|c: char| !is_ident_char(c)).unwrap_or(rest.len());
+            let ident = &rest[..end];
+            match self.defines.get(ident) {
+                Some(v) if !v.is_empty() => out.push_str(v),
+                _ => out.push_str(ident),
+            }
+            rest = &rest[end..];
+        }
+        out.push_str(rest);
+        Cow::Owned(out)
+    }
+
+    fn eval(&self, expr: &str) -> Result<i64, String> {
+        let tokens = tokenize(expr)?;
+        let mut p = ExprParser { tokens: &tokens, pos: 0, defines: &self.defines };
+        let v = p.or()?;
+        if p.pos != tokens.len() {
+            return Err(format!("unexpected `{}`", tokens[p.pos]));
+        }
+        Ok(v)
+    }
+}
+
+fn is_ident_start(c: char) -> bool { c.is_ascii_alphabetic() || c == '_' }
+fn is_ident_char(c: char) -> bool { c.is_ascii_alphanumeric() || c == '_' }
+
+fn tokenize(expr: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.char_indices().peekable();
+    while let Some(&(i, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if is_ident_char(c) {
+            let end = expr[i..].find(|c: char| !is_ident_char(c)).map_or(expr.len(), |e| i + e);
+            tokens.push(expr[i..end].to_string());
+            while chars.peek().is_some_and(|&(j, _)| j < end) { chars.next(); }
+        } else {
+            let two = expr.get(i..i + 2).unwrap_or("");
+            if ["&&", "||", "==", "!=", "<=", ">="].contains(&two) {
+                tokens.push(two.to_string());
+                chars.next();
+                chars.next();
+            } else if "()!<>".contains(c) {
+                tokens.push(c.to_string());
+                chars.next();
+            } else {
+                return Err(format!("unexpected character `{c}`"));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// `||` < `&&` < comparisons < unary `!` < atoms (numbers, names, `defined(X)`, parens).
+struct ExprParser<'a> {
+    tokens: &'a [String],
+    pos: usize,
+    defines: &'a BTreeMap<String, String>,
+}
+
+impl ExprParser<'_> {
+    fn peek(&self) -> Option<&str> { self.tokens.get(self.pos).map(String::as_str) }
+
+    fn next(&mut self) -> Result<&str, String> {
+        let t = self.tokens.get(self.pos).ok_or("unexpected end of expression")?;
+        self.pos += 1;
+        Ok(t)
+    }
+
+    fn expect(&mut self, tok: &str) -> Result<(), String> {
+        match self.next()? {
+            t if t == tok => Ok(()),
+            t => Err(format!("expected `{tok}`, found `{t}`")),
+        }
+    }
+
+    fn or(&mut self) -> Result<i64, String> {
+        let mut v = self.and()?;
+        while self.peek() == Some("||") {
+            self.pos += 1;
+            let r = self.and()?;
+            v = (v != 0 || r != 0) as i64;
+        }
+        Ok(v)
+    }
+
+    fn and(&mut self) -> Result<i64, String> {
+        let mut v = self.cmp()?;
+        while self.peek() == Some("&&") {
+            self.pos += 1;
+            let r = self.cmp()?;
+            v = (v != 0 && r != 0) as i64;
+        }
+        Ok(v)
+    }
+
+    fn cmp(&mut self) -> Result<i64, String> {
+        let l = self.unary()?;
+        let op = match self.peek() {
+            Some(op @ ("==" | "!=" | "<" | ">" | "<=" | ">=")) => op.to_string(),
+            _ => return Ok(l),
+        };
+        self.pos += 1;
+        let r = self.unary()?;
+        Ok(match op.as_str() {
+            "==" => l == r,
+            "!=" => l != r,
+            "<" => l < r,
+            ">" => l > r,
+            "<=" => l <= r,
+            _ => l >= r,
+        } as i64)
+    }
+
+    fn unary(&mut self) -> Result<i64, String> {
+        if self.peek() == Some("!") {
+            self.pos += 1;
+            return Ok((self.unary()? == 0) as i64);
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<i64, String> {
+        let tok = self.next()?.to_string();
+        match tok.as_str() {
+            "(" => {
+                let v = self.or()?;
+                self.expect(")")?;
+                Ok(v)
+            }
+            "defined" => {
+                let paren = self.peek() == Some("(");
+                if paren { self.pos += 1; }
+                let name = self.next()?.to_string();
+                if paren { self.expect(")")?; }
+                Ok(self.defines.contains_key(&name) as i64)
+            }
+            t if t.starts_with(|c: char| c.is_ascii_digit()) => {
+                t.parse().map_err(|_| format!("bad number `{t}`"))
+            }
+            // Undefined names are 0, like in C.
+            name => match self.defines.get(name).map(|v| v.trim()) {
+                None | Some("") => Ok(0),
+                Some("true") => Ok(1),
+                Some("false") => Ok(0),
+                Some(v) => v.parse().map_err(|_| format!("`{name}` = `{v}` is not an integer")),
+            },
+        }
+    }
+}
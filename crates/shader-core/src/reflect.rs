@@ -0,0 +1,354 @@
+use naga::valid::{Capabilities, ValidationFlags, Validator};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ShaderStage { Vertex, Fragment, Compute }
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ScalarType { Bool, I32, U32, I64, U64, F16, F32, F64 }
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ValueType {
+    Scalar(ScalarType),
+    Vector { size: u8, scalar: ScalarType },
+    Matrix { columns: u8, rows: u8, scalar: ScalarType },
+    /// Arrays, structs, atomics...
+    Other,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TextureDimension { D1, D2, D2Array, D3, Cube, CubeArray }
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TextureSample { Float, Sint, Uint, Depth }
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BindingType {
+    /// `size` is the byte size of the bound type (minimum binding size).
+    Uniform { size: u32 },
+    Storage { size: u32, read_only: bool },
+    Texture { dimension: TextureDimension, sample: TextureSample, multisampled: bool },
+    StorageTexture { dimension: TextureDimension, format: String, read: bool, write: bool },
+    Sampler { comparison: bool },
+    Other,
+}
+
+#[derive(Clone, Debug)]
+pub struct BindingInfo {
+    pub group: u32,
+    pub binding: u32,
+    pub name: Option<String>,
+    pub ty: BindingType,
+    /// Stages whose entry points actually use the binding.
+    pub stages: Vec<ShaderStage>,
+}
+
+#[derive(Clone, Debug)]
+pub struct OverrideInfo {
+    pub name: String,
+    pub id: Option<u16>,
+    pub ty: ScalarType,
+    /// Whether the declaration has an initializer; pipelines can be built without setting it.
+    pub has_default: bool,
+    /// The initializer's value when it is a plain literal.
+    pub default: Option<f64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct IoVariable {
+    pub location: u32,
+    pub name: Option<String>,
+    pub ty: ValueType,
+}
+
+#[derive(Clone, Debug)]
+pub struct EntryPointInfo {
+    pub name: String,
+    pub stage: ShaderStage,
+    pub workgroup_size: [u32; 3],
+    /// `@location` arguments of a vertex entry point.
+    pub vertex_inputs: Vec<IoVariable>,
+    /// `@location` results of a fragment entry point.
+    pub fragment_outputs: Vec<IoVariable>,
+}
+
+/// What a WGSL module declares, as far as building pipelines is concerned.
+#[derive(Clone, Debug, Default)]
+pub struct ShaderReflection {
+    pub entry_points: Vec<EntryPointInfo>,
+    pub overrides: Vec<OverrideInfo>,
+    pub bindings: Vec<BindingInfo>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceLocation {
+    /// Original file, once resolved through `WgslSource`; `None` straight out of naga.
+    pub file: Option<String>,
+    /// 1-based.
+    pub line: u32,
+    /// 1-based, in bytes.
+    pub column: u32,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ReflectErrorKind { Parse, Validation }
+
+#[derive(Clone, Debug)]
+pub struct ReflectError {
+    pub kind: ReflectErrorKind,
+    pub message: String,
+    pub location: Option<SourceLocation>,
+}
+
+impl std::fmt::Display for ReflectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(loc) = &self.location {
+            write!(f, "{}:{}:{}: ", loc.file.as_deref().unwrap_or("<wgsl>"), loc.line, loc.column)?;
+        }
+        match self.kind {
+            ReflectErrorKind::Parse => write!(f, "WGSL parse error: {}", self.message),
+            ReflectErrorKind::Validation => write!(f, "WGSL validation error: {}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for ReflectError {}
+
+fn location(loc: Option<naga::SourceLocation>) -> Option<SourceLocation> {
+    loc.map(|l| SourceLocation { file: None, line: l.line_number, column: l.line_position })
+}
+
+impl ShaderReflection {
+    /// Parses and validates `code`.
+    pub fn from_wgsl(code: &str) -> Result<Self, ReflectError> {
+        let module = naga::front::wgsl::parse_str(code).map_err(|e| ReflectError {
+            kind: ReflectErrorKind::Parse,
+            message: e.message().to_string(),
+            location: location(e.location(code)),
+        })?;
+        let info = Validator::new(ValidationFlags::all(), Capabilities::all())
+            .validate(&module)
+            .map_err(|e| ReflectError {
+                kind: ReflectErrorKind::Validation,
+                message: error_chain(e.as_inner()),
+                location: location(e.location(code)),
+            })?;
+        Ok(Self::from_module(&module, &info))
+    }
+
+    pub fn from_module(module: &naga::Module, info: &naga::valid::ModuleInfo) -> Self {
+        let entry_points = module.entry_points.iter().filter_map(|ep| {
+            let stage = shader_stage(ep.stage)?;
+            let mut vertex_inputs = Vec::new();
+            if stage == ShaderStage::Vertex {
+                for arg in &ep.function.arguments {
+                    collect_io(module, arg.name.as_deref(), arg.ty, arg.binding.as_ref(), &mut vertex_inputs);
+                }
+            }
+            let mut fragment_outputs = Vec::new();
+            if let (ShaderStage::Fragment, Some(res)) = (stage, &ep.function.result) {
+                collect_io(module, None, res.ty, res.binding.as_ref(), &mut fragment_outputs);
+            }
+            vertex_inputs.sort_by_key(|v| v.location);
+            fragment_outputs.sort_by_key(|v| v.location);
+            Some(EntryPointInfo {
+                name: ep.name.clone(),
+                stage,
+                workgroup_size: ep.workgroup_size,
+                vertex_inputs,
+                fragment_outputs,
+            })
+        }).collect();
+
+        let overrides = module.overrides.iter().filter_map(|(_, o)| {
+            let ty = match &module.types[o.ty].inner {
+                naga::TypeInner::Scalar(s) => scalar_type(*s)?,
+                _ => return None,
+            };
+            let default = o.init.and_then(|e| match module.global_expressions[e] {
+                naga::Expression::Literal(lit) => literal_f64(lit),
+                _ => None,
+            });
+            Some(OverrideInfo {
+                name: o.name.clone().unwrap_or_else(|| o.id.map(|id| id.to_string()).unwrap_or_default()),
+                id: o.id,
+                ty,
+                has_default: o.init.is_some(),
+                default,
+            })
+        }).collect();
+
+        let mut bindings: Vec<BindingInfo> = module.global_variables.iter().filter_map(|(handle, var)| {
+            let rb = var.binding.as_ref()?;
+            let mut stages = Vec::new();
+            for (i, ep) in module.entry_points.iter().enumerate() {
+                let Some(stage) = shader_stage(ep.stage) else { continue };
+                if !info.get_entry_point(i)[handle].is_empty() && !stages.contains(&stage) {
+                    stages.push(stage);
+                }
+            }
+            Some(BindingInfo {
+                group: rb.group,
+                binding: rb.binding,
+                name: var.name.clone(),
+                ty: binding_type(module, var),
+                stages,
+            })
+        }).collect();
+        bindings.sort_by_key(|b| (b.group, b.binding));
+
+        Self { entry_points, overrides, bindings }
+    }
+
+    pub fn entry_point(&self, name: &str) -> Option<&EntryPointInfo> {
+        self.entry_points.iter().find(|e| e.name == name)
+    }
+
+    pub fn override_info(&self, name: &str) -> Option<&OverrideInfo> {
+        self.overrides.iter().find(|o| o.name == name || o.id.is_some_and(|id| id.to_string() == name))
+    }
+
+    pub fn group(&self, group: u32) -> impl Iterator<Item = &BindingInfo> {
+        self.bindings.iter().filter(move |b| b.group == group)
+    }
+
+    /// One past the highest group index used, i.e. how many bind group layouts a pipeline needs.
+    pub fn group_count(&self) -> u32 {
+        self.bindings.iter().map(|b| b.group + 1).max().unwrap_or(0)
+    }
+}
+
+/// naga nests the interesting part of validation errors in `source()`.
+fn error_chain(e: &dyn std::error::Error) -> String {
+    let mut msg = e.to_string();
+    let mut cur = e.source();
+    while let Some(inner) = cur {
+        msg.push_str(": ");
+        msg.push_str(&inner.to_string());
+        cur = inner.source();
+    }
+    msg
+}
+
+fn collect_io(
+    module: &naga::Module,
+    name: Option<&str>,
+    ty: naga::Handle<naga::Type>,
+    binding: Option<&naga::Binding>,
+    out: &mut Vec<IoVariable>,
+) {
+    match binding {
+        Some(naga::Binding::Location { location, .. }) => out.push(IoVariable {
+            location: *location,
+            name: name.map(str::to_string),
+            ty: value_type(&module.types[ty].inner),
+        }),
+        Some(naga::Binding::BuiltIn(_)) => {}
+        None => {
+            if let naga::TypeInner::Struct { members, .. } = &module.types[ty].inner {
+                for m in members {
+                    collect_io(module, m.name.as_deref(), m.ty, m.binding.as_ref(), out);
+                }
+            }
+        }
+    }
+}
+
+/// `None` for task and mesh shaders, which nothing here builds pipelines for.
+fn shader_stage(stage: naga::ShaderStage) -> Option<ShaderStage> {
+    match stage {
+        naga::ShaderStage::Vertex => Some(ShaderStage::Vertex),
+        naga::ShaderStage::Fragment => Some(ShaderStage::Fragment),
+        naga::ShaderStage::Compute => Some(ShaderStage::Compute),
+        _ => None,
+    }
+}
+
+fn scalar_type(s: naga::Scalar) -> Option<ScalarType> {
+    Some(match (s.kind, s.width) {
+        (naga::ScalarKind::Bool, _) => ScalarType::Bool,
+        (naga::ScalarKind::Sint, 4) => ScalarType::I32,
+        (naga::ScalarKind::Sint, 8) => ScalarType::I64,
+        (naga::ScalarKind::Uint, 4) => ScalarType::U32,
+        (naga::ScalarKind::Uint, 8) => ScalarType::U64,
+        (naga::ScalarKind::Float, 2) => ScalarType::F16,
+        (naga::ScalarKind::Float, 4) => ScalarType::F32,
+        (naga::ScalarKind::Float, 8) => ScalarType::F64,
+        _ => return None,
+    })
+}
+
+fn value_type(inner: &naga::TypeInner) -> ValueType {
+    match *inner {
+        naga::TypeInner::Scalar(s) => scalar_type(s).map_or(ValueType::Other, ValueType::Scalar),
+        naga::TypeInner::Vector { size, scalar: s } => scalar_type(s)
+            .map_or(ValueType::Other, |scalar| ValueType::Vector { size: size as u8, scalar }),
+        naga::TypeInner::Matrix { columns, rows, scalar: s } => scalar_type(s)
+            .map_or(ValueType::Other, |scalar| ValueType::Matrix { columns: columns as u8, rows: rows as u8, scalar }),
+        _ => ValueType::Other,
+    }
+}
+
+fn literal_f64(lit: naga::Literal) -> Option<f64> {
+    Some(match lit {
+        naga::Literal::F64(v) | naga::Literal::AbstractFloat(v) => v,
+        naga::Literal::F32(v) => v as f64,
+        naga::Literal::F16(v) => f64::from(v),
+        naga::Literal::U32(v) => v as f64,
+        naga::Literal::I32(v) => v as f64,
+        naga::Literal::U64(v) => v as f64,
+        naga::Literal::I64(v) | naga::Literal::AbstractInt(v) => v as f64,
+        naga::Literal::Bool(v) => if v { 1.0 } else { 0.0 },
+    })
+}
+
+fn texture_dimension(dim: naga::ImageDimension, arrayed: bool) -> TextureDimension {
+    match (dim, arrayed) {
+        (naga::ImageDimension::D1, _) => TextureDimension::D1,
+        (naga::ImageDimension::D2, false) => TextureDimension::D2,
+        (naga::ImageDimension::D2, true) => TextureDimension::D2Array,
+        (naga::ImageDimension::D3, _) => TextureDimension::D3,
+        (naga::ImageDimension::Cube, false) => TextureDimension::Cube,
+        (naga::ImageDimension::Cube, true) => TextureDimension::CubeArray,
+    }
+}
+
+fn binding_type(module: &naga::Module, var: &naga::GlobalVariable) -> BindingType {
+    let inner = &module.types[var.ty].inner;
+    match var.space {
+        naga::AddressSpace::Uniform => BindingType::Uniform { size: inner.size(module.to_ctx()) },
+        naga::AddressSpace::Storage { access } => BindingType::Storage {
+            size: inner.size(module.to_ctx()),
+            read_only: !access.contains(naga::StorageAccess::STORE),
+        },
+        naga::AddressSpace::Handle => match *inner {
+            naga::TypeInner::Sampler { comparison } => BindingType::Sampler { comparison },
+            naga::TypeInner::Image { dim, arrayed, class } => {
+                let dimension = texture_dimension(dim, arrayed);
+                match class {
+                    naga::ImageClass::Sampled { kind, multi } => BindingType::Texture {
+                        dimension,
+                        sample: match kind {
+                            naga::ScalarKind::Sint => TextureSample::Sint,
+                            naga::ScalarKind::Uint => TextureSample::Uint,
+                            _ => TextureSample::Float,
+                        },
+                        multisampled: multi,
+                    },
+                    naga::ImageClass::Depth { multi } => BindingType::Texture {
+                        dimension,
+                        sample: TextureSample::Depth,
+                        multisampled: multi,
+                    },
+                    naga::ImageClass::Storage { format, access } => BindingType::StorageTexture {
+                        dimension,
+                        format: format!("{format:?}"),
+                        read: access.contains(naga::StorageAccess::LOAD),
+                        write: access.contains(naga::StorageAccess::STORE),
+                    },
+                }
+            }
+            _ => BindingType::Other,
+        },
+        _ => BindingType::Other,
+    }
+}
@@ -0,0 +1,303 @@
+use std::hash::{Hash, Hasher};
+
+/// Color attachments a pipeline can write, the first one included.
+pub const MAX_COLOR_TARGETS: usize = 8;
+
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub enum Topology { TriangleList, TriangleStrip, LineList }
+
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub enum BlendFactor {
+    Zero,
+    One,
+    Src,
+    OneMinusSrc,
+    SrcAlpha,
+    OneMinusSrcAlpha,
+    Dst,
+    OneMinusDst,
+    DstAlpha,
+    OneMinusDstAlpha,
+    SrcAlphaSaturated,
+    Constant,
+    OneMinusConstant,
+}
+
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub enum BlendOp { Add, Subtract, ReverseSubtract, Min, Max }
+
+/// `src * src_factor <op> dst * dst_factor` for either color or alpha.
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub struct BlendComponent {
+    pub src: BlendFactor,
+    pub dst: BlendFactor,
+    pub op: BlendOp,
+}
+
+impl BlendComponent {
+    pub const fn new(src: BlendFactor, dst: BlendFactor, op: BlendOp) -> Self { Self { src, dst, op } }
+}
+
+#[derive(Clone, Copy, Debug, Default, Hash, Eq, PartialEq)]
+pub enum BlendMode {
+    /// No blending, the fragment overwrites the target.
+    #[default]
+    Replace,
+    /// Straight alpha: `src * a + dst * (1 - a)`.
+    Alpha,
+    /// `src * a + dst`, for glows and particles.
+    Additive,
+    /// Color already multiplied by alpha: `src + dst * (1 - a)`.
+    Premultiplied,
+    Custom { color: BlendComponent, alpha: BlendComponent },
+}
+
+impl BlendMode {
+    /// (color, alpha) equations; `None` for `Replace`.
+    pub fn components(self) -> Option<(BlendComponent, BlendComponent)> {
+        use BlendFactor::*;
+        let add = |src, dst| BlendComponent::new(src, dst, BlendOp::Add);
+        match self {
+            BlendMode::Replace => None,
+            BlendMode::Alpha => Some((add(SrcAlpha, OneMinusSrcAlpha), add(One, OneMinusSrcAlpha))),
+            BlendMode::Additive => Some((add(SrcAlpha, One), add(One, One))),
+            BlendMode::Premultiplied => Some((add(One, OneMinusSrcAlpha), add(One, OneMinusSrcAlpha))),
+            BlendMode::Custom { color, alpha } => Some((color, alpha)),
+        }
+    }
+}
+
+/// One color attachment of a pipeline.
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub struct ColorTarget<TFmt> {
+    pub format: TFmt,
+    pub blend: BlendMode,
+}
+
+#[derive(Clone, Copy, Debug, Default, Hash, Eq, PartialEq)]
+pub enum CullMode {
+    #[default]
+    None,
+    Front,
+    Back,
+}
+
+#[derive(Clone, Copy, Debug, Default, Hash, Eq, PartialEq)]
+pub enum FrontFace {
+    /// Counter-clockwise triangles face the camera.
+    #[default]
+    Ccw,
+    Cw,
+}
+
+/// `Line` and `Point` need device features; pipelines using them fail to build without.
+#[derive(Clone, Copy, Debug, Default, Hash, Eq, PartialEq)]
+pub enum PolygonMode {
+    #[default]
+    Fill,
+    Line,
+    Point,
+}
+
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub enum CompareFunction { Never, Less, Equal, LessEqual, Greater, NotEqual, GreaterEqual, Always }
+
+impl CompareFunction {
+    /// The same test with depth running the other way (reverse-Z): `Less` becomes `Greater`.
+    pub fn reversed(self) -> Self {
+        use CompareFunction::*;
+        match self {
+            Less => Greater,
+            LessEqual => GreaterEqual,
+            Greater => Less,
+            GreaterEqual => LessEqual,
+            other => other,
+        }
+    }
+}
+
+/// Added to fragment depth: `constant` in units of the depth format's precision plus
+/// `slope_scale` times the polygon's depth slope, clamped to `clamp` (0 = no clamp).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DepthBias {
+    pub constant: i32,
+    pub slope_scale: f32,
+    pub clamp: f32,
+}
+
+impl Eq for DepthBias {}
+
+impl Hash for DepthBias {
+    fn hash<H: Hasher>(&self, h: &mut H) {
+        self.constant.hash(h);
+        self.slope_scale.to_bits().hash(h);
+        self.clamp.to_bits().hash(h);
+    }
+}
+
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub enum StencilOp {
+    Keep,
+    Zero,
+    /// Write the draw's stencil reference.
+    Replace,
+    Invert,
+    IncrementClamp,
+    DecrementClamp,
+    IncrementWrap,
+    DecrementWrap,
+}
+
+/// Stencil test and update for one facing.
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub struct StencilFace {
+    pub compare: CompareFunction,
+    pub fail_op: StencilOp,
+    pub depth_fail_op: StencilOp,
+    pub pass_op: StencilOp,
+}
+
+impl StencilFace {
+    pub const IGNORE: Self = Self {
+        compare: CompareFunction::Always,
+        fail_op: StencilOp::Keep,
+        depth_fail_op: StencilOp::Keep,
+        pass_op: StencilOp::Keep,
+    };
+}
+
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub struct StencilState {
+    pub front: StencilFace,
+    pub back: StencilFace,
+    pub read_mask: u32,
+    pub write_mask: u32,
+}
+
+impl StencilState {
+    pub const DISABLED: Self = Self { front: StencilFace::IGNORE, back: StencilFace::IGNORE, read_mask: 0, write_mask: 0 };
+
+    /// Same test and ops for both facings, full masks.
+    pub const fn both(face: StencilFace) -> Self {
+        Self { front: face, back: face, read_mask: 0xff, write_mask: 0xff }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        (self.front != StencilFace::IGNORE || self.back != StencilFace::IGNORE)
+            && (self.read_mask != 0 || self.write_mask != 0)
+    }
+}
+
+impl Default for StencilState {
+    fn default() -> Self { Self::DISABLED }
+}
+
+/// Fixed-function state a pipeline is built with. `format` and `blend` describe color target 0;
+/// `new` gives an opaque, depth-tested, unculled triangle list.
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub struct RenderState<TFmt: Hash + Eq + Copy> {
+    pub format: TFmt,
+    pub blend: BlendMode,
+    /// Targets 1.. for multiple render targets; `None` leaves that location unwritten.
+    pub extra_targets: [Option<ColorTarget<TFmt>>; MAX_COLOR_TARGETS - 1],
+    /// Depth attachment present and tested. Everything below up to and including `stencil`
+    /// needs it; pipelines with stencil state but no depth are rejected.
+    pub depth: bool,
+    pub depth_write: bool,
+    pub depth_compare: CompareFunction,
+    pub depth_bias: DepthBias,
+    pub stencil: StencilState,
+    /// Samples per pixel; has to match the attachments of the pass the pipeline draws in.
+    pub msaa: u32,
+    pub topo: Topology,
+    pub cull: CullMode,
+    pub front_face: FrontFace,
+    pub polygon: PolygonMode,
+}
+
+impl<TFmt: Hash + Eq + Copy> RenderState<TFmt> {
+    pub fn new(format: TFmt) -> Self {
+        Self {
+            format,
+            blend: BlendMode::Replace,
+            extra_targets: [None; MAX_COLOR_TARGETS - 1],
+            depth: true,
+            depth_write: true,
+            depth_compare: CompareFunction::Less,
+            depth_bias: DepthBias::default(),
+            stencil: StencilState::DISABLED,
+            msaa: 1,
+            topo: Topology::TriangleList,
+            cull: CullMode::None,
+            front_face: FrontFace::Ccw,
+            polygon: PolygonMode::Fill,
+        }
+    }
+
+    pub fn with_blend(mut self, blend: BlendMode) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    /// Adds a color target after the existing ones.
+    ///
+    /// # Panics
+    /// With `MAX_COLOR_TARGETS` targets already.
+    pub fn with_target(mut self, format: TFmt, blend: BlendMode) -> Self {
+        let used = self.extra_targets.iter().rposition(Option::is_some).map_or(0, |i| i + 1);
+        assert!(used < self.extra_targets.len(), "at most {MAX_COLOR_TARGETS} color targets");
+        self.extra_targets[used] = Some(ColorTarget { format, blend });
+        self
+    }
+
+    /// Color targets by location, up to the last one in use.
+    pub fn targets(&self) -> Vec<Option<ColorTarget<TFmt>>> {
+        let mut targets = vec![Some(ColorTarget { format: self.format, blend: self.blend })];
+        let used = self.extra_targets.iter().rposition(Option::is_some).map_or(0, |i| i + 1);
+        targets.extend_from_slice(&self.extra_targets[..used]);
+        targets
+    }
+
+    pub fn with_depth(mut self, compare: CompareFunction, write: bool) -> Self {
+        self.depth = true;
+        self.depth_compare = compare;
+        self.depth_write = write;
+        self
+    }
+
+    pub fn without_depth(mut self) -> Self {
+        self.depth = false;
+        self
+    }
+
+    pub fn with_depth_bias(mut self, bias: DepthBias) -> Self {
+        self.depth_bias = bias;
+        self
+    }
+
+    pub fn with_stencil(mut self, stencil: StencilState) -> Self {
+        self.stencil = stencil;
+        self
+    }
+
+    pub fn with_msaa(mut self, samples: u32) -> Self {
+        self.msaa = samples;
+        self
+    }
+
+    pub fn with_topology(mut self, topo: Topology) -> Self {
+        self.topo = topo;
+        self
+    }
+
+    pub fn with_cull(mut self, cull: CullMode, front_face: FrontFace) -> Self {
+        self.cull = cull;
+        self.front_face = front_face;
+        self
+    }
+
+    pub fn with_polygon_mode(mut self, polygon: PolygonMode) -> Self {
+        self.polygon = polygon;
+        self
+    }
+}
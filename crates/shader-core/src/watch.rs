@@ -0,0 +1,83 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Arc;
+
+use notify::{RecursiveMode, Watcher};
+
+/// Watches shader files for changes. Directories are watched rather than the files themselves,
+/// since editors often save by writing a new file and renaming it over the old one.
+pub struct ShaderWatcher {
+    watcher: notify::RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+    pending: Arc<AtomicBool>,
+    files: HashSet<PathBuf>,
+    dirs: HashSet<PathBuf>,
+}
+
+impl ShaderWatcher {
+    pub fn new() -> notify::Result<Self> {
+        let (tx, events) = channel();
+        let pending = Arc::new(AtomicBool::new(false));
+        let flag = pending.clone();
+        let watcher = notify::recommended_watcher(move |ev| {
+            let _ = tx.send(ev);
+            flag.store(true, Ordering::Release);
+        })?;
+        Ok(Self { watcher, events, pending, files: HashSet::new(), dirs: HashSet::new() })
+    }
+
+    pub fn watch(&mut self, file: &Path) -> notify::Result<()> {
+        let file = canonical(file);
+        let Some(dir) = file.parent().map(Path::to_path_buf) else { return Ok(()) };
+        if !self.dirs.contains(&dir) {
+            self.watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+            self.dirs.insert(dir);
+        }
+        self.files.insert(file);
+        Ok(())
+    }
+
+    pub fn is_watched(&self, file: &Path) -> bool {
+        self.files.contains(&canonical(file))
+    }
+
+    /// Whether anything happened in the watched directories since the last `changed`, which is
+    /// only worth calling then.
+    pub fn has_events(&self) -> bool {
+        self.pending.load(Ordering::Acquire)
+    }
+
+    /// Watched files modified since the last call. Never blocks.
+    pub fn changed(&self) -> HashSet<PathBuf> {
+        let mut changed = HashSet::new();
+        if !self.pending.swap(false, Ordering::AcqRel) {
+            return changed;
+        }
+        for ev in self.events.try_iter().flatten() {
+            if ev.kind.is_access() {
+                continue;
+            }
+            for path in ev.paths {
+                let path = canonical(&path);
+                if self.files.contains(&path) {
+                    changed.insert(path);
+                }
+            }
+        }
+        changed
+    }
+}
+
+/// Best-effort absolute path, so the same file reached through different routes compares equal.
+pub fn canonical(path: &Path) -> PathBuf {
+    if let Ok(p) = path.canonicalize() {
+        return p;
+    }
+    // Deleted mid-save: canonicalize the directory instead.
+    match (path.parent().and_then(|d| d.canonicalize().ok()), path.file_name()) {
+        (Some(dir), Some(name)) => dir.join(name),
+        _ => path.to_path_buf(),
+    }
+}
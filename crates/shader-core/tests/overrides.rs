@@ -0,0 +1,79 @@
+use shader_core::{OverrideIssueKind, OverrideValue, Overrides, RenderState, ScalarType, ShaderKey, ShaderReflection, WgslSource};
+
+const SRC: &str = "
+override USE_FOG: bool = false;
+override TINT_R: f32 = 1.0;
+override SAMPLES: u32;
+override OFFSET: i32 = 0;
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+  let s = f32(SAMPLES) + f32(OFFSET) * 0.0;
+  return vec4<f32>(TINT_R * s, select(0.0, 1.0, USE_FOG), 0.0, 1.0);
+}
+";
+
+fn reflection() -> ShaderReflection {
+    ShaderReflection::from_wgsl(SRC).expect("reflect")
+}
+
+#[test]
+fn valid_values_are_converted_to_declared_types() {
+    let ov = Overrides::default()
+        .with("SAMPLES", 4i32)
+        .with("TINT_R", 2u32)
+        .with("USE_FOG", true);
+    let mut constants = ov.validate("test.wgsl", &reflection()).expect("valid");
+    constants.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(constants, [
+        ("SAMPLES".to_string(), 4.0),
+        ("TINT_R".to_string(), 2.0),
+        ("USE_FOG".to_string(), 1.0),
+    ]);
+}
+
+#[test]
+fn reports_every_issue() {
+    let ov = Overrides::default()
+        .with("USE_FGO", true)
+        .with("TINT_R", true)
+        .with("OFFSET", u32::MAX);
+    let err = ov.validate("test.wgsl", &reflection()).unwrap_err();
+    let kinds: Vec<_> = err.issues.iter().map(|i| (i.name.as_str(), i.kind.clone())).collect();
+    assert_eq!(kinds, [
+        ("OFFSET", OverrideIssueKind::OutOfRange { value: OverrideValue::U32(u32::MAX), expected: ScalarType::I32 }),
+        ("TINT_R", OverrideIssueKind::TypeMismatch { value: OverrideValue::Bool(true), expected: ScalarType::F32 }),
+        ("USE_FGO", OverrideIssueKind::Unknown { suggestion: Some("USE_FOG".to_string()) }),
+        ("SAMPLES", OverrideIssueKind::Missing { expected: ScalarType::U32 }),
+    ]);
+
+    let msg = err.to_string();
+    assert!(msg.contains("did you mean `USE_FOG`"), "{msg}");
+    assert!(msg.contains("`SAMPLES: u32` has no default"), "{msg}");
+}
+
+#[test]
+fn negative_int_for_unsigned_is_out_of_range() {
+    let ov = Overrides::default().with("SAMPLES", -1i32);
+    let err = ov.validate("test.wgsl", &reflection()).unwrap_err();
+    assert!(matches!(err.issues[0].kind, OverrideIssueKind::OutOfRange { .. }));
+}
+
+#[test]
+fn values_converting_to_the_same_constant_share_a_key() {
+    let src = WgslSource::new("overrides.wgsl", SRC);
+    let key = |ov: &Overrides| ShaderKey::new(&src, RenderState::new(0u32), ov);
+    let as_u32 = Overrides::default().with("SAMPLES", 4u32).with("TINT_R", 1.0f32);
+    let as_i32 = Overrides::default().with("SAMPLES", 4i32).with("TINT_R", 1i32);
+    assert_eq!(as_u32.validate("overrides.wgsl", &reflection()).unwrap(), as_i32.validate("overrides.wgsl", &reflection()).unwrap());
+    assert_eq!(key(&as_u32), key(&as_i32));
+    assert_ne!(key(&as_u32), key(&Overrides::default().with("SAMPLES", 5u32).with("TINT_R", 1.0f32)));
+}
+
+#[test]
+fn permutation_ids_are_the_same_on_every_run() {
+    // Pinned, since a hasher seeded per process would still agree with itself within one test.
+    let src = WgslSource::new("overrides.wgsl", SRC);
+    let key = ShaderKey::new(&src, RenderState::new(0u32), &Overrides::default().with("SAMPLES", 4u32));
+    assert_eq!(key.permutation_id(&"layout"), 0xa432_0305_c129_4347);
+}
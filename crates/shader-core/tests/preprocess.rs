@@ -0,0 +1,104 @@
+use shader_core::{Defines, DiagnosticKind, PreprocessErrorKind, RenderState, ShaderKey, ShaderLibrary, Overrides};
+
+fn library() -> ShaderLibrary {
+    let mut lib = ShaderLibrary::new();
+    lib.add("common/camera.wgsl", "#include \"types.wgsl\"\nvar<uniform> cam: Camera;\n")
+        .add("common/types.wgsl", "struct Camera { view_proj: mat4x4<f32> };\n")
+        .add("lit.wgsl", concat!(
+            "#include \"common/camera.wgsl\"\n",
+            "#include \"common/types.wgsl\"\n",
+            "#ifdef SHADOWS\n",
+            "const SHADOW_TAPS: u32 = TAPS;\n",
+            "#elif defined(FAST) && !defined(SHADOWS)\n",
+            "const FAST_PATH: bool = true;\n",
+            "#else\n",
+            "const PLAIN: bool = true;\n",
+            "#endif\n",
+            "#if TAPS >= 4 || 0\n",
+            "const MANY_TAPS: bool = true;\n",
+            "#endif\n",
+        ));
+    lib
+}
+
+fn state() -> RenderState<u32> {
+    RenderState::new(0)
+}
+
+#[test]
+fn includes_are_pasted_once() {
+    let src = library().compose("lit.wgsl", &Defines::default()).expect("compose");
+    assert_eq!(src.code.matches("struct Camera").count(), 1);
+    assert!(src.code.contains("const PLAIN"));
+    assert!(!src.code.contains("MANY_TAPS"));
+}
+
+#[test]
+fn defines_select_branches_and_substitute() {
+    let src = library()
+        .compose("lit.wgsl", &Defines::default().with_flag("SHADOWS").with("TAPS", 8))
+        .expect("compose");
+    assert!(src.code.contains("const SHADOW_TAPS: u32 = 8;"));
+    assert!(src.code.contains("MANY_TAPS"));
+    assert!(!src.code.contains("PLAIN"));
+
+    let fast = library().compose("lit.wgsl", &Defines::default().with_flag("FAST")).expect("compose");
+    assert!(fast.code.contains("FAST_PATH"));
+}
+
+#[test]
+fn permutations_get_distinct_keys() {
+    let lib = library();
+    let a = lib.compose("lit.wgsl", &Defines::default()).unwrap();
+    let b = lib.compose("lit.wgsl", &Defines::default().with_flag("FAST")).unwrap();
+    let ov = Overrides::default();
+    assert_ne!(ShaderKey::new(&a, state(), &ov), ShaderKey::new(&b, state(), &ov));
+    assert_eq!(ShaderKey::new(&a, state(), &ov), ShaderKey::new(&a.clone(), state(), &ov));
+}
+
+#[test]
+fn errors_point_at_original_files() {
+    let mut lib = library();
+    lib.add("broken.wgsl", "#include \"common/camera.wgsl\"\n\nfn f() -> f32 {\n  return 1.0 +;\n}\n");
+    let src = lib.compose("broken.wgsl", &Defines::default()).expect("compose");
+    let err = src.reflect().unwrap_err();
+    let loc = err.location.expect("location");
+    assert_eq!((loc.file.as_deref(), loc.line), (Some("broken.wgsl"), 4));
+
+    lib.add("bad_camera.wgsl", "#include \"common/types.wgsl\"\nvar<uniform> cam: Camra;\n");
+    lib.add("uses_bad.wgsl", "#include \"bad_camera.wgsl\"\n");
+    let err = lib.compose("uses_bad.wgsl", &Defines::default()).unwrap().reflect().unwrap_err();
+    let loc = err.location.expect("location");
+    assert_eq!((loc.file.as_deref(), loc.line), (Some("bad_camera.wgsl"), 2));
+}
+
+#[test]
+fn diagnostics_carry_snippets() {
+    let mut lib = library();
+    lib.add("broken.wgsl", "#include \"common/camera.wgsl\"\n\nfn f() -> f32 {\n  return 1.0 +;\n}\n");
+    let src = lib.compose("broken.wgsl", &Defines::default()).expect("compose");
+    let diag = src.check().unwrap_err();
+    assert_eq!(diag.kind, DiagnosticKind::Parse);
+    assert_eq!((diag.file.as_str(), diag.line), ("broken.wgsl", 4));
+    assert_eq!(diag.snippet.as_deref(), Some("  return 1.0 +;"));
+    assert!(diag.column > 0);
+    let text = diag.to_string();
+    assert!(text.contains("--> broken.wgsl:4:"), "{text}");
+    assert!(text.contains("4 |   return 1.0 +;"), "{text}");
+}
+
+#[test]
+fn directive_errors() {
+    let mut lib = ShaderLibrary::new();
+    lib.add("a.wgsl", "\n#include \"missing.wgsl\"\n")
+        .add("b.wgsl", "#ifdef X\n")
+        .add("c.wgsl", "#endif\n")
+        .add("d.wgsl", "#if (1 +\n#endif\n");
+    let kind = |name| lib.compose(name, &Defines::default()).unwrap_err();
+
+    let a = kind("a.wgsl");
+    assert_eq!((a.line, a.kind), (2, PreprocessErrorKind::IncludeNotFound("missing.wgsl".into())));
+    assert_eq!(kind("b.wgsl").kind, PreprocessErrorKind::UnterminatedConditional);
+    assert_eq!(kind("c.wgsl").kind, PreprocessErrorKind::UnmatchedConditional("endif".into()));
+    assert!(matches!(kind("d.wgsl").kind, PreprocessErrorKind::BadExpression(_)));
+}
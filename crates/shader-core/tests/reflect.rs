@@ -0,0 +1,64 @@
+use shader_core::{
+    BindingType, Defines, ReflectErrorKind, ScalarType, ShaderLibrary, ShaderStage, TextureDimension, TextureSample,
+    ValueType, WgslSource,
+};
+
+fn demo(name: &str) -> WgslSource {
+    ShaderLibrary::new()
+        .add_root(concat!(env!("CARGO_MANIFEST_DIR"), "/../../demos/triangle/shaders"))
+        .compose(name, &Defines::default())
+        .expect("compose")
+}
+
+#[test]
+fn triangle_overrides_and_io() {
+    let refl = demo("triangle.wgsl")
+        .reflect()
+        .expect("reflect");
+
+    let fog = refl.override_info("USE_FOG").expect("USE_FOG");
+    assert_eq!(fog.ty, ScalarType::Bool);
+    assert!(fog.has_default);
+    assert_eq!(fog.default, Some(0.0));
+    assert_eq!(refl.override_info("TINT_G").map(|o| o.ty), Some(ScalarType::F32));
+    assert!(refl.override_info("NOPE").is_none());
+
+    let vs = refl.entry_point("vs_main").expect("vs_main");
+    assert_eq!(vs.stage, ShaderStage::Vertex);
+    let inputs: Vec<_> = vs.vertex_inputs.iter().map(|v| (v.location, v.ty)).collect();
+    assert_eq!(inputs, [
+        (0, ValueType::Vector { size: 2, scalar: ScalarType::F32 }),
+        (1, ValueType::Vector { size: 3, scalar: ScalarType::F32 }),
+    ]);
+
+    let fs = refl.entry_point("fs_main").expect("fs_main");
+    assert_eq!(fs.fragment_outputs.len(), 1);
+    assert_eq!(fs.fragment_outputs[0].ty, ValueType::Vector { size: 4, scalar: ScalarType::F32 });
+
+    assert_eq!(refl.group_count(), 2);
+    let cam = refl.group(0).next().expect("camera binding");
+    assert_eq!(cam.ty, BindingType::Uniform { size: 64 });
+    assert_eq!(cam.stages, [ShaderStage::Vertex]);
+}
+
+#[test]
+fn material_bindings() {
+    let refl = demo("material.wgsl")
+        .reflect()
+        .expect("reflect");
+
+    let group: Vec<_> = refl.group(2).map(|b| (b.binding, b.ty.clone())).collect();
+    assert_eq!(group, [
+        (0, BindingType::Uniform { size: 32 }),
+        (1, BindingType::Texture { dimension: TextureDimension::D2, sample: TextureSample::Float, multisampled: false }),
+        (2, BindingType::Sampler { comparison: false }),
+    ]);
+}
+
+#[test]
+fn errors_have_locations() {
+    let err = WgslSource::new("broken.wgsl", "fn main() {\n  let x = ;\n}\n").reflect().unwrap_err();
+    assert_eq!(err.kind, ReflectErrorKind::Parse);
+    let loc = err.location.expect("location");
+    assert_eq!((loc.file.as_deref(), loc.line), (Some("broken.wgsl"), 2));
+}
@@ -0,0 +1,68 @@
+use shader_core::{
+    BlendMode, ColorTarget, CompareFunction, CullMode, DepthBias, FrontFace, Overrides, RenderState, ShaderKey,
+    StencilFace, StencilOp, StencilState, WgslSource, MAX_COLOR_TARGETS,
+};
+
+fn key(state: RenderState<u32>) -> ShaderKey<u32> {
+    let src = WgslSource::new("flat.wgsl", "@fragment fn fs_main() -> @location(0) vec4<f32> { return vec4<f32>(1.0); }");
+    ShaderKey::new(&src, state, &Overrides::default())
+}
+
+#[test]
+fn every_state_field_changes_the_key() {
+    let base = RenderState::new(0);
+    let replace = StencilFace { pass_op: StencilOp::Replace, ..StencilFace::IGNORE };
+    let variants = [
+        base.with_blend(BlendMode::Alpha),
+        base.with_blend(BlendMode::Additive),
+        base.with_target(1, BlendMode::Replace),
+        base.with_depth(CompareFunction::GreaterEqual, true),
+        base.with_depth(CompareFunction::Less, false),
+        base.without_depth(),
+        base.with_depth_bias(DepthBias { constant: 2, slope_scale: 1.5, clamp: 0.0 }),
+        base.with_stencil(StencilState::both(replace)),
+        base.with_cull(CullMode::Back, FrontFace::Ccw),
+        base.with_cull(CullMode::None, FrontFace::Cw),
+        base.with_polygon_mode(shader_core::PolygonMode::Line),
+    ];
+    let mut keys: Vec<_> = variants.iter().map(|s| key(*s)).collect();
+    keys.push(key(base));
+    for (i, a) in keys.iter().enumerate() {
+        for b in &keys[i + 1..] {
+            assert_ne!(a, b);
+        }
+    }
+    assert_eq!(key(base), key(RenderState::new(0)));
+}
+
+#[test]
+fn targets_are_listed_in_order() {
+    let state = RenderState::new(0u32)
+        .with_blend(BlendMode::Alpha)
+        .with_target(1, BlendMode::Replace)
+        .with_target(2, BlendMode::Additive);
+    assert_eq!(
+        state.targets(),
+        [
+            Some(ColorTarget { format: 0, blend: BlendMode::Alpha }),
+            Some(ColorTarget { format: 1, blend: BlendMode::Replace }),
+            Some(ColorTarget { format: 2, blend: BlendMode::Additive }),
+        ],
+    );
+    assert_eq!(RenderState::new(0u32).targets().len(), 1);
+}
+
+#[test]
+#[should_panic(expected = "color targets")]
+fn too_many_targets_panic() {
+    (1..MAX_COLOR_TARGETS as u32).fold(RenderState::new(0u32), |s, f| s.with_target(f, BlendMode::Replace))
+        .with_target(99, BlendMode::Replace);
+}
+
+#[test]
+fn stencil_is_off_unless_it_does_something() {
+    assert!(!StencilState::default().is_enabled());
+    assert!(!StencilState::both(StencilFace::IGNORE).is_enabled());
+    let test = StencilFace { compare: CompareFunction::Equal, ..StencilFace::IGNORE };
+    assert!(StencilState::both(test).is_enabled());
+}
@@ -0,0 +1,8 @@
+//! Ahead-of-time validation and compilation of shader permutations, so they don't hitch at
+//! first draw. See `Manifest` for the input format.
+
+mod manifest;
+mod precompile;
+
+pub use manifest::{LayoutSpec, Manifest, ManifestError, Permutation, ShaderEntry, VertexLayout};
+pub use precompile::{precompile, validate, Failure, Report};
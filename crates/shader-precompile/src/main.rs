@@ -0,0 +1,90 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use shader_precompile::Manifest;
+
+const USAGE: &str = "usage: shader-precompile <manifest> [--out <cache dir>] [--validate-only] [--software]
+
+Builds every permutation in <manifest> and exits non-zero if any fails.
+  --out <dir>        write the warm pipeline cache to <dir>
+  --validate-only    parse and validate only, no GPU needed
+  --software         compile on the software adapter";
+
+struct Args {
+    manifest: PathBuf,
+    out: Option<PathBuf>,
+    validate_only: bool,
+    software: bool,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = std::env::args_os().skip(1);
+    let (mut manifest, mut out, mut validate_only, mut software) = (None, None, false, false);
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--out") => out = Some(args.next().ok_or("--out needs a directory")?.into()),
+            Some("--validate-only") => validate_only = true,
+            Some("--software") => software = true,
+            Some("-h" | "--help") => return Err(String::new()),
+            Some(flag) if flag.starts_with("--") => return Err(format!("unknown flag {flag}")),
+            _ if manifest.is_none() => manifest = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument {}", arg.to_string_lossy())),
+        }
+    }
+    let manifest = manifest.ok_or("missing manifest")?;
+    if validate_only && out.is_some() {
+        return Err("--out needs compilation, drop --validate-only".into());
+    }
+    Ok(Args { manifest, out, validate_only, software })
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            if !e.is_empty() { eprintln!("error: {e}\n"); }
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    let manifest = match Manifest::load(&args.manifest) {
+        Ok(m) => m,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let report = if args.validate_only {
+        shader_precompile::validate(&manifest)
+    } else {
+        let mut renderer = gfx_wgpu::Renderer::new_headless(&gfx_wgpu::HeadlessConfig {
+            width: 64,
+            height: 64,
+            force_fallback_adapter: args.software,
+            ..Default::default()
+        });
+        let info = renderer.ctx.adapter.get_info();
+        eprintln!("compiling on {} ({:?})", info.name, info.backend);
+        if let Some(out) = &args.out {
+            if let Err(e) = renderer.open_pipeline_cache(out) {
+                eprintln!("error: can't open cache at {}: {e}", out.display());
+                return ExitCode::FAILURE;
+            }
+        }
+        let report = shader_precompile::precompile(&manifest, &mut renderer);
+        if report.failures.is_empty() {
+            if let Err(e) = renderer.save_pipeline_cache() {
+                eprintln!("error: can't write cache: {e}");
+                return ExitCode::FAILURE;
+            }
+        }
+        report
+    };
+
+    for failure in &report.failures {
+        eprintln!("error: {failure}\n");
+    }
+    eprintln!("{} permutation(s) ok, {} failed", report.ok, report.failures.len());
+    if report.failures.is_empty() { ExitCode::SUCCESS } else { ExitCode::FAILURE }
+}
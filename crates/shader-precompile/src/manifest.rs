@@ -0,0 +1,319 @@
+use std::path::{Path, PathBuf};
+
+use shader_core::{
+    BlendMode, CompareFunction, CullMode, Defines, FrontFace, OverrideValue, Overrides, PolygonMode, RenderState, Topology,
+};
+use wgpu::TextureFormat;
+
+/// Which of the renderer's pipeline layouts a shader is built against.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LayoutSpec {
+    Scene,
+    Textured,
+    /// Material group derived from the shader's group 2 bindings.
+    Material,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum VertexLayout { Vertex, TexVertex, Instance }
+
+impl VertexLayout {
+    pub fn layout(self) -> wgpu::VertexBufferLayout<'static> {
+        match self {
+            VertexLayout::Vertex => gfx_wgpu::Vertex::layout(),
+            VertexLayout::TexVertex => gfx_wgpu::TexVertex::layout(),
+            VertexLayout::Instance => gfx_wgpu::InstanceData::layout(),
+        }
+    }
+}
+
+/// One `[path]` section; every combination of its lists is a permutation.
+#[derive(Clone, Debug)]
+pub struct ShaderEntry {
+    /// Relative to the manifest's `root`.
+    pub path: String,
+    /// Line of the section header.
+    pub line: u32,
+    pub layout: LayoutSpec,
+    pub vertex: Vec<VertexLayout>,
+    /// Everything but format, msaa and topology, which come from the lists below.
+    pub state: RenderState<TextureFormat>,
+    pub formats: Vec<TextureFormat>,
+    pub msaa: Vec<u32>,
+    pub topologies: Vec<Topology>,
+    pub defines: Vec<Defines>,
+    pub overrides: Vec<Overrides>,
+}
+
+pub struct Permutation<'a> {
+    pub defines: &'a Defines,
+    pub state: RenderState<TextureFormat>,
+    pub overrides: &'a Overrides,
+}
+
+impl ShaderEntry {
+    fn new(path: String, line: u32) -> Self {
+        Self {
+            path,
+            line,
+            layout: LayoutSpec::Scene,
+            vertex: vec![VertexLayout::Vertex],
+            state: RenderState::new(TextureFormat::Bgra8UnormSrgb),
+            formats: vec![TextureFormat::Bgra8UnormSrgb],
+            msaa: vec![1],
+            topologies: vec![Topology::TriangleList],
+            defines: Vec::new(),
+            overrides: Vec::new(),
+        }
+    }
+
+    pub fn permutations(&self) -> Vec<Permutation<'_>> {
+        self.defines.iter().flat_map(|d| self.permutations_with(d)).collect()
+    }
+
+    /// Permutations sharing one define set, i.e. one composed source.
+    pub fn permutations_with<'a>(&'a self, defines: &'a Defines) -> Vec<Permutation<'a>> {
+        let mut out = Vec::new();
+        for &format in &self.formats {
+            for &msaa in &self.msaa {
+                for &topo in &self.topologies {
+                    for overrides in &self.overrides {
+                        let state = RenderState { format, msaa, topo, ..self.state };
+                        out.push(Permutation { defines, state, overrides });
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Shaders and the permutations of each to precompile.
+///
+/// ```text
+/// root = shaders
+///
+/// [triangle.wgsl]
+/// formats = bgra8unorm-srgb rgba8unorm-srgb
+/// msaa = 1 4
+/// overrides = USE_FOG=false
+/// overrides = USE_FOG=true TINT_R=1.0
+///
+/// [material.wgsl]
+/// layout = material
+/// vertex = tex-vertex
+/// defines = SHADOWS TAPS=8
+/// ```
+///
+/// `overrides` and `defines` may repeat, one set per line. Defaults: `layout = scene`,
+/// `vertex = vertex`, `formats = bgra8unorm-srgb`, `msaa = 1`, `topology = triangle-list`,
+/// a single empty define and override set, and the rest of `RenderState::new`. Those are set
+/// with `blend` (replace, alpha, additive, premultiplied), `cull` (none, front, back),
+/// `front-face` (ccw, cw), `polygon` (fill, line, point), `depth` (true, false),
+/// `depth-write` (true, false) and `depth-compare` (less, less-equal, greater, ...).
+#[derive(Clone, Debug)]
+pub struct Manifest {
+    /// Shader library root, resolved against the manifest's directory.
+    pub root: PathBuf,
+    pub shaders: Vec<ShaderEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ManifestError {
+    pub file: String,
+    pub line: u32,
+    pub message: String,
+}
+
+impl std::fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}: {}", self.file, self.message)
+        } else {
+            write!(f, "{}:{}: {}", self.file, self.line, self.message)
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+impl Manifest {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ManifestError> {
+        let path = path.as_ref();
+        let file = path.display().to_string();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| ManifestError { file: file.clone(), line: 0, message: e.to_string() })?;
+        Self::parse(&text, path.parent().unwrap_or(Path::new(".")), &file)
+    }
+
+    /// `base` is what `root` is relative to; `file` only names the manifest in errors.
+    pub fn parse(text: &str, base: &Path, file: &str) -> Result<Self, ManifestError> {
+        let mut root = base.to_path_buf();
+        let mut shaders: Vec<ShaderEntry> = Vec::new();
+
+        for (i, raw) in text.lines().enumerate() {
+            let line = i as u32 + 1;
+            let err = |message: String| ManifestError { file: file.to_string(), line, message };
+            let text = raw.split('#').next().unwrap_or("").trim();
+            if text.is_empty() {
+                continue;
+            }
+            if let Some(section) = text.strip_prefix('[') {
+                let path = section.strip_suffix(']').ok_or_else(|| err("missing `]`".into()))?.trim();
+                shaders.push(ShaderEntry::new(path.to_string(), line));
+                continue;
+            }
+            let (key, value) = text.split_once('=').ok_or_else(|| err(format!("expected `key = value`, got `{text}`")))?;
+            let (key, value) = (key.trim(), value.trim());
+
+            let Some(entry) = shaders.last_mut() else {
+                match key {
+                    "root" => root = base.join(value),
+                    _ => return Err(err(format!("`{key}` outside a [shader] section"))),
+                }
+                continue;
+            };
+            match key {
+                "layout" => entry.layout = match value {
+                    "scene" => LayoutSpec::Scene,
+                    "textured" => LayoutSpec::Textured,
+                    "material" => LayoutSpec::Material,
+                    _ => return Err(err(format!("unknown layout `{value}` (scene, textured or material)"))),
+                },
+                "vertex" => entry.vertex = list(value, |v| match v {
+                    "vertex" => Some(VertexLayout::Vertex),
+                    "tex-vertex" => Some(VertexLayout::TexVertex),
+                    "instance" => Some(VertexLayout::Instance),
+                    _ => None,
+                }).map_err(|v| err(format!("unknown vertex layout `{v}`")))?,
+                "depth" => entry.state.depth = boolean(value).ok_or_else(|| err(format!("expected true or false, got `{value}`")))?,
+                "depth-write" => entry.state.depth_write = boolean(value)
+                    .ok_or_else(|| err(format!("expected true or false, got `{value}`")))?,
+                "depth-compare" => entry.state.depth_compare = compare_function(value)
+                    .ok_or_else(|| err(format!("unknown compare function `{value}`")))?,
+                "blend" => entry.state.blend = match value {
+                    "replace" => BlendMode::Replace,
+                    "alpha" => BlendMode::Alpha,
+                    "additive" => BlendMode::Additive,
+                    "premultiplied" => BlendMode::Premultiplied,
+                    _ => return Err(err(format!("unknown blend mode `{value}`"))),
+                },
+                "cull" => entry.state.cull = match value {
+                    "none" => CullMode::None,
+                    "front" => CullMode::Front,
+                    "back" => CullMode::Back,
+                    _ => return Err(err(format!("unknown cull mode `{value}`"))),
+                },
+                "front-face" => entry.state.front_face = match value {
+                    "ccw" => FrontFace::Ccw,
+                    "cw" => FrontFace::Cw,
+                    _ => return Err(err(format!("unknown front face `{value}`"))),
+                },
+                "polygon" => entry.state.polygon = match value {
+                    "fill" => PolygonMode::Fill,
+                    "line" => PolygonMode::Line,
+                    "point" => PolygonMode::Point,
+                    _ => return Err(err(format!("unknown polygon mode `{value}`"))),
+                },
+                "formats" => entry.formats = list(value, texture_format)
+                    .map_err(|v| err(format!("unknown texture format `{v}`")))?,
+                "msaa" => entry.msaa = list(value, |v| v.parse().ok().filter(|n: &u32| n.is_power_of_two()))
+                    .map_err(|v| err(format!("bad sample count `{v}`")))?,
+                "topology" => entry.topologies = list(value, |v| match v {
+                    "triangle-list" => Some(Topology::TriangleList),
+                    "triangle-strip" => Some(Topology::TriangleStrip),
+                    "line-list" => Some(Topology::LineList),
+                    _ => None,
+                }).map_err(|v| err(format!("unknown topology `{v}`")))?,
+                "defines" => {
+                    let mut defines = Defines::default();
+                    for word in value.split_whitespace() {
+                        match word.split_once('=') {
+                            Some((name, v)) => defines.set(name, v),
+                            None => defines.set(word, ""),
+                        }
+                    }
+                    entry.defines.push(defines);
+                }
+                "overrides" => {
+                    let mut overrides = Overrides::default();
+                    for word in value.split_whitespace() {
+                        let (name, v) = word.split_once('=').ok_or_else(|| err(format!("expected `NAME=value`, got `{word}`")))?;
+                        let v = override_value(v).ok_or_else(|| err(format!("bad value for `{name}`: `{v}`")))?;
+                        overrides.map.insert(name.to_string(), v);
+                    }
+                    entry.overrides.push(overrides);
+                }
+                _ => return Err(err(format!("unknown key `{key}`"))),
+            }
+        }
+
+        for entry in &mut shaders {
+            if entry.defines.is_empty() { entry.defines.push(Defines::default()); }
+            if entry.overrides.is_empty() { entry.overrides.push(Overrides::default()); }
+        }
+        Ok(Self { root, shaders })
+    }
+}
+
+fn boolean(v: &str) -> Option<bool> {
+    match v {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+fn compare_function(v: &str) -> Option<CompareFunction> {
+    Some(match v {
+        "never" => CompareFunction::Never,
+        "less" => CompareFunction::Less,
+        "equal" => CompareFunction::Equal,
+        "less-equal" => CompareFunction::LessEqual,
+        "greater" => CompareFunction::Greater,
+        "not-equal" => CompareFunction::NotEqual,
+        "greater-equal" => CompareFunction::GreaterEqual,
+        "always" => CompareFunction::Always,
+        _ => return None,
+    })
+}
+
+/// Whitespace-separated list; the first word `parse` rejects is the error.
+fn list<T>(value: &str, parse: impl Fn(&str) -> Option<T>) -> Result<Vec<T>, String> {
+    let items: Vec<T> = value.split_whitespace()
+        .map(|v| parse(v).ok_or_else(|| v.to_string()))
+        .collect::<Result<_, _>>()?;
+    if items.is_empty() { Err("<empty>".to_string()) } else { Ok(items) }
+}
+
+/// Same spelling as `OverrideValue`'s `Display`: `true`, `1.5`/`1.5f`, `3`/`3i`, `3u`.
+fn override_value(v: &str) -> Option<OverrideValue> {
+    match v {
+        "true" | "false" => return boolean(v).map(OverrideValue::Bool),
+        _ => {}
+    }
+    if let Some(n) = v.strip_suffix('u') {
+        return n.parse().ok().map(OverrideValue::U32);
+    }
+    if let Some(n) = v.strip_suffix('i') {
+        return n.parse().ok().map(OverrideValue::I32);
+    }
+    let f = v.strip_suffix('f');
+    if f.is_some() || v.contains(['.', 'e', 'E']) {
+        return f.unwrap_or(v).parse().ok().map(OverrideValue::F32);
+    }
+    v.parse().ok().map(OverrideValue::I32)
+}
+
+fn texture_format(name: &str) -> Option<TextureFormat> {
+    Some(match name {
+        "rgba8unorm" => TextureFormat::Rgba8Unorm,
+        "rgba8unorm-srgb" => TextureFormat::Rgba8UnormSrgb,
+        "bgra8unorm" => TextureFormat::Bgra8Unorm,
+        "bgra8unorm-srgb" => TextureFormat::Bgra8UnormSrgb,
+        "rgb10a2unorm" => TextureFormat::Rgb10a2Unorm,
+        "rgba16float" => TextureFormat::Rgba16Float,
+        "rgba32float" => TextureFormat::Rgba32Float,
+        _ => return None,
+    })
+}
@@ -0,0 +1,110 @@
+use gfx_wgpu::{LayoutKey, MaterialDesc, Renderer};
+use shader_core::{ShaderLibrary, ShaderReflection, WgslSource};
+
+use crate::manifest::{LayoutSpec, Manifest, Permutation, ShaderEntry};
+
+/// A permutation that didn't make it, with the error already formatted.
+#[derive(Clone, Debug)]
+pub struct Failure {
+    pub shader: String,
+    pub permutation: String,
+    pub error: String,
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} [{}]\n{}", self.shader, self.permutation, self.error)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    /// Permutations that validated (or compiled).
+    pub ok: usize,
+    pub failures: Vec<Failure>,
+}
+
+/// Parses and validates every permutation, overrides included, without touching a GPU.
+pub fn validate(manifest: &Manifest) -> Report {
+    run(manifest, |_, _, _, _| Ok(()))
+}
+
+/// Builds every permutation into `renderer`'s pipeline cache; open a disk cache on it first to
+/// get a warm cache out of this.
+pub fn precompile(manifest: &Manifest, renderer: &mut Renderer) -> Report {
+    run(manifest, |entry, src, refl, perm| {
+        let vertex_layouts: Vec<_> = entry.vertex.iter().map(|v| v.layout()).collect();
+        renderer
+            .precompile(layout_key(entry.layout, refl), src, &perm.state, perm.overrides, &vertex_layouts)
+            .map_err(|e| e.to_string())
+    })
+}
+
+/// Composes each define set once and checks it, then hands each permutation whose overrides
+/// validate to `build`.
+fn run(
+    manifest: &Manifest,
+    mut build: impl FnMut(&ShaderEntry, &WgslSource, &ShaderReflection, &Permutation) -> Result<(), String>,
+) -> Report {
+    let mut library = ShaderLibrary::new();
+    library.add_root(&manifest.root);
+    let mut report = Report::default();
+
+    for entry in &manifest.shaders {
+        for defines in &entry.defines {
+            let perms = entry.permutations_with(defines);
+            let fail_all = |report: &mut Report, error: String| {
+                report.failures.extend(perms.iter().map(|p| Failure {
+                    shader: entry.path.clone(),
+                    permutation: describe(p),
+                    error: error.clone(),
+                }));
+            };
+            let src = match library.compose(&entry.path, defines) {
+                Ok(src) => src,
+                Err(e) => { fail_all(&mut report, e.to_string()); continue; }
+            };
+            let refl = match src.check() {
+                Ok(refl) => refl,
+                Err(e) => { fail_all(&mut report, e.to_string()); continue; }
+            };
+            for perm in &perms {
+                let result = perm.overrides.validate(&src.name, &refl).map_err(|e| e.to_string())
+                    .and_then(|_| build(entry, &src, &refl, perm));
+                match result {
+                    Ok(()) => report.ok += 1,
+                    Err(error) => report.failures.push(Failure {
+                        shader: entry.path.clone(),
+                        permutation: describe(perm),
+                        error,
+                    }),
+                }
+            }
+        }
+    }
+    report
+}
+
+fn layout_key(spec: LayoutSpec, refl: &ShaderReflection) -> LayoutKey {
+    match spec {
+        LayoutSpec::Scene => LayoutKey::Scene,
+        LayoutSpec::Textured => LayoutKey::Textured,
+        LayoutSpec::Material => LayoutKey::Material(MaterialDesc::layout_key_for(refl)),
+    }
+}
+
+fn describe(p: &Permutation) -> String {
+    let mut parts = Vec::new();
+    if !p.defines.is_empty() {
+        let defines: Vec<_> = p.defines.map.iter()
+            .map(|(k, v)| if v.is_empty() { k.clone() } else { format!("{k}={v}") })
+            .collect();
+        parts.push(defines.join(" "));
+    }
+    parts.push(format!("{:?} msaa={} {:?}", p.state.format, p.state.msaa, p.state.topo));
+    let overrides: Vec<_> = p.overrides.sorted().into_iter().map(|(k, v)| format!("{k}={v}")).collect();
+    if !overrides.is_empty() {
+        parts.push(overrides.join(" "));
+    }
+    parts.join(", ")
+}
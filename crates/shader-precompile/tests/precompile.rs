@@ -0,0 +1,107 @@
+use std::path::Path;
+use std::process::Command;
+
+use shader_precompile::{LayoutSpec, Manifest, VertexLayout};
+
+const DEMO_MANIFEST: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../demos/triangle/precompile.manifest");
+
+fn headless_renderer() -> gfx_wgpu::Renderer {
+    gfx_wgpu::Renderer::new_headless(&gfx_wgpu::HeadlessConfig {
+        width: 64,
+        height: 64,
+        force_fallback_adapter: true,
+        ..Default::default()
+    })
+}
+
+#[test]
+fn manifest_expands_permutations() {
+    let text = "
+        root = shaders
+        [a.wgsl]   # comment
+        formats = rgba8unorm bgra8unorm-srgb
+        msaa = 1 4
+        overrides = FOG=true
+        overrides = FOG=false SCALE=2.5 COUNT=3u
+
+        [b.wgsl]
+        layout = material
+        vertex = tex-vertex instance
+        defines = SHADOWS TAPS=8
+        defines =
+    ";
+    let m = Manifest::parse(text, Path::new("/base"), "test.manifest").expect("parse");
+    assert_eq!(m.root, Path::new("/base/shaders"));
+    assert_eq!(m.shaders[0].permutations().len(), 2 * 2 * 2);
+    assert_eq!(m.shaders[0].overrides[1].get("COUNT"), Some(shader_core::OverrideValue::U32(3)));
+    assert_eq!(m.shaders[1].layout, LayoutSpec::Material);
+    assert_eq!(m.shaders[1].vertex, [VertexLayout::TexVertex, VertexLayout::Instance]);
+    assert_eq!(m.shaders[1].permutations().len(), 2);
+}
+
+#[test]
+fn manifest_errors_have_lines() {
+    let err = Manifest::parse("[a.wgsl]\nmsaa = 1 3\n", Path::new("."), "test.manifest").unwrap_err();
+    assert_eq!((err.line, err.to_string()), (2, "test.manifest:2: bad sample count `3`".to_string()));
+    let err = Manifest::parse("formats = rgba8unorm\n", Path::new("."), "test.manifest").unwrap_err();
+    assert_eq!(err.line, 1);
+}
+
+#[test]
+fn broken_permutations_fail() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("precompile_broken");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("ok.wgsl"), concat!(
+        "override BRIGHT: f32 = 1.0;\n",
+        "@vertex fn vs_main(@location(0) pos: vec2<f32>) -> @builtin(position) vec4<f32> { return vec4<f32>(pos, 0.0, 1.0); }\n",
+        "@fragment fn fs_main() -> @location(0) vec4<f32> { return vec4<f32>(BRIGHT); }\n",
+    )).unwrap();
+    std::fs::write(dir.join("bad.wgsl"), "@vertex fn vs_main() -> @builtin(position) vec4<f32> {\n  return vec4<f32>(1.0;\n}\n").unwrap();
+    let text = "[ok.wgsl]\noverrides = BRIGHT=0.5\noverrides = BRIHGT=0.5\n[bad.wgsl]\nformats = rgba8unorm rgba16float\n";
+    let manifest = Manifest::parse(text, &dir, "broken.manifest").unwrap();
+
+    let report = shader_precompile::validate(&manifest);
+    assert_eq!(report.ok, 1);
+    assert_eq!(report.failures.len(), 3);
+    assert!(report.failures[0].error.contains("did you mean `BRIGHT`"), "{}", report.failures[0]);
+    assert!(report.failures[1].error.contains("bad.wgsl:2:"), "{}", report.failures[1]);
+}
+
+#[test]
+fn demo_manifest_warms_the_cache() {
+    let manifest = Manifest::load(DEMO_MANIFEST).expect("demo manifest");
+    let report = shader_precompile::validate(&manifest);
+    assert!(report.failures.is_empty(), "{:?}", report.failures);
+
+    let cache = Path::new(env!("CARGO_TARGET_TMPDIR")).join("precompile_warm");
+    let _ = std::fs::remove_dir_all(&cache);
+    let permutations = manifest.shaders.iter().map(|s| s.permutations().len()).sum::<usize>();
+    let precompile = || {
+        let out = Command::new(env!("CARGO_BIN_EXE_shader-precompile"))
+            .arg(DEMO_MANIFEST)
+            .arg("--out")
+            .arg(&cache)
+            .arg("--software")
+            .output()
+            .expect("run shader-precompile");
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        assert!(out.status.success(), "{stderr}");
+        assert!(stderr.contains(&format!("{permutations} permutation(s) ok, 0 failed")), "{stderr}");
+        std::fs::read_to_string(cache.join("pipelines.manifest")).unwrap()
+    };
+    let first = precompile();
+    assert_eq!(first.lines().filter(|l| l.starts_with("p ")).count(), permutations);
+    // A second process comes up with the same ids, so nothing is added.
+    assert_eq!(precompile(), first);
+
+    // And so does this one, building one of those pipelines the normal way.
+    let mut renderer = headless_renderer();
+    renderer.open_pipeline_cache(&cache).unwrap();
+    let src = shader_core::ShaderLibrary::new()
+        .add_root(&manifest.root)
+        .compose("triangle.wgsl", &shader_core::Defines::default())
+        .unwrap();
+    let state = shader_core::RenderState::new(wgpu::TextureFormat::Rgba8UnormSrgb);
+    renderer.build_pipeline(&src, &state, &shader_core::Overrides::default(), &[gfx_wgpu::Vertex::layout()]).unwrap();
+    assert_eq!(renderer.pipeline_cache_stats().unwrap().hits, 1);
+}
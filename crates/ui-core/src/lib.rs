@@ -26,13 +26,16 @@ pub trait UiBackend {
         event: &winit::event::WindowEvent,
     );
 
+    /// `window` is `None` when rendering headless; the backend then uses the size set with `resize`.
     fn build_and_render(
         &mut self,
-        window: &winit::window::Window,
+        window: Option<&winit::window::Window>,
         device: &Self::Device,
         queue: &Self::Queue,
         encoder: &mut Self::Encoder,
         view: &Self::View,
         build: &mut dyn for<'a> FnMut(&'a dyn Ui),
     );
+
+    fn resize(&mut self, _width: u32, _height: u32) {}
 }
@@ -0,0 +1,13 @@
+struct CameraUBO {
+  view_proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> cam: CameraUBO;
+
+struct ObjectUBO {
+  model: mat4x4<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> obj: ObjectUBO;
@@ -0,0 +1,29 @@
+#include "common/scene.wgsl"
+
+struct VsIn {
+  @location(0) pos: vec2<f32>,
+  @location(1) col: vec3<f32>,
+  @location(4) m0: vec4<f32>,
+  @location(5) m1: vec4<f32>,
+  @location(6) m2: vec4<f32>,
+  @location(7) m3: vec4<f32>,
+};
+
+struct VsOut {
+  @builtin(position) pos: vec4<f32>,
+  @location(0) col: vec3<f32>,
+};
+
+@vertex
+fn vs_main(in: VsIn) -> VsOut {
+  var o: VsOut;
+  let instance = mat4x4<f32>(in.m0, in.m1, in.m2, in.m3);
+  o.pos = cam.view_proj * obj.model * instance * vec4<f32>(in.pos, 0.0, 1.0);
+  o.col = in.col;
+  return o;
+}
+
+@fragment
+fn fs_main(in: VsOut) -> @location(0) vec4<f32> {
+  return vec4<f32>(in.col, 1.0);
+}
@@ -0,0 +1,27 @@
+#include "common/scene.wgsl"
+
+@group(2) @binding(0) var tex: texture_2d<f32>;
+@group(2) @binding(1) var samp: sampler;
+
+struct VsIn {
+  @location(0) pos: vec3<f32>,
+  @location(1) uv: vec2<f32>,
+};
+
+struct VsOut {
+  @builtin(position) pos: vec4<f32>,
+  @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(in: VsIn) -> VsOut {
+  var o: VsOut;
+  o.pos = cam.view_proj * obj.model * vec4<f32>(in.pos, 1.0);
+  o.uv = in.uv;
+  return o;
+}
+
+@fragment
+fn fs_main(in: VsOut) -> @location(0) vec4<f32> {
+  return textureSample(tex, samp, in.uv);
+}
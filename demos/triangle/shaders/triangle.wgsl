@@ -3,12 +3,7 @@ override TINT_R: f32 = 1.0;
 override TINT_G: f32 = 1.0;
 override TINT_B: f32 = 1.0;
 
-struct CameraUBO {
-  mvp: mat4x4<f32>,
-};
-
-@group(0) @binding(0)
-var<uniform> cam: CameraUBO;
+#include "common/scene.wgsl"
 
 struct VsIn {
   @location(0) pos: vec2<f32>,
@@ -24,7 +19,7 @@ struct VsOut {
 fn vs_main(in: VsIn) -> VsOut {
   var o: VsOut;
   let tint = vec3<f32>(TINT_R, TINT_G, TINT_B);
-  o.pos = cam.mvp * vec4<f32>(in.pos, 0.0, 1.0);
+  o.pos = cam.view_proj * obj.model * vec4<f32>(in.pos, 0.0, 1.0);
   o.col = in.col * tint;
   return o;
 }
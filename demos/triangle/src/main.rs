@@ -1,37 +1,168 @@
 #![cfg_attr(all(windows, not(debug_assertions)), windows_subsystem = "windows")]
 
 use std::time::Instant;
+use engine_core::ecs::{Entity, Schedule, Stage, With, World};
+use engine_core::{CameraController, InputState, Key, MouseButton, Transform};
 use winit::{
     application::ApplicationHandler,
-    event::WindowEvent,
+    event::{MouseScrollDelta, WindowEvent},
     event_loop::{ActiveEventLoop, ControlFlow},
+    keyboard::{KeyCode, PhysicalKey},
 };
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ControllerKind { Orbit, Fly, FirstPerson }
+
+impl ControllerKind {
+    fn next(self) -> Self {
+        match self {
+            ControllerKind::Orbit => ControllerKind::Fly,
+            ControllerKind::Fly => ControllerKind::FirstPerson,
+            ControllerKind::FirstPerson => ControllerKind::Orbit,
+        }
+    }
+
+    /// Picks up from where `camera` is. Looking and orbiting use the right button, the left
+    /// one belongs to the UI.
+    fn create(self, camera: &gfx_wgpu::Camera) -> Box<dyn CameraController> {
+        match self {
+            ControllerKind::Orbit => Box::new(engine_core::OrbitController::from_camera(camera).with_buttons(MouseButton::Right, MouseButton::Middle)),
+            ControllerKind::Fly => Box::new(engine_core::FlyController::from_camera(camera)),
+            ControllerKind::FirstPerson => Box::new(engine_core::FirstPersonController::from_camera(camera)),
+        }
+    }
+}
+
 struct Demo {
     inner: platform::App,
     renderer: Option<gfx_wgpu::Renderer>,
     rot_speed: f32,
     camera: gfx_wgpu::Camera,
-    angle: f32,
+    input: InputState,
+    controller_kind: ControllerKind,
+    controller: Box<dyn CameraController>,
     last_frame: Instant,
     fog: bool,
-    shader_src: Option<shader_core::WgslSource>,
+    pipeline: Option<gfx_wgpu::PipelineHandle>,
+    world: World,
+    schedule: Schedule,
+    ring: Option<Entity>,
+    show_ring: bool,
+    floor_mat: Option<gfx_wgpu::MaterialHandle>,
+    floor_brightness: f32,
+    hdr: gfx_wgpu::HdrSettings,
+    hdr_on: bool,
+    /// Last left click's hit, as a name and world position.
+    picked: Option<(&'static str, glam::Vec3)>,
+}
+
+/// What the UI calls an entity.
+struct Name(&'static str);
+
+/// Turns about Y by `SpinStep` each frame.
+struct Spin;
+
+/// Radians spinning entities turn this frame.
+struct SpinStep(f32);
+
+fn schedule() -> Schedule {
+    let mut schedule = Schedule::new();
+    schedule.add_system(Stage::Update, "spin", |world| {
+        let Some(step) = world.resource::<SpinStep>().map(|s| s.0) else { return };
+        world.query_filtered::<&mut Transform, With<Spin>>().for_each(|_, mut t| {
+            t.rotation = glam::Quat::from_rotation_y(step) * t.rotation;
+        });
+    });
+    schedule
 }
 
 impl Demo {
     fn new() -> Self {
         let inner = platform::App::new(|_win| { });
+        let camera = gfx_wgpu::Camera::new(glam::f32::Vec3::new(1.5, 1.5, 2.5), glam::f32::Vec3::ZERO);
         Self {
             inner,
             renderer: None,
             rot_speed: 1.0,
-            camera: gfx_wgpu::Camera::new(glam::f32::Vec3::new(1.5, 1.5, 2.5), glam::f32::Vec3::ZERO),
-            angle: 0.0,
+            input: InputState::new(),
+            controller_kind: ControllerKind::Orbit,
+            controller: ControllerKind::Orbit.create(&camera),
+            camera,
             last_frame: Instant::now(),
             fog: true,
-            shader_src: None,
+            pipeline: None,
+            world: World::new(),
+            schedule: schedule(),
+            ring: None,
+            show_ring: true,
+            floor_mat: None,
+            picked: None,
+            floor_brightness: 1.0,
+            hdr: gfx_wgpu::HdrSettings::default(),
+            hdr_on: false,
+        }
+    }
+}
+
+fn engine_key(code: KeyCode) -> Option<Key> {
+    Some(match code {
+        KeyCode::KeyW => Key::W,
+        KeyCode::KeyA => Key::A,
+        KeyCode::KeyS => Key::S,
+        KeyCode::KeyD => Key::D,
+        KeyCode::KeyQ => Key::Q,
+        KeyCode::KeyE => Key::E,
+        KeyCode::ArrowUp => Key::Up,
+        KeyCode::ArrowDown => Key::Down,
+        KeyCode::ArrowLeft => Key::Left,
+        KeyCode::ArrowRight => Key::Right,
+        KeyCode::Space => Key::Space,
+        KeyCode::ShiftLeft | KeyCode::ShiftRight => Key::Shift,
+        KeyCode::ControlLeft | KeyCode::ControlRight => Key::Ctrl,
+        KeyCode::Escape => Key::Escape,
+        _ => return None,
+    })
+}
+
+fn feed_input(input: &mut InputState, event: &WindowEvent) {
+    match event {
+        WindowEvent::KeyboardInput { event, .. } => {
+            if let Some(key) = match event.physical_key { PhysicalKey::Code(code) => engine_key(code), _ => None } {
+                input.set_key(key, event.state.is_pressed());
+            }
         }
+        WindowEvent::MouseInput { state, button, .. } => {
+            let button = match button {
+                winit::event::MouseButton::Left => MouseButton::Left,
+                winit::event::MouseButton::Right => MouseButton::Right,
+                winit::event::MouseButton::Middle => MouseButton::Middle,
+                _ => return,
+            };
+            input.set_button(button, state.is_pressed());
+        }
+        WindowEvent::CursorMoved { position, .. } => input.set_cursor(glam::vec2(position.x as f32, position.y as f32)),
+        WindowEvent::MouseWheel { delta, .. } => input.add_scroll(match delta {
+            MouseScrollDelta::LineDelta(_, y) => *y,
+            MouseScrollDelta::PixelDelta(p) => p.y as f32 / 40.0,
+        }),
+        WindowEvent::Focused(false) => input.release_all(),
+        _ => {}
+    }
+}
+
+/// Straight from the source tree when it's around, so shader edits hot-reload; embedded otherwise.
+fn shader_library() -> shader_core::ShaderLibrary {
+    let mut lib = shader_core::ShaderLibrary::new();
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("shaders");
+    if dir.is_dir() {
+        lib.add_root(dir);
+        return lib;
     }
+    lib.add("common/scene.wgsl", include_str!("../shaders/common/scene.wgsl"))
+        .add("triangle.wgsl", include_str!("../shaders/triangle.wgsl"))
+        .add("instanced.wgsl", include_str!("../shaders/instanced.wgsl"))
+        .add("material.wgsl", include_str!("../shaders/material.wgsl"));
+    lib
 }
 
 impl ApplicationHandler for Demo {
@@ -47,18 +178,16 @@ impl ApplicationHandler for Demo {
 
         if let Some(win) = &self.inner.window {
             let mut renderer = gfx_wgpu::Renderer::new(win);
+            let cache_dir = std::env::temp_dir().join("rusted-game-engine").join("pipelines");
+            if let Err(e) = renderer.open_pipeline_cache(&cache_dir) {
+                eprintln!("pipeline cache disabled: {e}");
+            }
 
-            let src = shader_core::WgslSource {
-                name: "triangle.wgsl",
-                code: include_str!("../shaders/triangle.wgsl"),
-            };
+            let shaders = shader_library();
+            let no_defines = shader_core::Defines::default();
+            let src = shaders.compose("triangle.wgsl", &no_defines).expect("triangle.wgsl");
 
-            let state = shader_core::RenderState {
-                format: renderer.ctx.config.format,
-                depth: true,
-                msaa: 1,
-                topo: shader_core::Topology::TriangleList,
-            };
+            let state = shader_core::RenderState::new(renderer.ctx.config.format);
 
             let mut ov = shader_core::Overrides::default();
             ov.set_bool("USE_FOG", true);
@@ -66,13 +195,95 @@ impl ApplicationHandler for Demo {
             ov.set_f32("TINT_G", 0.9);
             ov.set_f32("TINT_B", 0.9);
 
-            renderer.build_pipeline(&src, &state, &ov, &[gfx_wgpu::Vertex::layout()]);
+            self.pipeline = Some(
+                renderer.build_pipeline(&src, &state, &ov, &[gfx_wgpu::Vertex::layout()]).expect("triangle pipeline"),
+            );
+
+            let verts = [
+                gfx_wgpu::Vertex { pos: [-0.6, -0.5], col: [1.0, 0.2, 0.2] },
+                gfx_wgpu::Vertex { pos: [ 0.6, -0.5], col: [0.2, 1.0, 0.2] },
+                gfx_wgpu::Vertex { pos: [ 0.0,  0.6], col: [0.2, 0.2, 1.0] },
+            ];
+            let mesh = renderer.upload_mesh(&verts, None);
+            renderer.set_mesh_bounds(mesh, gfx_wgpu::Aabb::from_points(verts.iter().map(|v| glam::Vec2::from(v.pos).extend(0.0))));
+            self.world.spawn((
+                Name("triangle"),
+                gfx_wgpu::Renderable(gfx_wgpu::DrawItem::new(mesh, self.pipeline.unwrap())),
+                Transform::IDENTITY,
+                Spin,
+            ));
+
+            // Ring of small triangles around the big one, drawn with a single instanced call.
+            let inst_src = shaders.compose("instanced.wgsl", &no_defines).expect("instanced.wgsl");
+            let inst_pipeline = renderer.build_pipeline(
+                &inst_src,
+                &state,
+                &shader_core::Overrides::default(),
+                &[gfx_wgpu::Vertex::layout(), gfx_wgpu::InstanceData::layout()],
+            ).expect("instanced pipeline");
+            let ring: Vec<_> = (0..256)
+                .map(|i| {
+                    let a = i as f32 / 256.0 * std::f32::consts::TAU;
+                    gfx_wgpu::InstanceData::new(
+                        glam::Mat4::from_translation(glam::Vec3::new(a.cos() * 1.4, (a * 8.0).sin() * 0.1, a.sin() * 1.4))
+                            * glam::Mat4::from_rotation_y(-a)
+                            * glam::Mat4::from_scale(glam::Vec3::splat(0.08)),
+                    )
+                })
+                .collect();
+            let ring = renderer.upload_instances(&ring);
+            let ring_bounds = gfx_wgpu::Aabb::from_center_half_extents(glam::Vec3::ZERO, glam::Vec3::new(1.5, 0.2, 1.5));
+            self.ring = Some(self.world.spawn((
+                Name("ring"),
+                gfx_wgpu::Renderable(gfx_wgpu::DrawItem::new(mesh, inst_pipeline).with_instances(ring).with_bounds(ring_bounds)),
+                Transform::IDENTITY,
+                Spin,
+            )));
+
+            // Checkerboard floor below the triangle.
+            let checker: Vec<u8> = (0..64 * 64)
+                .flat_map(|i| if ((i % 64) / 8 + (i / 64) / 8) % 2 == 0 { [200, 200, 210, 255] } else { [40, 45, 60, 255] })
+                .collect();
+            let checker = renderer.create_texture_rgba8(64, 64, &checker, gfx_wgpu::TextureOptions {
+                sampler: gfx_wgpu::SamplerDesc::ANISO_REPEAT,
+                ..Default::default()
+            }).expect("checker texture");
+            let (s, y, r) = (4.0, -0.8, 8.0);
+            let floor = renderer.upload_mesh(
+                &[
+                    gfx_wgpu::TexVertex { pos: [-s, y, -s], uv: [0.0, 0.0] },
+                    gfx_wgpu::TexVertex { pos: [-s, y,  s], uv: [0.0, r] },
+                    gfx_wgpu::TexVertex { pos: [ s, y,  s], uv: [r, r] },
+                    gfx_wgpu::TexVertex { pos: [ s, y, -s], uv: [r, 0.0] },
+                ],
+                Some(gfx_wgpu::Indices::U16(&[0, 1, 2, 0, 2, 3])),
+            );
+            renderer.set_mesh_bounds(floor, Some(gfx_wgpu::Aabb::new(glam::Vec3::new(-s, y, -s), glam::Vec3::new(s, y, s))));
+            let mat_src = shaders.compose("material.wgsl", &no_defines).expect("material.wgsl");
+            let floor_mat = renderer.create_material(
+                gfx_wgpu::MaterialDesc::new(mat_src, state, &[gfx_wgpu::TexVertex::layout()])
+                    .with_param("tint", gfx_wgpu::MaterialParam::Vec4([0.9, 0.95, 1.0, 1.0]))
+                    .with_param("brightness", gfx_wgpu::MaterialParam::F32(self.floor_brightness))
+                    .with_texture(checker, gfx_wgpu::SamplerDesc::ANISO_REPEAT),
+            ).expect("floor material");
+            self.world.spawn((Name("floor"), gfx_wgpu::Renderable(gfx_wgpu::DrawItem::from_material(floor, floor_mat))));
+            self.floor_mat = Some(floor_mat);
+
+            // Every effect, off until ticked in the Post-processing window.
+            let mut post = gfx_wgpu::PostStack::standard();
+            let names: Vec<&str> = post.entries().iter().map(|e| e.effect.name()).collect();
+            for name in names {
+                post.set_enabled(name, false);
+            }
+            renderer.set_post_stack(post).expect("post-process stack");
 
-            self.shader_src = Some(src);
+            if let Err(e) = renderer.watch_shaders() {
+                eprintln!("shader hot-reload disabled: {e}");
+            }
             self.renderer = Some(renderer);
 
-            let aspect = self.renderer.as_ref().unwrap().aspect();
-            let ubo = self.camera.make_mvp(aspect, 0.0);
+            let viewport = self.renderer.as_ref().unwrap().viewport();
+            let ubo = self.camera.make_ubo_viewport(viewport);
             self.renderer.as_mut().unwrap().update_camera_ubo(&ubo);
             win.request_redraw();
         }
@@ -80,12 +291,31 @@ impl ApplicationHandler for Demo {
 
     fn window_event(&mut self, el: &ActiveEventLoop, id: winit::window::WindowId, event: WindowEvent) {
         self.inner.window_event(el, id, event.clone());
+        feed_input(&mut self.input, &event);
 
         if let (Some(win), Some(renderer)) = (&self.inner.window, self.renderer.as_mut()) {
             renderer.ui_event(win, id, &event);
 
             match event {
                 WindowEvent::Resized(sz) => { renderer.resize(sz); win.request_redraw(); }
+                WindowEvent::MouseInput { state: winit::event::ElementState::Pressed, button: winit::event::MouseButton::Left, .. } => {
+                    let Some(cursor) = self.input.cursor() else { return };
+                    self.picked = match renderer.pick_entity(&self.world, cursor) {
+                        Ok(hit) => hit.and_then(|(entity, hit)| {
+                            let name = self.world.get::<Name>(entity)?.0;
+                            Some((name, self.camera.screen_to_world(cursor, hit.depth, renderer.viewport())))
+                        }),
+                        Err(e) => {
+                            eprintln!("pick failed: {e}");
+                            None
+                        }
+                    };
+                }
+                WindowEvent::CloseRequested => {
+                    if let Err(e) = renderer.save_pipeline_cache() {
+                        eprintln!("failed to save pipeline cache: {e}");
+                    }
+                }
                 WindowEvent::RedrawRequested => {
                     let now = Instant::now();
                     let dt = now.duration_since(self.last_frame).as_secs_f32();
@@ -93,16 +323,65 @@ impl ApplicationHandler for Demo {
 
                     let mut local_speed = self.rot_speed;
                     let mut apply_overrides = false;
+                    let mut brightness = self.floor_brightness;
+                    let mut next_msaa = None;
+                    let (mut hdr, mut hdr_on) = (self.hdr, self.hdr_on);
+                    let (mut post, mut post_changed) = (renderer.post_stack().clone(), false);
+                    let mut reverse_z = self.camera.reverse_z;
+                    let mut ortho = self.camera.is_orthographic();
+                    let mut next_controller = false;
+
+                    renderer.submit_world(&self.world);
 
-                    let _ = renderer.render_with_ui(win, |ui| {
-                        ui.window("Camera", [300.0, 220.0], &mut |ui| {
+                    let (msaa, supported_msaa) = (renderer.msaa(), renderer.supported_msaa());
+                    let (mut culling, cull_stats) = (renderer.culling(), renderer.cull_stats());
+                    let _ = renderer.render_with_ui(Some(win), |ui| {
+                        ui.window("Camera", [300.0, 380.0], &mut |ui| {
                             ui.text("Camera controls");
                             ui.separator();
                             ui.slider_f32("Delta Time Scale", 0.0..=5.0, &mut local_speed);
                             ui.checkbox("Fog", &mut self.fog);
+                            ui.checkbox("Instanced ring", &mut self.show_ring);
+                            ui.slider_f32("Floor brightness", 0.0..=2.0, &mut brightness);
                             if ui.button("Apply shader overrides") {
                                 apply_overrides = true;
                             }
+                            ui.checkbox("Reverse-Z", &mut reverse_z);
+                            ui.checkbox("Orthographic", &mut ortho);
+                            ui.checkbox("Frustum culling", &mut culling);
+                            ui.text(&format!("Culled {} of {} draws", cull_stats.culled, cull_stats.submitted));
+                            match self.picked {
+                                Some((name, p)) => ui.text(&format!("Picked {name} at ({:.2}, {:.2}, {:.2})", p.x, p.y, p.z)),
+                                None => ui.text("Left click to pick"),
+                            }
+                            if ui.button(&format!("Controller: {:?}", self.controller_kind)) {
+                                next_controller = true;
+                            }
+                            if ui.button(&format!("MSAA: {}x", msaa)) {
+                                next_msaa = Some(supported_msaa.iter().copied().find(|&n| n > msaa).unwrap_or(1));
+                            }
+                            ui.separator();
+                            ui.checkbox("HDR", &mut hdr_on);
+                            if ui.button(&format!("Tonemapper: {:?}", hdr.tonemapper)) {
+                                let all = gfx_wgpu::Tonemapper::ALL;
+                                let i = all.iter().position(|&t| t == hdr.tonemapper).unwrap_or(0);
+                                hdr.tonemapper = all[(i + 1) % all.len()];
+                            }
+                            let mut auto = matches!(hdr.exposure, gfx_wgpu::Exposure::Auto(_));
+                            if ui.checkbox("Auto exposure", &mut auto) {
+                                hdr.exposure = if auto {
+                                    gfx_wgpu::Exposure::default()
+                                } else {
+                                    gfx_wgpu::Exposure::Manual { ev: 0.0 }
+                                };
+                            }
+                            match &mut hdr.exposure {
+                                gfx_wgpu::Exposure::Manual { ev } => ui.slider_f32("Exposure (EV)", -4.0..=4.0, ev),
+                                gfx_wgpu::Exposure::Auto(auto) => ui.slider_f32("Compensation (EV)", -4.0..=4.0, &mut auto.compensation),
+                            };
+                        });
+                        ui.window("Post-processing", [300.0, 380.0], &mut |ui| {
+                            post_changed |= post.ui(ui);
                         });
                     });
 
@@ -112,15 +391,76 @@ impl ApplicationHandler for Demo {
                         new_ov.set_f32("TINT_R", 1.0);
                         new_ov.set_f32("TINT_G", 0.9);
                         new_ov.set_f32("TINT_B", 0.9);
-                        let src = self.shader_src.as_ref().unwrap();
-                        renderer.rebuild_pipeline(src, new_ov, shader_core::Topology::TriangleList);
+                        // Current source rather than the one at startup, it may have been hot-reloaded since.
+                        let src = self.pipeline.and_then(|p| renderer.pipeline(p)).map(|p| p.src.clone());
+                        if let (Some(pipeline), Some(src)) = (self.pipeline, src) {
+                            if let Err(e) = renderer.rebuild_pipeline(pipeline, &src, new_ov, shader_core::Topology::TriangleList) {
+                                eprintln!("{e}");
+                            }
+                        }
+                    }
+
+                    if let Some(samples) = next_msaa {
+                        if let Err(e) = renderer.set_msaa(samples) {
+                            eprintln!("{e}");
+                        }
+                    }
+
+                    if (hdr, hdr_on) != (self.hdr, self.hdr_on) {
+                        (self.hdr, self.hdr_on) = (hdr, hdr_on);
+                        renderer.set_hdr(hdr_on.then_some(hdr));
+                    }
+
+                    if next_controller {
+                        self.controller_kind = self.controller_kind.next();
+                        self.controller = self.controller_kind.create(&self.camera);
+                    }
+
+                    if reverse_z != self.camera.reverse_z {
+                        self.camera.reverse_z = reverse_z;
+                        renderer.set_reverse_z(reverse_z);
+                    }
+
+                    renderer.set_culling(culling);
+
+                    if ortho != self.camera.is_orthographic() {
+                        self.camera.projection = if ortho {
+                            engine_core::camera::Projection::Orthographic { height: 6.0 }
+                        } else {
+                            engine_core::camera::Projection::Perspective
+                        };
+                    }
+
+                    if post_changed {
+                        if let Err(e) = renderer.set_post_stack(post) {
+                            eprintln!("{e}");
+                        }
+                    }
+
+                    if brightness != self.floor_brightness {
+                        self.floor_brightness = brightness;
+                        if let Some(floor_mat) = self.floor_mat {
+                            renderer.set_material_param(floor_mat, "brightness", gfx_wgpu::MaterialParam::F32(brightness));
+                        }
                     }
 
                     self.rot_speed = local_speed;
-                    self.angle += dt * self.rot_speed;
+                    if let Some(ring) = self.ring {
+                        if self.world.has::<gfx_wgpu::Hidden>(ring) == self.show_ring {
+                            if self.show_ring {
+                                self.world.remove::<gfx_wgpu::Hidden>(ring);
+                            } else {
+                                self.world.insert(ring, gfx_wgpu::Hidden);
+                            }
+                        }
+                    }
+                    self.world.insert_resource(SpinStep(dt * self.rot_speed));
+                    self.schedule.run(&mut self.world);
+
+                    self.controller.update(&mut self.camera, &self.input, dt);
+                    self.input.end_frame();
 
-                    let aspect = renderer.aspect();
-                    let ubo = self.camera.make_mvp(aspect, self.angle);
+                    let ubo = self.camera.make_ubo_viewport(renderer.viewport());
                     renderer.update_camera_ubo(&ubo);
                 }
                 WindowEvent::Occluded(false) | WindowEvent::Focused(true) => win.request_redraw(),